@@ -34,6 +34,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = StatuslineInput {
         workspace: Some(Workspace {
             current_dir: Some("/home/user/awesome-project".to_string()),
+            project_dir: None,
         }),
         model: Some(Model {
             display_name: Some("Claude 3 Opus".to_string()),
@@ -42,9 +43,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             total_cost_usd: Some(15.75),
             total_lines_added: Some(500),
             total_lines_removed: Some(80),
+            ..Default::default()
         }),
         session_id: Some("structured-example".to_string()),
         transcript: None,
+        ..Default::default()
     };
 
     // Render with stats update enabled
@@ -0,0 +1,228 @@
+//! Snapshots of the stats database and config (`statusline backup`).
+//!
+//! A backup is a single SQLite file made with SQLite's online backup API, so
+//! it is consistent even while sessions keep writing. The config file and the
+//! version that wrote the backup are stored alongside the data in its `meta`
+//! table under `backup_*` keys, which are removed again on restore.
+
+use crate::common::{get_data_dir, get_database_path};
+use crate::config::Config;
+use crate::database::{apply_encryption_key, CURRENT_SCHEMA_VERSION};
+use crate::error::{Result, StatuslineError};
+use rusqlite::backup::Backup;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Pages copied per backup step; the source is unlocked between steps
+const PAGES_PER_STEP: std::os::raw::c_int = 256;
+
+/// What a backup file contains, read back from its `meta` table
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupInfo {
+    /// Statusline version that wrote the backup
+    pub statusline_version: String,
+    /// RFC 3339 time the backup was taken
+    pub created_at: String,
+    /// Latest schema migration applied to the backed-up database
+    pub schema_version: u32,
+    /// Contents of the config file at backup time
+    pub config: Option<String>,
+}
+
+/// Where `backup create` writes: `to` itself, a timestamped file inside `to`
+/// when it is a directory, or `<data dir>/backups/` by default.
+pub fn backup_path(to: Option<&Path>) -> PathBuf {
+    let file_name = format!(
+        "statusline-backup-{}.db",
+        crate::environment::now_local().format("%Y%m%d-%H%M%S")
+    );
+    match to {
+        Some(dir) if dir.is_dir() => dir.join(file_name),
+        Some(path) => path.to_path_buf(),
+        None => get_data_dir().join("backups").join(file_name),
+    }
+}
+
+/// Copies `src` into `dst` page by page with SQLite's backup API
+fn copy_database(src: &Connection, dst: &mut Connection) -> Result<()> {
+    let backup = Backup::new(src, dst)?;
+    backup.run_to_completion(PAGES_PER_STEP, Duration::from_millis(10), None)?;
+    Ok(())
+}
+
+/// Opens (or creates) a database file, keyed for SQLCipher if configured
+fn open_keyed(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    apply_encryption_key(&conn)?;
+    Ok(conn)
+}
+
+/// Snapshots the stats database and config file into `dest`, which must not exist yet
+pub fn create(dest: &Path) -> Result<BackupInfo> {
+    let db_path = get_database_path();
+    if !db_path.exists() {
+        return Err(StatuslineError::stats(format!(
+            "No database at {}",
+            db_path.display()
+        )));
+    }
+    if dest.exists() {
+        return Err(StatuslineError::input(format!(
+            "{} already exists",
+            dest.display()
+        )));
+    }
+    if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let src = crate::database::open_read_only(&db_path)?;
+    let mut dst = open_keyed(dest)?;
+    if let Err(e) = copy_database(&src, &mut dst) {
+        drop(dst);
+        let _ = fs::remove_file(dest);
+        return Err(e);
+    }
+
+    let info = BackupInfo {
+        statusline_version: env!("CLAUDIA_VERSION").to_string(),
+        created_at: crate::environment::now_local().to_rfc3339(),
+        schema_version: schema_version(&dst)?,
+        config: Config::find_config_file().and_then(|path| fs::read_to_string(path).ok()),
+    };
+    dst.execute_batch(
+        "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+    )?;
+    let mut entries = vec![
+        (
+            "backup_statusline_version",
+            info.statusline_version.as_str(),
+        ),
+        ("backup_created_at", info.created_at.as_str()),
+    ];
+    if let Some(config) = &info.config {
+        entries.push(("backup_config", config.as_str()));
+    }
+    for (key, value) in entries {
+        dst.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+    }
+    Ok(info)
+}
+
+/// Reads and validates a backup file without changing anything
+pub fn inspect(path: &Path) -> Result<BackupInfo> {
+    if !path.is_file() {
+        return Err(StatuslineError::input(format!(
+            "No backup at {}",
+            path.display()
+        )));
+    }
+    let conn = crate::database::open_read_only(path)?;
+
+    let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if check != "ok" {
+        return Err(StatuslineError::stats(format!(
+            "{} is damaged: {}",
+            path.display(),
+            check
+        )));
+    }
+
+    let meta = |key: &str| -> Result<Option<String>> {
+        Ok(conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    };
+    let not_a_backup =
+        || StatuslineError::input(format!("{} is not a statusline backup", path.display()));
+    let created_at = meta("backup_created_at")
+        .map_err(|_| not_a_backup())?
+        .ok_or_else(not_a_backup)?;
+
+    Ok(BackupInfo {
+        statusline_version: meta("backup_statusline_version")?.unwrap_or_default(),
+        created_at,
+        schema_version: schema_version(&conn)?,
+        config: meta("backup_config")?,
+    })
+}
+
+/// Replaces the stats database (and config file, unless `skip_config`) with a
+/// backup's contents.
+///
+/// Backups from a newer schema are refused; older ones are migrated after the
+/// restore. The replaced database, JSON backup and config are kept next to the
+/// originals with a `.pre-restore` suffix.
+pub fn restore(path: &Path, skip_config: bool) -> Result<BackupInfo> {
+    let info = inspect(path)?;
+    if info.schema_version > CURRENT_SCHEMA_VERSION {
+        return Err(StatuslineError::input(format!(
+            "Backup uses schema v{} (statusline {}), newer than this version supports (v{}); upgrade statusline first",
+            info.schema_version, info.statusline_version, CURRENT_SCHEMA_VERSION
+        )));
+    }
+
+    let db_path = get_database_path();
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut live = open_keyed(&db_path)?;
+    if db_path.metadata().map(|m| m.len() > 0).unwrap_or(false) {
+        let mut safety = open_keyed(&with_suffix(&db_path, ".pre-restore"))?;
+        copy_database(&live, &mut safety)?;
+    }
+
+    let src = crate::database::open_read_only(path)?;
+    copy_database(&src, &mut live)?;
+    live.execute(
+        "DELETE FROM meta WHERE key LIKE 'backup\\_%' ESCAPE '\\'",
+        [],
+    )?;
+    drop(live);
+    crate::migrations::run_migrations_on_db(&db_path)?;
+
+    // The JSON backup still describes the replaced data
+    let json_path = crate::stats::StatsData::get_stats_file_path();
+    if json_path.exists() {
+        fs::rename(&json_path, with_suffix(&json_path, ".pre-restore"))?;
+    }
+
+    if let (Some(config), false) = (&info.config, skip_config) {
+        let config_path = match Config::find_config_file() {
+            Some(path) => path,
+            None => Config::default_config_path()?,
+        };
+        if config_path.exists() {
+            fs::copy(&config_path, with_suffix(&config_path, ".pre-restore"))?;
+        } else if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&config_path, config)?;
+    }
+
+    Ok(info)
+}
+
+/// Latest applied schema migration (0 when none are recorded)
+fn schema_version(conn: &Connection) -> Result<u32> {
+    let version: Option<u32> = conn
+        .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })
+        .optional()?
+        .flatten();
+    Ok(version.unwrap_or(0))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
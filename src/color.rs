@@ -0,0 +1,118 @@
+//! Color decision for all terminal output.
+//!
+//! Precedence, highest first:
+//! 1. `--color always|never` (or `--no-color`)
+//! 2. `NO_COLOR` (set and non-empty) disables colors
+//! 3. `CLICOLOR_FORCE` (set and not `0`) enables colors
+//! 4. `CLICOLOR=0` disables colors
+//! 5. For interactive commands (`stats`, `health`, ...), colors only when
+//!    stdout is a terminal
+//!
+//! The statusline itself is always read through a pipe by Claude Code, so it
+//! skips the terminal check and stays colored unless one of the above says no.
+
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+/// Accepted values for `--color`
+pub const COLOR_CHOICES: [&str; 3] = ["auto", "always", "never"];
+
+/// Color choice from the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Parses a `--color` value, treating anything unknown as `auto`
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "always" => ColorChoice::Always,
+            "never" => ColorChoice::Never,
+            _ => ColorChoice::Auto,
+        }
+    }
+}
+
+/// Choice and whether `auto` should check for a terminal, set once at startup
+static SETTINGS: OnceLock<(ColorChoice, bool)> = OnceLock::new();
+
+/// Records the command-line choice. `check_tty` is true for commands whose
+/// output is read by a person rather than Claude Code.
+pub fn init(choice: ColorChoice, check_tty: bool) {
+    let _ = SETTINGS.set((choice, check_tty));
+}
+
+/// Whether ANSI colors should be emitted
+pub fn enabled() -> bool {
+    let (choice, check_tty) = SETTINGS
+        .get()
+        .copied()
+        .unwrap_or((ColorChoice::Auto, false));
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => decide(
+            |name| crate::environment::current().var(name),
+            check_tty,
+            std::io::stdout().is_terminal(),
+        ),
+    }
+}
+
+fn decide(var: impl Fn(&str) -> Option<String>, check_tty: bool, is_terminal: bool) -> bool {
+    if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+    if var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    if var("CLICOLOR").as_deref() == Some("0") {
+        return false;
+    }
+    !check_tty || is_terminal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        // Statusline output stays colored through a pipe
+        assert!(decide(with(&[]), false, false));
+        // Interactive commands follow the terminal
+        assert!(decide(with(&[]), true, true));
+        assert!(!decide(with(&[]), true, false));
+
+        // CLICOLOR_FORCE wins over the terminal check, NO_COLOR wins over both
+        assert!(decide(with(&[("CLICOLOR_FORCE", "1")]), true, false));
+        assert!(!decide(with(&[("CLICOLOR_FORCE", "0")]), true, false));
+        assert!(!decide(
+            with(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]),
+            false,
+            true
+        ));
+
+        // Empty NO_COLOR is ignored, CLICOLOR=0 disables
+        assert!(decide(with(&[("NO_COLOR", "")]), false, false));
+        assert!(!decide(with(&[("CLICOLOR", "0")]), false, true));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(ColorChoice::parse("always"), ColorChoice::Always);
+        assert_eq!(ColorChoice::parse("never"), ColorChoice::Never);
+        assert_eq!(ColorChoice::parse("auto"), ColorChoice::Auto);
+    }
+}
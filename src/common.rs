@@ -3,14 +3,17 @@
 //! This module provides shared functionality to reduce code duplication
 //! and ensure consistent behavior across the application.
 
+use crate::environment::{self, Environment};
 use crate::error::Result;
-use chrono::Local;
 use sha2::{Digest, Sha256};
-use std::path::PathBuf;
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 
 /// Gets the application data directory using XDG Base Directory specification.
 ///
-/// Returns `~/.local/share/claudia-statusline/` on Unix-like systems.
+/// Returns `~/.local/share/claudia-statusline/` on Unix-like systems, or the
+/// directory in `STATUSLINE_DATA_DIR` (set by `--data-dir`) when present.
 ///
 /// # Example
 ///
@@ -21,24 +24,75 @@ use std::path::PathBuf;
 /// let stats_file = data_dir.join("stats.json");
 /// ```
 pub fn get_data_dir() -> PathBuf {
+    data_dir_in(&*environment::current())
+}
+
+/// [`get_data_dir`] resolved against a given environment.
+pub fn data_dir_in(env: &dyn Environment) -> PathBuf {
+    // Explicit override from --data-dir, used as-is
+    if let Some(dir) = env.var("STATUSLINE_DATA_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+
     // Check XDG_DATA_HOME environment variable first (for testing and user overrides)
-    if let Ok(xdg_data_home) = std::env::var("XDG_DATA_HOME") {
+    if let Some(xdg_data_home) = env.var("XDG_DATA_HOME") {
         return PathBuf::from(xdg_data_home).join("claudia-statusline");
     }
 
-    // Use dirs crate for proper XDG handling
-    let base_dir = dirs::data_dir().unwrap_or_else(|| {
-        // Fallback if dirs crate fails
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home).join(".local").join("share")
+    // Platform data directory, with a fallback if it can't be determined
+    let base_dir = env.data_dir().unwrap_or_else(|| {
+        let home = env.home_dir().unwrap_or_else(|| PathBuf::from("."));
+        home.join(".local").join("share")
     });
 
     base_dir.join("claudia-statusline")
 }
 
+/// Free space available to the current user on the filesystem holding `path`, in MB.
+///
+/// Missing paths are resolved to their nearest existing ancestor. Returns None
+/// when it can't be determined (including on non-Unix platforms).
+pub fn free_space_mb(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|p| p.exists())?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(existing.as_os_str().as_bytes()).ok()?;
+        let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: `c_path` is NUL-terminated and `stat` is only read after statvfs succeeds
+        let stat = unsafe {
+            if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                return None;
+            }
+            stat.assume_init()
+        };
+        #[allow(clippy::useless_conversion)] // Field widths differ between platforms
+        let bytes = u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize));
+        Some(bytes / (1024 * 1024))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = existing;
+        None
+    }
+}
+
+/// Sizes of a SQLite database file and its write-ahead log (`<db>-wal`), in bytes.
+///
+/// Missing files count as 0.
+pub fn database_file_sizes(db_path: &Path) -> (u64, u64) {
+    let size = |path: &Path| std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let mut wal_path = db_path.as_os_str().to_owned();
+    wal_path.push("-wal");
+    (size(db_path), size(Path::new(&wal_path)))
+}
+
 /// Gets the application config directory using XDG Base Directory specification.
 ///
-/// Returns `~/.config/claudia-statusline/` on Unix-like systems.
+/// Returns `~/.config/claudia-statusline/` on Unix-like systems, or the
+/// directory in `STATUSLINE_CONFIG_DIR` (set by `--config <DIR>`) when present.
 ///
 /// # Example
 ///
@@ -49,21 +103,154 @@ pub fn get_data_dir() -> PathBuf {
 /// let config_file = config_dir.join("config.toml");
 /// ```
 pub fn get_config_dir() -> PathBuf {
+    config_dir_in(&*environment::current())
+}
+
+/// [`get_config_dir`] resolved against a given environment.
+pub fn config_dir_in(env: &dyn Environment) -> PathBuf {
+    // Explicit override from --config <DIR>, used as-is
+    if let Some(dir) = env.var("STATUSLINE_CONFIG_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+
     // Check XDG_CONFIG_HOME environment variable first (for testing and user overrides)
-    if let Ok(xdg_config_home) = std::env::var("XDG_CONFIG_HOME") {
+    if let Some(xdg_config_home) = env.var("XDG_CONFIG_HOME") {
         return PathBuf::from(xdg_config_home).join("claudia-statusline");
     }
 
-    // Use dirs crate for proper XDG handling
-    let base_dir = dirs::config_dir().unwrap_or_else(|| {
-        // Fallback if dirs crate fails
-        let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home).join(".config")
+    // Platform config directory, with a fallback if it can't be determined
+    let base_dir = env.config_dir().unwrap_or_else(|| {
+        let home = env.home_dir().unwrap_or_else(|| PathBuf::from("."));
+        home.join(".config")
     });
 
     base_dir.join("claudia-statusline")
 }
 
+/// Gets the path of the stats database.
+///
+/// Uses `database.path` from the config: absolute paths are used as-is (e.g. a
+/// team-wide database on a shared host), relative paths are resolved against
+/// the data directory. Defaults to `<data dir>/stats.db`.
+///
+/// When a non-default Claude Code profile is active (see [`get_profile`]), the
+/// file name is namespaced with it, e.g. `stats-work.db`.
+pub fn get_database_path() -> PathBuf {
+    let configured = &crate::config::get_config().database.path;
+    let path = PathBuf::from(if configured.is_empty() {
+        "stats.db"
+    } else {
+        configured
+    });
+    let path = if path.is_absolute() {
+        path
+    } else {
+        get_data_dir().join(path)
+    };
+    match get_profile() {
+        Some(profile) => namespaced_path(&path, &profile),
+        None => path,
+    }
+}
+
+/// Sets the Claude Code profile reported in the input payload, if any.
+///
+/// It takes precedence over `CLAUDE_CONFIG_DIR` and applies to the calling
+/// thread, within the current [`environment::scoped`] scope.
+pub fn set_profile(profile: Option<&str>) {
+    environment::set_profile_override(profile.and_then(sanitize_profile));
+}
+
+/// Gets the active Claude Code profile used to namespace stats.
+///
+/// The profile comes from the input payload's `profile` field, falling back to
+/// the `CLAUDE_CONFIG_DIR` directory name. Returns `None` for the default
+/// profile (`~/.claude`) or when `database.namespace_by_profile` is disabled.
+pub fn get_profile() -> Option<String> {
+    if !crate::config::get_config().database.namespace_by_profile {
+        return None;
+    }
+    environment::profile_override().or_else(|| {
+        let env = environment::current();
+        env.var("CLAUDE_CONFIG_DIR")
+            .filter(|dir| !dir.is_empty())
+            .and_then(|dir| profile_from_config_dir(Path::new(&dir), env.home_dir()))
+    })
+}
+
+/// Derives a profile name from a Claude Code config directory.
+///
+/// `~/.claude` is the default profile (`None`); `~/.claude-work` becomes
+/// `work`, and `~/work/.claude` uses the parent directory name.
+pub fn profile_from_config_dir(dir: &Path, home: Option<PathBuf>) -> Option<String> {
+    if home.is_some_and(|home| dir == home.join(".claude")) {
+        return None;
+    }
+    let name = dir.file_name()?.to_str()?;
+    match sanitize_profile(name) {
+        Some(profile) => Some(profile),
+        // A plain `.claude` directory outside $HOME is named after its parent
+        None => sanitize_profile(dir.parent()?.file_name()?.to_str()?),
+    }
+}
+
+/// Normalizes a profile name for use in file names (`None` for the default profile).
+fn sanitize_profile(name: &str) -> Option<String> {
+    let name = name.trim_start_matches('.').to_lowercase();
+    let name = name
+        .strip_prefix("claude-")
+        .or_else(|| name.strip_prefix("claude_"))
+        .unwrap_or(&name);
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let name = name.trim_matches('-');
+    if name.is_empty() || name == "claude" || name == "default" {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Claude Code config directory: `CLAUDE_CONFIG_DIR`, else `~/.claude`.
+pub fn claude_config_dir() -> Option<PathBuf> {
+    let env = environment::current();
+    env.var("CLAUDE_CONFIG_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env.home_dir().map(|home| home.join(".claude")))
+}
+
+/// Inserts a profile name before the extension: `stats.db` -> `stats-work.db`.
+pub fn namespaced_path(path: &Path, profile: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, profile, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, profile),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Gets the current OS username (`USER` or `USERNAME`), used to attribute stats
+/// in shared databases.
+pub fn get_username() -> String {
+    let env = environment::current();
+    env.var("USER")
+        .or_else(|| env.var("USERNAME"))
+        .filter(|u| !u.is_empty())
+        .unwrap_or_else(|| "unknown-user".to_string())
+}
+
 /// Gets the current timestamp in ISO 8601 format.
 ///
 /// # Example
@@ -75,7 +262,7 @@ pub fn get_config_dir() -> PathBuf {
 /// assert!(timestamp.contains("T")); // ISO 8601 format
 /// ```
 pub fn current_timestamp() -> String {
-    Local::now().to_rfc3339()
+    environment::now_local().to_rfc3339()
 }
 
 /// Gets the current date in YYYY-MM-DD format.
@@ -89,7 +276,7 @@ pub fn current_timestamp() -> String {
 /// assert_eq!(date.len(), 10); // YYYY-MM-DD
 /// ```
 pub fn current_date() -> String {
-    Local::now().format("%Y-%m-%d").to_string()
+    environment::now_local().format("%Y-%m-%d").to_string()
 }
 
 /// Gets the current month in YYYY-MM format.
@@ -103,7 +290,7 @@ pub fn current_date() -> String {
 /// assert_eq!(month.len(), 7); // YYYY-MM
 /// ```
 pub fn current_month() -> String {
-    Local::now().format("%Y-%m").to_string()
+    environment::now_local().format("%Y-%m").to_string()
 }
 
 /// Validates a path for security issues.
@@ -155,8 +342,6 @@ pub fn validate_path_security(path: &str) -> Result<PathBuf> {
 /// assert_eq!(device_id.len(), 16); // First 64 bits of SHA-256 in hex
 /// ```
 pub fn get_device_id() -> String {
-    use std::env;
-
     // Get hostname (fallback to "unknown-host" if unavailable)
     let hostname = hostname::get()
         .ok()
@@ -164,9 +349,7 @@ pub fn get_device_id() -> String {
         .unwrap_or_else(|| "unknown-host".to_string());
 
     // Get username (fallback to "unknown-user" if unavailable)
-    let username = env::var("USER")
-        .or_else(|_| env::var("USERNAME"))
-        .unwrap_or_else(|_| "unknown-user".to_string());
+    let username = get_username();
 
     // Create a stable SHA-256 hash of hostname + username
     let mut hasher = Sha256::new();
@@ -182,6 +365,57 @@ pub fn get_device_id() -> String {
     )
 }
 
+thread_local! {
+    /// Whether any segment of the current render failed and was omitted.
+    static DEGRADED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Records that a component failed and its output was omitted or defaulted.
+///
+/// The statusline shows a small `!` marker when this has happened during a render,
+/// so failures are visible instead of silently blanking segments.
+pub fn mark_degraded(component: &str, reason: &str) {
+    log::debug!("{} unavailable, degrading output: {}", component, reason);
+    DEGRADED.with(|d| d.set(true));
+}
+
+/// Returns true if any component failed during the current render.
+pub fn is_degraded() -> bool {
+    DEGRADED.with(|d| d.get())
+}
+
+/// Clears the degraded flag (for callers that render more than once per process).
+pub fn reset_degraded() {
+    DEGRADED.with(|d| d.set(false));
+}
+
+/// Runs `f`, returning `fallback` and marking the render degraded if it panics.
+///
+/// # Example
+///
+/// ```rust
+/// use statusline::common::{guarded, is_degraded};
+///
+/// let value = guarded("example", 0, || 42);
+/// assert_eq!(value, 42);
+/// assert!(!is_degraded());
+/// ```
+pub fn guarded<T>(component: &str, fallback: T, f: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(payload) => {
+            let reason = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panic".to_string());
+            log::warn!("{} panicked, omitting it: {}", component, reason);
+            mark_degraded(component, &reason);
+            fallback
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,6 +427,36 @@ mod tests {
         assert!(dir.to_string_lossy().contains("claudia-statusline"));
     }
 
+    #[test]
+    fn test_profile_from_config_dir() {
+        let home = Some(PathBuf::from("/home/u"));
+        let profile = |dir: &str| profile_from_config_dir(Path::new(dir), home.clone());
+
+        assert_eq!(profile("/home/u/.claude"), None);
+        assert_eq!(profile("/home/u/.claude-work"), Some("work".to_string()));
+        assert_eq!(
+            profile("/home/u/.claude_Personal"),
+            Some("personal".to_string())
+        );
+        assert_eq!(
+            profile("/srv/client a/.claude"),
+            Some("client-a".to_string())
+        );
+        assert_eq!(profile("/home/u/profiles/team"), Some("team".to_string()));
+    }
+
+    #[test]
+    fn test_namespaced_path() {
+        assert_eq!(
+            namespaced_path(Path::new("/data/stats.db"), "work"),
+            PathBuf::from("/data/stats-work.db")
+        );
+        assert_eq!(
+            namespaced_path(Path::new("/data/stats"), "work"),
+            PathBuf::from("/data/stats-work")
+        );
+    }
+
     #[test]
     fn test_get_config_dir() {
         let config_dir = get_config_dir();
@@ -261,4 +525,18 @@ mod tests {
         let device_id2 = get_device_id();
         assert_eq!(device_id, device_id2);
     }
+
+    #[test]
+    fn test_guarded_degrades_on_panic() {
+        reset_degraded();
+        assert_eq!(guarded("ok", 0, || 1), 1);
+        assert!(!is_degraded());
+
+        let value = guarded("boom", 7, || -> i32 { panic!("segment failed") });
+        assert_eq!(value, 7);
+        assert!(is_degraded());
+
+        reset_degraded();
+        assert!(!is_degraded());
+    }
 }
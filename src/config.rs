@@ -1,7 +1,7 @@
 use crate::error::{Result, StatuslineError};
 use log::warn;
 use serde::{Deserialize, Serialize};
-use std::env;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -30,9 +30,21 @@ pub struct Config {
     /// Git configuration
     pub git: GitConfig,
 
+    /// Update availability check (opt-in)
+    pub update: UpdateConfig,
+
     /// Sync configuration (optional cloud sync)
     #[cfg(feature = "turso-sync")]
     pub sync: SyncConfig,
+
+    /// OpenTelemetry span export (optional)
+    #[cfg(feature = "otel")]
+    pub telemetry: TelemetryConfig,
+
+    /// Named overrides selected with `STATUSLINE_VARIANT` (or `--variant`), each
+    /// holding any subset of the sections above, e.g. `[variants.minimal.display]`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub variants: BTreeMap<String, toml::Table>,
 }
 
 /// Display-related configuration
@@ -58,6 +70,18 @@ pub struct DisplayConfig {
     /// Show current directory path
     pub show_directory: bool,
 
+    /// Marker after the directory when it isn't writable by you ("" = off)
+    pub readonly_marker: String,
+
+    /// Directory to show: "cwd" (Claude Code's current directory) or
+    /// "project" (the project root the session was started in)
+    pub path_style: String,
+
+    /// Directories (and everything below them) where only the path is shown,
+    /// skipping git, transcript and database work, e.g. slow network mounts.
+    /// A `.statuslineignore` file in a directory or its parents does the same
+    pub ignore_dirs: Vec<String>,
+
     /// Show git branch and status
     pub show_git: bool,
 
@@ -73,11 +97,162 @@ pub struct DisplayConfig {
     /// Show lines added/removed
     pub show_lines_changed: bool,
 
+    /// Where lines added/removed come from: "cost" (Claude Code's cumulative
+    /// counts), "git" (uncommitted diff from `git diff HEAD --numstat`) or "both"
+    /// (side by side)
+    pub lines_source: String,
+
+    /// Show lines changed within the last `lines_delta_window_minutes` (e.g., "+37 in 5m")
+    pub show_lines_delta: bool,
+
+    /// Window of the recent lines-changed delta, in minutes
+    pub lines_delta_window_minutes: u64,
+
+    /// Show how many distinct files Claude edited in the session (e.g., "12 files")
+    pub show_files_edited: bool,
+
+    /// Show how many other sessions are active and their combined burn rate
+    /// (e.g., "+2 others $8.40/hr")
+    pub show_other_sessions: bool,
+
+    /// Sessions updated within this many minutes count as active
+    pub active_session_minutes: u64,
+
     /// Show session cost and burn rate
     pub show_cost: bool,
 
+    /// Show the note set with `statusline session note`
+    pub show_session_note: bool,
+
     /// Show token counts in context bar (e.g., "179k/1000k")
     pub show_context_tokens: bool,
+
+    /// Context bar fill: "solid" (one severity color) or "segmented" (cache-read,
+    /// input and output tokens in their own colors)
+    pub context_bar_style: String,
+
+    /// Show a sparkline of recent context usage next to the bar (e.g., "▂▃▅▇")
+    pub show_context_sparkline: bool,
+
+    /// Show the session cost's percentile among past sessions (e.g., "p92")
+    pub show_cost_percentile: bool,
+
+    /// Show the first 8 characters of the session ID (for correlating with DB rows)
+    pub show_session_id: bool,
+
+    /// Show transcript size and message count (e.g., "1.2MB/3.4k msgs")
+    pub show_transcript_size: bool,
+
+    /// Show active (non-idle) time next to the duration (e.g., "act 42m / 2h10m")
+    pub show_active_time: bool,
+
+    /// Show `break_reminder_text` after this many minutes of continuous activity
+    /// (no gap over `transcript.idle_threshold_secs`); 0 disables the reminder
+    pub break_reminder_minutes: u64,
+
+    /// Text of the break reminder (message or emoji)
+    pub break_reminder_text: String,
+
+    /// Number of recent context samples kept per session for the sparkline
+    pub sparkline_samples: usize,
+
+    /// Append severity markers to context and cost values so severity is never
+    /// conveyed by color alone
+    pub severity_markers: bool,
+
+    /// Markers for the caution, warning and critical levels
+    pub severity_symbols: Vec<String>,
+
+    /// Render a minimal line for narrow panes: abbreviated directory, branch,
+    /// context percentage and cost, without brackets, bars or day totals
+    pub compact: bool,
+
+    /// Text used for the literal words in the statusline (`[display.labels]`)
+    pub labels: DisplayLabels,
+
+    /// Duration style: "compact" (1h5m), "clock" (01:05) or "verbose" (1 hr 5 min),
+    /// used by the duration segment and stats reports
+    pub duration_format: String,
+
+    /// Icons shown before the model abbreviation, by model family ("Opus") or
+    /// family and version ("Sonnet 4.5", which takes precedence)
+    ///
+    /// Example in config.toml:
+    /// ```toml
+    /// [display.model_icons]
+    /// Opus = "🏛"
+    /// Sonnet = "✨"
+    /// ```
+    #[serde(skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub model_icons: std::collections::HashMap<String, String>,
+}
+
+/// Overridable label strings, e.g. to translate the statusline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayLabels {
+    /// Prefix of the daily cost total (e.g., "day: $12.50")
+    pub day: String,
+
+    /// Suffix of the burn rate (e.g., "$3.20/hr")
+    pub per_hour: String,
+
+    /// Shown instead of the context bar while compaction runs
+    pub compacting: String,
+
+    /// Uncommitted diff with no changes (`lines_source = "both"`)
+    pub clean: String,
+
+    /// Marks git diff counts (`lines_source = "both"` or "git")
+    pub git: String,
+
+    /// Prefix of the active time (e.g., "act 42m / 2h10m")
+    pub active: String,
+
+    /// Daily cost alert (e.g., "⚠ day > $20.00")
+    pub alert_daily: String,
+
+    /// Session cost alert (e.g., "⚠ session > $10.00")
+    pub alert_session: String,
+
+    /// Unit of the transcript message count (e.g., "3.4k msgs")
+    pub messages: String,
+
+    /// Shown after the directory when stdin is empty or not valid JSON
+    pub no_data: String,
+
+    /// Low disk space warning, stats not being saved (e.g., "⚠ disk 42MB")
+    pub disk_low: String,
+
+    /// Joins the recent lines-changed delta and its window (e.g., "+37 in 5m")
+    pub lines_in: String,
+
+    /// Unit of the edited file count (e.g., "12 files")
+    pub files: String,
+
+    /// Other active sessions (e.g., "+2 others $8.40/hr")
+    pub others: String,
+}
+
+impl Default for DisplayLabels {
+    fn default() -> Self {
+        DisplayLabels {
+            day: "day:".to_string(),
+            per_hour: "/hr".to_string(),
+            compacting: "Compacting...".to_string(),
+            clean: "clean".to_string(),
+            git: "git".to_string(),
+            active: "act".to_string(),
+            alert_daily: "day >".to_string(),
+            alert_session: "session >".to_string(),
+            messages: "msgs".to_string(),
+            no_data: "no data".to_string(),
+            disk_low: "disk".to_string(),
+            lines_in: "in".to_string(),
+            files: "files".to_string(),
+            others: "others".to_string(),
+        }
+    }
 }
 
 /// Context window configuration
@@ -122,6 +297,21 @@ pub struct ContextConfig {
     #[serde(skip_serializing_if = "std::collections::HashMap::is_empty")]
     pub model_windows: std::collections::HashMap<String, usize>,
 
+    /// Per-model buffer size and warning threshold overrides
+    ///
+    /// Key is the model display name, as in `model_windows`. Models and plans
+    /// reserve different response buffers, so unset fields fall back to the
+    /// global `buffer_size` and `auto_compact_threshold`.
+    ///
+    /// Example in config.toml:
+    /// ```toml
+    /// [context.model_overrides."Claude Opus 4.5"]
+    /// buffer_size = 30000
+    /// auto_compact_threshold = 80.0
+    /// ```
+    #[serde(skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub model_overrides: std::collections::HashMap<String, ModelContextOverride>,
+
     /// Enable adaptive learning of context window sizes from usage patterns
     ///
     /// **Default: false (disabled)**
@@ -158,6 +348,25 @@ pub struct ContextConfig {
     /// Only applies when `adaptive_learning = true`.
     pub learning_confidence_threshold: f64,
 
+    /// Which heuristics build confidence in learned windows
+    ///
+    /// **Default: "combined"**
+    ///
+    /// - **"combined"**: ceiling patterns and compaction drops both count
+    /// - **"compaction"**: only detected compactions count (conservative)
+    /// - **"ceiling"**: only repeated ceilings count (aggressive)
+    pub learning_strategy: String,
+
+    /// Thresholds used by the learning heuristics (unset fields keep their defaults)
+    ///
+    /// Example in config.toml:
+    /// ```toml
+    /// [context.learning_params]
+    /// compaction_drop_threshold = 0.2
+    /// ceiling_variance_threshold = 0.01
+    /// ```
+    pub learning_params: LearningParams,
+
     /// Claude Code buffer reserved for responses (not available for conversation)
     ///
     /// **Default: 40000 tokens (40K)**
@@ -221,6 +430,37 @@ pub struct ContextConfig {
     pub percentage_mode: String,
 }
 
+/// Tunable thresholds for adaptive context learning
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LearningParams {
+    /// Minimum tokens before a drop can count as a compaction (default 150000)
+    pub min_compaction_tokens: Option<usize>,
+
+    /// Minimum relative drop for a compaction, 0.0-1.0 (default 0.10)
+    pub compaction_drop_threshold: Option<f64>,
+
+    /// Maximum distance from the observed max for a ceiling hit, 0.0-1.0 (default 0.02)
+    pub ceiling_variance_threshold: Option<f64>,
+
+    /// Minimum fraction of the observed max a compaction must start from (default 0.95)
+    pub compaction_proximity_threshold: Option<f64>,
+
+    /// Minimum tokens for a model's first compaction (default 190000)
+    pub first_compaction_min_tokens: Option<usize>,
+}
+
+/// Context settings overridden for a single model
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelContextOverride {
+    /// Response buffer in tokens (replaces `context.buffer_size`)
+    pub buffer_size: Option<usize>,
+
+    /// Warning threshold percentage, used as-is in both percentage modes
+    pub auto_compact_threshold: Option<f64>,
+}
+
 /// Cost threshold configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -230,6 +470,46 @@ pub struct CostConfig {
 
     /// Medium cost threshold (below this is yellow, above is red)
     pub medium_threshold: f64,
+
+    /// Decimal places for displayed costs (0-4)
+    pub decimals: usize,
+
+    /// Rounding behavior: "nearest", "up" or "down"
+    pub rounding: String,
+
+    /// Show tiny non-zero costs as "<$0.01" instead of "$0.00"
+    pub show_less_than: bool,
+
+    /// Hide the session cost when it is below this amount (0 = always show); the
+    /// day total and burn rate still render
+    pub hide_below: f64,
+
+    /// Raise an alert when the daily total exceeds this amount (0 = off)
+    pub alert_daily: f64,
+
+    /// Raise an alert when a session's cost exceeds this amount (0 = off)
+    pub alert_session: f64,
+
+    /// Minimum minutes between deliveries of the same alert across invocations
+    pub alert_cooldown_minutes: u64,
+
+    /// Shell command run (detached) to deliver an alert, e.g. a desktop
+    /// notification or webhook call; details are passed in `STATUSLINE_ALERT_*`
+    /// environment variables (empty = no delivery)
+    pub alert_command: String,
+
+    /// Mark the day total with ▲ when it reaches this multiple of the trailing
+    /// 7-day average, prorated to the time of day (0 = off, the default)
+    pub anomaly_factor: f64,
+
+    /// Spending budget for `budget_period`, shown as a mini progress bar (0 = off)
+    pub budget: f64,
+
+    /// Budget period: "month" (calendar month) or "week" (starting Monday)
+    pub budget_period: String,
+
+    /// Width of the budget progress bar in cells
+    pub budget_bar_width: usize,
 }
 
 /// Database configuration
@@ -242,12 +522,27 @@ pub struct DatabaseConfig {
     /// Busy timeout in milliseconds
     pub busy_timeout_ms: u32,
 
-    /// Path to database file (relative to data directory)
+    /// Path to database file (relative to data directory, or absolute)
     pub path: String,
 
+    /// Keep separate stats per Claude Code profile (`CLAUDE_CONFIG_DIR`)
+    pub namespace_by_profile: bool,
+
     /// Whether to maintain JSON backup alongside SQLite (default: true for compatibility)
     pub json_backup: bool,
 
+    /// Skip stats writes while the stats database's filesystem has less free
+    /// space than this, in MB (0 = never skip)
+    pub min_free_space_mb: u64,
+
+    /// `db-maintain` closes sessions idle for longer than this many hours, so
+    /// sessions that never got a SessionEnd hook stop counting as open (0 = never)
+    pub session_idle_hours: u32,
+
+    /// Prune and vacuum automatically once stats.db and its WAL together exceed
+    /// this size, in MB (0 = no limit)
+    pub max_size_mb: u64,
+
     /// Retention period for session data in days (0 = keep forever)
     pub retention_days_sessions: Option<u32>,
 
@@ -256,6 +551,18 @@ pub struct DatabaseConfig {
 
     /// Retention period for monthly stats in days (0 = keep forever)
     pub retention_days_monthly: Option<u32>,
+
+    /// Retention period for learned context windows and context samples in
+    /// days (0 = keep forever)
+    pub retention_days_learning: Option<u32>,
+
+    /// Maximum context samples kept per model (0 = unlimited)
+    pub context_samples_per_model: Option<u32>,
+
+    /// SQLCipher key for stats.db (requires the `sqlcipher` feature).
+    /// STATUSLINE_DB_KEY takes precedence; if neither is set, the OS keyring
+    /// entry `claudia-statusline` / `stats-db` is used.
+    pub encryption_key: Option<String>,
 }
 
 /// Retry configuration
@@ -273,6 +580,10 @@ pub struct RetryConfig {
 
     /// Network operation retry configuration
     pub network_ops: RetrySettings,
+
+    /// Backoff between background sync pushes (requires turso-sync feature).
+    /// Failed pushes keep retrying at max_delay_ms, so max_attempts is unused.
+    pub sync_ops: RetrySettings,
 }
 
 /// Individual retry settings
@@ -298,6 +609,10 @@ pub struct RetrySettings {
 pub struct TranscriptConfig {
     /// Number of lines to keep in memory (circular buffer size)
     pub buffer_lines: usize,
+
+    /// Gaps between transcript entries longer than this (seconds) are idle time,
+    /// excluded from a session's active time
+    pub idle_threshold_secs: u64,
 }
 
 /// Git configuration
@@ -306,6 +621,90 @@ pub struct TranscriptConfig {
 pub struct GitConfig {
     /// Timeout for git operations in milliseconds
     pub timeout_ms: u32,
+
+    /// Branch styling rules, first match wins (e.g., color `fix/*` branches red)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub branch_rules: Vec<BranchRule>,
+
+    /// Show which identity (`user.email`) commits in this repository will use
+    pub show_identity: bool,
+
+    /// Show the most recent tag reachable from HEAD, e.g. `v2.3.1+4` four commits after it
+    pub show_tag: bool,
+
+    /// Mark uncommitted changes whose oldest file was last written this many
+    /// minutes ago (0 disables the check)
+    pub uncommitted_warn_minutes: u64,
+
+    /// Marker shown before the age of uncommitted work (e.g., "💾45m")
+    pub uncommitted_marker: String,
+
+    /// Identity labels by email regex, first match wins (e.g., `@acme\.com$` → "work")
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub identities: Vec<IdentityRule>,
+
+    /// `git status` options by repository path regex, first match wins (for monorepos)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub repos: Vec<RepoRule>,
+}
+
+/// `git status` options for repositories whose path matches a regex
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepoRule {
+    /// Regex matched against the repository's absolute path (e.g., "/monorepo$")
+    pub pattern: String,
+
+    /// Pass `--no-optional-locks`. Turning it off lets git refresh the index
+    /// while reading status, which speeds up later runs on huge working trees.
+    /// Unset: on, except in repositories that use fsmonitor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_optional_locks: Option<bool>,
+
+    /// Scan for untracked files (false passes `-uno` and hides the `?n` count)
+    pub untracked: bool,
+
+    /// Stop counting after this many changed files (0 = unlimited)
+    pub max_status_entries: usize,
+}
+
+impl Default for RepoRule {
+    fn default() -> Self {
+        RepoRule {
+            pattern: String::new(),
+            no_optional_locks: None,
+            untracked: true,
+            max_status_entries: 0,
+        }
+    }
+}
+
+/// Labels a commit identity whose email matches a regex
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdentityRule {
+    /// Regex matched against `user.email` (e.g., `@acme\.com$`)
+    pub pattern: String,
+
+    /// Short label shown in the git segment (e.g., "work")
+    pub label: String,
+
+    /// Color name, hex code or ANSI code for the label
+    pub color: Option<String>,
+}
+
+/// Styles branches whose name matches a regex
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BranchRule {
+    /// Regex matched against the branch name (e.g., "^feat/")
+    pub pattern: String,
+
+    /// Color name, hex code or ANSI code for the branch
+    pub color: Option<String>,
+
+    /// Text shown before the branch name (e.g., "✨")
+    pub prefix: Option<String>,
 }
 
 /// Sync configuration for cloud synchronization
@@ -319,13 +718,19 @@ pub struct SyncConfig {
     /// Sync provider (currently only "turso" is supported)
     pub provider: String,
 
-    /// Sync interval in seconds
+    /// Minimum seconds between background pushes
     pub sync_interval_seconds: u64,
 
     /// Soft quota warning threshold (0.0 - 1.0)
     /// Warns when usage exceeds this fraction of quota
     pub soft_quota_fraction: f64,
 
+    /// Plan storage limit in MB (Turso free tier: 9 GB)
+    pub quota_storage_mb: u64,
+
+    /// Plan limit on rows stored remotely (0 = no row limit)
+    pub quota_rows: u64,
+
     /// Turso-specific configuration
     pub turso: TursoConfig,
 }
@@ -338,10 +743,40 @@ pub struct TursoConfig {
     /// Turso database URL (e.g., "libsql://your-db.turso.io")
     pub database_url: String,
 
-    /// Authentication token (or environment variable reference like "${TURSO_AUTH_TOKEN}")
+    /// Authentication token, environment variable reference like "${TURSO_AUTH_TOKEN}",
+    /// or "keyring" to read the token stored by `statusline sync login`
     pub auth_token: String,
 }
 
+/// Update availability check configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// Whether to check GitHub for newer releases (never contacts the network when false)
+    pub check: bool,
+
+    /// Minimum hours between checks
+    pub interval_hours: u64,
+}
+
+/// OpenTelemetry span export configuration
+#[cfg(feature = "otel")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Whether to export a span per render
+    pub enabled: bool,
+
+    /// OTLP/HTTP traces endpoint (JSON encoding, plain http only)
+    pub endpoint: String,
+
+    /// Value of the `service.name` resource attribute
+    pub service_name: String,
+
+    /// Total time budget for one export (lookup, connect, send, reply) in milliseconds
+    pub timeout_ms: u64,
+}
+
 // Default implementations
 // Default is derived above
 
@@ -355,14 +790,50 @@ impl Default for DisplayConfig {
             theme: "dark".to_string(),
             // All components visible by default (backward compatible)
             show_directory: true,
+            readonly_marker: "🔒".to_string(),
+            path_style: "cwd".to_string(),
+            ignore_dirs: Vec::new(),
             show_git: true,
             show_context: true,
             show_model: true,
             show_duration: true,
             show_lines_changed: true,
             show_cost: true,
+            // Reads the stats database on every render, opt-in
+            show_session_note: false,
+            lines_source: "cost".to_string(),
+            // Recent change velocity opt-in (needs a session_id to track counts)
+            show_lines_delta: false,
+            lines_delta_window_minutes: 5,
+            // Needs the stats database to keep the per-session file set, opt-in
+            show_files_edited: false,
+            // Concurrent sessions opt-in (reads the stats database)
+            show_other_sessions: false,
+            active_session_minutes: 5,
             // Token counts opt-in (new feature, default off for minimal statusline)
             show_context_tokens: false,
+            context_bar_style: "solid".to_string(),
+            // Sparkline opt-in (needs a session_id to track history)
+            show_context_sparkline: false,
+            sparkline_samples: 10,
+            // Needs session history in the stats database, opt-in
+            show_cost_percentile: false,
+            // Debug aid, opt-in
+            show_session_id: false,
+            // Session weight beyond tokens, opt-in
+            show_transcript_size: false,
+            // Active vs wall-clock time, opt-in
+            show_active_time: false,
+            // Break reminder opt-in
+            break_reminder_minutes: 0,
+            break_reminder_text: "☕ break?".to_string(),
+            // Accessibility markers opt-in
+            severity_markers: false,
+            severity_symbols: vec!["!".to_string(), "!!".to_string(), "!!!".to_string()],
+            compact: false,
+            labels: DisplayLabels::default(),
+            duration_format: "compact".to_string(),
+            model_icons: std::collections::HashMap::new(),
         }
     }
 }
@@ -409,6 +880,22 @@ impl ContextConfig {
             }
         }
     }
+
+    /// Response buffer for a model, honoring `model_overrides`
+    pub fn buffer_size_for(&self, model: Option<&str>) -> usize {
+        model
+            .and_then(|m| self.model_overrides.get(m))
+            .and_then(|o| o.buffer_size)
+            .unwrap_or(self.buffer_size)
+    }
+
+    /// Warning threshold for a model: its override, else the mode-aware default
+    pub fn effective_threshold_for(&self, model: Option<&str>) -> f64 {
+        model
+            .and_then(|m| self.model_overrides.get(m))
+            .and_then(|o| o.auto_compact_threshold)
+            .unwrap_or_else(|| self.get_effective_threshold())
+    }
 }
 
 impl Default for ContextConfig {
@@ -416,9 +903,12 @@ impl Default for ContextConfig {
         ContextConfig {
             window_size: 200_000, // Default for modern Claude models (Sonnet 3.5+, Opus 3.5+, Sonnet 4.5+)
             model_windows: std::collections::HashMap::new(),
+            model_overrides: std::collections::HashMap::new(),
             adaptive_learning: false, // Disabled by default (experimental feature)
             learning_confidence_threshold: 0.7, // Require 70% confidence before using learned values
-            buffer_size: 40_000,                // Claude Code reserves ~40-45K tokens for responses
+            learning_strategy: "combined".to_string(),
+            learning_params: LearningParams::default(),
+            buffer_size: 40_000, // Claude Code reserves ~40-45K tokens for responses
             auto_compact_threshold: 75.0, // Mode-aware: 75% for "full", auto-adjusted to 94% for "working"
             percentage_mode: default_percentage_mode(), // Default to "full" for user expectations
         }
@@ -430,6 +920,18 @@ impl Default for CostConfig {
         CostConfig {
             low_threshold: 5.0,
             medium_threshold: 20.0,
+            decimals: 2,
+            rounding: "nearest".to_string(),
+            show_less_than: false,
+            hide_below: 0.0,
+            alert_daily: 0.0,
+            alert_session: 0.0,
+            alert_cooldown_minutes: 60,
+            alert_command: String::new(),
+            anomaly_factor: 0.0,
+            budget: 0.0,
+            budget_period: "month".to_string(),
+            budget_bar_width: 5,
         }
     }
 }
@@ -440,10 +942,17 @@ impl Default for DatabaseConfig {
             max_connections: 5,
             busy_timeout_ms: 10000,
             path: "stats.db".to_string(),
+            namespace_by_profile: false,
             json_backup: true, // Default to true for backward compatibility
+            min_free_space_mb: 100,
+            max_size_mb: 0,
+            session_idle_hours: 24,
             retention_days_sessions: None, // None means use default (90 days)
-            retention_days_daily: None, // None means use default (365 days)
-            retention_days_monthly: None, // None means use default (0 = forever)
+            retention_days_daily: None,    // None means use default (365 days)
+            retention_days_monthly: None,  // None means use default (0 = forever)
+            retention_days_learning: None, // None means use default (180 days)
+            context_samples_per_model: None, // None means use default (500)
+            encryption_key: None,
         }
     }
 }
@@ -475,6 +984,12 @@ impl Default for RetryConfig {
                 max_delay_ms: 1000,
                 backoff_factor: 2.0,
             },
+            sync_ops: RetrySettings {
+                max_attempts: 0,
+                initial_delay_ms: 30_000, // 30s after the first failure
+                max_delay_ms: 3_600_000,  // At most hourly while offline
+                backoff_factor: 2.0,
+            },
         }
     }
 }
@@ -492,7 +1007,10 @@ impl Default for RetrySettings {
 
 impl Default for TranscriptConfig {
     fn default() -> Self {
-        TranscriptConfig { buffer_lines: 50 }
+        TranscriptConfig {
+            buffer_lines: 50,
+            idle_threshold_secs: 300,
+        }
     }
 }
 
@@ -500,6 +1018,13 @@ impl Default for GitConfig {
     fn default() -> Self {
         GitConfig {
             timeout_ms: 200, // 200ms default timeout for git operations
+            branch_rules: Vec::new(),
+            show_identity: false,
+            show_tag: false,
+            uncommitted_warn_minutes: 0,
+            uncommitted_marker: "💾".to_string(),
+            identities: Vec::new(),
+            repos: Vec::new(),
         }
     }
 }
@@ -512,11 +1037,34 @@ impl Default for SyncConfig {
             provider: "turso".to_string(),
             sync_interval_seconds: 60,
             soft_quota_fraction: 0.75, // Warn at 75% of quota
+            quota_storage_mb: 9 * 1024,
+            quota_rows: 0,
             turso: TursoConfig::default(),
         }
     }
 }
 
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        UpdateConfig {
+            check: false,
+            interval_hours: 24, // At most one request per day
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            enabled: false,
+            endpoint: "http://localhost:4318/v1/traces".to_string(),
+            service_name: "claudia-statusline".to_string(),
+            timeout_ms: 200, // Keep exports from slowing down the statusline
+        }
+    }
+}
+
 // From trait implementations for better ergonomics
 impl From<PathBuf> for Config {
     fn from(path: PathBuf) -> Self {
@@ -555,12 +1103,35 @@ impl Config {
         }
     }
 
-    /// Load configuration from a specific file
+    /// Load configuration from a specific file, expanding `${VAR}` references
+    /// and applying the active variant
     pub fn load_from_file(path: &Path) -> Result<Self> {
+        Self::read_file(path, true)
+    }
+
+    /// Load configuration as written, without expanding `${VAR}` references or
+    /// applying a variant, for rewriting the file without baking environment
+    /// values (e.g. secrets) or variant overrides into it
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+    pub fn load_from_file_unexpanded(path: &Path) -> Result<Self> {
+        Self::read_file(path, false)
+    }
+
+    fn read_file(path: &Path, resolve: bool) -> Result<Self> {
         let contents = fs::read_to_string(path)
             .map_err(|e| StatuslineError::Config(format!("Failed to read config file: {}", e)))?;
 
-        let config: Config = toml::from_str(&contents)
+        let mut value: toml::Value = toml::from_str(&contents)
+            .map_err(|e| StatuslineError::Config(format!("Failed to parse config file: {}", e)))?;
+        if resolve {
+            expand_env_vars(&mut value);
+            if let Some(variant) = active_variant() {
+                apply_variant(&mut value, &variant);
+            }
+        }
+
+        let config: Config = value
+            .try_into()
             .map_err(|e| StatuslineError::Config(format!("Failed to parse config file: {}", e)))?;
 
         Ok(config)
@@ -585,11 +1156,13 @@ impl Config {
         Ok(())
     }
 
-    /// Find config file in standard locations
-    fn find_config_file() -> Option<PathBuf> {
+    /// Find config file in standard locations (the file `load` reads, if any)
+    pub fn find_config_file() -> Option<PathBuf> {
+        let env = crate::environment::current();
+
         // Check in order of priority:
         // 1. Environment variable from CLI flag
-        if let Ok(path) = std::env::var("STATUSLINE_CONFIG_PATH") {
+        if let Some(path) = env.var("STATUSLINE_CONFIG_PATH") {
             let path = PathBuf::from(path);
             if path.exists() {
                 return Some(path);
@@ -597,7 +1170,7 @@ impl Config {
         }
 
         // 2. Environment variable
-        if let Ok(path) = std::env::var("STATUSLINE_CONFIG") {
+        if let Some(path) = env.var("STATUSLINE_CONFIG") {
             let path = PathBuf::from(path);
             if path.exists() {
                 return Some(path);
@@ -605,14 +1178,14 @@ impl Config {
         }
 
         // 3. XDG config directory
-        let config_dir = crate::common::get_config_dir();
+        let config_dir = crate::common::config_dir_in(&*env);
         let path = config_dir.join("config.toml");
         if path.exists() {
             return Some(path);
         }
 
         // 4. Home directory
-        if let Some(home_dir) = dirs::home_dir() {
+        if let Some(home_dir) = env.home_dir() {
             let path = home_dir.join(".claudia-statusline.toml");
             if path.exists() {
                 return Some(path);
@@ -647,7 +1220,7 @@ context_caution_threshold = 50.0     # Yellow color above this
 # Theme: "dark" or "light"
 theme = "dark"
 
-# Component visibility toggles (all default to true except show_context_tokens)
+# Component visibility toggles (all default to true except show_session_note and show_context_tokens)
 # show_directory = true
 # show_git = true
 # show_context = true
@@ -655,10 +1228,116 @@ theme = "dark"
 # show_duration = true
 # show_lines_changed = true
 # show_cost = true
+# show_session_note = false  # Note from `statusline session note "..."`, dimmed
+
+# Source of the +/- line counts: "cost" (Claude Code's cumulative counts),
+# "git" (uncommitted diff of tracked files, cached for 10s within git.timeout_ms)
+# or "both" (side by side, e.g. "+500 -9 (git +2 -1)")
+# lines_source = "cost"
+
+# Show how many lines changed recently (e.g., "+37 in 5m"), which highlights
+# bursts of generated code better than the cumulative totals. Counts are kept
+# per session in the stats database (stats.db)
+# show_lines_delta = false
+# lines_delta_window_minutes = 5
+
+# Show how many distinct files Claude edited or wrote in the session
+# (e.g., "12 files"), from Edit/Write tool calls in the transcript. Paths are
+# stored as SHA-256 hashes in the stats database
+# show_files_edited = false
+
+# Show the other Claude Code sessions active right now (updated within
+# active_session_minutes and not ended) and the combined burn rate of all active
+# sessions, including this one (e.g., "+2 others $8.40/hr")
+# show_other_sessions = false
+# active_session_minutes = 5
+
+# Marker after the directory when you can't write to it (read-only checkout or
+# mount), where Claude Code's edits would fail. "" turns it off
+# readonly_marker = "🔒"
+
+# Directory to show: "cwd" (where Claude Code currently is) or "project" (the
+# project root the session started in). Stats are always keyed on the project root
+# path_style = "cwd"
+
+# Directories where only the path is rendered, skipping git, transcript and stats
+# database work (e.g. slow network mounts). Subdirectories match too; "~" and
+# ${VAR} are expanded. An empty `.statuslineignore` file in a directory (or any
+# parent) has the same effect
+# ignore_dirs = ["/mnt/nfs", "~/remote"]
 
 # Show token counts in context bar (e.g., "179k/1000k")
 # show_context_tokens = false
 
+# Context bar fill: "solid" (one color by severity) or "segmented", which splits
+# the filled part into cache-read (gray), input incl. cache writes (cyan) and
+# output (magenta) tokens, showing how much of the context is cached history
+# context_bar_style = "solid"
+
+# Show a sparkline of recent context usage next to the bar (e.g., "▂▃▅▇")
+# Samples are stored per session in the stats database
+# show_context_sparkline = false
+# sparkline_samples = 10  # Number of recent samples to keep and render
+
+# Show where the session cost ranks among your past sessions (e.g., "$4.20 p92":
+# 92% of them were cheaper). Needs 10 sessions in the stats database
+# show_cost_percentile = false
+
+# Show the first 8 characters of the session ID, to match the line with
+# database rows and transcript files when debugging stats
+# show_session_id = false
+
+# Show transcript size and message count (e.g., "1.2MB/3.4k msgs"), measured during
+# the transcript tail read. "~" marks counts estimated for files over 1MB
+# show_transcript_size = false
+
+# Show active time before the wall-clock duration (e.g., "act 42m / 2h10m").
+# Gaps longer than transcript.idle_threshold_secs don't count as active
+# show_active_time = false
+
+# Nudge to take a break after this many minutes of continuous activity; an idle gap
+# longer than transcript.idle_threshold_secs resets the clock (0 = off)
+# break_reminder_minutes = 0
+# break_reminder_text = "☕ break?"
+
+# Accessibility: never convey severity by color alone. Appends a marker to the
+# context percentage (caution/warning/critical) and to costs (medium/high)
+# severity_markers = false
+# severity_symbols = ["!", "!!", "!!!"]  # caution, warning, critical
+
+# Compact mode for narrow panes, e.g. "~/p/x ⌥main 62% $1.2"
+# Keeps directory, branch, context percentage and cost (show_* toggles still apply)
+# compact = false
+
+# Duration style for the duration segment and stats reports:
+# "compact" (1h5m), "clock" (01:05) or "verbose" (1 hr 5 min)
+# duration_format = "compact"
+
+# Icons before the model abbreviation, by family or "Family version" (more specific
+# wins). Emoji or Nerd Font glyphs both work
+# [display.model_icons]
+# Opus = "🏛"
+# Sonnet = "✨"
+# "Sonnet 4.5" = "\uf0e7"   # Nerd Font bolt
+# Haiku = "🍃"
+
+# Literal words in the statusline, e.g. to translate it
+# [display.labels]
+# day = "day:"                # Daily total prefix
+# per_hour = "/hr"            # Burn rate suffix
+# compacting = "Compacting..."
+# clean = "clean"             # No uncommitted changes (lines_source = "both")
+# git = "git"                 # Marks git diff counts
+# active = "act"              # Active time prefix
+# alert_daily = "day >"       # Cost alerts: "⚠ day > $20.00"
+# alert_session = "session >"
+# messages = "msgs"           # Transcript message count unit
+# no_data = "no data"          # After the directory when stdin is empty or not JSON
+# disk_low = "disk"           # Low disk space, stats not saved: "⚠ disk 42MB"
+# lines_in = "in"             # Recent lines changed: "+37 in 5m"
+# files = "files"            # Edited file count unit: "12 files"
+# others = "others"          # Other active sessions: "+2 others $8.40/hr"
+
 [context]
 # Default context window size in tokens (fallback for unknown models)
 # Auto-detection: Sonnet 4.5 (1M context) uses 1M, Sonnet 3.5+/4.5/Opus 3.5+ use 200k
@@ -676,6 +1355,12 @@ window_size = 200000
 # "Claude 3.5 Opus" = 200000
 # "Claude 3 Haiku" = 100000
 
+# Per-model response buffer and warning threshold (optional overrides)
+# Unset fields fall back to the global buffer_size / auto_compact_threshold
+# [context.model_overrides."Claude Opus 4.5"]
+# buffer_size = 30000
+# auto_compact_threshold = 80.0
+
 # Adaptive Learning (Experimental) - DISABLED BY DEFAULT
 # When enabled, the statusline learns actual context window sizes from usage patterns
 # by detecting compaction events and token ceiling observations
@@ -686,28 +1371,105 @@ adaptive_learning = false
 # Confidence increases with more observations (0.7 = 70% confidence)
 learning_confidence_threshold = 0.7
 
+# Which heuristics build confidence: "combined" (default), "compaction"
+# (conservative: only detected compactions count) or "ceiling" (aggressive:
+# repeated token ceilings are enough)
+learning_strategy = "combined"
+
+# Heuristic thresholds (optional; defaults shown)
+# [context.learning_params]
+# min_compaction_tokens = 150000          # Drops below this never count as compactions
+# compaction_drop_threshold = 0.10        # Minimum relative drop for a compaction
+# ceiling_variance_threshold = 0.02       # Within 2% of the max counts as a ceiling hit
+# compaction_proximity_threshold = 0.95   # Compaction must start near the observed max
+# first_compaction_min_tokens = 190000    # First compaction without a known max
+
 [cost]
 # Cost thresholds for color coding
 low_threshold = 5.0      # Green below this
 medium_threshold = 20.0  # Yellow between low and medium, red above
 
+# Cost formatting
+# decimals = 2             # Decimal places (0-4)
+# rounding = "nearest"     # "nearest", "up" or "down"
+# show_less_than = false   # Show tiny costs as "<$0.01" instead of "$0.00"
+
+# Hide the session cost for trivially cheap sessions (0 = always show);
+# the day total and burn rate are still shown
+# hide_below = 0.05
+
+# Cost alerts (0 = off): shown as "⚠ day > $20.00" until `statusline alerts ack`
+# alert_daily = 20.0
+# alert_session = 10.0
+# Command run to deliver an alert (notification, webhook, ...), with
+# STATUSLINE_ALERT_KIND, _SCOPE, _THRESHOLD, _VALUE and _MESSAGE set
+# alert_command = 'notify-send "Claude spend" "$STATUSLINE_ALERT_MESSAGE"'
+# Deliver the same alert at most once per cooldown, however often the
+# statusline runs
+# alert_cooldown_minutes = 60
+
+# Mark the day total with ▲ when today reaches 2× the average of the last 7
+# active days, prorated to the time of day, e.g. a runaway agent loop.
+# Off by default (0); checks the database on every render when enabled
+# anomaly_factor = 2.0
+
+# Budget progress bar after the cost segment, e.g. "[■■■□□] 61%" of a $200 month
+# (0 = off). budget_period is "month" or "week" (Monday to Sunday)
+# budget = 200.0
+# budget_period = "month"
+# budget_bar_width = 5
+
 [database]
 # Database connection settings
 max_connections = 5
 busy_timeout_ms = 10000
-path = "stats.db"  # Relative to data directory
+path = "stats.db"  # Relative to data directory, or absolute for a shared team database
+# e.g. path = "/srv/claude/stats.db" - every user writes to one file, and stats are
+# attributed to the OS username ($USER) so `statusline stats --by-user` can break them down
 json_backup = true  # Maintain JSON backup alongside SQLite (set to false for SQLite-only mode)
 
+# Stop writing stats while the database's disk (see `path`) has less free space
+# than this (MB), so the statusline never helps fill a disk. Shown as "⚠ disk" and
+# reported by `statusline health` (0 = off)
+min_free_space_mb = 100
+
+# Once stats.db plus its WAL grow past this size (MB), old rows are pruned by the
+# retention settings below and free pages reclaimed, at most once an hour.
+# `statusline health` shows the current sizes (0 = no limit)
+max_size_mb = 0
+
+# `db-maintain` closes sessions without activity for this many hours that never
+# received a SessionEnd hook, so durations aren't stretched over days (0 = never)
+session_idle_hours = 24
+
+# Keep separate stats per Claude Code profile. The profile comes from the input's
+# `profile` field or the CLAUDE_CONFIG_DIR directory name (e.g. ~/.claude-work -> "work")
+# and is stored in its own files (stats-work.db, stats-work.json). The default
+# ~/.claude profile keeps using stats.db. Off by default: enabling it starts each
+# non-default profile with empty stats.
+namespace_by_profile = false
+
 # Data retention settings (for db-maintain command)
 retention_days_sessions = 90    # Keep session data for N days
 retention_days_daily = 365      # Keep daily aggregates for N days
 retention_days_monthly = 0      # Keep monthly aggregates for N days (0 = forever)
+retention_days_learning = 180   # Keep learned context windows and context samples for N days
+context_samples_per_model = 500 # Keep at most N context samples per model
+
+# Encrypt stats.db with SQLCipher (requires building with --features sqlcipher)
+# Key lookup order: STATUSLINE_DB_KEY env var, this setting, then the OS keyring
+# (service "claudia-statusline", account "stats-db")
+# encryption_key = "change-me"
 
 [transcript]
 # Number of transcript lines to keep in memory (circular buffer)
 # For large files, only the last N lines are read (tail-reading optimization)
 buffer_lines = 50
 
+# Gaps between transcript entries longer than this many seconds count as idle:
+# they are left out of the session's active time (display.show_active_time)
+idle_threshold_secs = 300
+
 [retry.file_ops]
 # File operation retry settings
 max_attempts = 3
@@ -736,10 +1498,66 @@ initial_delay_ms = 200
 max_delay_ms = 1000
 backoff_factor = 2.0
 
+[retry.sync_ops]
+# Backoff between background sync pushes (turso-sync builds)
+initial_delay_ms = 30000
+max_delay_ms = 3600000
+backoff_factor = 2.0
+
 [git]
 # Git operation settings
 timeout_ms = 200  # Timeout for git operations
 
+# Show the commit identity (user.email) as "@label", so commits don't go in with
+# the wrong identity. Unmatched or missing emails show "@?"
+# show_identity = false
+
+# Show the nearest tag reachable from HEAD ("v2.3.1", or "v2.3.1+4" four commits later)
+# show_tag = false
+
+# Nudge to commit: show "💾45m" once uncommitted changes to tracked files are older
+# than this many minutes, judged by the modification time of the least recently
+# written changed file. 0 turns the check off
+# uncommitted_warn_minutes = 0
+# uncommitted_marker = "💾"
+
+# Branch styling by naming convention (first matching regex wins)
+# [[git.branch_rules]]
+# pattern = "^(main|master)$"
+# color = "bright_blue"
+#
+# [[git.branch_rules]]
+# pattern = "^feat(ure)?/"
+# color = "green"
+# prefix = "+"
+#
+# [[git.branch_rules]]
+# pattern = "^(fix|hotfix)/"
+# color = "orange"
+# prefix = "!"
+#
+# [[git.branch_rules]]
+# pattern = "^release/"
+# color = "magenta"
+
+# Identity labels for show_identity (first matching regex wins)
+# [[git.identities]]
+# pattern = '@acme\.com$'
+# label = "work"
+#
+# [[git.identities]]
+# pattern = '@(gmail|fastmail)\.com$'
+# label = "home"
+
+# Large repositories: skip the untracked-file scan and cap how many changed
+# files are counted ("…" marks a capped count), so git stays within timeout_ms
+# [[git.repos]]
+# pattern = "/monorepo$"
+# untracked = false           # git status -uno
+# max_status_entries = 1000   # 0 = unlimited
+# no_optional_locks = true    # false lets git refresh the index for faster later runs
+#                             # (default: true, false when the repo uses core.fsmonitor)
+
 # Optional cloud sync configuration
 # Requires building with --features turso-sync
 # [sync]
@@ -747,48 +1565,161 @@ timeout_ms = 200  # Timeout for git operations
 # provider = "turso"
 # sync_interval_seconds = 60
 # soft_quota_fraction = 0.75  # Warn when usage exceeds 75% of quota
+# quota_storage_mb = 9216     # Plan storage limit (Turso free tier: 9 GB)
+# quota_rows = 0              # Plan row limit (0 = none)
 #
 # [sync.turso]
 # database_url = "libsql://claude-stats.turso.io"
-# auth_token = "${TURSO_AUTH_TOKEN}"  # Or paste token directly
+# auth_token = "${TURSO_AUTH_TOKEN}"  # Or "keyring" (see `statusline sync login`), or paste token directly
+
+# Optional update availability check (disabled by default)
+# Fetches the latest GitHub release at most once per interval, in the background via
+# curl, and shows "⬆ v2.x" when a newer version exists. Offline failures are ignored
+# [update]
+# check = false
+# interval_hours = 24
+
+# Optional OpenTelemetry span export (one trace per render with stage timings)
+# Requires building with --features otel
+# [telemetry]
+# enabled = false
+# endpoint = "http://localhost:4318/v1/traces"  # OTLP/HTTP (JSON), plain http only
+# service_name = "claudia-statusline"
+# timeout_ms = 200
+
+# Variants: named overrides for different panes or terminals sharing this file.
+# STATUSLINE_VARIANT=minimal (or --variant minimal) merges [variants.minimal]
+# over the settings above; anything a variant doesn't set keeps its value.
+# Unknown variant names fall back to the settings above
+# [variants.minimal.display]
+# show_git = false
+# show_cost = false
+# show_duration = false
 "#
     }
 }
 
 // Global configuration instance
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 
-static CONFIG: OnceLock<Config> = OnceLock::new();
+static CONFIG: OnceLock<Arc<Config>> = OnceLock::new();
 
 /// Get the global configuration instance
-pub fn get_config() -> &'static Config {
-    CONFIG.get_or_init(|| {
-        let mut config = Config::load().unwrap_or_else(|e| {
-            warn!("Failed to load config: {}. Using defaults.", e);
-            Config::default()
-        });
+///
+/// Within [`crate::environment::scoped`], the config is loaded from the scoped
+/// environment instead and cached for that scope only.
+pub fn get_config() -> Arc<Config> {
+    crate::environment::scoped_config(load_config)
+        .unwrap_or_else(|| Arc::clone(CONFIG.get_or_init(|| Arc::new(load_config()))))
+}
+
+/// Loads the config file and applies the environment overrides
+fn load_config() -> Config {
+    let mut config = Config::load().unwrap_or_else(|e| {
+        warn!("Failed to load config: {}. Using defaults.", e.coded());
+        Config::default()
+    });
+    let env = crate::environment::current();
+
+    // Override theme from environment if set
+    if let Some(theme) = env.var("CLAUDE_THEME") {
+        config.display.theme = theme;
+    } else if let Some(theme) = env.var("STATUSLINE_THEME") {
+        config.display.theme = theme;
+    }
+
+    // Override json_backup from environment if set (for testing)
+    if let Some(val) = env.var("STATUSLINE_JSON_BACKUP") {
+        config.database.json_backup = val == "true" || val == "1";
+    }
 
-        // Override theme from environment if set
-        if let Ok(theme) = env::var("CLAUDE_THEME") {
-            config.display.theme = theme;
-        } else if let Ok(theme) = env::var("STATUSLINE_THEME") {
-            config.display.theme = theme;
+    config
+}
+
+/// Variant selected with `STATUSLINE_VARIANT` (set by `--variant`), if any
+pub fn active_variant() -> Option<String> {
+    crate::environment::current()
+        .var("STATUSLINE_VARIANT")
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Merges `[variants.<name>]` over the rest of a parsed config. Unknown
+/// variants leave the config as it is.
+fn apply_variant(value: &mut toml::Value, name: &str) {
+    let overrides = value
+        .get("variants")
+        .and_then(|variants| variants.get(name))
+        .cloned();
+    match overrides {
+        Some(toml::Value::Table(mut overrides)) => {
+            // Variants can't define further variants
+            overrides.remove("variants");
+            merge_toml(value, toml::Value::Table(overrides));
         }
+        _ => warn!("Unknown variant '{}', using the base config", name),
+    }
+}
 
-        // Override json_backup from environment if set (for testing)
-        if let Ok(val) = env::var("STATUSLINE_JSON_BACKUP") {
-            config.database.json_backup = val == "true" || val == "1";
+/// Recursively merges `overlay` into `base`: tables merge key by key, any
+/// other value replaces the one in `base`
+fn merge_toml(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
         }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Expands environment variables in every string of a parsed config
+fn expand_env_vars(value: &mut toml::Value) {
+    match value {
+        toml::Value::String(s) if s.contains("${") => *s = expand_env(s),
+        toml::Value::Array(items) => items.iter_mut().for_each(expand_env_vars),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, v)| expand_env_vars(v)),
+        _ => {}
+    }
+}
 
-        config
-    })
+/// Expands `${VAR}` and `${VAR:-fallback}` (fallback when unset or empty)
+///
+/// A plain `${VAR}` expands to the value even when it is empty. References to
+/// unset variables without a fallback are left as written, so consumers such
+/// as the sync token can still report which variable is missing.
+pub fn expand_env(text: &str) -> String {
+    static PATTERN: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        regex::Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").expect("valid regex")
+    });
+
+    let env = crate::environment::current();
+    pattern
+        .replace_all(text, |caps: &regex::Captures| {
+            match (env.var(&caps[1]), caps.get(2)) {
+                (Some(value), None) => value,
+                (Some(value), Some(_)) if !value.is_empty() => value,
+                (_, Some(fallback)) => fallback.as_str().to_string(),
+                (None, None) => caps[0].to_string(),
+            }
+        })
+        .into_owned()
 }
 
 /// Get the current theme (with environment override support)
+#[cfg_attr(not(feature = "themes"), allow(dead_code))]
 pub fn get_theme() -> String {
-    env::var("CLAUDE_THEME")
-        .or_else(|_| env::var("STATUSLINE_THEME"))
-        .unwrap_or_else(|_| get_config().display.theme.clone())
+    let env = crate::environment::current();
+    env.var("CLAUDE_THEME")
+        .or_else(|| env.var("STATUSLINE_THEME"))
+        .unwrap_or_else(|| get_config().display.theme.clone())
 }
 
 #[cfg(test)]
@@ -802,6 +1733,7 @@ mod tests {
         assert_eq!(config.display.progress_bar_width, 10);
         assert_eq!(config.context.window_size, 200_000); // Updated for modern Claude models
         assert_eq!(config.cost.low_threshold, 5.0);
+        assert_eq!(config.cost.hide_below, 0.0); // Cost segment always shown by default
     }
 
     #[test]
@@ -819,6 +1751,132 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_env_var_expansion() {
+        use crate::environment::{scoped, FixedEnvironment};
+
+        let env = FixedEnvironment::new()
+            .with_var("STATUSLINE_TEST_EXPAND", "from-env")
+            .with_var("STATUSLINE_TEST_EMPTY", "");
+        scoped(env, check_env_var_expansion);
+    }
+
+    fn check_env_var_expansion() {
+        assert_eq!(expand_env("${STATUSLINE_TEST_EXPAND}"), "from-env");
+        assert_eq!(
+            expand_env("a/${STATUSLINE_TEST_EXPAND}/b/${STATUSLINE_TEST_UNSET:-x}"),
+            "a/from-env/b/x"
+        );
+        assert_eq!(
+            expand_env("${STATUSLINE_TEST_UNSET}"),
+            "${STATUSLINE_TEST_UNSET}"
+        );
+        assert_eq!(expand_env("${STATUSLINE_TEST_UNSET:-}"), "");
+        // Set but empty: plain references expand to "", fallbacks still apply
+        assert_eq!(expand_env("a${STATUSLINE_TEST_EMPTY}b"), "ab");
+        assert_eq!(expand_env("${STATUSLINE_TEST_EMPTY:-x}"), "x");
+        assert_eq!(expand_env("@acme\\.com$"), "@acme\\.com$");
+
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            r#"
+            [display]
+            theme = "${STATUSLINE_TEST_UNSET:-light}"
+
+            [database]
+            path = "${STATUSLINE_TEST_EXPAND}.db"
+            "#,
+        )
+        .unwrap();
+        let config = Config::load_from_file(&config_path).unwrap();
+        assert_eq!(config.display.theme, "light");
+        assert_eq!(config.database.path, "from-env.db");
+
+        let raw = Config::load_from_file_unexpanded(&config_path).unwrap();
+        assert_eq!(raw.database.path, "${STATUSLINE_TEST_EXPAND}.db");
+    }
+
+    #[test]
+    fn test_variants() {
+        use crate::environment::{scoped, FixedEnvironment};
+
+        let temp_dir = TempDir::new().unwrap();
+        let config_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            r#"
+            [display]
+            show_cost = true
+            show_model = true
+
+            [variants.minimal.display]
+            show_cost = false
+
+            [variants.minimal.context]
+            buffer_size = 10000
+            "#,
+        )
+        .unwrap();
+
+        let base = Config::load_from_file(&config_path).unwrap();
+        assert!(base.display.show_cost);
+        assert!(base.variants.contains_key("minimal"));
+
+        let env = FixedEnvironment::new().with_var("STATUSLINE_VARIANT", "minimal");
+        let minimal = scoped(env, || Config::load_from_file(&config_path).unwrap());
+        assert!(!minimal.display.show_cost);
+        // Settings the variant doesn't mention keep their base values
+        assert!(minimal.display.show_model);
+        assert_eq!(minimal.context.buffer_size, 10_000);
+
+        let env = FixedEnvironment::new().with_var("STATUSLINE_VARIANT", "missing");
+        let unknown = scoped(env, || Config::load_from_file(&config_path).unwrap());
+        assert!(unknown.display.show_cost);
+
+        // Rewriting the file keeps variants separate from the base settings
+        let env = FixedEnvironment::new().with_var("STATUSLINE_VARIANT", "minimal");
+        let raw = scoped(env, || {
+            Config::load_from_file_unexpanded(&config_path).unwrap()
+        });
+        assert!(raw.display.show_cost);
+        let saved = toml::to_string_pretty(&raw).unwrap();
+        assert!(saved.contains("[variants.minimal.display]"));
+    }
+
+    #[test]
+    fn test_model_context_overrides() {
+        let toml = r#"
+        [context]
+        buffer_size = 40000
+        percentage_mode = "working"
+
+        [context.model_overrides."Claude Opus 4.5"]
+        buffer_size = 30000
+        auto_compact_threshold = 80.0
+
+        [context.model_overrides."Claude Haiku 4.5"]
+        buffer_size = 20000
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let context = &config.context;
+
+        assert_eq!(context.buffer_size_for(Some("Claude Opus 4.5")), 30_000);
+        assert_eq!(
+            context.effective_threshold_for(Some("Claude Opus 4.5")),
+            80.0
+        );
+        // Partial override: threshold falls back to the mode-aware default
+        assert_eq!(context.buffer_size_for(Some("Claude Haiku 4.5")), 20_000);
+        assert_eq!(
+            context.effective_threshold_for(Some("Claude Haiku 4.5")),
+            94.0
+        );
+        assert_eq!(context.buffer_size_for(Some("Claude Sonnet 4.5")), 40_000);
+        assert_eq!(context.buffer_size_for(None), 40_000);
+    }
+
     #[test]
     fn test_example_config() {
         let example = Config::example_toml();
@@ -937,4 +1995,20 @@ mod tests {
         assert!(serialized.contains("show_lines_changed"));
         assert!(serialized.contains("show_cost"));
     }
+
+    #[test]
+    fn test_severity_markers_config() {
+        let config = DisplayConfig::default();
+        assert!(!config.severity_markers);
+        assert_eq!(config.severity_symbols, vec!["!", "!!", "!!!"]);
+
+        let toml = r#"
+        [display]
+        severity_markers = true
+        severity_symbols = ["~", "*", "^"]
+        "#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert!(config.display.severity_markers);
+        assert_eq!(config.display.severity_symbols, vec!["~", "*", "^"]);
+    }
 }
@@ -16,44 +16,139 @@
 //! - `adaptive_learning = true` in config
 //! - Confidence score >= `learning_confidence_threshold`
 //! - No user override exists in `model_windows`
+//!
+//! `learning_strategy` selects which of the two heuristics build confidence
+//! (see [`LearningStrategy`]); their thresholds are tunable via
+//! `[context.learning_params]`.
 
+use crate::config::ContextConfig;
 use crate::database::SqliteDatabase;
-use crate::error::Result;
+use crate::environment::now_local;
+use crate::error::{Result, StatuslineError};
 use crate::models::ModelType;
-use chrono::Local;
 use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-/// Minimum token count to consider for compaction detection
+/// Default minimum token count to consider for compaction detection
 const MIN_COMPACTION_TOKENS: usize = 150_000;
 
-/// Minimum percentage drop to consider a compaction event
+/// Default minimum percentage drop to consider a compaction event
 const COMPACTION_DROP_THRESHOLD: f64 = 0.10; // 10%
 
-/// Token variance threshold for ceiling detection (within 2% = same ceiling)
+/// Default token variance threshold for ceiling detection (within 2% = same ceiling)
 const CEILING_VARIANCE_THRESHOLD: f64 = 0.02; // 2%
 
-/// Proximity threshold for compaction detection (95% of observed max)
+/// Default proximity threshold for compaction detection (95% of observed max)
 /// Used as fallback when transcript is unavailable
 const COMPACTION_PROXIMITY_THRESHOLD: f64 = 0.95;
 
+/// Default minimum previous tokens for a model's first compaction (no known ceiling yet)
+const FIRST_COMPACTION_MIN_TOKENS: usize = 190_000;
+
 /// Number of recent messages to check for manual compaction commands
 const MANUAL_COMPACTION_CHECK_LINES: usize = 10;
 
+/// Format version written by `learning export`; imports of other versions are rejected
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
 /// Audit entry for session observations
 /// Format: (last_updated, session_id, tokens, workspace_dir, device_id)
 type SessionAuditEntry = (String, String, usize, Option<String>, Option<String>);
 
+/// Which observations build confidence in a learned window (`context.learning_strategy`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LearningStrategy {
+    /// Ceiling patterns and compaction drops both count (default)
+    Combined,
+    /// Only detected compactions count: conservative, needs ~3 compactions
+    Compaction,
+    /// Only repeated ceilings count: aggressive, learns without any compaction
+    Ceiling,
+}
+
+impl LearningStrategy {
+    /// Parse a strategy name, falling back to `Combined` for unknown values
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "combined" => LearningStrategy::Combined,
+            "compaction" => LearningStrategy::Compaction,
+            "ceiling" => LearningStrategy::Ceiling,
+            other => {
+                warn!("Unknown learning_strategy '{}', using \"combined\"", other);
+                LearningStrategy::Combined
+            }
+        }
+    }
+}
+
+/// Strategy and thresholds used to interpret token observations
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LearningHeuristics {
+    pub strategy: LearningStrategy,
+    pub min_compaction_tokens: usize,
+    pub compaction_drop_threshold: f64,
+    pub ceiling_variance_threshold: f64,
+    pub compaction_proximity_threshold: f64,
+    pub first_compaction_min_tokens: usize,
+}
+
+impl Default for LearningHeuristics {
+    fn default() -> Self {
+        Self {
+            strategy: LearningStrategy::Combined,
+            min_compaction_tokens: MIN_COMPACTION_TOKENS,
+            compaction_drop_threshold: COMPACTION_DROP_THRESHOLD,
+            ceiling_variance_threshold: CEILING_VARIANCE_THRESHOLD,
+            compaction_proximity_threshold: COMPACTION_PROXIMITY_THRESHOLD,
+            first_compaction_min_tokens: FIRST_COMPACTION_MIN_TOKENS,
+        }
+    }
+}
+
+impl LearningHeuristics {
+    /// Resolve heuristics from `[context]`; unset parameters keep their defaults
+    pub fn from_config(config: &ContextConfig) -> Self {
+        let defaults = Self::default();
+        let params = &config.learning_params;
+        Self {
+            strategy: LearningStrategy::from_name(&config.learning_strategy),
+            min_compaction_tokens: params
+                .min_compaction_tokens
+                .unwrap_or(defaults.min_compaction_tokens),
+            compaction_drop_threshold: params
+                .compaction_drop_threshold
+                .unwrap_or(defaults.compaction_drop_threshold),
+            ceiling_variance_threshold: params
+                .ceiling_variance_threshold
+                .unwrap_or(defaults.ceiling_variance_threshold),
+            compaction_proximity_threshold: params
+                .compaction_proximity_threshold
+                .unwrap_or(defaults.compaction_proximity_threshold),
+            first_compaction_min_tokens: params
+                .first_compaction_min_tokens
+                .unwrap_or(defaults.first_compaction_min_tokens),
+        }
+    }
+}
+
 /// Context window learning manager
 pub struct ContextLearner {
     db: SqliteDatabase,
+    heuristics: LearningHeuristics,
 }
 
 impl ContextLearner {
-    /// Create a new context learner with database connection
+    /// Create a new context learner using the configured strategy and parameters
     pub fn new(db: SqliteDatabase) -> Self {
-        Self { db }
+        let heuristics = LearningHeuristics::from_config(&crate::config::get_config().context);
+        Self::with_heuristics(db, heuristics)
+    }
+
+    /// Create a context learner with explicit heuristics
+    pub fn with_heuristics(db: SqliteDatabase, heuristics: LearningHeuristics) -> Self {
+        Self { db, heuristics }
     }
 
     /// Observe token usage for a model and update learned values
@@ -98,7 +193,9 @@ impl ContextLearner {
             .map(|r| r.observed_max_tokens)
             .unwrap_or(0);
 
-        // Detect compaction event
+        // Detect compaction event (ignored entirely by the ceiling-only strategy)
+        let previous_tokens =
+            previous_tokens.filter(|_| self.heuristics.strategy != LearningStrategy::Ceiling);
         if let Some(prev) = previous_tokens {
             if self.is_compaction_event(current_tokens, prev, observed_max, transcript_path) {
                 info!(
@@ -113,7 +210,7 @@ impl ContextLearner {
         }
 
         // Update ceiling observation
-        if current_tokens > MIN_COMPACTION_TOKENS {
+        if current_tokens > self.heuristics.min_compaction_tokens {
             self.update_ceiling_observation(
                 &canonical_name,
                 current_tokens,
@@ -131,9 +228,9 @@ impl ContextLearner {
     /// Check if this represents a compaction event
     ///
     /// A compaction event is detected when:
-    /// - Previous tokens >= MIN_COMPACTION_TOKENS (150k)
+    /// - Previous tokens >= `min_compaction_tokens` (default 150k)
     /// - Current tokens < previous tokens
-    /// - Drop percentage >= COMPACTION_DROP_THRESHOLD (10%)
+    /// - Drop percentage >= `compaction_drop_threshold` (default 10%)
     /// - NOT a manual compaction (user explicitly requested summary)
     /// - Close to observed ceiling (if known) OR first observation at high level
     fn is_compaction_event(
@@ -144,7 +241,8 @@ impl ContextLearner {
         transcript_path: Option<&str>,
     ) -> bool {
         // Basic checks
-        if previous_tokens < MIN_COMPACTION_TOKENS {
+        let h = &self.heuristics;
+        if previous_tokens < h.min_compaction_tokens {
             return false;
         }
 
@@ -153,7 +251,7 @@ impl ContextLearner {
         }
 
         let drop_percent = (previous_tokens - current_tokens) as f64 / previous_tokens as f64;
-        if drop_percent < COMPACTION_DROP_THRESHOLD {
+        if drop_percent < h.compaction_drop_threshold {
             return false;
         }
 
@@ -172,7 +270,7 @@ impl ContextLearner {
         // This filters out manual compactions that weren't detected by pattern matching
         if observed_max > 0 {
             let proximity = previous_tokens as f64 / observed_max as f64;
-            if proximity < COMPACTION_PROXIMITY_THRESHOLD {
+            if proximity < h.compaction_proximity_threshold {
                 debug!(
                     "Skipping compaction at {} (only {:.1}% of observed max {})",
                     previous_tokens,
@@ -183,10 +281,10 @@ impl ContextLearner {
             }
         } else {
             // First observation - must be at high level (190k+) to be automatic
-            if previous_tokens < 190_000 {
+            if previous_tokens < h.first_compaction_min_tokens {
                 debug!(
-                    "Skipping first compaction at {} (below 190k threshold)",
-                    previous_tokens
+                    "Skipping first compaction at {} (below {} threshold)",
+                    previous_tokens, h.first_compaction_min_tokens
                 );
                 return false;
             }
@@ -327,7 +425,7 @@ impl ContextLearner {
         workspace_dir: Option<&str>,
         device_id: Option<&str>,
     ) -> Result<()> {
-        let now = Local::now().to_rfc3339();
+        let now = now_local().to_rfc3339();
 
         // Get existing record or create new one
         let existing = self.db.get_learned_context(model_name)?;
@@ -369,7 +467,7 @@ impl ContextLearner {
 
     /// Update ceiling observation for a model
     ///
-    /// If the current tokens are within CEILING_VARIANCE_THRESHOLD of the
+    /// If the current tokens are within `ceiling_variance_threshold` of the
     /// observed maximum, increment ceiling_observations.
     fn update_ceiling_observation(
         &self,
@@ -378,7 +476,7 @@ impl ContextLearner {
         workspace_dir: Option<&str>,
         device_id: Option<&str>,
     ) -> Result<()> {
-        let now = Local::now().to_rfc3339();
+        let now = now_local().to_rfc3339();
 
         let existing = self.db.get_learned_context(model_name)?;
 
@@ -391,7 +489,7 @@ impl ContextLearner {
                 1.0 // First observation
             };
 
-            if variance <= CEILING_VARIANCE_THRESHOLD {
+            if variance <= self.heuristics.ceiling_variance_threshold {
                 // Within 2% of observed max = ceiling hit
                 record.ceiling_observations += 1;
                 debug!(
@@ -458,7 +556,7 @@ impl ContextLearner {
                 );
 
                 record.confidence_score = confidence;
-                record.last_updated = Local::now().to_rfc3339();
+                record.last_updated = now_local().to_rfc3339();
                 self.db.update_learned_context(&record)?;
             }
         }
@@ -468,14 +566,23 @@ impl ContextLearner {
 
     /// Calculate confidence score based on observations
     ///
-    /// Formula:
+    /// Formula for the combined strategy:
     /// - Ceiling score: min(ceiling_observations * 0.1, 0.5)
     /// - Compaction score: min(compaction_count * 0.3, 0.5)
     /// - Total: min(ceiling_score + compaction_score, 1.0)
+    ///
+    /// Single-heuristic strategies drop the 0.5 cap on their own score and
+    /// ignore the other one.
     pub fn calculate_confidence(&self, ceiling_observations: i32, compaction_count: i32) -> f64 {
-        let ceiling_score = (ceiling_observations as f64 * 0.1).min(0.5);
-        let compaction_score = (compaction_count as f64 * 0.3).min(0.5);
-        (ceiling_score + compaction_score).min(1.0)
+        let ceiling_score = ceiling_observations as f64 * 0.1;
+        let compaction_score = compaction_count as f64 * 0.3;
+        match self.heuristics.strategy {
+            LearningStrategy::Combined => {
+                (ceiling_score.min(0.5) + compaction_score.min(0.5)).min(1.0)
+            }
+            LearningStrategy::Compaction => compaction_score.min(1.0),
+            LearningStrategy::Ceiling => ceiling_score.min(1.0),
+        }
     }
 
     /// Get learned context window for a model if confidence is high enough
@@ -552,6 +659,54 @@ impl ContextLearner {
         Ok(self.db.delete_all_learned_contexts()?)
     }
 
+    /// Export all learned context windows for transfer to another machine
+    pub fn export(&self) -> Result<LearningExport> {
+        Ok(LearningExport {
+            version: EXPORT_FORMAT_VERSION,
+            exported_at: now_local().to_rfc3339(),
+            windows: self.get_all_learned_windows()?,
+        })
+    }
+
+    /// Import learned context windows from an export
+    ///
+    /// Each imported record replaces the local record for the same model, so
+    /// re-importing the same file is idempotent. With `replace`, all local
+    /// learning data is cleared first. The reset and all records are applied in
+    /// one transaction, so a failed import leaves local data untouched. Returns
+    /// the number of imported records.
+    pub fn import(&self, export: &LearningExport, replace: bool) -> Result<usize> {
+        if export.version != EXPORT_FORMAT_VERSION {
+            return Err(StatuslineError::other(format!(
+                "Unsupported learning export version {} (expected {})",
+                export.version, EXPORT_FORMAT_VERSION
+            )));
+        }
+
+        let records: Vec<LearnedContextWindow> = export
+            .windows
+            .iter()
+            .map(|window| {
+                // Normalize names so exports from older versions merge with current records
+                let mut record = window.clone();
+                record.model_name = ModelType::from_name(&window.model_name).canonical_name();
+                record.confidence_score = record.confidence_score.clamp(0.0, 1.0);
+                record
+            })
+            .collect();
+        if replace {
+            warn!("Replacing ALL learned context data with the import");
+        }
+        self.db.import_learned_contexts(&records, replace)?;
+
+        info!(
+            "Imported {} learned context windows (replace={})",
+            export.windows.len(),
+            replace
+        );
+        Ok(export.windows.len())
+    }
+
     /// Rebuild learned context windows from historical session data
     ///
     /// This recovery function replays all session token observations to rebuild
@@ -627,7 +782,7 @@ impl ContextLearner {
 }
 
 /// Learned context window record from database
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LearnedContextWindow {
     pub model_name: String,
     pub observed_max_tokens: usize,
@@ -641,6 +796,14 @@ pub struct LearnedContextWindow {
     pub device_id: Option<String>,
 }
 
+/// Portable snapshot of learned context windows (`learning export`/`import`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearningExport {
+    pub version: u32,
+    pub exported_at: String,
+    pub windows: Vec<LearnedContextWindow>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -655,7 +818,10 @@ mod tests {
         let mut runner = crate::migrations::MigrationRunner::new(&db_path).unwrap();
         runner.migrate().unwrap();
 
-        (ContextLearner::new(db), temp_dir)
+        (
+            ContextLearner::with_heuristics(db, LearningHeuristics::default()),
+            temp_dir,
+        )
     }
 
     #[test]
@@ -1001,4 +1167,147 @@ mod tests {
         // Now compaction count should have increased
         assert_eq!(record.compaction_count, 1);
     }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let (source, _source_dir) = create_test_learner();
+        source
+            .observe_usage("Claude Sonnet 4.5", 198_000, None, None, None, None)
+            .unwrap();
+        source
+            .observe_usage(
+                "Claude Sonnet 4.5",
+                100_000,
+                Some(197_000),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        let export = source.export().unwrap();
+        assert_eq!(export.version, EXPORT_FORMAT_VERSION);
+        let json = serde_json::to_string(&export).unwrap();
+        let parsed: LearningExport = serde_json::from_str(&json).unwrap();
+
+        let (target, _target_dir) = create_test_learner();
+        target
+            .observe_usage("Claude Opus 4.5", 160_000, None, None, None, None)
+            .unwrap();
+
+        // Merge keeps unrelated local records; importing twice is idempotent
+        assert_eq!(target.import(&parsed, false).unwrap(), 1);
+        assert_eq!(target.import(&parsed, false).unwrap(), 1);
+        let all = target.get_all_learned_windows().unwrap();
+        assert_eq!(all.len(), 2);
+        let imported = target
+            .get_learned_window_details("Sonnet 4.5")
+            .unwrap()
+            .unwrap();
+        assert_eq!(imported.observed_max_tokens, 198_000);
+        assert_eq!(imported.compaction_count, 1);
+
+        // Replace clears local data first
+        target.import(&parsed, true).unwrap();
+        assert_eq!(target.get_all_learned_windows().unwrap().len(), 1);
+
+        let mut future = parsed.clone();
+        future.version = EXPORT_FORMAT_VERSION + 1;
+        assert!(target.import(&future, false).is_err());
+    }
+
+    #[test]
+    fn test_failed_replace_import_keeps_existing_data() {
+        let (learner, temp_dir) = create_test_learner();
+        learner
+            .observe_usage("Claude Opus 4.5", 160_000, None, None, None, None)
+            .unwrap();
+        let existing = learner.export().unwrap();
+
+        // Reject one record partway through the import
+        rusqlite::Connection::open(temp_dir.path().join("test.db"))
+            .unwrap()
+            .execute_batch(
+                "CREATE TRIGGER reject_haiku BEFORE INSERT ON learned_context_windows
+                 WHEN NEW.model_name LIKE '%Haiku%'
+                 BEGIN SELECT RAISE(ABORT, 'rejected'); END;",
+            )
+            .unwrap();
+        let mut export = existing.clone();
+        for name in ["Claude Sonnet 4.5", "Claude Haiku 4.5"] {
+            let mut window = existing.windows[0].clone();
+            window.model_name = name.to_string();
+            export.windows.push(window);
+        }
+        export.windows.remove(0);
+
+        assert!(learner.import(&export, true).is_err());
+        let all = learner.get_all_learned_windows().unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].model_name, existing.windows[0].model_name);
+    }
+
+    #[test]
+    fn test_learning_strategies() {
+        let temp_dir = TempDir::new().unwrap();
+        let learner_for = |strategy: LearningStrategy, name: &str| {
+            let db_path = temp_dir.path().join(name);
+            let db = SqliteDatabase::new(&db_path).unwrap();
+            let heuristics = LearningHeuristics {
+                strategy,
+                ..LearningHeuristics::default()
+            };
+            ContextLearner::with_heuristics(db, heuristics)
+        };
+
+        let compaction = learner_for(LearningStrategy::Compaction, "compaction.db");
+        assert_eq!(compaction.calculate_confidence(10, 0), 0.0);
+        assert!((compaction.calculate_confidence(0, 3) - 0.9).abs() < 1e-9);
+
+        let ceiling = learner_for(LearningStrategy::Ceiling, "ceiling.db");
+        assert!((ceiling.calculate_confidence(7, 2) - 0.7).abs() < 1e-9);
+        assert_eq!(ceiling.calculate_confidence(20, 0), 1.0);
+
+        // The ceiling strategy never records compactions
+        ceiling
+            .observe_usage("Claude Sonnet 4.5", 198_000, None, None, None, None)
+            .unwrap();
+        ceiling
+            .observe_usage(
+                "Claude Sonnet 4.5",
+                100_000,
+                Some(197_000),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let record = ceiling
+            .get_learned_window_details("Claude Sonnet 4.5")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.compaction_count, 0);
+
+        assert_eq!(
+            LearningStrategy::from_name("Compaction"),
+            LearningStrategy::Compaction
+        );
+        assert_eq!(
+            LearningStrategy::from_name("bogus"),
+            LearningStrategy::Combined
+        );
+    }
+
+    #[test]
+    fn test_tuned_compaction_threshold() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let mut config = ContextConfig::default();
+        config.learning_params.compaction_drop_threshold = Some(0.5);
+        let learner = ContextLearner::with_heuristics(db, LearningHeuristics::from_config(&config));
+
+        // A 40% drop counts by default but not with the stricter threshold
+        assert!(!learner.is_compaction_event(120_000, 200_000, 0, None));
+        assert!(learner.is_compaction_event(90_000, 200_000, 0, None));
+    }
 }
@@ -5,15 +5,27 @@ use chrono::Local;
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{params, Connection, OptionalExtension, Result, Transaction};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 // Track which database files have been migrated to avoid redundant migration checks
 static MIGRATED_DBS: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
 
+// Track database files that could not be opened, with when to try them again,
+// so later calls in the same process fail fast instead of waiting on the
+// connection pool again
+static UNAVAILABLE_DBS: OnceLock<Mutex<HashMap<PathBuf, Instant>>> = OnceLock::new();
+
+/// How long a database that failed to open is skipped before it is probed again
+const UNAVAILABLE_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Schema version a freshly created database starts at (latest migration)
+pub(crate) const CURRENT_SCHEMA_VERSION: u32 = 19;
+
 pub const SCHEMA: &str = r#"
--- Sessions table (includes all migration v3, v4, v5 columns)
+-- Sessions table (includes all migration v3, v4, v5, v6, v9, v11, v12, v16, v19 columns)
 CREATE TABLE IF NOT EXISTS sessions (
     session_id TEXT PRIMARY KEY,
     start_time TEXT NOT NULL,
@@ -29,27 +41,41 @@ CREATE TABLE IF NOT EXISTS sessions (
     total_input_tokens INTEGER DEFAULT 0,
     total_output_tokens INTEGER DEFAULT 0,
     total_cache_read_tokens INTEGER DEFAULT 0,
-    total_cache_creation_tokens INTEGER DEFAULT 0
+    total_cache_creation_tokens INTEGER DEFAULT 0,
+    user TEXT,
+    active_seconds INTEGER DEFAULT 0,
+    note TEXT,
+    ended_at TEXT,
+    git_branch TEXT,
+    last_tokens_observed INTEGER DEFAULT 0
 );
 
--- Daily aggregates (materialized for performance)
+-- Daily aggregates per user (materialized for performance, token columns from migration v13)
 CREATE TABLE IF NOT EXISTS daily_stats (
-    date TEXT PRIMARY KEY,
+    date TEXT NOT NULL,
+    user TEXT NOT NULL DEFAULT '',
     total_cost REAL DEFAULT 0.0,
     total_lines_added INTEGER DEFAULT 0,
     total_lines_removed INTEGER DEFAULT 0,
     session_count INTEGER DEFAULT 0,
-    device_id TEXT
+    device_id TEXT,
+    total_input_tokens INTEGER DEFAULT 0,
+    total_output_tokens INTEGER DEFAULT 0,
+    total_cache_read_tokens INTEGER DEFAULT 0,
+    total_cache_creation_tokens INTEGER DEFAULT 0,
+    PRIMARY KEY (date, user)
 );
 
--- Monthly aggregates
+-- Monthly aggregates per user
 CREATE TABLE IF NOT EXISTS monthly_stats (
-    month TEXT PRIMARY KEY,
+    month TEXT NOT NULL,
+    user TEXT NOT NULL DEFAULT '',
     total_cost REAL DEFAULT 0.0,
     total_lines_added INTEGER DEFAULT 0,
     total_lines_removed INTEGER DEFAULT 0,
     session_count INTEGER DEFAULT 0,
-    device_id TEXT
+    device_id TEXT,
+    PRIMARY KEY (month, user)
 );
 
 -- Learned context windows table (migration v4)
@@ -73,6 +99,84 @@ CREATE INDEX IF NOT EXISTS idx_learned_device
     ON learned_context_windows(device_id);
 CREATE INDEX IF NOT EXISTS idx_learned_confidence
     ON learned_context_windows(confidence_score DESC);
+CREATE INDEX IF NOT EXISTS idx_learned_last_updated
+    ON learned_context_windows(last_updated);
+
+-- Recent context usage samples per session (migration v5)
+CREATE TABLE IF NOT EXISTS context_samples (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    recorded_at TEXT NOT NULL,
+    percentage REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_context_samples_session
+    ON context_samples(session_id, id DESC);
+CREATE INDEX IF NOT EXISTS idx_context_samples_recorded
+    ON context_samples(recorded_at);
+
+-- Cost alerts shown until acknowledged (migration v10)
+CREATE TABLE IF NOT EXISTS cost_alerts (
+    kind TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    threshold REAL NOT NULL,
+    value REAL NOT NULL,
+    fired_at TEXT NOT NULL,
+    acknowledged_at TEXT,
+    PRIMARY KEY (kind, scope)
+);
+
+-- Last delivery per alert, for notification cooldowns (migration v14)
+CREATE TABLE IF NOT EXISTS alert_log (
+    alert_type TEXT NOT NULL,
+    alert_key TEXT NOT NULL,
+    last_fired_at INTEGER NOT NULL,
+    fire_count INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (alert_type, alert_key)
+);
+
+-- Hashed paths of files edited per session (migration v15)
+CREATE TABLE IF NOT EXISTS session_files (
+    session_id TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    first_seen TEXT NOT NULL,
+    PRIMARY KEY (session_id, file_hash)
+);
+
+-- Token usage per API call, counted once per message ID (migration v17)
+CREATE TABLE IF NOT EXISTS session_usage (
+    session_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    date TEXT NOT NULL,
+    user TEXT NOT NULL DEFAULT '',
+    input_tokens INTEGER DEFAULT 0,
+    output_tokens INTEGER DEFAULT 0,
+    cache_read_tokens INTEGER DEFAULT 0,
+    cache_creation_tokens INTEGER DEFAULT 0,
+    PRIMARY KEY (session_id, message_id)
+);
+
+-- Cumulative line counts per session as they change, for the recent delta (migration v18)
+CREATE TABLE IF NOT EXISTS lines_samples (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    sampled_at INTEGER NOT NULL,
+    lines_added INTEGER NOT NULL,
+    lines_removed INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_lines_samples_session
+    ON lines_samples(session_id, id);
+
+-- Cost per git branch and day, from the deltas of each update (migration v16)
+CREATE TABLE IF NOT EXISTS branch_stats (
+    date TEXT NOT NULL,
+    user TEXT NOT NULL DEFAULT '',
+    workspace_dir TEXT NOT NULL DEFAULT '',
+    branch TEXT NOT NULL,
+    total_cost REAL DEFAULT 0.0,
+    total_lines_added INTEGER DEFAULT 0,
+    total_lines_removed INTEGER DEFAULT 0,
+    PRIMARY KEY (date, user, workspace_dir, branch)
+);
 
 -- Sync metadata table (migration v3 - turso-sync feature)
 CREATE TABLE IF NOT EXISTS sync_meta (
@@ -89,6 +193,7 @@ CREATE INDEX IF NOT EXISTS idx_sessions_cost ON sessions(cost DESC);
 CREATE INDEX IF NOT EXISTS idx_sessions_model_name ON sessions(model_name);
 CREATE INDEX IF NOT EXISTS idx_sessions_workspace ON sessions(workspace_dir);
 CREATE INDEX IF NOT EXISTS idx_sessions_device ON sessions(device_id);
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user);
 CREATE INDEX IF NOT EXISTS idx_learned_confidence ON learned_context_windows(confidence_score DESC);
 CREATE INDEX IF NOT EXISTS idx_daily_date_cost ON daily_stats(date DESC, total_cost DESC);
 CREATE INDEX IF NOT EXISTS idx_daily_device ON daily_stats(device_id);
@@ -110,18 +215,60 @@ CREATE TABLE IF NOT EXISTS meta (
 );
 "#;
 
-/// Parameters for updating a session in the database
-#[derive(Clone)]
-pub struct SessionUpdate {
-    pub cost: f64,
-    pub lines_added: u64,
-    pub lines_removed: u64,
-    pub model_name: Option<String>,
-    pub workspace_dir: Option<String>,
-    pub device_id: Option<String>,
-    pub token_breakdown: Option<crate::models::TokenBreakdown>,
-    pub max_tokens_observed: Option<u32>,
-}
+/// Reporting views for BI tools and `db query` (migration v7)
+///
+/// Pre-joined, human-friendly shapes over the raw tables. Dates are local
+/// days of `last_updated`, matching how the statusline attributes cost.
+pub const REPORTING_VIEWS: &str = r#"
+CREATE VIEW IF NOT EXISTS v_daily_project_costs AS
+SELECT
+    date(last_updated, 'localtime') AS date,
+    COALESCE(workspace_dir, '') AS project,
+    COALESCE(user, '') AS user,
+    COUNT(*) AS sessions,
+    SUM(cost) AS cost,
+    SUM(lines_added) AS lines_added,
+    SUM(lines_removed) AS lines_removed,
+    SUM(total_input_tokens + total_output_tokens
+        + total_cache_read_tokens + total_cache_creation_tokens) AS tokens
+FROM sessions
+GROUP BY 1, 2, 3;
+
+CREATE VIEW IF NOT EXISTS v_model_usage AS
+SELECT
+    COALESCE(model_name, '') AS model,
+    COUNT(*) AS sessions,
+    SUM(cost) AS cost,
+    SUM(total_input_tokens) AS input_tokens,
+    SUM(total_output_tokens) AS output_tokens,
+    SUM(total_cache_read_tokens) AS cache_read_tokens,
+    SUM(total_cache_creation_tokens) AS cache_creation_tokens,
+    MIN(start_time) AS first_used,
+    MAX(last_updated) AS last_used
+FROM sessions
+GROUP BY 1;
+
+CREATE VIEW IF NOT EXISTS v_session_summary AS
+SELECT
+    session_id,
+    date(last_updated, 'localtime') AS date,
+    start_time,
+    last_updated,
+    ended_at,
+    ROUND((julianday(COALESCE(ended_at, last_updated)) - julianday(start_time)) * 1440, 1) AS duration_minutes,
+    model_name AS model,
+    workspace_dir AS project,
+    user,
+    cost,
+    lines_added,
+    lines_removed,
+    total_input_tokens + total_output_tokens
+        + total_cache_read_tokens + total_cache_creation_tokens AS tokens,
+    max_tokens_observed
+FROM sessions;
+"#;
+
+pub use crate::stats::SessionUpdate;
 
 pub struct SqliteDatabase {
     #[allow(dead_code)]
@@ -145,14 +292,67 @@ impl SqliteDatabase {
             })?;
         }
 
+        // Fail fast if this database recently failed to open in this process.
+        // Callers fall back to in-memory stats, so the statusline keeps rendering.
+        let unavailable = UNAVAILABLE_DBS.get_or_init(|| Mutex::new(HashMap::new()));
+        if unavailable
+            .lock()
+            .map(|map| {
+                map.get(db_path)
+                    .is_some_and(|retry| Instant::now() < *retry)
+            })
+            .unwrap_or(false)
+        {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CANTOPEN),
+                Some(format!("Database unavailable: {}", db_path.display())),
+            ));
+        }
+
         // Get configuration
         let config = config::get_config();
+        let busy_timeout_ms = config.database.busy_timeout_ms;
+
+        // Probe with a direct connection first: the pool retries failed opens until
+        // its connection timeout, which would stall rendering on a locked, corrupted
+        // or read-only database
+        if let Err(e) = open_connection(db_path).and_then(|conn| {
+            conn.pragma_update(None, "busy_timeout", busy_timeout_ms)?;
+            conn.pragma_update(None, "journal_mode", "WAL")
+        }) {
+            log::warn!(
+                "Stats database {} unavailable, using in-memory stats: {}",
+                db_path.display(),
+                e
+            );
+            // A concurrent writer holding the lock is transient; only skip
+            // databases that can't be opened at all
+            if marks_unavailable(&e) {
+                if let Ok(mut map) = unavailable.lock() {
+                    map.insert(
+                        db_path.to_path_buf(),
+                        Instant::now() + UNAVAILABLE_RETRY_AFTER,
+                    );
+                }
+            }
+            return Err(e);
+        }
+        if let Ok(mut map) = unavailable.lock() {
+            map.remove(db_path);
+        }
+
+        // Resolved here: the pool opens connections on its own threads, outside
+        // this thread's environment
+        let key = encryption_key();
 
         // Create connection pool
         let manager = SqliteConnectionManager::file(db_path).with_init(move |conn| {
+            // Key must be applied before any other statement (SQLCipher builds)
+            apply_key(conn, key.as_deref())?;
+            // Wait on concurrent writers before anything that takes a lock
+            conn.pragma_update(None, "busy_timeout", busy_timeout_ms)?;
             // Enable WAL mode for concurrent access
             conn.pragma_update(None, "journal_mode", "WAL")?;
-            conn.pragma_update(None, "busy_timeout", config.database.busy_timeout_ms)?;
             conn.pragma_update(None, "synchronous", "NORMAL")?; // Balance between safety and speed
             Ok(())
         });
@@ -193,12 +393,20 @@ impl SqliteDatabase {
         if is_new_db {
             // NEW DATABASE: Create complete schema with all migration columns
             conn.execute_batch(SCHEMA)?;
+            conn.execute_batch(REPORTING_VIEWS)?;
 
-            // Mark as fully migrated (v4 includes all adaptive learning features)
+            // Mark as fully migrated (SCHEMA includes every migration's tables and columns)
             conn.execute(
                 "INSERT INTO schema_migrations (version, applied_at, checksum, description, execution_time_ms)
-                 VALUES (?1, ?2, '', 'New database with complete schema (v4)', 0)",
-                params![4, chrono::Local::now().to_rfc3339()],
+                 VALUES (?1, ?2, '', ?3, 0)",
+                params![
+                    CURRENT_SCHEMA_VERSION,
+                    crate::environment::now_local().to_rfc3339(),
+                    format!(
+                        "New database with complete schema (v{})",
+                        CURRENT_SCHEMA_VERSION
+                    )
+                ],
             )?;
         } else {
             // OLD DATABASE: Only ensure base tables exist, let migrations add columns/indexes
@@ -296,8 +504,188 @@ impl SqliteDatabase {
         })
     }
 
-    /// Update only max_tokens_observed for a session (for adaptive learning)
-    /// Only updates if new value is greater than current value
+    /// Overwrites a session's start time, active time and token counts with
+    /// values re-derived from its transcript (see `utils::transcript_stats`),
+    /// and raises its peak context size if the transcript saw a higher one.
+    /// Values the transcript doesn't provide are kept, and daily token totals
+    /// are left alone. Returns whether anything differed; with `dry_run`
+    /// nothing is written.
+    pub fn rebuild_session(
+        &self,
+        session_id: &str,
+        rebuilt: &crate::utils::TranscriptStats,
+        dry_run: bool,
+    ) -> Result<bool> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+
+        let current: Option<(String, i64, [i64; 4], i64)> = tx
+            .query_row(
+                "SELECT start_time, COALESCE(active_seconds, 0),
+                        COALESCE(total_input_tokens, 0), COALESCE(total_output_tokens, 0),
+                        COALESCE(total_cache_read_tokens, 0), COALESCE(total_cache_creation_tokens, 0),
+                        COALESCE(max_tokens_observed, 0)
+                 FROM sessions WHERE session_id = ?1",
+                params![session_id],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        [row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?],
+                        row.get(6)?,
+                    ))
+                },
+            )
+            .optional()?;
+        let Some((start_time, active, tokens, max_tokens)) = current else {
+            return Ok(false);
+        };
+
+        let new_start = rebuilt
+            .started
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+            .map(|started| started.with_timezone(&Local).to_rfc3339())
+            .filter(|started| {
+                // Same instant in another format or timezone isn't a change
+                chrono::DateTime::parse_from_rfc3339(&start_time).ok()
+                    != chrono::DateTime::parse_from_rfc3339(started).ok()
+            });
+        let new_active = rebuilt
+            .active_secs
+            .map(|secs| secs as i64)
+            .filter(|&secs| secs != active);
+        let new_tokens = rebuilt
+            .tokens
+            .as_ref()
+            .map(|t| {
+                [
+                    t.input_tokens as i64,
+                    t.output_tokens as i64,
+                    t.cache_read_tokens as i64,
+                    t.cache_creation_tokens as i64,
+                ]
+            })
+            .filter(|t| *t != tokens);
+        // The peak only ever rises: a compacted or truncated transcript can show less
+        let new_max = rebuilt
+            .max_tokens
+            .map(|t| t as i64)
+            .filter(|&t| t > max_tokens);
+
+        let changed = new_start.is_some()
+            || new_active.is_some()
+            || new_tokens.is_some()
+            || new_max.is_some();
+        if !changed || dry_run {
+            return Ok(changed);
+        }
+
+        tx.execute(
+            "UPDATE sessions SET
+                start_time = COALESCE(?2, start_time),
+                active_seconds = COALESCE(?3, active_seconds),
+                total_input_tokens = COALESCE(?4, total_input_tokens),
+                total_output_tokens = COALESCE(?5, total_output_tokens),
+                total_cache_read_tokens = COALESCE(?6, total_cache_read_tokens),
+                total_cache_creation_tokens = COALESCE(?7, total_cache_creation_tokens),
+                max_tokens_observed = MAX(COALESCE(max_tokens_observed, 0), COALESCE(?8, 0))
+             WHERE session_id = ?1",
+            params![
+                session_id,
+                new_start,
+                new_active,
+                new_tokens.map(|t| t[0]),
+                new_tokens.map(|t| t[1]),
+                new_tokens.map(|t| t[2]),
+                new_tokens.map(|t| t[3]),
+                new_max
+            ],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Mark a session as ended now (SessionEnd hook); an already closed session
+    /// keeps its end time. Returns false if the session is unknown or closed.
+    pub fn end_session(&self, session_id: &str) -> Result<bool> {
+        let conn = self.get_connection()?;
+        let updated = conn.execute(
+            "UPDATE sessions SET ended_at = ?2 WHERE session_id = ?1 AND ended_at IS NULL",
+            params![session_id, current_timestamp()],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Set or clear (None) a session's note
+    /// Returns false if the session has not been recorded yet
+    pub fn set_session_note(&self, session_id: &str, note: Option<&str>) -> Result<bool> {
+        let conn = self.get_connection()?;
+        let updated = conn.execute(
+            "UPDATE sessions SET note = ?2 WHERE session_id = ?1",
+            params![session_id, note],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Get the current user's sessions updated within the last `window_minutes`
+    /// that have not ended, with their combined burn rate.
+    ///
+    /// `current_session` is left out of the count but included in the burn rate.
+    /// Sessions younger than a minute don't contribute a rate yet.
+    pub fn get_active_sessions(
+        &self,
+        window_minutes: u64,
+        current_session: Option<&str>,
+    ) -> Result<ActiveSessions> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT session_id, COALESCE(cost, 0.0),
+                    (julianday(last_updated) - julianday(start_time)) * 24
+             FROM sessions
+             WHERE ended_at IS NULL AND user = ?1
+               AND julianday(last_updated) >= julianday(?2)",
+        )?;
+        let since =
+            crate::environment::current().now() - chrono::Duration::minutes(window_minutes as i64);
+        let rows = stmt.query_map(
+            params![crate::common::get_username(), since.to_rfc3339()],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, f64>(1)?,
+                    row.get::<_, Option<f64>>(2)?.unwrap_or(0.0),
+                ))
+            },
+        )?;
+
+        let mut active = ActiveSessions::default();
+        for row in rows {
+            let (session_id, cost, hours) = row?;
+            if Some(session_id.as_str()) != current_session {
+                active.others += 1;
+            }
+            if hours >= 1.0 / 60.0 {
+                active.burn_rate += cost / hours;
+            }
+        }
+        Ok(active)
+    }
+
+    /// Get a session's note, if one was set
+    pub fn get_session_note(&self, session_id: &str) -> Result<Option<String>> {
+        let conn = self.get_connection()?;
+        Ok(conn
+            .query_row(
+                "SELECT note FROM sessions WHERE session_id = ?1",
+                params![session_id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten())
+    }
+
+    /// Record the current context size of a session: always as the last seen
+    /// size, and as max_tokens_observed (for adaptive learning) if it is higher
     pub fn update_max_tokens_observed(&self, session_id: &str, current_tokens: u32) -> Result<()> {
         let retry_config = RetryConfig::for_db_ops();
 
@@ -305,9 +693,9 @@ impl SqliteDatabase {
             let conn = self.get_connection()?;
             conn.execute(
                 "UPDATE sessions
-                 SET max_tokens_observed = ?2
-                 WHERE session_id = ?1
-                   AND (max_tokens_observed IS NULL OR max_tokens_observed < ?2)",
+                 SET last_tokens_observed = ?2,
+                     max_tokens_observed = MAX(COALESCE(max_tokens_observed, 0), ?2)
+                 WHERE session_id = ?1",
                 params![session_id, current_tokens as i64],
             )?;
             Ok(())
@@ -360,6 +748,8 @@ impl SqliteDatabase {
         let model_name = update.model_name.as_deref();
         let workspace_dir = update.workspace_dir.as_deref();
         let device_id = update.device_id.as_deref();
+        let git_branch = update.git_branch.as_deref();
+        let user = crate::common::get_username();
 
         // Check if session already exists and get old values
         let old_values: Option<(f64, i64, i64)> = tx
@@ -398,19 +788,23 @@ impl SqliteDatabase {
             })
             .unwrap_or((0, 0, 0, 0));
 
+        // An update without a breakdown leaves the session's stored counts untouched
+        let has_tokens = update.token_breakdown.is_some();
+
         // Convert max_tokens_observed to i64 for SQLite
         let max_tokens = update.max_tokens_observed.map(|t| t as i64);
 
         // UPSERT session (atomic operation)
-        // Note: On conflict, we REPLACE the values, not accumulate them
+        // Note: On conflict, we REPLACE the values, not accumulate them.
+        // An update after the session's end time reopens it.
         tx.execute(
             "INSERT INTO sessions (
                 session_id, start_time, last_updated, cost, lines_added, lines_removed,
                 model_name, workspace_dir, device_id,
                 total_input_tokens, total_output_tokens, total_cache_read_tokens, total_cache_creation_tokens,
-                max_tokens_observed
+                max_tokens_observed, user, git_branch
              )
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?17)
              ON CONFLICT(session_id) DO UPDATE SET
                 last_updated = ?3,
                 cost = ?4,
@@ -419,20 +813,26 @@ impl SqliteDatabase {
                 model_name = ?7,
                 workspace_dir = ?8,
                 device_id = ?9,
-                total_input_tokens = ?10,
-                total_output_tokens = ?11,
-                total_cache_read_tokens = ?12,
-                total_cache_creation_tokens = ?13,
+                total_input_tokens = CASE WHEN ?16 THEN ?10 ELSE total_input_tokens END,
+                total_output_tokens = CASE WHEN ?16 THEN ?11 ELSE total_output_tokens END,
+                total_cache_read_tokens = CASE WHEN ?16 THEN ?12 ELSE total_cache_read_tokens END,
+                total_cache_creation_tokens = CASE WHEN ?16 THEN ?13 ELSE total_cache_creation_tokens END,
                 max_tokens_observed = CASE
                     WHEN ?14 IS NOT NULL AND ?14 > COALESCE(max_tokens_observed, 0)
                     THEN ?14
                     ELSE max_tokens_observed
+                END,
+                user = ?15,
+                git_branch = COALESCE(?17, git_branch),
+                ended_at = CASE
+                    WHEN julianday(?3) > julianday(ended_at) THEN NULL
+                    ELSE ended_at
                 END",
             params![
                 session_id, &now, &now, cost, lines_added as i64, lines_removed as i64,
                 model_name, workspace_dir, device_id,
                 input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens,
-                max_tokens
+                max_tokens, &user, has_tokens, git_branch
             ],
         )?;
 
@@ -440,60 +840,86 @@ impl SqliteDatabase {
         // Since we don't have a junction table, we'll use the session_count field itself
         // as a counter that gets SET (not incremented) based on actual distinct sessions
 
-        // For daily: count distinct sessions that have been updated today
+        // For daily: count distinct sessions this user has updated today
         // We determine "updated today" by checking if last_updated matches today's date
         // Use 'localtime' modifier to ensure timezone consistency with current_date()
         let daily_session_count: i64 = tx
             .query_row(
                 "SELECT COUNT(DISTINCT session_id) FROM sessions
-                 WHERE date(last_updated, 'localtime') = ?1",
-                params![&today],
+                 WHERE date(last_updated, 'localtime') = ?1 AND user = ?2",
+                params![&today, &user],
                 |row| row.get(0),
             )
             .unwrap_or(1); // Default to 1 (this session) if query fails
 
-        // For monthly: count distinct sessions updated this month
+        // For monthly: count distinct sessions this user has updated this month
         // Use 'localtime' modifier to ensure timezone consistency with current_month()
         let monthly_session_count: i64 = tx
             .query_row(
                 "SELECT COUNT(DISTINCT session_id) FROM sessions
-                 WHERE strftime('%Y-%m', last_updated, 'localtime') = ?1",
-                params![&month],
+                 WHERE strftime('%Y-%m', last_updated, 'localtime') = ?1 AND user = ?2",
+                params![&month, &user],
                 |row| row.get(0),
             )
             .unwrap_or(1);
 
         // Update daily stats atomically with delta values
         // Note: session_count is SET (not incremented) to the actual count of distinct sessions
+        // Token totals are added per API call by record_transcript_activity
         tx.execute(
-            "INSERT INTO daily_stats (date, total_cost, total_lines_added, total_lines_removed, session_count)
-             VALUES (?1, ?2, ?3, ?4, ?5)
-             ON CONFLICT(date) DO UPDATE SET
+            "INSERT INTO daily_stats (date, user, total_cost, total_lines_added, total_lines_removed, session_count)
+             VALUES (?1, ?6, ?2, ?3, ?4, ?5)
+             ON CONFLICT(date, user) DO UPDATE SET
                 total_cost = total_cost + ?2,
                 total_lines_added = total_lines_added + ?3,
                 total_lines_removed = total_lines_removed + ?4,
                 session_count = ?5",
-            params![&today, cost_delta, lines_added_delta, lines_removed_delta, daily_session_count],
+            params![&today, cost_delta, lines_added_delta, lines_removed_delta, daily_session_count, &user],
         )?;
 
         // Update monthly stats atomically with delta values
         // Note: session_count is SET (not incremented) to the actual count of distinct sessions
         tx.execute(
-            "INSERT INTO monthly_stats (month, total_cost, total_lines_added, total_lines_removed, session_count)
-             VALUES (?1, ?2, ?3, ?4, ?5)
-             ON CONFLICT(month) DO UPDATE SET
+            "INSERT INTO monthly_stats (month, user, total_cost, total_lines_added, total_lines_removed, session_count)
+             VALUES (?1, ?6, ?2, ?3, ?4, ?5)
+             ON CONFLICT(month, user) DO UPDATE SET
                 total_cost = total_cost + ?2,
                 total_lines_added = total_lines_added + ?3,
                 total_lines_removed = total_lines_removed + ?4,
                 session_count = ?5",
-            params![&month, cost_delta, lines_added_delta, lines_removed_delta, monthly_session_count],
+            params![&month, cost_delta, lines_added_delta, lines_removed_delta, monthly_session_count, &user],
         )?;
 
+        // Attribute the change to the branch checked out while it happened
+        if let Some(branch) = git_branch {
+            if cost_delta != 0.0 || lines_added_delta != 0 || lines_removed_delta != 0 {
+                tx.execute(
+                    "INSERT INTO branch_stats (
+                        date, user, workspace_dir, branch, total_cost, total_lines_added, total_lines_removed
+                     )
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(date, user, workspace_dir, branch) DO UPDATE SET
+                        total_cost = total_cost + ?5,
+                        total_lines_added = total_lines_added + ?6,
+                        total_lines_removed = total_lines_removed + ?7",
+                    params![
+                        &today,
+                        &user,
+                        workspace_dir.unwrap_or(""),
+                        branch,
+                        cost_delta,
+                        lines_added_delta,
+                        lines_removed_delta
+                    ],
+                )?;
+            }
+        }
+
         // Get totals for return
         let day_total: f64 = tx
             .query_row(
-                "SELECT total_cost FROM daily_stats WHERE date = ?1",
-                params![&today],
+                "SELECT total_cost FROM daily_stats WHERE date = ?1 AND user = ?2",
+                params![&today, &user],
                 |row| row.get(0),
             )
             .unwrap_or(0.0);
@@ -524,7 +950,7 @@ impl SqliteDatabase {
 
         // Parse ISO 8601 timestamp
         if let Ok(start) = chrono::DateTime::parse_from_rfc3339(&start_time) {
-            let now = Local::now();
+            let now = crate::environment::now_local();
             let duration = now.signed_duration_since(start);
             Some(duration.num_seconds() as u64)
         } else {
@@ -545,6 +971,19 @@ impl SqliteDatabase {
         Some(max_tokens as usize)
     }
 
+    /// Context size a session was last seen at (0 if none was recorded since migration v19)
+    pub fn get_session_last_tokens(&self, session_id: &str) -> Option<usize> {
+        let conn = self.get_connection().ok()?;
+        let last_tokens: i64 = conn
+            .query_row(
+                "SELECT COALESCE(last_tokens_observed, 0) FROM sessions WHERE session_id = ?1",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .ok()?;
+        Some(last_tokens as usize)
+    }
+
     /// Get all-time total cost
     #[allow(dead_code)]
     pub fn get_all_time_total(&self) -> Result<f64> {
@@ -563,6 +1002,18 @@ impl SqliteDatabase {
         Ok(count as usize)
     }
 
+    /// Get the current user's all-time cost and session count
+    pub fn get_user_all_time_totals(&self) -> Result<(f64, usize)> {
+        let conn = self.get_connection()?;
+        let (total, count): (f64, i64) = conn.query_row(
+            "SELECT COALESCE(SUM(cost), 0.0), COUNT(*) FROM sessions
+             WHERE user = ?1 OR user IS NULL",
+            params![crate::common::get_username()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((total, count as usize))
+    }
+
     /// Get earliest session date (since date)
     pub fn get_earliest_session_date(&self) -> Result<Option<String>> {
         let conn = self.get_connection()?;
@@ -587,36 +1038,103 @@ impl SqliteDatabase {
         Ok(count > 0)
     }
 
-    /// Get today's total cost
+    /// Get today's total cost for the current user
     #[allow(dead_code)]
     pub fn get_today_total(&self) -> Result<f64> {
         let conn = self.get_connection()?;
         let today = current_date();
         let total: f64 = conn
             .query_row(
-                "SELECT COALESCE(total_cost, 0.0) FROM daily_stats WHERE date = ?1",
-                params![&today],
+                "SELECT COALESCE(total_cost, 0.0) FROM daily_stats WHERE date = ?1 AND user = ?2",
+                params![&today, crate::common::get_username()],
                 |row| row.get(0),
             )
             .unwrap_or(0.0);
         Ok(total)
     }
 
-    /// Get current month's total cost
-    #[allow(dead_code)]
+    /// Average daily cost of the current user's active days in `[start_date, end_date)`,
+    /// with the number of days it covers
+    pub fn get_average_daily_cost(&self, start_date: &str, end_date: &str) -> Result<(f64, usize)> {
+        let conn = self.get_connection()?;
+        let (average, days): (f64, i64) = conn.query_row(
+            "SELECT COALESCE(AVG(total_cost), 0.0), COUNT(*) FROM daily_stats
+             WHERE date >= ?1 AND date < ?2 AND user = ?3 AND total_cost > 0",
+            params![start_date, end_date, crate::common::get_username()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((average, days as usize))
+    }
+
+    /// Share of the current user's other sessions that cost less than `cost` (0-100),
+    /// with the number of sessions compared against
+    pub fn get_cost_percentile(&self, session_id: &str, cost: f64) -> Result<(u32, usize)> {
+        let conn = self.get_connection()?;
+        let (cheaper, total): (i64, i64) = conn.query_row(
+            "SELECT COALESCE(SUM(cost < ?2), 0), COUNT(*) FROM sessions
+             WHERE session_id != ?1 AND (user = ?3 OR user IS NULL)",
+            params![session_id, cost, crate::common::get_username()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let percentile = if total > 0 {
+            (cheaper * 100 / total) as u32
+        } else {
+            0
+        };
+        Ok((percentile, total as usize))
+    }
+
+    /// Get current month's total cost for the current user
     pub fn get_month_total(&self) -> Result<f64> {
         let conn = self.get_connection()?;
         let month = current_month();
         let total: f64 = conn
             .query_row(
-                "SELECT COALESCE(total_cost, 0.0) FROM monthly_stats WHERE month = ?1",
-                params![&month],
+                "SELECT COALESCE(total_cost, 0.0) FROM monthly_stats WHERE month = ?1 AND user = ?2",
+                params![&month, crate::common::get_username()],
                 |row| row.get(0),
             )
             .unwrap_or(0.0);
         Ok(total)
     }
 
+    /// Get the current user's total cost from `daily_stats` for dates between
+    /// `start_date` and `end_date` (inclusive)
+    pub fn get_cost_between(&self, start_date: &str, end_date: &str) -> Result<f64> {
+        let conn = self.get_connection()?;
+        let total: f64 = conn.query_row(
+            "SELECT COALESCE(SUM(total_cost), 0.0) FROM daily_stats
+             WHERE date BETWEEN ?1 AND ?2 AND user = ?3",
+            params![start_date, end_date, crate::common::get_username()],
+            |row| row.get(0),
+        )?;
+        Ok(total)
+    }
+
+    /// Get the current user's token sums from `daily_stats` for dates between
+    /// `start_date` and `end_date` (inclusive)
+    pub fn get_token_totals(&self, start_date: &str, end_date: &str) -> Result<TokenTotals> {
+        let conn = self.get_connection()?;
+        conn.query_row(
+            "SELECT COALESCE(SUM(total_input_tokens), 0),
+                    COALESCE(SUM(total_output_tokens), 0),
+                    COALESCE(SUM(total_cache_read_tokens), 0),
+                    COALESCE(SUM(total_cache_creation_tokens), 0)
+             FROM daily_stats
+             WHERE date BETWEEN ?1 AND ?2 AND user = ?3",
+            params![start_date, end_date, crate::common::get_username()],
+            |row| {
+                let count = |idx: usize| row.get::<_, i64>(idx).map(|v| v.max(0) as u64);
+                Ok(TokenTotals {
+                    input: count(0)?,
+                    output: count(1)?,
+                    cache_read: count(2)?,
+                    cache_creation: count(3)?,
+                })
+            },
+        )
+    }
+
     /// Check if database is initialized and accessible
     #[allow(dead_code)]
     pub fn is_healthy(&self) -> bool {
@@ -663,7 +1181,7 @@ impl SqliteDatabase {
         Ok(count)
     }
 
-    /// Get all sessions from the database
+    /// Get all of the current user's sessions from the database
     pub fn get_all_sessions(
         &self,
     ) -> Result<std::collections::HashMap<String, crate::stats::SessionStats>> {
@@ -673,10 +1191,11 @@ impl SqliteDatabase {
         let conn = self.get_connection()?;
         let mut stmt = conn.prepare(
             "SELECT session_id, start_time, last_updated, cost, lines_added, lines_removed, max_tokens_observed
-             FROM sessions",
+             FROM sessions
+             WHERE user = ?1 OR user IS NULL",
         )?;
 
-        let session_iter = stmt.query_map([], |row| {
+        let session_iter = stmt.query_map(params![crate::common::get_username()], |row| {
             let session_id: String = row.get(0)?;
             let start_time: Option<String> = row.get(1).ok();
             let last_updated: String = row.get(2)?;
@@ -710,6 +1229,7 @@ impl SqliteDatabase {
     /// Get all sessions with token data for rebuilding learned context windows
     /// Prefers max_tokens_observed (actual context usage) over token sum
     /// Preserves device_id and last_updated for accurate historical replay
+    #[cfg(feature = "adaptive-learning")]
     pub fn get_all_sessions_with_tokens(&self) -> Result<Vec<SessionWithModel>> {
         let conn = self.get_connection()?;
         let mut stmt = conn.prepare(
@@ -744,7 +1264,7 @@ impl SqliteDatabase {
         Ok(sessions)
     }
 
-    /// Get all daily stats from the database
+    /// Get the current user's daily stats from the database
     pub fn get_all_daily_stats(
         &self,
     ) -> Result<std::collections::HashMap<String, crate::stats::DailyStats>> {
@@ -754,10 +1274,11 @@ impl SqliteDatabase {
         let conn = self.get_connection()?;
         let mut stmt = conn.prepare(
             "SELECT date, total_cost, total_lines_added, total_lines_removed
-             FROM daily_stats",
+             FROM daily_stats
+             WHERE user = ?1",
         )?;
 
-        let daily_iter = stmt.query_map([], |row| {
+        let daily_iter = stmt.query_map(params![crate::common::get_username()], |row| {
             let date: String = row.get(0)?;
             let total_cost: f64 = row.get(1)?;
             let lines_added: i64 = row.get(2)?;
@@ -783,7 +1304,7 @@ impl SqliteDatabase {
         Ok(daily)
     }
 
-    /// Get all monthly stats from the database
+    /// Get the current user's monthly stats from the database
     pub fn get_all_monthly_stats(
         &self,
     ) -> Result<std::collections::HashMap<String, crate::stats::MonthlyStats>> {
@@ -793,10 +1314,11 @@ impl SqliteDatabase {
         let conn = self.get_connection()?;
         let mut stmt = conn.prepare(
             "SELECT month, total_cost, total_lines_added, total_lines_removed, session_count
-             FROM monthly_stats",
+             FROM monthly_stats
+             WHERE user = ?1",
         )?;
 
-        let monthly_iter = stmt.query_map([], |row| {
+        let monthly_iter = stmt.query_map(params![crate::common::get_username()], |row| {
             let month: String = row.get(0)?;
             let total_cost: f64 = row.get(1)?;
             let lines_added: i64 = row.get(2)?;
@@ -823,6 +1345,376 @@ impl SqliteDatabase {
         Ok(monthly)
     }
 
+    // ========================================================================
+    // Reporting Queries (stats command)
+    // ========================================================================
+
+    /// Get daily cost totals (all users) for dates on or after `start_date` (YYYY-MM-DD),
+    /// oldest first
+    pub fn get_daily_costs_since(&self, start_date: &str) -> Result<Vec<(String, f64)>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT date, SUM(total_cost) FROM daily_stats
+             WHERE date >= ?1
+             GROUP BY date
+             ORDER BY date ASC",
+        )?;
+        let rows = stmt
+            .query_map(params![start_date], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<(String, f64)>>>()?;
+        Ok(rows)
+    }
+
+    /// Get cost per local hour of day for sessions active on `date` (YYYY-MM-DD)
+    ///
+    /// Session cost is attributed to the hour the session started, since
+    /// per-hour cost deltas are not stored.
+    pub fn get_hourly_costs(&self, date: &str) -> Result<Vec<(u32, f64)>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT CAST(strftime('%H', start_time, 'localtime') AS INTEGER) AS hour, SUM(cost)
+             FROM sessions
+             WHERE date(last_updated, 'localtime') = ?1
+               AND strftime('%H', start_time, 'localtime') IS NOT NULL
+             GROUP BY hour
+             ORDER BY hour ASC",
+        )?;
+        let rows = stmt
+            .query_map(params![date], |row| {
+                Ok((row.get::<_, i64>(0)? as u32, row.get(1)?))
+            })?
+            .collect::<Result<Vec<(u32, f64)>>>()?;
+        Ok(rows)
+    }
+
+    /// Get the current user's aggregated totals for dates between `start_date`
+    /// and `end_date` (inclusive)
+    ///
+    /// Cost, lines and tokens (input + output, summed per API call) come from
+    /// `daily_stats`; sessions are counted once each if they were active on any
+    /// day of the range.
+    pub fn get_period_totals(&self, start_date: &str, end_date: &str) -> Result<PeriodTotals> {
+        let conn = self.get_connection()?;
+        let user = crate::common::get_username();
+        let (cost, lines_changed, tokens) = conn.query_row(
+            "SELECT COALESCE(SUM(total_cost), 0.0),
+                    COALESCE(SUM(total_lines_added + total_lines_removed), 0),
+                    COALESCE(SUM(COALESCE(total_input_tokens, 0) + COALESCE(total_output_tokens, 0)), 0)
+             FROM daily_stats
+             WHERE date BETWEEN ?1 AND ?2 AND user = ?3",
+            params![start_date, end_date, &user],
+            |row| {
+                Ok((
+                    row.get::<_, f64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            },
+        )?;
+        let sessions: i64 = conn.query_row(
+            "SELECT COUNT(DISTINCT session_id) FROM sessions
+             WHERE date(start_time, 'localtime') <= ?2
+               AND date(last_updated, 'localtime') >= ?1
+               AND (user = ?3 OR user IS NULL)",
+            params![start_date, end_date, &user],
+            |row| row.get(0),
+        )?;
+
+        Ok(PeriodTotals {
+            cost,
+            sessions: sessions.max(0) as u64,
+            lines_changed: lines_changed.max(0) as u64,
+            tokens: tokens.max(0) as u64,
+        })
+    }
+
+    /// Get usage per workspace for sessions last updated on or after `since`
+    /// (YYYY-MM-DD, `None` for all time), ranked by cost then tokens
+    pub fn get_project_usage(
+        &self,
+        since: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<GroupedUsage>> {
+        self.get_grouped_usage("workspace_dir", since, Some(limit))
+    }
+
+    /// Get usage per stored model name for sessions last updated on or after `since`
+    /// (YYYY-MM-DD, `None` for all time)
+    pub fn get_model_usage(&self, since: Option<&str>) -> Result<Vec<GroupedUsage>> {
+        self.get_grouped_usage("model_name", since, None)
+    }
+
+    /// Get usage per user for sessions last updated on or after `since`
+    /// (YYYY-MM-DD, `None` for all time), ranked by cost then tokens
+    pub fn get_user_usage(&self, since: Option<&str>) -> Result<Vec<GroupedUsage>> {
+        self.get_grouped_usage("user", since, None)
+    }
+
+    /// Get cost and line changes per (workspace, git branch) on or after `since`
+    /// (YYYY-MM-DD, `None` for all time), most expensive first
+    pub fn get_branch_usage(&self, since: Option<&str>) -> Result<Vec<BranchUsage>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT workspace_dir, branch,
+                    COALESCE(SUM(total_cost), 0.0),
+                    COALESCE(SUM(total_lines_added), 0),
+                    COALESCE(SUM(total_lines_removed), 0),
+                    MAX(date)
+             FROM branch_stats
+             WHERE ?1 IS NULL OR date >= ?1
+             GROUP BY workspace_dir, branch
+             ORDER BY 3 DESC, 4 DESC",
+        )?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                Ok(BranchUsage {
+                    workspace_dir: row.get(0)?,
+                    branch: row.get(1)?,
+                    cost: row.get(2)?,
+                    lines_added: row.get::<_, i64>(3)?.max(0) as u64,
+                    lines_removed: row.get::<_, i64>(4)?.max(0) as u64,
+                    last_date: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Get session counts and cost per (weekday, hour) of local session start time
+    /// for sessions last updated on or after `since` (YYYY-MM-DD, `None` for all time)
+    ///
+    /// Weekdays use SQLite's `%w` numbering (0 = Sunday).
+    pub fn get_weekday_hour_activity(
+        &self,
+        since: Option<&str>,
+    ) -> Result<Vec<(u32, u32, u64, f64)>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT CAST(strftime('%w', start_time, 'localtime') AS INTEGER) AS weekday,
+                    CAST(strftime('%H', start_time, 'localtime') AS INTEGER) AS hour,
+                    COUNT(*),
+                    COALESCE(SUM(cost), 0.0)
+             FROM sessions
+             WHERE (?1 IS NULL OR date(last_updated, 'localtime') >= ?1)
+               AND strftime('%w', start_time, 'localtime') IS NOT NULL
+             GROUP BY weekday, hour",
+        )?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u32,
+                    row.get::<_, i64>(1)? as u32,
+                    row.get::<_, i64>(2)?.max(0) as u64,
+                    row.get(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Get a single session's totals, model and workspace
+    pub fn get_session_summary(&self, session_id: &str) -> Result<Option<SessionSummary>> {
+        let conn = self.get_connection()?;
+        conn.query_row(
+            "SELECT session_id, start_time, cost,
+                    COALESCE(total_input_tokens, 0) + COALESCE(total_output_tokens, 0),
+                    model_name, workspace_dir,
+                    CAST((julianday(COALESCE(ended_at, last_updated)) - julianday(start_time)) * 86400 AS INTEGER)
+             FROM sessions
+             WHERE session_id = ?1",
+            params![session_id],
+            |row| {
+                Ok(SessionSummary {
+                    session_id: row.get(0)?,
+                    start_time: row.get(1)?,
+                    cost: row.get(2)?,
+                    tokens: row.get::<_, i64>(3)?.max(0) as u64,
+                    model_name: row.get(4)?,
+                    workspace_dir: row.get(5)?,
+                    duration_secs: row.get::<_, Option<i64>>(6)?.map(|d| d.max(0) as u64),
+                })
+            },
+        )
+        .optional()
+    }
+
+    /// Get the most expensive sessions last updated between `start_date` and
+    /// `end_date` (YYYY-MM-DD, inclusive), with tokens summed over their
+    /// recorded API calls
+    pub fn get_top_sessions(
+        &self,
+        start_date: &str,
+        end_date: &str,
+        limit: usize,
+    ) -> Result<Vec<SessionSummary>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT s.session_id, s.start_time, s.cost,
+                    (SELECT COALESCE(SUM(COALESCE(u.input_tokens, 0) + COALESCE(u.output_tokens, 0)), 0)
+                     FROM session_usage u WHERE u.session_id = s.session_id) AS tokens,
+                    s.model_name, s.workspace_dir,
+                    CAST((julianday(COALESCE(s.ended_at, s.last_updated)) - julianday(s.start_time)) * 86400 AS INTEGER)
+             FROM sessions s
+             WHERE date(s.last_updated, 'localtime') BETWEEN ?1 AND ?2
+             ORDER BY s.cost DESC, tokens DESC
+             LIMIT ?3",
+        )?;
+        let rows = stmt
+            .query_map(params![start_date, end_date, limit as i64], |row| {
+                Ok(SessionSummary {
+                    session_id: row.get(0)?,
+                    start_time: row.get(1)?,
+                    cost: row.get(2)?,
+                    tokens: row.get::<_, i64>(3)?.max(0) as u64,
+                    model_name: row.get(4)?,
+                    workspace_dir: row.get(5)?,
+                    duration_secs: row.get::<_, Option<i64>>(6)?.map(|d| d.max(0) as u64),
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Get the time blocks of sessions last updated on or after `since`
+    /// (YYYY-MM-DD, `None` for all time), oldest first, for timesheet exports
+    pub fn get_timesheet_entries(&self, since: Option<&str>) -> Result<Vec<TimesheetEntry>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT session_id, start_time, COALESCE(ended_at, last_updated),
+                    COALESCE(active_seconds, 0), workspace_dir, cost, note
+             FROM sessions
+             WHERE ?1 IS NULL OR date(last_updated, 'localtime') >= ?1
+             ORDER BY start_time",
+        )?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                Ok(TimesheetEntry {
+                    session_id: row.get(0)?,
+                    start_time: row.get(1)?,
+                    end_time: row.get(2)?,
+                    active_secs: row.get::<_, i64>(3)?.max(0) as u64,
+                    workspace_dir: row.get(4)?,
+                    cost: row.get(5)?,
+                    note: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Get token and cost totals per (local date, model) on or after `since`
+    /// (YYYY-MM-DD, `None` for all time), oldest first
+    ///
+    /// Tokens are the recorded API calls (`session_usage`), counted on the day
+    /// of each call under their session's model. Cost is only stored per
+    /// session, so it counts toward the day the session was last updated.
+    pub fn get_daily_model_usage(&self, since: Option<&str>) -> Result<Vec<DailyModelUsage>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT day, model,
+                    COALESCE(SUM(input), 0),
+                    COALESCE(SUM(output), 0),
+                    COALESCE(SUM(cache_creation), 0),
+                    COALESCE(SUM(cache_read), 0),
+                    COALESCE(SUM(cost), 0.0)
+             FROM (
+                SELECT u.date AS day, COALESCE(s.model_name, '') AS model,
+                       u.input_tokens AS input, u.output_tokens AS output,
+                       u.cache_creation_tokens AS cache_creation,
+                       u.cache_read_tokens AS cache_read, 0.0 AS cost
+                FROM session_usage u LEFT JOIN sessions s ON s.session_id = u.session_id
+                UNION ALL
+                SELECT date(last_updated, 'localtime'), COALESCE(model_name, ''),
+                       0, 0, 0, 0, cost
+                FROM sessions
+             )
+             WHERE day IS NOT NULL AND (?1 IS NULL OR day >= ?1)
+             GROUP BY day, model
+             ORDER BY day ASC, model ASC",
+        )?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                let count = |idx: usize| row.get::<_, i64>(idx).map(|v| v.max(0) as u64);
+                Ok(DailyModelUsage {
+                    date: row.get(0)?,
+                    model_name: row.get(1)?,
+                    input_tokens: count(2)?,
+                    output_tokens: count(3)?,
+                    cache_creation_tokens: count(4)?,
+                    cache_read_tokens: count(5)?,
+                    cost: row.get(6)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Get the current user's cache-read token totals per stored model name,
+    /// summed over the API calls made on or after `since` (YYYY-MM-DD, `None`
+    /// for all time)
+    pub fn get_cache_read_tokens_by_model(
+        &self,
+        since: Option<&str>,
+    ) -> Result<Vec<(String, u64)>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(s.model_name, ''), COALESCE(SUM(u.cache_read_tokens), 0)
+             FROM session_usage u
+             LEFT JOIN sessions s ON s.session_id = u.session_id
+             WHERE (?1 IS NULL OR u.date >= ?1) AND (s.user = ?2 OR s.user IS NULL)
+             GROUP BY 1",
+        )?;
+        let rows = stmt
+            .query_map(params![since, crate::common::get_username()], |row| {
+                Ok((row.get(0)?, row.get::<_, i64>(1)?.max(0) as u64))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Aggregate session usage grouped by a sessions column
+    ///
+    /// Tokens (input + output) are summed over each session's recorded API
+    /// calls. `column` must be a trusted, hard-coded column name.
+    fn get_grouped_usage(
+        &self,
+        column: &str,
+        since: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<Vec<GroupedUsage>> {
+        let conn = self.get_connection()?;
+        let sql = format!(
+            "SELECT COALESCE(s.{column}, '') AS key,
+                    COALESCE(SUM(s.cost), 0.0),
+                    COALESCE(SUM(u.tokens), 0),
+                    COUNT(*)
+             FROM sessions s
+             LEFT JOIN (
+                SELECT session_id,
+                       SUM(COALESCE(input_tokens, 0) + COALESCE(output_tokens, 0)) AS tokens
+                FROM session_usage
+                GROUP BY session_id
+             ) u ON u.session_id = s.session_id
+             WHERE ?1 IS NULL OR date(s.last_updated, 'localtime') >= ?1
+             GROUP BY key
+             ORDER BY 2 DESC, 3 DESC
+             LIMIT ?2"
+        );
+        let limit = limit.map(|l| l as i64).unwrap_or(-1);
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params![since, limit], |row| {
+                Ok(GroupedUsage {
+                    key: row.get(0)?,
+                    cost: row.get(1)?,
+                    tokens: row.get::<_, i64>(2)?.max(0) as u64,
+                    sessions: row.get::<_, i64>(3)?.max(0) as u64,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
     /// Import sessions from JSON stats data (for migration)
     pub fn import_sessions(
         &self,
@@ -830,12 +1722,13 @@ impl SqliteDatabase {
     ) -> Result<()> {
         let mut conn = self.get_connection()?;
         let tx = conn.transaction()?;
+        let user = crate::common::get_username();
 
         for (session_id, session) in sessions.iter() {
             // Insert session (don't use UPSERT, just INSERT as this is initial import)
             tx.execute(
-                "INSERT OR IGNORE INTO sessions (session_id, start_time, last_updated, cost, lines_added, lines_removed)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                "INSERT OR IGNORE INTO sessions (session_id, start_time, last_updated, cost, lines_added, lines_removed, user)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
                 params![
                     session_id,
                     session.start_time.as_deref().unwrap_or(""),
@@ -843,6 +1736,7 @@ impl SqliteDatabase {
                     session.cost,
                     session.lines_added as i64,
                     session.lines_removed as i64,
+                    &user,
                 ],
             )?;
         }
@@ -851,6 +1745,111 @@ impl SqliteDatabase {
         Ok(())
     }
 
+    /// Import per-day, per-model usage exported by another tool.
+    ///
+    /// Each row becomes one session at noon of its day (local time), whose
+    /// tokens are recorded as a single API call, and is added to the daily and
+    /// monthly totals. Rows imported before are skipped, so importing the same
+    /// file twice is harmless. Returns the number of new rows.
+    pub fn import_daily_model_usage(&self, rows: &[DailyModelUsage]) -> Result<usize> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+        let user = crate::common::get_username();
+        let mut imported = 0;
+
+        for row in rows {
+            let Ok(day) = chrono::NaiveDate::parse_from_str(&row.date, "%Y-%m-%d") else {
+                continue;
+            };
+            let Some(timestamp) = day
+                .and_hms_opt(12, 0, 0)
+                .and_then(|t| t.and_local_timezone(chrono::Local).single())
+                .map(|t| t.to_rfc3339())
+            else {
+                continue;
+            };
+            let date = day.format("%Y-%m-%d").to_string();
+            let month = day.format("%Y-%m").to_string();
+            let session_id = format!("import:{}:{}", date, row.model_name);
+            let model_name = (!row.model_name.is_empty()).then_some(row.model_name.as_str());
+
+            let inserted = tx.execute(
+                "INSERT OR IGNORE INTO sessions (
+                    session_id, start_time, last_updated, cost, model_name, user,
+                    total_input_tokens, total_output_tokens,
+                    total_cache_read_tokens, total_cache_creation_tokens
+                 )
+                 VALUES (?1, ?2, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    &session_id,
+                    &timestamp,
+                    row.cost,
+                    model_name,
+                    &user,
+                    row.input_tokens as i64,
+                    row.output_tokens as i64,
+                    row.cache_read_tokens as i64,
+                    row.cache_creation_tokens as i64
+                ],
+            )?;
+            if inserted == 0 {
+                continue;
+            }
+            tx.execute(
+                "INSERT OR IGNORE INTO session_usage (
+                    session_id, message_id, date, user,
+                    input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens
+                 )
+                 VALUES (?1, ?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    &session_id,
+                    &date,
+                    &user,
+                    row.input_tokens as i64,
+                    row.output_tokens as i64,
+                    row.cache_read_tokens as i64,
+                    row.cache_creation_tokens as i64
+                ],
+            )?;
+            tx.execute(
+                "INSERT INTO daily_stats (
+                    date, user, total_cost, total_lines_added, total_lines_removed, session_count,
+                    total_input_tokens, total_output_tokens,
+                    total_cache_read_tokens, total_cache_creation_tokens
+                 )
+                 VALUES (?1, ?2, ?3, 0, 0, 1, ?4, ?5, ?6, ?7)
+                 ON CONFLICT(date, user) DO UPDATE SET
+                    total_cost = total_cost + ?3,
+                    session_count = session_count + 1,
+                    total_input_tokens = COALESCE(total_input_tokens, 0) + ?4,
+                    total_output_tokens = COALESCE(total_output_tokens, 0) + ?5,
+                    total_cache_read_tokens = COALESCE(total_cache_read_tokens, 0) + ?6,
+                    total_cache_creation_tokens = COALESCE(total_cache_creation_tokens, 0) + ?7",
+                params![
+                    &date,
+                    &user,
+                    row.cost,
+                    row.input_tokens as i64,
+                    row.output_tokens as i64,
+                    row.cache_read_tokens as i64,
+                    row.cache_creation_tokens as i64
+                ],
+            )?;
+            tx.execute(
+                "INSERT INTO monthly_stats (month, user, total_cost, total_lines_added, total_lines_removed, session_count)
+                 VALUES (?1, ?2, ?3, 0, 0, 1)
+                 ON CONFLICT(month, user) DO UPDATE SET
+                    total_cost = total_cost + ?3,
+                    session_count = session_count + 1",
+                params![&month, &user, row.cost],
+            )?;
+            imported += 1;
+        }
+
+        tx.commit()?;
+        Ok(imported)
+    }
+
     /// Upsert session data directly (for sync pull)
     /// This replaces the entire session without delta calculations
     #[cfg(feature = "turso-sync")]
@@ -865,8 +1864,8 @@ impl SqliteDatabase {
     ) -> Result<()> {
         let conn = self.get_connection()?;
         conn.execute(
-            "INSERT OR REPLACE INTO sessions (session_id, start_time, last_updated, cost, lines_added, lines_removed)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            "INSERT OR REPLACE INTO sessions (session_id, start_time, last_updated, cost, lines_added, lines_removed, user)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
             params![
                 session_id,
                 start_time.unwrap_or(""),
@@ -874,6 +1873,7 @@ impl SqliteDatabase {
                 cost,
                 lines_added as i64,
                 lines_removed as i64,
+                crate::common::get_username(),
             ],
         )?;
         Ok(())
@@ -890,13 +1890,14 @@ impl SqliteDatabase {
     ) -> Result<()> {
         let conn = self.get_connection()?;
         conn.execute(
-            "INSERT OR REPLACE INTO daily_stats (date, total_cost, total_lines_added, total_lines_removed, session_count)
-             VALUES (?1, ?2, ?3, ?4, 0)",
+            "INSERT OR REPLACE INTO daily_stats (date, user, total_cost, total_lines_added, total_lines_removed, session_count)
+             VALUES (?1, ?5, ?2, ?3, ?4, 0)",
             params![
                 date,
                 total_cost,
                 lines_added as i64,
                 lines_removed as i64,
+                crate::common::get_username(),
             ],
         )?;
         Ok(())
@@ -914,23 +1915,27 @@ impl SqliteDatabase {
     ) -> Result<()> {
         let conn = self.get_connection()?;
         conn.execute(
-            "INSERT OR REPLACE INTO monthly_stats (month, total_cost, total_lines_added, total_lines_removed, session_count)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
+            "INSERT OR REPLACE INTO monthly_stats (month, user, total_cost, total_lines_added, total_lines_removed, session_count)
+             VALUES (?1, ?6, ?2, ?3, ?4, ?5)",
             params![
                 month,
                 total_cost,
                 lines_added as i64,
                 lines_removed as i64,
                 session_count as i64,
+                crate::common::get_username(),
             ],
         )?;
         Ok(())
     }
+}
 
-    // ========================================================================
-    // Adaptive Context Learning Methods
-    // ========================================================================
+// ========================================================================
+// Adaptive Context Learning Methods
+// ========================================================================
 
+#[cfg(feature = "adaptive-learning")]
+impl SqliteDatabase {
     /// Get learned context window data for a specific model
     pub fn get_learned_context(
         &self,
@@ -995,10 +2000,29 @@ impl SqliteDatabase {
         Ok(())
     }
 
-    /// Update an existing learned context window record
-    pub fn update_learned_context(
+    /// Upsert learned context windows in one transaction, optionally clearing
+    /// all existing records first. Nothing changes if any record fails.
+    pub fn import_learned_contexts(
         &self,
-        record: &crate::context_learning::LearnedContextWindow,
+        records: &[crate::context_learning::LearnedContextWindow],
+        replace: bool,
+    ) -> Result<()> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+        if replace {
+            tx.execute("DELETE FROM learned_context_windows", [])?;
+        }
+        for record in records {
+            upsert_learned_row(&tx, record)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Update an existing learned context window record
+    pub fn update_learned_context(
+        &self,
+        record: &crate::context_learning::LearnedContextWindow,
     ) -> Result<()> {
         let conn = self.get_connection()?;
         conn.execute(
@@ -1083,6 +2107,398 @@ impl SqliteDatabase {
     }
 }
 
+/// Insert or replace a learned context window record, keyed by model name.
+fn upsert_learned_row(
+    conn: &Connection,
+    record: &crate::context_learning::LearnedContextWindow,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO learned_context_windows
+         (model_name, observed_max_tokens, ceiling_observations, compaction_count,
+          last_observed_max, last_updated, confidence_score, first_seen,
+          workspace_dir, device_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            &record.model_name,
+            record.observed_max_tokens as i64,
+            record.ceiling_observations,
+            record.compaction_count,
+            record.last_observed_max as i64,
+            &record.last_updated,
+            record.confidence_score,
+            &record.first_seen,
+            &record.workspace_dir,
+            &record.device_id,
+        ],
+    )?;
+    Ok(())
+}
+
+impl SqliteDatabase {
+    // ========================================================================
+    // Context Usage History (sparkline)
+    // ========================================================================
+
+    /// Record a context usage sample for a session, keeping only the newest `keep` samples.
+    ///
+    /// Consecutive samples that round to the same whole percentage are collapsed,
+    /// so rapid refreshes don't flatten the trend.
+    pub fn record_context_sample(
+        &self,
+        session_id: &str,
+        percentage: f64,
+        keep: usize,
+    ) -> Result<()> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+
+        let last: Option<f64> = tx
+            .query_row(
+                "SELECT percentage FROM context_samples WHERE session_id = ?1 ORDER BY id DESC LIMIT 1",
+                params![session_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        if last.map(|l| l.round() as i64) != Some(percentage.round() as i64) {
+            tx.execute(
+                "INSERT INTO context_samples (session_id, recorded_at, percentage) VALUES (?1, ?2, ?3)",
+                params![session_id, current_timestamp(), percentage],
+            )?;
+
+            // Trim the ring to the newest `keep` samples
+            tx.execute(
+                "DELETE FROM context_samples
+                 WHERE session_id = ?1 AND id NOT IN (
+                     SELECT id FROM context_samples WHERE session_id = ?1 ORDER BY id DESC LIMIT ?2
+                 )",
+                params![session_id, keep as i64],
+            )?;
+        }
+
+        tx.commit()
+    }
+
+    /// Get the most recent context samples for a session, oldest first
+    pub fn get_context_samples(&self, session_id: &str, limit: usize) -> Result<Vec<f64>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT percentage FROM context_samples WHERE session_id = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let mut samples = stmt
+            .query_map(params![session_id, limit as i64], |row| {
+                row.get::<_, f64>(0)
+            })?
+            .collect::<Result<Vec<f64>>>()?;
+        samples.reverse();
+        Ok(samples)
+    }
+
+    // ========================================================================
+    // Edited Files
+    // ========================================================================
+
+    /// Records what a render read from a session's transcript in one
+    /// transaction: its active time (only ever raised), the usage of API calls
+    /// not seen before and, with `with_files`, the files edited. Returns the
+    /// number of new calls.
+    pub fn record_transcript_activity(
+        &self,
+        session_id: &str,
+        activity: &crate::utils::TranscriptActivity,
+        with_files: bool,
+    ) -> Result<usize> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction()?;
+        if let Some(times) = &activity.times {
+            tx.execute(
+                "UPDATE sessions
+                 SET active_seconds = ?2
+                 WHERE session_id = ?1
+                   AND (active_seconds IS NULL OR active_seconds < ?2)",
+                params![session_id, times.active_secs as i64],
+            )?;
+        }
+        let recorded = insert_session_usage(&tx, session_id, &activity.api_calls)?;
+        if with_files {
+            insert_session_files(&tx, session_id, &activity.edited_files)?;
+        }
+        tx.commit()?;
+        Ok(recorded)
+    }
+
+    /// Number of distinct files edited in a session
+    pub fn count_session_files(&self, session_id: &str) -> Result<u64> {
+        let conn = self.get_connection()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM session_files WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
+    // ========================================================================
+    // Line Count Samples (recent change velocity)
+    // ========================================================================
+
+    /// Updates a session's line count samples with `update`, oldest first, in
+    /// one transaction so concurrent renders don't lose samples
+    pub fn update_lines_samples<T>(
+        &self,
+        session_id: &str,
+        update: impl FnOnce(&mut Vec<crate::velocity::LinesSample>) -> T,
+    ) -> Result<T> {
+        let mut conn = self.get_connection()?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+
+        let before = {
+            let mut stmt = tx.prepare(
+                "SELECT sampled_at, lines_added, lines_removed FROM lines_samples
+                 WHERE session_id = ?1 ORDER BY id",
+            )?;
+            let rows = stmt.query_map(params![session_id], |row| {
+                Ok(crate::velocity::LinesSample {
+                    at: row.get(0)?,
+                    added: row.get::<_, i64>(1)?.max(0) as u64,
+                    removed: row.get::<_, i64>(2)?.max(0) as u64,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let mut samples = before.clone();
+        let result = update(&mut samples);
+
+        // A handful of samples per window, so rewriting them is cheap
+        if samples != before {
+            tx.execute(
+                "DELETE FROM lines_samples WHERE session_id = ?1",
+                params![session_id],
+            )?;
+            for sample in &samples {
+                tx.execute(
+                    "INSERT INTO lines_samples (session_id, sampled_at, lines_added, lines_removed)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        session_id,
+                        sample.at,
+                        sample.added as i64,
+                        sample.removed as i64
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Fire a cost alert, or update the value of one that already fired
+    ///
+    /// An alert fires once per (kind, scope), e.g. ("daily", "2025-03-07"); an
+    /// acknowledged alert stays acknowledged. Returns true if it fired now.
+    pub fn record_cost_alert(
+        &self,
+        kind: &str,
+        scope: &str,
+        threshold: f64,
+        value: f64,
+    ) -> Result<bool> {
+        let conn = self.get_connection()?;
+        let updated = conn.execute(
+            "UPDATE cost_alerts SET value = MAX(value, ?3) WHERE kind = ?1 AND scope = ?2",
+            params![kind, scope, value],
+        )?;
+        if updated > 0 {
+            return Ok(false);
+        }
+        conn.execute(
+            "INSERT INTO cost_alerts (kind, scope, threshold, value, fired_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![kind, scope, threshold, value, current_timestamp()],
+        )?;
+        Ok(true)
+    }
+
+    /// Get the alerts that have not been acknowledged, oldest first
+    pub fn get_active_alerts(&self) -> Result<Vec<CostAlert>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT kind, scope, threshold, value, fired_at FROM cost_alerts
+             WHERE acknowledged_at IS NULL
+             ORDER BY fired_at",
+        )?;
+        let alerts = stmt
+            .query_map([], |row| {
+                Ok(CostAlert {
+                    kind: row.get(0)?,
+                    scope: row.get(1)?,
+                    threshold: row.get(2)?,
+                    value: row.get(3)?,
+                    fired_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(alerts)
+    }
+
+    /// Claim delivery of an alert, e.g. ("cost_daily", "2025-03-07")
+    ///
+    /// Returns true, and records the time, only if the alert has not fired
+    /// within the last `cooldown_secs`; concurrent statusline processes race on
+    /// a single upsert, so exactly one of them gets to deliver it.
+    pub fn try_fire_alert(&self, alert_type: &str, key: &str, cooldown_secs: u64) -> Result<bool> {
+        let conn = self.get_connection()?;
+        let now = crate::environment::current().now().timestamp();
+        let fired = conn.execute(
+            "INSERT INTO alert_log (alert_type, alert_key, last_fired_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (alert_type, alert_key) DO UPDATE
+             SET last_fired_at = excluded.last_fired_at, fire_count = fire_count + 1
+             WHERE alert_log.last_fired_at <= ?3 - ?4",
+            params![alert_type, key, now, cooldown_secs as i64],
+        )?;
+        Ok(fired > 0)
+    }
+
+    /// Acknowledge all active alerts, returning how many were acknowledged
+    pub fn acknowledge_alerts(&self) -> Result<usize> {
+        let conn = self.get_connection()?;
+        conn.execute(
+            "UPDATE cost_alerts SET acknowledged_at = ?1 WHERE acknowledged_at IS NULL",
+            params![current_timestamp()],
+        )
+    }
+}
+
+/// Inserts a session's edited files (as hashes), skipping known ones
+fn insert_session_files(tx: &Transaction, session_id: &str, paths: &[String]) -> Result<()> {
+    let now = current_timestamp();
+    let mut stmt = tx.prepare(
+        "INSERT OR IGNORE INTO session_files (session_id, file_hash, first_seen)
+         VALUES (?1, ?2, ?3)",
+    )?;
+    for path in paths {
+        stmt.execute(params![session_id, hash_file_path(path), now])?;
+    }
+    Ok(())
+}
+
+/// Inserts the API calls not recorded yet and adds their tokens to the daily
+/// totals. Returns the number of new calls.
+fn insert_session_usage(
+    tx: &Transaction,
+    session_id: &str,
+    calls: &[crate::utils::ApiCallUsage],
+) -> Result<usize> {
+    let user = crate::common::get_username();
+    let mut recorded = 0;
+    for call in calls {
+        let tokens = [
+            call.tokens.input_tokens as i64,
+            call.tokens.output_tokens as i64,
+            call.tokens.cache_read_tokens as i64,
+            call.tokens.cache_creation_tokens as i64,
+        ];
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO session_usage (
+                session_id, message_id, date, user,
+                input_tokens, output_tokens, cache_read_tokens, cache_creation_tokens
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                session_id,
+                &call.message_id,
+                &call.date,
+                &user,
+                tokens[0],
+                tokens[1],
+                tokens[2],
+                tokens[3]
+            ],
+        )?;
+        if inserted == 0 {
+            continue;
+        }
+        tx.execute(
+            "INSERT INTO daily_stats (
+                date, user, total_input_tokens, total_output_tokens,
+                total_cache_read_tokens, total_cache_creation_tokens
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(date, user) DO UPDATE SET
+                total_input_tokens = COALESCE(total_input_tokens, 0) + ?3,
+                total_output_tokens = COALESCE(total_output_tokens, 0) + ?4,
+                total_cache_read_tokens = COALESCE(total_cache_read_tokens, 0) + ?5,
+                total_cache_creation_tokens = COALESCE(total_cache_creation_tokens, 0) + ?6",
+            params![&call.date, &user, tokens[0], tokens[1], tokens[2], tokens[3]],
+        )?;
+        recorded += 1;
+    }
+    Ok(recorded)
+}
+
+/// Whether an open failure means the database is unusable for a while
+/// (missing, corrupted, read-only), rather than briefly locked by another session
+fn marks_unavailable(error: &rusqlite::Error) -> bool {
+    !matches!(
+        error.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Opens a direct (non-pooled) connection to a stats database, keyed for
+/// SQLCipher when built with the `sqlcipher` feature
+pub fn open_connection(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    apply_encryption_key(&conn)?;
+    Ok(conn)
+}
+
+/// Applies the database encryption key to a freshly opened connection
+///
+/// Must run before any other statement. Without the `sqlcipher` feature this
+/// only warns (once) if a key is configured, since it cannot be honored.
+pub fn apply_encryption_key(conn: &Connection) -> Result<()> {
+    apply_key(conn, encryption_key().as_deref())
+}
+
+fn apply_key(conn: &Connection, key: Option<&str>) -> Result<()> {
+    #[cfg(feature = "sqlcipher")]
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)?;
+    }
+    #[cfg(not(feature = "sqlcipher"))]
+    let _ = (conn, key);
+    Ok(())
+}
+
+/// Resolves the SQLCipher key: STATUSLINE_DB_KEY, then config, then the OS keyring
+#[cfg(feature = "sqlcipher")]
+fn encryption_key() -> Option<String> {
+    // The keyring is per user, not per environment, and slow to query
+    static KEYRING: OnceLock<Option<String>> = OnceLock::new();
+    crate::environment::current()
+        .var("STATUSLINE_DB_KEY")
+        .filter(|k| !k.is_empty())
+        .or_else(|| config::get_config().database.encryption_key.clone())
+        .or_else(|| {
+            KEYRING
+                .get_or_init(|| crate::keyring::get_secret("stats-db"))
+                .clone()
+        })
+}
+
+/// Without SQLCipher there is no key to apply; warns (once) if one is configured
+#[cfg(not(feature = "sqlcipher"))]
+fn encryption_key() -> Option<String> {
+    static WARNED: std::sync::Once = std::sync::Once::new();
+    if config::get_config().database.encryption_key.is_some() {
+        WARNED.call_once(|| {
+            log::warn!("database.encryption_key is set but this build lacks the sqlcipher feature; stats.db is not encrypted");
+        });
+    }
+    None
+}
+
 /// Results from database maintenance operations
 pub struct MaintenanceResult {
     pub checkpoint_done: bool,
@@ -1090,10 +2506,114 @@ pub struct MaintenanceResult {
     pub vacuum_done: bool,
     pub prune_done: bool,
     pub records_pruned: usize,
+    pub sessions_closed: usize,
     pub integrity_ok: bool,
 }
 
+/// Aggregated usage over a date range (used by `stats` comparisons)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeriodTotals {
+    pub cost: f64,
+    pub sessions: u64,
+    pub lines_changed: u64,
+    pub tokens: u64,
+}
+
+/// Concurrently active sessions (see `SqliteDatabase::get_active_sessions`)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActiveSessions {
+    /// Active sessions other than the current one
+    pub others: usize,
+    /// Sum of the active sessions' burn rates, in dollars per hour
+    pub burn_rate: f64,
+}
+
+/// Token sums by type from `daily_stats` (used by `stats`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenTotals {
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_creation: u64,
+}
+
+/// Usage aggregated per group key (workspace, model, ...) for `stats` reports
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupedUsage {
+    /// Group key; empty when the sessions did not record one
+    pub key: String,
+    pub cost: f64,
+    pub tokens: u64,
+    pub sessions: u64,
+}
+
+/// Cost and line changes on one git branch of a workspace, for `stats --by-branch`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchUsage {
+    /// Workspace directory; empty when the updates did not report one
+    pub workspace_dir: String,
+    pub branch: String,
+    pub cost: f64,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+    /// Last day (YYYY-MM-DD) with activity on the branch
+    pub last_date: String,
+}
+
+/// A cost alert that fired (`cost.alert_daily` / `cost.alert_session`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostAlert {
+    /// "daily" or "session"
+    pub kind: String,
+    /// Date (YYYY-MM-DD) or session ID the alert applies to
+    pub scope: String,
+    pub threshold: f64,
+    /// Highest cost seen since the alert fired
+    pub value: f64,
+    pub fired_at: String,
+}
+
+/// A single session's totals, used for "biggest sessions" reports
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub start_time: String,
+    pub cost: f64,
+    pub tokens: u64,
+    pub model_name: Option<String>,
+    pub workspace_dir: Option<String>,
+    /// Seconds from start to last update, if both timestamps parse
+    pub duration_secs: Option<u64>,
+}
+
+/// One session as a block of working time, used by timesheet exports
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimesheetEntry {
+    pub session_id: String,
+    /// RFC 3339 start and end (`ended_at`, else the last update)
+    pub start_time: String,
+    pub end_time: String,
+    /// Active time from the transcript; 0 when not recorded
+    pub active_secs: u64,
+    pub workspace_dir: Option<String>,
+    pub cost: f64,
+    pub note: Option<String>,
+}
+
+/// Token and cost totals for one (date, model) pair, used by exports
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyModelUsage {
+    pub date: String,
+    pub model_name: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost: f64,
+}
+
 /// Session data with model name for rebuilding learned context windows
+#[cfg(feature = "adaptive-learning")]
 #[derive(Debug)]
 pub struct SessionWithModel {
     pub session_id: String,
@@ -1104,20 +2624,176 @@ pub struct SessionWithModel {
     pub last_updated: String,
 }
 
+/// A table, index or view in the stats database, for `db schema`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SchemaObject {
+    /// "table", "index" or "view"
+    pub kind: String,
+    pub name: String,
+    /// Table the object belongs to (the table itself for tables)
+    pub table: String,
+    /// Row count, for tables only
+    pub rows: Option<i64>,
+    /// SHA-256 (first 12 hex digits) of the object's CREATE statement
+    pub checksum: String,
+}
+
+/// A row of `schema_migrations`
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub applied_at: String,
+    pub description: Option<String>,
+    /// Recorded checksum; None when the migration stored none
+    pub checksum: Option<String>,
+}
+
+/// Schema snapshot of a stats database
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SchemaReport {
+    pub objects: Vec<SchemaObject>,
+    pub migrations: Vec<AppliedMigration>,
+}
+
+/// Opens a stats database read-only, keyed for SQLCipher if configured
+pub fn open_read_only(db_path: &Path) -> Result<Connection> {
+    let conn = Connection::open_with_flags(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    apply_encryption_key(&conn)?;
+    Ok(conn)
+}
+
+/// Reads the tables, indices, views and applied migrations of a database
+///
+/// SQLite's internal objects (auto-indices, `sqlite_sequence`) are skipped.
+pub fn read_schema(conn: &Connection) -> Result<SchemaReport> {
+    use sha2::{Digest, Sha256};
+
+    let mut stmt = conn.prepare(
+        "SELECT type, name, tbl_name, sql FROM sqlite_master
+         WHERE type IN ('table', 'index', 'view') AND name NOT LIKE 'sqlite_%'
+         ORDER BY CASE type WHEN 'table' THEN 0 WHEN 'view' THEN 1 ELSE 2 END, name",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+        ))
+    })?;
+
+    let mut objects = Vec::new();
+    for row in rows {
+        let (kind, name, table, sql) = row?;
+        let rows = if kind == "table" {
+            let query = format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\""));
+            Some(conn.query_row(&query, [], |r| r.get(0))?)
+        } else {
+            None
+        };
+        let digest = Sha256::digest(sql.unwrap_or_default().as_bytes());
+        let checksum = digest
+            .iter()
+            .take(6)
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        objects.push(SchemaObject {
+            kind,
+            name,
+            table,
+            rows,
+            checksum,
+        });
+    }
+
+    let has_migrations = objects
+        .iter()
+        .any(|o| o.kind == "table" && o.name == "schema_migrations");
+    let mut migrations = Vec::new();
+    if has_migrations {
+        let mut stmt = conn.prepare(
+            "SELECT version, applied_at, description, checksum
+             FROM schema_migrations ORDER BY version",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(AppliedMigration {
+                version: row.get(0)?,
+                applied_at: row.get(1)?,
+                description: row.get(2)?,
+                checksum: row.get::<_, Option<String>>(3)?.filter(|c| !c.is_empty()),
+            })
+        })?;
+        for row in rows {
+            migrations.push(row?);
+        }
+    }
+
+    Ok(SchemaReport {
+        objects,
+        migrations,
+    })
+}
+
+/// Result set of an ad-hoc `db query`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Whether `sql` is a single SELECT (or WITH ... SELECT) statement
+///
+/// A cheap pre-check for a friendly error; `run_select` additionally relies
+/// on SQLite's own read-only verdict for the prepared statement.
+pub fn is_select_statement(sql: &str) -> bool {
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let keyword: String = sql
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    !sql.contains(';') && matches!(keyword.as_str(), "SELECT" | "WITH")
+}
+
+/// Runs a single read-only statement and collects its rows as JSON values
+pub fn run_select(conn: &Connection, sql: &str) -> Result<QueryResult> {
+    use rusqlite::types::ValueRef;
+
+    let mut stmt = conn.prepare(sql.trim().trim_end_matches(';'))?;
+    if !stmt.readonly() {
+        return Err(rusqlite::Error::InvalidQuery);
+    }
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows = Vec::new();
+    let mut query = stmt.query([])?;
+    while let Some(row) = query.next()? {
+        let mut values = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            values.push(match row.get_ref(i)? {
+                ValueRef::Null => serde_json::Value::Null,
+                ValueRef::Integer(n) => n.into(),
+                ValueRef::Real(f) => f.into(),
+                ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+                ValueRef::Blob(b) => format!("<{} bytes>", b.len()).into(),
+            });
+        }
+        rows.push(values);
+    }
+    Ok(QueryResult { columns, rows })
+}
+
 /// Perform database maintenance operations
 pub fn perform_maintenance(
     force_vacuum: bool,
     no_prune: bool,
     quiet: bool,
 ) -> Result<MaintenanceResult> {
-    use chrono::{Duration, Utc};
     use log::info;
 
-    let config = crate::config::get_config();
-    let db_path = crate::common::get_data_dir().join("stats.db");
+    let db_path = crate::common::get_database_path();
 
     // Get a direct connection (not from pool) for maintenance operations
-    let conn = Connection::open(&db_path)?;
+    let conn = open_connection(&db_path)?;
 
     // 1. WAL checkpoint
     if !quiet {
@@ -1140,55 +2816,18 @@ pub fn perform_maintenance(
         if !quiet {
             info!("Checking retention policies...");
         }
-
-        // Get retention settings from config (with defaults)
-        let days_sessions = config.database.retention_days_sessions.unwrap_or(90);
-        let days_daily = config.database.retention_days_daily.unwrap_or(365);
-        let days_monthly = config.database.retention_days_monthly.unwrap_or(0);
-
-        let now = Utc::now();
-
-        // Prune old sessions
-        if days_sessions > 0 {
-            let cutoff = now - Duration::days(days_sessions as i64);
-            let cutoff_str = cutoff.format("%Y-%m-%dT%H:%M:%S").to_string();
-
-            let deleted = conn.execute(
-                "DELETE FROM sessions WHERE last_updated < ?1",
-                params![cutoff_str],
-            )?;
-            records_pruned += deleted;
-        }
-
-        // Prune old daily stats
-        if days_daily > 0 {
-            let cutoff = now - Duration::days(days_daily as i64);
-            let cutoff_str = cutoff.format("%Y-%m-%d").to_string();
-
-            let deleted = conn.execute(
-                "DELETE FROM daily_stats WHERE date < ?1",
-                params![cutoff_str],
-            )?;
-            records_pruned += deleted;
-        }
-
-        // Prune old monthly stats
-        if days_monthly > 0 {
-            let cutoff = now - Duration::days(days_monthly as i64);
-            let cutoff_str = cutoff.format("%Y-%m").to_string();
-
-            let deleted = conn.execute(
-                "DELETE FROM monthly_stats WHERE month < ?1",
-                params![cutoff_str],
-            )?;
-            records_pruned += deleted;
-        }
-
+        records_pruned = prune_by_retention(&conn)?;
         records_pruned > 0
     } else {
         false
     };
 
+    // 3b. Close sessions that never got a SessionEnd hook
+    let sessions_closed = close_idle_sessions(
+        &conn,
+        crate::config::get_config().database.session_idle_hours,
+    )?;
+
     // 4. Conditional VACUUM
     let vacuum_done = if force_vacuum || should_vacuum(&conn)? {
         if !quiet {
@@ -1217,26 +2856,703 @@ pub fn perform_maintenance(
         vacuum_done,
         prune_done,
         records_pruned,
+        sessions_closed,
         integrity_ok,
     })
 }
 
-/// Check if VACUUM should be performed
-fn should_vacuum(conn: &Connection) -> Result<bool> {
-    use chrono::Utc;
+/// Hashes an edited file path, so the database never holds the path itself
+fn hash_file_path(path: &str) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(path.as_bytes()))
+}
 
-    // Check database size (vacuum if > 10MB)
-    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
-    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
-    let db_size_mb = (page_count * page_size) as f64 / (1024.0 * 1024.0);
+/// Days an `alert_log` entry is kept after it last fired, well past any cooldown
+const ALERT_LOG_RETENTION_DAYS: i64 = 30;
+
+/// Cutoffs of the `retention_days_*` settings: rows before them are pruned,
+/// None keeps the table forever
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionCutoffs {
+    /// Sessions last updated before this timestamp (`%Y-%m-%dT%H:%M:%S`)
+    pub sessions: Option<String>,
+    /// Daily stats before this date (`%Y-%m-%d`)
+    pub daily: Option<String>,
+    /// Monthly stats before this month (`%Y-%m`)
+    pub monthly: Option<String>,
+}
 
-    if db_size_mb > 10.0 {
-        return Ok(true);
+/// Cutoffs for the configured retention periods (with defaults)
+pub fn retention_cutoffs() -> RetentionCutoffs {
+    use chrono::Duration;
+
+    let config = crate::config::get_config();
+    let now = crate::environment::current().now();
+    let cutoff = |days: u32, format: &str| {
+        (days > 0).then(|| {
+            (now - Duration::days(days as i64))
+                .format(format)
+                .to_string()
+        })
+    };
+
+    RetentionCutoffs {
+        sessions: cutoff(
+            config.database.retention_days_sessions.unwrap_or(90),
+            "%Y-%m-%dT%H:%M:%S",
+        ),
+        daily: cutoff(
+            config.database.retention_days_daily.unwrap_or(365),
+            "%Y-%m-%d",
+        ),
+        monthly: cutoff(config.database.retention_days_monthly.unwrap_or(0), "%Y-%m"),
     }
+}
 
-    // Check last vacuum time (vacuum if > 7 days ago)
-    let last_vacuum: Option<String> = conn
-        .query_row(
+/// Delete sessions, daily/monthly stats, learning data and the rows that
+/// depend on them past the configured `retention_days_*` periods. Returns the
+/// number of deleted rows.
+fn prune_by_retention(conn: &Connection) -> Result<usize> {
+    use chrono::Duration;
+
+    let config = crate::config::get_config();
+    let cutoffs = retention_cutoffs();
+    let now = crate::environment::current().now();
+    let mut records_pruned = 0;
+
+    // Prune old sessions
+    if let Some(cutoff) = &cutoffs.sessions {
+        records_pruned += conn.execute(
+            "DELETE FROM sessions WHERE last_updated < ?1",
+            params![cutoff],
+        )?;
+    }
+
+    // Prune old daily stats
+    if let Some(cutoff) = &cutoffs.daily {
+        records_pruned +=
+            conn.execute("DELETE FROM daily_stats WHERE date < ?1", params![cutoff])?;
+    }
+
+    // Prune old monthly stats
+    if let Some(cutoff) = &cutoffs.monthly {
+        records_pruned += conn.execute(
+            "DELETE FROM monthly_stats WHERE month < ?1",
+            params![cutoff],
+        )?;
+    }
+
+    // Branch costs are daily aggregates too
+    if let (true, Some(cutoff)) = (has_table(conn, "branch_stats")?, &cutoffs.daily) {
+        records_pruned +=
+            conn.execute("DELETE FROM branch_stats WHERE date < ?1", params![cutoff])?;
+    }
+
+    // Per-call usage is the source of the daily token totals
+    if let (true, Some(cutoff)) = (has_table(conn, "session_usage")?, &cutoffs.daily) {
+        records_pruned +=
+            conn.execute("DELETE FROM session_usage WHERE date < ?1", params![cutoff])?;
+    }
+
+    // Edited files of sessions that no longer exist
+    if has_table(conn, "session_files")? {
+        records_pruned += conn.execute(
+            "DELETE FROM session_files
+             WHERE session_id NOT IN (SELECT session_id FROM sessions)",
+            [],
+        )?;
+    }
+
+    // Line count samples of sessions that no longer exist
+    if has_table(conn, "lines_samples")? {
+        records_pruned += conn.execute(
+            "DELETE FROM lines_samples
+             WHERE session_id NOT IN (SELECT session_id FROM sessions)",
+            [],
+        )?;
+    }
+
+    // Learned windows and context samples the learner no longer needs
+    records_pruned += prune_learning_data(
+        conn,
+        config.database.context_samples_per_model.unwrap_or(500),
+        config.database.retention_days_learning.unwrap_or(180),
+    )?;
+
+    // Alert delivery records are only needed for the cooldown window
+    if has_table(conn, "alert_log")? {
+        let cutoff = (now - Duration::days(ALERT_LOG_RETENTION_DAYS)).timestamp();
+        records_pruned += conn.execute(
+            "DELETE FROM alert_log WHERE last_fired_at < ?1",
+            params![cutoff],
+        )?;
+    }
+
+    Ok(records_pruned)
+}
+
+/// Whether `name` is a table in the database, so pruning can skip tables that
+/// older schemas don't have yet.
+fn has_table(conn: &Connection, name: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![name],
+        |row| row.get(0),
+    )
+}
+
+/// Prune learning data: learned context windows not updated and context
+/// samples not recorded within `max_age_days`, and all but the newest
+/// `keep_per_model` context samples of each model (0 disables either limit).
+/// Returns the number of deleted rows.
+pub fn prune_learning_data(
+    conn: &Connection,
+    keep_per_model: u32,
+    max_age_days: u32,
+) -> Result<usize> {
+    // Databases that predate adaptive learning have nothing to prune
+    let has_windows = has_table(conn, "learned_context_windows")?;
+    let has_samples = has_table(conn, "context_samples")?;
+    let mut deleted = 0;
+
+    if max_age_days > 0 {
+        let cutoff = (crate::environment::current().now()
+            - chrono::Duration::days(max_age_days as i64))
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string();
+        if has_windows {
+            deleted += conn.execute(
+                "DELETE FROM learned_context_windows WHERE last_updated < ?1",
+                params![cutoff],
+            )?;
+        }
+        if has_samples {
+            deleted += conn.execute(
+                "DELETE FROM context_samples WHERE recorded_at < ?1",
+                params![cutoff],
+            )?;
+        }
+    }
+
+    // Samples are attributed to the model of their session
+    if has_samples && keep_per_model > 0 {
+        deleted += conn.execute(
+            "DELETE FROM context_samples WHERE id IN (
+                 SELECT id FROM (
+                     SELECT c.id, ROW_NUMBER() OVER (
+                         PARTITION BY s.model_name ORDER BY c.id DESC
+                     ) AS rank
+                     FROM context_samples c
+                     LEFT JOIN sessions s ON s.session_id = c.session_id
+                 ) WHERE rank > ?1
+             )",
+            params![keep_per_model as i64],
+        )?;
+    }
+
+    Ok(deleted)
+}
+
+/// A session `db-maintain` would prune
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrunableSession {
+    pub session_id: String,
+    pub last_updated: String,
+    pub cost: f64,
+}
+
+/// A daily or monthly stats row `db-maintain` would prune
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrunablePeriod {
+    /// Date (`%Y-%m-%d`) or month (`%Y-%m`)
+    pub period: String,
+    pub user: String,
+    pub cost: f64,
+}
+
+/// What retention pruning would delete, without deleting it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionPreview {
+    pub cutoffs: RetentionCutoffs,
+    pub sessions: Vec<PrunableSession>,
+    pub daily: Vec<PrunablePeriod>,
+    pub monthly: Vec<PrunablePeriod>,
+    /// Every row pruning would delete, including edited files, line samples,
+    /// learning data and alert log entries
+    pub total_rows: usize,
+    /// Pages the deletions free entirely, reclaimed by the next VACUUM. Rows
+    /// sharing pages with kept rows free more space than this once compacted
+    pub reclaimable_bytes: u64,
+    /// Space already free in the database file
+    pub free_bytes: u64,
+    /// Sessions without a `SessionEnd` that would be marked ended after
+    /// `database.session_idle_hours`
+    pub sessions_closed: usize,
+}
+
+/// Reports what `db-maintain` would prune under the current retention settings.
+pub fn preview_retention() -> Result<RetentionPreview> {
+    let conn = open_connection(&crate::common::get_database_path())?;
+    preview_retention_in(&conn)
+}
+
+/// Lists the rows past the retention cutoffs, then prunes them in a
+/// transaction that is rolled back, so the totals match a real run exactly.
+fn preview_retention_in(conn: &Connection) -> Result<RetentionPreview> {
+    let cutoffs = retention_cutoffs();
+    let mut preview = RetentionPreview::default();
+
+    if let Some(cutoff) = &cutoffs.sessions {
+        let mut stmt = conn.prepare(
+            "SELECT session_id, last_updated, COALESCE(cost, 0.0) FROM sessions
+             WHERE last_updated < ?1 ORDER BY last_updated",
+        )?;
+        preview.sessions = stmt
+            .query_map(params![cutoff], |row| {
+                Ok(PrunableSession {
+                    session_id: row.get(0)?,
+                    last_updated: row.get(1)?,
+                    cost: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<_, _>>()?;
+    }
+    let periods = |sql: &str, cutoff: &str| -> Result<Vec<PrunablePeriod>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt
+            .query_map(params![cutoff], |row| {
+                Ok(PrunablePeriod {
+                    period: row.get(0)?,
+                    user: row.get(1)?,
+                    cost: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(rows)
+    };
+    if let Some(cutoff) = &cutoffs.daily {
+        preview.daily = periods(
+            "SELECT date, user, COALESCE(total_cost, 0.0) FROM daily_stats
+             WHERE date < ?1 ORDER BY date, user",
+            cutoff,
+        )?;
+    }
+    if let Some(cutoff) = &cutoffs.monthly {
+        preview.monthly = periods(
+            "SELECT month, user, COALESCE(total_cost, 0.0) FROM monthly_stats
+             WHERE month < ?1 ORDER BY month, user",
+            cutoff,
+        )?;
+    }
+
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let free_pages = |conn: &Connection| -> Result<i64> {
+        conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))
+    };
+    let free_before = free_pages(conn)?;
+    {
+        let tx = conn.unchecked_transaction()?;
+        preview.total_rows = prune_by_retention(&tx)?;
+        let freed = free_pages(&tx)? - free_before;
+        preview.reclaimable_bytes = (freed.max(0) * page_size) as u64;
+        // Same order as perform_maintenance: only sessions that survive pruning are closed
+        preview.sessions_closed =
+            close_idle_sessions(&tx, crate::config::get_config().database.session_idle_hours)?;
+        tx.rollback()?;
+    }
+    preview.free_bytes = (free_before.max(0) * page_size) as u64;
+    preview.cutoffs = cutoffs;
+
+    Ok(preview)
+}
+
+/// Totals of one `daily_stats` or `monthly_stats` row
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AggregateTotals {
+    pub cost: f64,
+    pub lines_added: i64,
+    pub lines_removed: i64,
+    pub sessions: i64,
+}
+
+impl AggregateTotals {
+    fn matches(&self, other: &AggregateTotals) -> bool {
+        (self.cost - other.cost).abs() < 0.005
+            && self.lines_added == other.lines_added
+            && self.lines_removed == other.lines_removed
+            && self.sessions == other.sessions
+    }
+}
+
+/// What is wrong with an aggregate row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// More sessions counted than were active in the period
+    DuplicateSessions,
+    /// Totals differ from the sums of the period's sessions
+    TotalsMismatch,
+}
+
+/// An aggregate row that disagrees with the sessions table
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateAnomaly {
+    /// `daily_stats` or `monthly_stats`
+    pub table: &'static str,
+    /// Date (`%Y-%m-%d`) or month (`%Y-%m`)
+    pub period: String,
+    pub user: String,
+    pub kind: AnomalyKind,
+    pub stored: AggregateTotals,
+    pub expected: AggregateTotals,
+    /// Sessions spanning the period boundary split their cost with the
+    /// neighbouring period in ways the sessions table doesn't record, so only
+    /// the session count is repaired
+    pub count_only: bool,
+}
+
+/// Result of checking (and repairing) the aggregates
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepairReport {
+    /// Sessions the aggregates were checked against
+    pub sessions: usize,
+    pub anomalies: Vec<AggregateAnomaly>,
+    /// Periods whose totals couldn't be checked because sessions span their
+    /// boundary (session counts are still checked)
+    pub partial_periods: usize,
+    pub repaired: bool,
+}
+
+/// A session's span and totals, as the aggregates attribute them
+struct SessionSums {
+    user: String,
+    first_day: String,
+    last_day: String,
+    totals: AggregateTotals,
+}
+
+/// Checks `daily_stats` and `monthly_stats` against the sessions table and,
+/// unless `dry_run`, recomputes the rows that disagree from it.
+pub fn repair_aggregates(dry_run: bool) -> Result<RepairReport> {
+    let conn = open_connection(&crate::common::get_database_path())?;
+    repair_aggregates_in(&conn, dry_run)
+}
+
+/// Only periods the sessions table fully covers are checked: periods after the
+/// session retention cutoff with at least one session. Like the live updates,
+/// sessions are attributed to the day (month) they were last updated.
+fn repair_aggregates_in(conn: &Connection, dry_run: bool) -> Result<RepairReport> {
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(user, ''), date(start_time, 'localtime'), date(last_updated, 'localtime'),
+                COALESCE(cost, 0.0), COALESCE(lines_added, 0), COALESCE(lines_removed, 0)
+         FROM sessions",
+    )?;
+    let sessions: Vec<SessionSums> = stmt
+        .query_map([], |row| {
+            Ok(SessionSums {
+                user: row.get(0)?,
+                first_day: row.get(1)?,
+                last_day: row.get(2)?,
+                totals: AggregateTotals {
+                    cost: row.get(3)?,
+                    lines_added: row.get(4)?,
+                    lines_removed: row.get(5)?,
+                    sessions: 1,
+                },
+            })
+        })?
+        .collect::<std::result::Result<_, _>>()?;
+
+    // Days on or before the cutoff may have lost sessions to retention
+    let covered_after = retention_cutoffs()
+        .sessions
+        .map(|cutoff| cutoff[..10].to_string());
+
+    let mut report = RepairReport {
+        sessions: sessions.len(),
+        ..Default::default()
+    };
+    for (table, key_len) in [("daily_stats", 10), ("monthly_stats", 7)] {
+        let period_column = if key_len == 10 { "date" } else { "month" };
+        let stored = read_aggregates(conn, table, period_column)?;
+        let after = covered_after.as_deref().map(|day| &day[..key_len]);
+        let (anomalies, partial) = find_anomalies(table, &sessions, &stored, key_len, after);
+        report.anomalies.extend(anomalies);
+        report.partial_periods += partial;
+    }
+
+    if !dry_run && !report.anomalies.is_empty() {
+        let tx = conn.unchecked_transaction()?;
+        for anomaly in &report.anomalies {
+            write_repaired_aggregate(&tx, anomaly)?;
+        }
+        tx.commit()?;
+        report.repaired = true;
+    }
+    Ok(report)
+}
+
+/// Stored totals per (period, user) of an aggregate table
+fn read_aggregates(
+    conn: &Connection,
+    table: &str,
+    period_column: &str,
+) -> Result<std::collections::HashMap<(String, String), AggregateTotals>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {period_column}, user, COALESCE(total_cost, 0.0), COALESCE(total_lines_added, 0),
+                COALESCE(total_lines_removed, 0), COALESCE(session_count, 0)
+         FROM {table}"
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                (row.get(0)?, row.get(1)?),
+                AggregateTotals {
+                    cost: row.get(2)?,
+                    lines_added: row.get(3)?,
+                    lines_removed: row.get(4)?,
+                    sessions: row.get(5)?,
+                },
+            ))
+        })?
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(rows)
+}
+
+/// Compares stored aggregates with the session sums for periods of `key_len`
+/// characters (10 = day, 7 = month). Returns the anomalies and the number of
+/// periods whose totals couldn't be checked.
+fn find_anomalies(
+    table: &'static str,
+    sessions: &[SessionSums],
+    stored: &std::collections::HashMap<(String, String), AggregateTotals>,
+    key_len: usize,
+    covered_after: Option<&str>,
+) -> (Vec<AggregateAnomaly>, usize) {
+    let period_of = |day: &str| day.get(..key_len).unwrap_or(day).to_string();
+
+    // Expected totals: each session counts in the period it was last updated in
+    let mut expected: std::collections::BTreeMap<(String, String), AggregateTotals> =
+        std::collections::BTreeMap::new();
+    for session in sessions {
+        let sums = expected
+            .entry((period_of(&session.last_day), session.user.clone()))
+            .or_default();
+        sums.cost += session.totals.cost;
+        sums.lines_added += session.totals.lines_added;
+        sums.lines_removed += session.totals.lines_removed;
+        sums.sessions += session.totals.sessions;
+    }
+    let mut keys: Vec<(String, String)> = expected.keys().cloned().collect();
+    keys.extend(stored.keys().filter(|k| !expected.contains_key(k)).cloned());
+    keys.sort();
+
+    let mut anomalies = Vec::new();
+    let mut partial = 0;
+    for (period, user) in keys {
+        if covered_after.is_some_and(|after| period.as_str() <= after) {
+            continue;
+        }
+        // Sessions active in the period, and whether any crossed its boundary
+        let (mut active, mut spanning) = (0, false);
+        for session in sessions.iter().filter(|s| s.user == user) {
+            let (first, last) = (period_of(&session.first_day), period_of(&session.last_day));
+            if first <= period && period <= last {
+                active += 1;
+                spanning |= first != last;
+            }
+        }
+        if active == 0 {
+            // All of the period's sessions are gone; nothing to compare against
+            continue;
+        }
+
+        let stored_totals = stored
+            .get(&(period.clone(), user.clone()))
+            .copied()
+            .unwrap_or_default();
+        let expected_totals = expected
+            .get(&(period.clone(), user.clone()))
+            .copied()
+            .unwrap_or_default();
+        let found = if spanning {
+            partial += 1;
+            (stored_totals.sessions > active).then(|| {
+                let counted = AggregateTotals {
+                    sessions: active,
+                    ..stored_totals
+                };
+                (AnomalyKind::DuplicateSessions, counted, true)
+            })
+        } else if stored_totals.sessions > expected_totals.sessions {
+            Some((AnomalyKind::DuplicateSessions, expected_totals, false))
+        } else if !stored_totals.matches(&expected_totals) {
+            Some((AnomalyKind::TotalsMismatch, expected_totals, false))
+        } else {
+            None
+        };
+
+        if let Some((kind, expected, count_only)) = found {
+            anomalies.push(AggregateAnomaly {
+                table,
+                period,
+                user,
+                kind,
+                stored: stored_totals,
+                expected,
+                count_only,
+            });
+        }
+    }
+    (anomalies, partial)
+}
+
+/// Writes the expected totals of an anomaly back to its aggregate row
+///
+/// Only cost, lines and session count are compared, so only those are
+/// rewritten; token totals are left as recorded.
+fn write_repaired_aggregate(tx: &Transaction, anomaly: &AggregateAnomaly) -> Result<()> {
+    let (table, period_column) = match anomaly.table {
+        "daily_stats" => ("daily_stats", "date"),
+        _ => ("monthly_stats", "month"),
+    };
+    let expected = &anomaly.expected;
+
+    if anomaly.count_only {
+        tx.execute(
+            &format!(
+                "UPDATE {table} SET session_count = ?3 WHERE {period_column} = ?1 AND user = ?2"
+            ),
+            params![anomaly.period, anomaly.user, expected.sessions],
+        )?;
+        return Ok(());
+    }
+
+    tx.execute(
+        &format!(
+            "INSERT INTO {table} ({period_column}, user, total_cost, total_lines_added, total_lines_removed, session_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT({period_column}, user) DO UPDATE SET
+                total_cost = ?3,
+                total_lines_added = ?4,
+                total_lines_removed = ?5,
+                session_count = ?6"
+        ),
+        params![
+            anomaly.period,
+            anomaly.user,
+            expected.cost,
+            expected.lines_added,
+            expected.lines_removed,
+            expected.sessions
+        ],
+    )?;
+    Ok(())
+}
+
+/// Close open sessions whose last update is more than `idle_hours` ago (0 = never),
+/// ending them at their last update so their duration stops growing.
+/// Returns the number of sessions closed.
+pub fn close_idle_sessions(conn: &Connection, idle_hours: u32) -> Result<usize> {
+    if idle_hours == 0 {
+        return Ok(0);
+    }
+    // db-maintain doesn't run migrations; nothing to close before v12
+    let has_ended_at: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('sessions') WHERE name = 'ended_at'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_ended_at {
+        return Ok(0);
+    }
+    let cutoff = crate::environment::current().now() - chrono::Duration::hours(idle_hours as i64);
+    conn.execute(
+        "UPDATE sessions SET ended_at = last_updated
+         WHERE ended_at IS NULL AND julianday(last_updated) < julianday(?1)",
+        params![cutoff.to_rfc3339()],
+    )
+}
+
+/// Minimum time between two automatic size-limit passes
+const SIZE_LIMIT_INTERVAL_MINUTES: i64 = 60;
+
+/// Keep stats.db and its WAL under `database.max_size_mb` (0 = no limit).
+///
+/// When the files together exceed the limit this checkpoints the WAL, prunes
+/// rows past the retention periods and reclaims free pages with an incremental
+/// vacuum (switching the database to incremental auto-vacuum with one full
+/// VACUUM the first time). Runs at most once per `SIZE_LIMIT_INTERVAL_MINUTES`,
+/// so a database that is still too big after pruning doesn't slow every render.
+/// Returns whether a pass ran.
+pub fn enforce_size_limit(db_path: &Path) -> Result<bool> {
+    use chrono::Utc;
+
+    let max_mb = config::get_config().database.max_size_mb;
+    if max_mb == 0 {
+        return Ok(false);
+    }
+    let (db_bytes, wal_bytes) = crate::common::database_file_sizes(db_path);
+    if db_bytes + wal_bytes <= max_mb * 1024 * 1024 {
+        return Ok(false);
+    }
+
+    let conn = open_connection(db_path)?;
+    let last_pass: Option<String> = conn
+        .query_row(
+            "SELECT value FROM meta WHERE key = 'last_size_limit'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(last) = last_pass.and_then(|s| chrono::DateTime::parse_from_rfc3339(&s).ok()) {
+        let now = crate::environment::current().now();
+        if (now - last.with_timezone(&Utc)).num_minutes() < SIZE_LIMIT_INTERVAL_MINUTES {
+            return Ok(false);
+        }
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('last_size_limit', ?1)",
+        params![crate::environment::current().now().to_rfc3339()],
+    )?;
+
+    log::info!(
+        "stats.db is {} MB, over the {} MB limit; pruning and vacuuming",
+        (db_bytes + wal_bytes) / (1024 * 1024),
+        max_mb
+    );
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    prune_by_retention(&conn)?;
+
+    // auto_vacuum: 0 = none, 1 = full, 2 = incremental
+    let auto_vacuum: i64 = conn.query_row("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+    if auto_vacuum == 2 {
+        conn.query_row("PRAGMA incremental_vacuum", [], |_| Ok(()))
+            .optional()?;
+    } else {
+        // The mode only takes effect on an existing database after a VACUUM
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;")?;
+        update_last_vacuum(&conn)?;
+    }
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(true)
+}
+
+/// Check if VACUUM should be performed
+fn should_vacuum(conn: &Connection) -> Result<bool> {
+    use chrono::Utc;
+
+    // Check database size (vacuum if > 10MB)
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let db_size_mb = (page_count * page_size) as f64 / (1024.0 * 1024.0);
+
+    if db_size_mb > 10.0 {
+        return Ok(true);
+    }
+
+    // Check last vacuum time (vacuum if > 7 days ago)
+    let last_vacuum: Option<String> = conn
+        .query_row(
             "SELECT value FROM meta WHERE key = 'last_vacuum'",
             [],
             |row| row.get(0),
@@ -1245,7 +3561,9 @@ fn should_vacuum(conn: &Connection) -> Result<bool> {
 
     if let Some(last_vacuum_str) = last_vacuum {
         if let Ok(last_vacuum_time) = chrono::DateTime::parse_from_rfc3339(&last_vacuum_str) {
-            let days_since = (Utc::now() - last_vacuum_time.with_timezone(&Utc)).num_days();
+            let days_since = (crate::environment::current().now()
+                - last_vacuum_time.with_timezone(&Utc))
+            .num_days();
             return Ok(days_since > 7);
         }
     }
@@ -1256,9 +3574,7 @@ fn should_vacuum(conn: &Connection) -> Result<bool> {
 
 /// Update the last_vacuum timestamp in meta table
 fn update_last_vacuum(conn: &Connection) -> Result<()> {
-    use chrono::Utc;
-
-    let now = Utc::now().to_rfc3339();
+    let now = crate::environment::current().now().to_rfc3339();
     conn.execute(
         "INSERT OR REPLACE INTO meta (key, value) VALUES ('last_vacuum', ?1)",
         params![now],
@@ -1272,6 +3588,28 @@ mod tests {
     use rusqlite::Connection;
     use tempfile::TempDir;
 
+    /// Records only edited files for a session
+    fn record_files(db: &SqliteDatabase, session_id: &str, paths: &[String]) -> Result<usize> {
+        let activity = crate::utils::TranscriptActivity {
+            edited_files: paths.to_vec(),
+            ..Default::default()
+        };
+        db.record_transcript_activity(session_id, &activity, true)
+    }
+
+    /// Records only API call usage for a session, returning the new calls
+    fn record_usage(
+        db: &SqliteDatabase,
+        session_id: &str,
+        calls: &[crate::utils::ApiCallUsage],
+    ) -> Result<usize> {
+        let activity = crate::utils::TranscriptActivity {
+            api_calls: calls.to_vec(),
+            ..Default::default()
+        };
+        db.record_transcript_activity(session_id, &activity, false)
+    }
+
     #[test]
     fn test_database_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -1288,6 +3626,79 @@ mod tests {
         assert_eq!(count, 0);
     }
 
+    #[test]
+    fn test_read_schema() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let _db = SqliteDatabase::new(&db_path).unwrap();
+
+        let conn = open_read_only(&db_path).unwrap();
+        let report = read_schema(&conn).unwrap();
+        let sessions = report
+            .objects
+            .iter()
+            .find(|o| o.kind == "table" && o.name == "sessions")
+            .unwrap();
+        assert_eq!(sessions.rows, Some(0));
+        assert_eq!(sessions.checksum.len(), 12);
+        assert!(report
+            .objects
+            .iter()
+            .any(|o| o.kind == "index" && o.table == "sessions"));
+        assert!(report
+            .objects
+            .iter()
+            .all(|o| !o.name.starts_with("sqlite_")));
+        assert_eq!(
+            report.migrations.last().map(|m| m.version),
+            Some(CURRENT_SCHEMA_VERSION)
+        );
+    }
+
+    #[test]
+    fn test_select_only_queries() {
+        assert!(is_select_statement("SELECT * FROM sessions"));
+        assert!(is_select_statement(
+            "  with t AS (SELECT 1) SELECT * FROM t;"
+        ));
+        assert!(!is_select_statement("DELETE FROM sessions"));
+        assert!(!is_select_statement("SELECT 1; DROP TABLE sessions"));
+        assert!(!is_select_statement("PRAGMA journal_mode=DELETE"));
+
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+        db.update_session(
+            "q-1",
+            SessionUpdate {
+                cost: 1.5,
+                lines_added: 10,
+                lines_removed: 2,
+                model_name: None,
+                workspace_dir: None,
+                device_id: None,
+                token_breakdown: None,
+                max_tokens_observed: None,
+                git_branch: None,
+            },
+        )
+        .unwrap();
+
+        let conn = open_read_only(&db_path).unwrap();
+        let result = run_select(&conn, "SELECT session_id, cost FROM sessions;").unwrap();
+        assert_eq!(result.columns, vec!["session_id", "cost"]);
+        assert_eq!(
+            result.rows,
+            vec![vec![serde_json::json!("q-1"), serde_json::json!(1.5)]]
+        );
+        // WITH can wrap a write; SQLite's read-only check still rejects it
+        assert!(run_select(
+            &conn,
+            "WITH t AS (SELECT 1) DELETE FROM sessions WHERE session_id IN (SELECT * FROM t)"
+        )
+        .is_err());
+    }
+
     #[test]
     fn test_session_update() {
         let temp_dir = TempDir::new().unwrap();
@@ -1306,6 +3717,7 @@ mod tests {
                     device_id: None,
                     token_breakdown: None,
                     max_tokens_observed: None,
+                    git_branch: None,
                 },
             )
             .unwrap();
@@ -1325,6 +3737,7 @@ mod tests {
                     device_id: None,
                     token_breakdown: None,
                     max_tokens_observed: None,
+                    git_branch: None,
                 },
             )
             .unwrap();
@@ -1360,6 +3773,7 @@ mod tests {
                     device_id: None,
                     token_breakdown: None,
                     max_tokens_observed: None,
+                    git_branch: None,
                 },
             )
             .unwrap();
@@ -1379,6 +3793,7 @@ mod tests {
                     device_id: None,
                     token_breakdown: None,
                     max_tokens_observed: None,
+                    git_branch: None,
                 },
             )
             .unwrap();
@@ -1398,6 +3813,7 @@ mod tests {
                     device_id: None,
                     token_breakdown: None,
                     max_tokens_observed: None,
+                    git_branch: None,
                 },
             )
             .unwrap();
@@ -1420,6 +3836,7 @@ mod tests {
                     device_id: None,
                     token_breakdown: None,
                     max_tokens_observed: None,
+                    git_branch: None,
                 },
             )
             .unwrap();
@@ -1442,6 +3859,7 @@ mod tests {
                     device_id: None,
                     token_breakdown: None,
                     max_tokens_observed: None,
+                    git_branch: None,
                 },
             )
             .unwrap();
@@ -1480,6 +3898,7 @@ mod tests {
                             device_id: None,
                             token_breakdown: None,
                             max_tokens_observed: None,
+                            git_branch: None,
                         },
                     )
                 })
@@ -1517,6 +3936,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         )
         .unwrap();
@@ -1531,6 +3951,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         )
         .unwrap();
@@ -1545,6 +3966,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         )
         .unwrap();
@@ -1723,6 +4145,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         )
         .unwrap();
@@ -1752,6 +4175,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         )
         .unwrap();
@@ -1766,6 +4190,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         )
         .unwrap();
@@ -1780,6 +4205,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         )
         .unwrap();
@@ -1796,4 +4222,1126 @@ mod tests {
         assert!(date_str.contains('-')); // Date separators
         assert!(date_str.len() > 10); // At least YYYY-MM-DD
     }
+
+    #[test]
+    fn test_session_files_distinct() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+
+        let paths = vec!["/src/a.rs".to_string(), "/src/b.rs".to_string()];
+        record_files(&db, "s1", &paths).unwrap();
+        record_files(&db, "s1", &["/src/a.rs".to_string()]).unwrap();
+        record_files(&db, "s2", &["/src/a.rs".to_string()]).unwrap();
+
+        assert_eq!(db.count_session_files("s1").unwrap(), 2);
+        assert_eq!(db.count_session_files("s2").unwrap(), 1);
+        assert_eq!(db.count_session_files("s3").unwrap(), 0);
+
+        // Only hashes are stored
+        let conn = db.get_connection().unwrap();
+        let stored: String = conn
+            .query_row("SELECT file_hash FROM session_files LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(stored.len(), 64);
+        assert!(!stored.contains("src"));
+    }
+
+    #[test]
+    fn test_preview_retention_changes_nothing() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let conn = db.get_connection().unwrap();
+
+        conn.execute_batch(
+            "INSERT INTO sessions (session_id, start_time, last_updated, cost)
+                 VALUES ('old', '2000-01-01T00:00:00', '2000-01-01T01:00:00', 2.5),
+                        ('idle', datetime('now', '-3 days'), datetime('now', '-2 days'), 0.5),
+                        ('new', '2999-01-01T00:00:00', '2999-01-01T01:00:00', 1.0);
+             INSERT INTO daily_stats (date, user, total_cost) VALUES ('2000-01-01', '', 2.5);
+             INSERT INTO monthly_stats (month, user, total_cost) VALUES ('2000-01', '', 2.5);",
+        )
+        .unwrap();
+        record_files(&db, "old", &["/src/a.rs".to_string()]).unwrap();
+
+        let preview = preview_retention_in(&conn).unwrap();
+        assert_eq!(preview.sessions.len(), 1);
+        assert_eq!(preview.sessions[0].session_id, "old");
+        assert_eq!(preview.sessions[0].cost, 2.5);
+        assert_eq!(preview.daily[0].period, "2000-01-01");
+        // Monthly stats are kept forever by default
+        assert!(preview.cutoffs.monthly.is_none());
+        assert!(preview.monthly.is_empty());
+        // Session, daily row and the session's edited file
+        assert_eq!(preview.total_rows, 3);
+        // The pruned session is not counted as closed
+        assert_eq!(preview.sessions_closed, 1);
+
+        let sessions: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sessions, 3);
+        let ended: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sessions WHERE ended_at IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(ended, 0);
+        assert_eq!(db.count_session_files("old").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_rebuild_session() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let conn = db.get_connection().unwrap();
+        conn.execute(
+            "INSERT INTO sessions (session_id, start_time, last_updated, cost, user)
+             VALUES ('s1', '2025-03-07T12:00:00+00:00', datetime('now'), 1.0, 'u')",
+            [],
+        )
+        .unwrap();
+
+        let rebuilt = crate::utils::TranscriptStats {
+            started: Some(1741341600), // 2025-03-07T10:00:00Z
+            active_secs: Some(600),
+            tokens: Some(crate::models::TokenBreakdown {
+                input_tokens: 10,
+                output_tokens: 20,
+                cache_read_tokens: 30,
+                cache_creation_tokens: 40,
+            }),
+            max_tokens: None,
+        };
+        assert!(db.rebuild_session("s1", &rebuilt, true).unwrap());
+        let active: i64 = conn
+            .query_row("SELECT active_seconds FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(active, 0);
+
+        assert!(db.rebuild_session("s1", &rebuilt, false).unwrap());
+        let (active, output): (i64, i64) = conn
+            .query_row(
+                "SELECT active_seconds, total_output_tokens FROM sessions",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((active, output), (600, 20));
+
+        // Already up to date; unknown sessions are left alone
+        assert!(!db.rebuild_session("s1", &rebuilt, false).unwrap());
+        assert!(!db.rebuild_session("s2", &rebuilt, false).unwrap());
+
+        // The peak context size is raised, never lowered
+        let peak = |max_tokens| crate::utils::TranscriptStats {
+            max_tokens: Some(max_tokens),
+            ..rebuilt.clone()
+        };
+        assert!(db.rebuild_session("s1", &peak(90_000), false).unwrap());
+        assert!(!db.rebuild_session("s1", &peak(40_000), false).unwrap());
+        let max_tokens: i64 = conn
+            .query_row("SELECT max_tokens_observed FROM sessions", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(max_tokens, 90_000);
+    }
+
+    #[test]
+    fn test_branch_usage() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let update = |cost: f64, lines_added: u64, branch: Option<&str>| SessionUpdate {
+            cost,
+            lines_added,
+            lines_removed: 0,
+            model_name: None,
+            workspace_dir: Some("/repo".to_string()),
+            device_id: None,
+            token_breakdown: None,
+            max_tokens_observed: None,
+            git_branch: branch.map(str::to_string),
+        };
+
+        // Only the deltas since the previous update count towards a branch
+        db.update_session("s1", update(1.0, 10, Some("main")))
+            .unwrap();
+        db.update_session("s1", update(3.0, 15, Some("feature")))
+            .unwrap();
+        // Unchanged values and updates without a branch add nothing
+        db.update_session("s1", update(3.0, 15, Some("feature")))
+            .unwrap();
+        db.update_session("s1", update(4.0, 15, None)).unwrap();
+        db.update_session("s2", update(0.5, 0, Some("main")))
+            .unwrap();
+
+        let branches = db.get_branch_usage(None).unwrap();
+        let summary: Vec<(&str, f64, u64)> = branches
+            .iter()
+            .map(|b| (b.branch.as_str(), b.cost, b.lines_added))
+            .collect();
+        assert_eq!(summary, vec![("feature", 2.0, 5), ("main", 1.5, 10)]);
+        assert_eq!(branches[0].workspace_dir, "/repo");
+        assert_eq!(branches[0].last_date, crate::common::current_date());
+        assert!(db.get_branch_usage(Some("9999-01-01")).unwrap().is_empty());
+
+        // The session keeps its last known branch
+        let conn = db.get_connection().unwrap();
+        let branch: String = conn
+            .query_row(
+                "SELECT git_branch FROM sessions WHERE session_id = 's1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(branch, "feature");
+    }
+
+    #[test]
+    fn test_repair_aggregates() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let conn = db.get_connection().unwrap();
+
+        // Two sessions on one day, a third spanning midnight into the next,
+        // counted over and over in the aggregates by an old bug
+        let today = Local::now().date_naive();
+        let day = |offset: i64| {
+            (today - chrono::Duration::days(offset))
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_local_timezone(Local)
+                .unwrap()
+                .with_timezone(&chrono::Utc)
+                .format("%Y-%m-%dT%H:%M:%S")
+                .to_string()
+        };
+        let (d3, d2, d1) = (day(3), day(2), day(1));
+        conn.execute(
+            "INSERT INTO sessions (session_id, start_time, last_updated, cost, lines_added, lines_removed, user)
+             VALUES ('a', ?1, ?1, 1.0, 10, 1, 'u'), ('b', ?1, ?1, 2.0, 20, 2, 'u'),
+                    ('c', ?2, ?3, 4.0, 40, 4, 'u')",
+            params![d3, d2, d1],
+        )
+        .unwrap();
+        let date = |ts: &str| {
+            conn.query_row("SELECT date(?1, 'localtime')", params![ts], |row| {
+                row.get::<_, String>(0)
+            })
+            .unwrap()
+        };
+        conn.execute(
+            "INSERT INTO daily_stats (date, user, total_cost, total_lines_added, total_lines_removed, session_count, total_input_tokens)
+             VALUES (?1, 'u', 3.0, 30, 3, 5, 1200), (?2, 'u', 1.5, 15, 1, 1, 0), (?3, 'u', 2.5, 25, 3, 4, 0)",
+            params![date(&d3), date(&d2), date(&d1)],
+        )
+        .unwrap();
+
+        let report = repair_aggregates_in(&conn, true).unwrap();
+        assert_eq!(report.sessions, 3);
+        assert!(!report.repaired);
+        let daily: Vec<_> = report
+            .anomalies
+            .iter()
+            .filter(|a| a.table == "daily_stats")
+            .collect();
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily[0].period, date(&d3));
+        assert_eq!(daily[0].kind, AnomalyKind::DuplicateSessions);
+        assert_eq!(daily[0].expected.cost, 3.0);
+        assert_eq!(daily[0].expected.sessions, 2);
+        // Spanned by session c: only the count is repaired
+        assert_eq!(daily[1].period, date(&d1));
+        assert!(daily[1].count_only);
+        assert_eq!(daily[1].expected.sessions, 1);
+        assert_eq!(daily[1].expected.cost, 2.5);
+        assert!(report.partial_periods >= 2);
+
+        let report = repair_aggregates_in(&conn, false).unwrap();
+        assert!(report.repaired);
+        let (count, input_tokens): (i64, i64) = conn
+            .query_row(
+                "SELECT session_count, total_input_tokens FROM daily_stats WHERE date = ?1",
+                params![date(&d3)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(count, 2);
+        // Token totals are not part of the repair
+        assert_eq!(input_tokens, 1200);
+        assert!(repair_aggregates_in(&conn, true)
+            .unwrap()
+            .anomalies
+            .iter()
+            .all(|a| a.table != "daily_stats"));
+    }
+
+    #[test]
+    fn test_context_samples_ring() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        for pct in [10.0, 20.0, 20.2, 30.0, 40.0, 50.0] {
+            db.record_context_sample("s1", pct, 4).unwrap();
+        }
+
+        // 20.2 collapses into 20.0, and only the newest 4 samples are kept
+        let samples = db.get_context_samples("s1", 10).unwrap();
+        assert_eq!(samples, vec![20.0, 30.0, 40.0, 50.0]);
+
+        // Other sessions are unaffected
+        assert!(db.get_context_samples("s2", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_last_and_max_tokens() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        db.update_session(
+            "s1",
+            SessionUpdate {
+                cost: 1.0,
+                lines_added: 0,
+                lines_removed: 0,
+                model_name: None,
+                workspace_dir: None,
+                device_id: None,
+                token_breakdown: None,
+                max_tokens_observed: None,
+                git_branch: None,
+            },
+        )
+        .unwrap();
+
+        db.update_max_tokens_observed("s1", 150_000).unwrap();
+        // Compaction shrinks the context: the peak stays, the last size follows
+        db.update_max_tokens_observed("s1", 40_000).unwrap();
+        assert_eq!(db.get_session_max_tokens("s1"), Some(150_000));
+        assert_eq!(db.get_session_last_tokens("s1"), Some(40_000));
+    }
+
+    #[test]
+    fn test_lines_samples() {
+        use crate::velocity::LinesSample;
+
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        let first = LinesSample {
+            at: 1000,
+            added: 10,
+            removed: 2,
+        };
+        let second = LinesSample { at: 1060, ..first };
+        db.update_lines_samples("s1", |samples| samples.push(first))
+            .unwrap();
+        db.update_lines_samples("s1", |samples| samples.push(second))
+            .unwrap();
+
+        let stored = db
+            .update_lines_samples("s1", |samples| samples.clone())
+            .unwrap();
+        assert_eq!(stored, vec![first, second]);
+        // Other sessions are unaffected
+        let other = db
+            .update_lines_samples("s2", |samples| samples.len())
+            .unwrap();
+        assert_eq!(other, 0);
+
+        // Samples of sessions that no longer exist are pruned
+        let conn = db.get_connection().unwrap();
+        assert!(prune_by_retention(&conn).unwrap() >= 2);
+        let left: i64 = conn
+            .query_row("SELECT COUNT(*) FROM lines_samples", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(left, 0);
+    }
+
+    #[test]
+    fn test_cost_alerts_until_acknowledged() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        assert!(db
+            .record_cost_alert("daily", "2025-03-07", 20.0, 21.0)
+            .unwrap());
+        assert!(!db
+            .record_cost_alert("daily", "2025-03-07", 20.0, 25.0)
+            .unwrap());
+        let active = db.get_active_alerts().unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].value, 25.0);
+
+        // Acknowledged alerts don't come back for the same scope
+        assert_eq!(db.acknowledge_alerts().unwrap(), 1);
+        assert!(!db
+            .record_cost_alert("daily", "2025-03-07", 20.0, 30.0)
+            .unwrap());
+        assert!(db.get_active_alerts().unwrap().is_empty());
+
+        // A new day fires again
+        assert!(db
+            .record_cost_alert("daily", "2025-03-08", 20.0, 22.0)
+            .unwrap());
+        assert_eq!(db.get_active_alerts().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_try_fire_alert_cooldown() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        assert!(db.try_fire_alert("cost_daily", "2025-03-07", 3600).unwrap());
+        assert!(!db.try_fire_alert("cost_daily", "2025-03-07", 3600).unwrap());
+        // Other keys and types are independent
+        assert!(db.try_fire_alert("cost_daily", "2025-03-08", 3600).unwrap());
+        assert!(db
+            .try_fire_alert("cost_session", "2025-03-07", 3600)
+            .unwrap());
+
+        // Once the cooldown has passed it fires again
+        let conn = db.get_connection().unwrap();
+        conn.execute(
+            "UPDATE alert_log SET last_fired_at = last_fired_at - 7200 WHERE alert_key = '2025-03-07'",
+            [],
+        )
+        .unwrap();
+        assert!(db.try_fire_alert("cost_daily", "2025-03-07", 3600).unwrap());
+        let count: i64 = conn
+            .query_row(
+                "SELECT fire_count FROM alert_log WHERE alert_type = 'cost_daily' AND alert_key = '2025-03-07'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_active_sessions() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let conn = db.get_connection().unwrap();
+        conn.execute(
+            "INSERT INTO sessions (session_id, start_time, last_updated, cost, user, ended_at)
+             VALUES ('current', strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-2 hours'),
+                     strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), 6.0, ?1, NULL),
+                    ('other', strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-30 minutes'),
+                     strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-1 minutes'), 1.0, ?1, NULL),
+                    ('idle', strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-3 hours'),
+                     strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-1 hours'), 9.0, ?1, NULL),
+                    ('ended', strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-1 hours'),
+                     strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), 9.0, ?1,
+                     strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+                    ('teammate', strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-1 hours'),
+                     strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), 9.0, 'someone-else', NULL)",
+            params![crate::common::get_username()],
+        )
+        .unwrap();
+
+        let active = db.get_active_sessions(5, Some("current")).unwrap();
+        assert_eq!(active.others, 1);
+        // $3.00/hr (current) + $1.00 over 29 minutes
+        assert!((active.burn_rate - (3.0 + 60.0 / 29.0)).abs() < 0.01);
+
+        assert_eq!(db.get_active_sessions(5, None).unwrap().others, 2);
+    }
+
+    #[test]
+    fn test_session_end_and_idle_close() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+        let conn = db.get_connection().unwrap();
+        conn.execute_batch(
+            "INSERT INTO sessions (session_id, start_time, last_updated)
+             VALUES ('stale', '2025-01-01T10:00:00+00:00', '2025-01-01T11:00:00+00:00');
+             INSERT INTO sessions (session_id, start_time, last_updated)
+             VALUES ('recent', strftime('%Y-%m-%dT%H:%M:%SZ', 'now', '-1 hours'),
+                     strftime('%Y-%m-%dT%H:%M:%SZ', 'now'));",
+        )
+        .unwrap();
+
+        assert_eq!(close_idle_sessions(&conn, 0).unwrap(), 0);
+        assert_eq!(close_idle_sessions(&conn, 24).unwrap(), 1);
+        let ended_at: Option<String> = conn
+            .query_row(
+                "SELECT ended_at FROM sessions WHERE session_id = 'stale'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(ended_at.as_deref(), Some("2025-01-01T11:00:00+00:00"));
+
+        // Until it's resumed, a closed session's duration stops at its end time
+        conn.execute(
+            "UPDATE sessions SET last_updated = '2025-01-08T11:00:00+00:00' WHERE session_id = 'stale'",
+            [],
+        )
+        .unwrap();
+        let summary = db.get_session_summary("stale").unwrap().unwrap();
+        assert_eq!(summary.duration_secs, Some(3600));
+
+        assert!(db.end_session("recent").unwrap());
+        assert!(!db.end_session("recent").unwrap());
+        assert!(!db.end_session("stale").unwrap());
+        assert_eq!(close_idle_sessions(&conn, 24).unwrap(), 0);
+
+        // A new update reopens the session, so it counts as active again
+        db.update_session(
+            "stale",
+            SessionUpdate {
+                cost: 1.0,
+                lines_added: 0,
+                lines_removed: 0,
+                model_name: None,
+                workspace_dir: None,
+                device_id: None,
+                token_breakdown: None,
+                max_tokens_observed: None,
+                git_branch: None,
+            },
+        )
+        .unwrap();
+        let ended_at: Option<String> = conn
+            .query_row(
+                "SELECT ended_at FROM sessions WHERE session_id = 'stale'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(ended_at, None);
+        assert!(db.end_session("stale").unwrap());
+    }
+
+    #[test]
+    fn test_session_note() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        // Unknown sessions can't be labelled
+        assert!(!db.set_session_note("s1", Some("migrating auth")).unwrap());
+        assert_eq!(db.get_session_note("s1").unwrap(), None);
+
+        db.update_session(
+            "s1",
+            SessionUpdate {
+                cost: 1.0,
+                lines_added: 0,
+                lines_removed: 0,
+                model_name: None,
+                workspace_dir: None,
+                device_id: None,
+                token_breakdown: None,
+                max_tokens_observed: None,
+                git_branch: None,
+            },
+        )
+        .unwrap();
+        assert!(db.set_session_note("s1", Some("migrating auth")).unwrap());
+        assert_eq!(
+            db.get_session_note("s1").unwrap().as_deref(),
+            Some("migrating auth")
+        );
+
+        assert!(db.set_session_note("s1", None).unwrap());
+        assert_eq!(db.get_session_note("s1").unwrap(), None);
+    }
+
+    #[test]
+    fn test_cost_percentile() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        assert_eq!(db.get_cost_percentile("current", 5.0).unwrap(), (0, 0));
+
+        for i in 1..=10 {
+            db.update_session(
+                &format!("past-{}", i),
+                SessionUpdate {
+                    cost: i as f64,
+                    lines_added: 0,
+                    lines_removed: 0,
+                    model_name: None,
+                    workspace_dir: None,
+                    device_id: None,
+                    token_breakdown: None,
+                    max_tokens_observed: None,
+                    git_branch: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // 1..=8 are cheaper than 8.5
+        assert_eq!(db.get_cost_percentile("current", 8.5).unwrap(), (80, 10));
+        // The session itself is not part of its own history
+        assert_eq!(db.get_cost_percentile("past-10", 10.0).unwrap(), (100, 9));
+    }
+
+    #[test]
+    fn test_average_daily_cost() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+        let user = crate::common::get_username();
+
+        assert_eq!(
+            db.get_average_daily_cost("2025-03-01", "2025-03-08")
+                .unwrap(),
+            (0.0, 0)
+        );
+
+        let conn = db.get_connection().unwrap();
+        for (date, user, cost) in [
+            ("2025-02-28", user.as_str(), 100.0), // before the window
+            ("2025-03-01", user.as_str(), 4.0),
+            ("2025-03-03", user.as_str(), 0.0), // inactive day
+            ("2025-03-05", user.as_str(), 8.0),
+            ("2025-03-05", "someone-else", 50.0),
+            ("2025-03-08", user.as_str(), 30.0), // today, excluded
+        ] {
+            conn.execute(
+                "INSERT INTO daily_stats (date, user, total_cost) VALUES (?1, ?2, ?3)",
+                params![date, user, cost],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            db.get_average_daily_cost("2025-03-01", "2025-03-08")
+                .unwrap(),
+            (6.0, 2)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "adaptive-learning")]
+    fn test_prune_learning_data() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+        let conn = db.get_connection().unwrap();
+
+        conn.execute_batch(
+            "INSERT INTO sessions (session_id, start_time, last_updated, model_name) VALUES
+                 ('s1', '2025-01-01T00:00:00', '2025-01-01T00:00:00', 'Sonnet 4.5'),
+                 ('s2', '2025-01-01T00:00:00', '2025-01-01T00:00:00', 'Opus 4.5');
+             INSERT INTO learned_context_windows
+                 (model_name, observed_max_tokens, last_observed_max, last_updated, first_seen)
+             VALUES
+                 ('Sonnet 4.5', 190000, 190000, '2099-01-01T00:00:00+00:00', '2020-01-01T00:00:00+00:00'),
+                 ('Opus 4.5', 180000, 180000, '2020-01-01T00:00:00+00:00', '2020-01-01T00:00:00+00:00');",
+        )
+        .unwrap();
+        for percentage in 0..5 {
+            db.record_context_sample("s1", percentage as f64 * 10.0, 100)
+                .unwrap();
+        }
+        db.record_context_sample("s2", 50.0, 100).unwrap();
+        conn.execute(
+            "UPDATE context_samples SET recorded_at = '2020-01-01T00:00:00+00:00'
+             WHERE session_id = 's2'",
+            [],
+        )
+        .unwrap();
+
+        // Limits of 0 disable pruning
+        assert_eq!(prune_learning_data(&conn, 0, 0).unwrap(), 0);
+
+        // The stale Opus window and sample by age, two oldest Sonnet samples by count
+        assert_eq!(prune_learning_data(&conn, 3, 30).unwrap(), 4);
+        assert!(db.get_learned_context("Sonnet 4.5").unwrap().is_some());
+        assert!(db.get_learned_context("Opus 4.5").unwrap().is_none());
+        assert_eq!(
+            db.get_context_samples("s1", 10).unwrap(),
+            vec![20.0, 30.0, 40.0]
+        );
+        assert!(db.get_context_samples("s2", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unavailable_database_fails_fast() {
+        let temp_dir = TempDir::new().unwrap();
+
+        // A directory where the database file should be
+        let dir_path = temp_dir.path().join("dir.db");
+        std::fs::create_dir_all(&dir_path).unwrap();
+        // A file that is not a SQLite database
+        let corrupt_path = temp_dir.path().join("corrupt.db");
+        std::fs::write(&corrupt_path, vec![0xAB; 4096]).unwrap();
+
+        for path in [&dir_path, &corrupt_path] {
+            let start = std::time::Instant::now();
+            assert!(SqliteDatabase::new(path).is_err());
+            // Second attempt is answered from the unavailable cache
+            assert!(SqliteDatabase::new(path).is_err());
+            assert!(start.elapsed() < std::time::Duration::from_secs(2));
+        }
+
+        // Once the retry deadline passes, a repaired database is used again
+        std::fs::remove_file(&corrupt_path).unwrap();
+        assert!(SqliteDatabase::new(&corrupt_path).is_err());
+        UNAVAILABLE_DBS
+            .get()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .insert(corrupt_path.clone(), Instant::now());
+        assert!(SqliteDatabase::new(&corrupt_path).is_ok());
+    }
+
+    #[test]
+    fn test_busy_database_is_not_marked_unavailable() {
+        let error = |code| rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), None);
+
+        assert!(!marks_unavailable(&error(rusqlite::ffi::SQLITE_BUSY)));
+        assert!(!marks_unavailable(&error(rusqlite::ffi::SQLITE_LOCKED)));
+        assert!(marks_unavailable(&error(rusqlite::ffi::SQLITE_CANTOPEN)));
+        assert!(marks_unavailable(&error(rusqlite::ffi::SQLITE_NOTADB)));
+        assert!(marks_unavailable(&error(rusqlite::ffi::SQLITE_READONLY)));
+    }
+
+    #[test]
+    fn test_reporting_queries() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        for (id, cost) in [("s1", 1.5), ("s2", 2.5)] {
+            db.update_session(
+                id,
+                SessionUpdate {
+                    cost,
+                    lines_added: 0,
+                    lines_removed: 0,
+                    model_name: None,
+                    workspace_dir: None,
+                    device_id: None,
+                    token_breakdown: None,
+                    max_tokens_observed: None,
+                    git_branch: None,
+                },
+            )
+            .unwrap();
+        }
+
+        let today = current_date();
+        let daily = db.get_daily_costs_since(&today).unwrap();
+        assert_eq!(daily, vec![(today.clone(), 4.0)]);
+        assert!(db.get_daily_costs_since("9999-01-01").unwrap().is_empty());
+
+        let hourly = db.get_hourly_costs(&today).unwrap();
+        assert_eq!(hourly.iter().map(|(_, c)| c).sum::<f64>(), 4.0);
+
+        let activity = db.get_weekday_hour_activity(None).unwrap();
+        assert_eq!(activity.iter().map(|(_, _, n, _)| n).sum::<u64>(), 2);
+        assert!(activity.iter().all(|(w, h, _, _)| *w < 7 && *h < 24));
+    }
+
+    #[test]
+    fn test_period_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        db.update_session(
+            "s1",
+            SessionUpdate {
+                cost: 2.0,
+                lines_added: 30,
+                lines_removed: 10,
+                model_name: None,
+                workspace_dir: None,
+                device_id: None,
+                token_breakdown: Some(crate::models::TokenBreakdown {
+                    input_tokens: 1000,
+                    output_tokens: 500,
+                    cache_read_tokens: 9000,
+                    cache_creation_tokens: 0,
+                }),
+                max_tokens_observed: None,
+                git_branch: None,
+            },
+        )
+        .unwrap();
+
+        // Tokens per day come from the recorded API calls, not the snapshot above
+        let today = current_date();
+        let totals = db.get_period_totals(&today, &today).unwrap();
+        assert_eq!(
+            totals,
+            PeriodTotals {
+                cost: 2.0,
+                sessions: 1,
+                lines_changed: 40,
+                tokens: 0,
+            }
+        );
+
+        record_usage(
+            &db,
+            "s1",
+            &[crate::utils::ApiCallUsage {
+                message_id: "m1".to_string(),
+                date: today.clone(),
+                tokens: crate::models::TokenBreakdown {
+                    input_tokens: 300,
+                    output_tokens: 40,
+                    cache_read_tokens: 9000,
+                    cache_creation_tokens: 0,
+                },
+            }],
+        )
+        .unwrap();
+        let daily = db.get_daily_model_usage(None).unwrap();
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].date, today);
+        assert_eq!(daily[0].input_tokens, 300);
+        assert_eq!(daily[0].cache_read_tokens, 9000);
+        assert_eq!(daily[0].cost, 2.0);
+        assert_eq!(db.get_period_totals(&today, &today).unwrap().tokens, 340);
+
+        let empty = db.get_period_totals("2000-01-01", "2000-01-31").unwrap();
+        assert_eq!(empty, PeriodTotals::default());
+    }
+
+    #[test]
+    fn test_import_daily_model_usage() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let row = |date: &str, model: &str, cost| DailyModelUsage {
+            date: date.to_string(),
+            model_name: model.to_string(),
+            input_tokens: 100,
+            output_tokens: 20,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 500,
+            cost,
+        };
+        let rows = [
+            row("2025-03-01", "Opus 4.1", 2.0),
+            row("2025-03-01", "Sonnet 4.5", 0.5),
+            row("2025-03-02", "", 1.0),
+        ];
+
+        assert_eq!(db.import_daily_model_usage(&rows).unwrap(), 3);
+        // Importing the same rows again adds nothing
+        assert_eq!(db.import_daily_model_usage(&rows).unwrap(), 0);
+
+        // The imported history reads back as it was exported
+        assert_eq!(db.get_daily_model_usage(None).unwrap(), rows.to_vec());
+        let totals = db.get_period_totals("2025-03-01", "2025-03-01").unwrap();
+        assert_eq!(totals.cost, 2.5);
+        assert_eq!(totals.sessions, 2);
+        assert_eq!(totals.tokens, 240);
+        let month: (f64, i64) = db
+            .get_connection()
+            .unwrap()
+            .query_row(
+                "SELECT total_cost, session_count FROM monthly_stats WHERE month = '2025-03'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(month, (3.5, 3));
+    }
+
+    #[test]
+    fn test_period_totals_count_sessions_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let conn = db.get_connection().unwrap();
+
+        // One session spanning three days, counted in each day's session_count
+        conn.execute_batch(
+            "INSERT INTO sessions (session_id, start_time, last_updated, cost)
+                 VALUES ('long', '2025-03-01T12:00:00', '2025-03-03T12:00:00', 3.0),
+                        ('other', '2025-03-05T12:00:00', '2025-03-05T13:00:00', 1.0);
+             INSERT INTO daily_stats (date, user, total_cost, session_count)
+                 VALUES ('2025-03-01', '', 1.0, 1), ('2025-03-02', '', 1.0, 1),
+                        ('2025-03-03', '', 1.0, 1), ('2025-03-05', '', 1.0, 1);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            db.get_period_totals("2025-03-01", "2025-03-07")
+                .unwrap()
+                .sessions,
+            2
+        );
+        // Active on the 2nd without starting or ending then
+        assert_eq!(
+            db.get_period_totals("2025-03-02", "2025-03-02")
+                .unwrap()
+                .sessions,
+            1
+        );
+    }
+
+    #[test]
+    fn test_period_totals_are_per_user() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+        let conn = db.get_connection().unwrap();
+        let me = crate::common::get_username();
+
+        // The same day on a shared database, for this user and a teammate
+        conn.execute(
+            "INSERT INTO sessions (session_id, start_time, last_updated, cost, user)
+                 VALUES ('mine', '2025-03-01T12:00:00', '2025-03-01T13:00:00', 1.0, ?1),
+                        ('theirs', '2025-03-01T12:00:00', '2025-03-01T13:00:00', 5.0, 'teammate')",
+            params![&me],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO daily_stats (date, user, total_cost, total_lines_added, session_count)
+                 VALUES ('2025-03-01', ?1, 1.0, 10, 1), ('2025-03-01', 'teammate', 5.0, 50, 1)",
+            params![&me],
+        )
+        .unwrap();
+
+        let totals = db.get_period_totals("2025-03-01", "2025-03-01").unwrap();
+        assert_eq!(totals.cost, 1.0);
+        assert_eq!(totals.sessions, 1);
+        assert_eq!(totals.lines_changed, 10);
+        assert_eq!(db.get_user_all_time_totals().unwrap(), (1.0, 1));
+    }
+
+    #[test]
+    fn test_model_usage_sums_calls() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+
+        for (id, model) in [("s1", "Opus"), ("s2", "Sonnet")] {
+            db.update_session(
+                id,
+                SessionUpdate {
+                    cost: 1.0,
+                    lines_added: 0,
+                    lines_removed: 0,
+                    model_name: Some(model.to_string()),
+                    workspace_dir: None,
+                    device_id: None,
+                    // Snapshot of the largest message only
+                    token_breakdown: Some(crate::models::TokenBreakdown {
+                        input_tokens: 1000,
+                        output_tokens: 100,
+                        cache_read_tokens: 0,
+                        cache_creation_tokens: 0,
+                    }),
+                    max_tokens_observed: None,
+                    git_branch: None,
+                },
+            )
+            .unwrap();
+        }
+        let call = |id: &str| crate::utils::ApiCallUsage {
+            message_id: id.to_string(),
+            date: current_date(),
+            tokens: crate::models::TokenBreakdown {
+                input_tokens: 1000,
+                output_tokens: 100,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+            },
+        };
+        record_usage(&db, "s1", &[call("m1"), call("m2"), call("m3")]).unwrap();
+
+        let mut usage = db.get_model_usage(None).unwrap();
+        usage.sort_by(|a, b| a.key.cmp(&b.key));
+        assert_eq!(usage[0].key, "Opus");
+        assert_eq!(usage[0].tokens, 3300);
+        // No calls recorded yet
+        assert_eq!(usage[1].key, "Sonnet");
+        assert_eq!(usage[1].tokens, 0);
+    }
+
+    #[test]
+    fn test_cache_read_tokens_by_model_sums_calls() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = SqliteDatabase::new(&temp_dir.path().join("test.db")).unwrap();
+
+        // The session row keeps a snapshot of one message: 4000 cache-read tokens
+        db.update_session(
+            "s1",
+            SessionUpdate {
+                cost: 1.0,
+                lines_added: 0,
+                lines_removed: 0,
+                model_name: Some("Opus".to_string()),
+                workspace_dir: None,
+                device_id: None,
+                token_breakdown: Some(crate::models::TokenBreakdown {
+                    input_tokens: 10,
+                    output_tokens: 10,
+                    cache_read_tokens: 4000,
+                    cache_creation_tokens: 0,
+                }),
+                max_tokens_observed: None,
+                git_branch: None,
+            },
+        )
+        .unwrap();
+
+        let call = |id: &str, date: &str, cache_read: u32| crate::utils::ApiCallUsage {
+            message_id: id.to_string(),
+            date: date.to_string(),
+            tokens: crate::models::TokenBreakdown {
+                input_tokens: 10,
+                output_tokens: 10,
+                cache_read_tokens: cache_read,
+                cache_creation_tokens: 0,
+            },
+        };
+        record_usage(
+            &db,
+            "s1",
+            &[
+                call("m0", "2000-01-01", 7000),
+                call("m1", "2999-01-01", 2000),
+                call("m2", "2999-01-01", 3000),
+                call("m3", "2999-01-02", 4000),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            db.get_cache_read_tokens_by_model(Some("2999-01-01"))
+                .unwrap(),
+            vec![("Opus".to_string(), 9000)]
+        );
+        assert_eq!(
+            db.get_cache_read_tokens_by_model(None).unwrap(),
+            vec![("Opus".to_string(), 16000)]
+        );
+    }
+
+    #[test]
+    fn test_daily_token_totals() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        let today = current_date();
+        let call = |id: &str, input: u32, output: u32| crate::utils::ApiCallUsage {
+            message_id: id.to_string(),
+            date: today.clone(),
+            tokens: crate::models::TokenBreakdown {
+                input_tokens: input,
+                output_tokens: output,
+                cache_read_tokens: 5000,
+                cache_creation_tokens: 0,
+            },
+        };
+        assert_eq!(
+            record_usage(&db, "s1", &[call("m1", 1000, 100), call("m2", 500, 50)]).unwrap(),
+            2
+        );
+        // Calls seen on an earlier render are not counted again
+        assert_eq!(
+            record_usage(&db, "s1", &[call("m2", 500, 50), call("m3", 200, 20)]).unwrap(),
+            1
+        );
+        record_usage(&db, "s2", &[call("m1", 10, 1)]).unwrap();
+
+        assert_eq!(
+            db.get_token_totals(&today, &today).unwrap(),
+            TokenTotals {
+                input: 1710,
+                output: 171,
+                cache_read: 20000,
+                cache_creation: 0,
+            }
+        );
+        assert_eq!(
+            db.get_token_totals("2000-01-01", "2000-01-31").unwrap(),
+            TokenTotals::default()
+        );
+    }
+
+    #[test]
+    fn test_project_usage_ranking() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let db = SqliteDatabase::new(&db_path).unwrap();
+
+        for (id, cost, workspace) in [
+            ("s1", 1.0, Some("/work/a")),
+            ("s2", 4.0, Some("/work/b")),
+            ("s3", 2.0, Some("/work/a")),
+            ("s4", 0.5, None),
+        ] {
+            db.update_session(
+                id,
+                SessionUpdate {
+                    cost,
+                    lines_added: 0,
+                    lines_removed: 0,
+                    model_name: None,
+                    workspace_dir: workspace.map(String::from),
+                    device_id: None,
+                    token_breakdown: None,
+                    max_tokens_observed: None,
+                    git_branch: None,
+                },
+            )
+            .unwrap();
+        }
+
+        let usage = db.get_project_usage(None, 10).unwrap();
+        let keys: Vec<&str> = usage.iter().map(|u| u.key.as_str()).collect();
+        assert_eq!(keys, vec!["/work/b", "/work/a", ""]);
+        assert_eq!(usage[1].cost, 3.0);
+        assert_eq!(usage[1].sessions, 2);
+        assert_eq!(usage[1].tokens, 0);
+
+        // Tokens add up every recorded call of the project's sessions
+        let call = |id: &str, input: u32| crate::utils::ApiCallUsage {
+            message_id: id.to_string(),
+            date: current_date(),
+            tokens: crate::models::TokenBreakdown {
+                input_tokens: input,
+                output_tokens: 10,
+                cache_read_tokens: 0,
+                cache_creation_tokens: 0,
+            },
+        };
+        record_usage(&db, "s1", &[call("m1", 100), call("m2", 200)]).unwrap();
+        record_usage(&db, "s3", &[call("m3", 300)]).unwrap();
+        let usage = db.get_project_usage(None, 10).unwrap();
+        assert_eq!(usage[1].key, "/work/a");
+        assert_eq!(usage[1].tokens, 630);
+        assert_eq!(usage[1].sessions, 2);
+
+        assert_eq!(db.get_project_usage(None, 1).unwrap().len(), 1);
+
+        let today = current_date();
+        let top = db.get_top_sessions(&today, &today, 2).unwrap();
+        let ids: Vec<&str> = top.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(ids, vec!["s2", "s3"]);
+        assert_eq!(top[0].workspace_dir.as_deref(), Some("/work/b"));
+        assert!(top[0].duration_secs.is_some());
+        // Tokens of every recorded call, not the session's snapshot
+        assert_eq!(top[0].tokens, 0);
+        assert_eq!(top[1].tokens, 310);
+        assert_eq!(
+            db.get_cache_read_tokens_by_model(None).unwrap(),
+            vec![(String::new(), 0)]
+        );
+        assert!(db
+            .get_project_usage(Some("9999-01-01"), 10)
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -3,11 +3,43 @@
 //! This module handles the visual formatting of the statusline output,
 //! including colors, progress bars, and layout.
 
+use crate::common::{guarded, is_degraded};
 use crate::config;
+#[cfg(feature = "git")]
 use crate::git::{format_git_info, get_git_status};
-use crate::models::{ContextUsage, Cost, ModelType};
-use crate::theme::{get_theme_manager, Theme};
-use crate::utils::{calculate_context_usage, parse_duration, sanitize_for_terminal, shorten_path};
+use crate::models::{ContextUsage, Cost, ModelType, TokenBreakdown, TranscriptSize};
+#[cfg(feature = "themes")]
+use crate::theme::get_theme_manager;
+use crate::theme::Theme;
+use crate::utils::{
+    abbreviate_path, calculate_current_context_usage, format_duration, parse_duration,
+    parse_session_times, sanitize_for_terminal, shorten_path,
+};
+use std::cell::RefCell;
+use std::env;
+use std::path::Path;
+use std::sync::OnceLock;
+
+thread_local! {
+    // Path shown in the directory segment, if not the working directory (see `set_display_path`)
+    static DISPLAY_PATH: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Shows `path` in the directory segment instead of the working directory
+/// (`display.path_style = "project"`) for the rest of the render.
+///
+/// Git, the read-only marker and other per-directory checks keep using the
+/// working directory passed to the formatters.
+pub fn set_display_path(path: Option<&str>) {
+    DISPLAY_PATH.with(|shown| *shown.borrow_mut() = path.map(str::to_string));
+}
+
+/// The path to show for `current_dir`, honouring `set_display_path`.
+pub fn display_path(current_dir: &str) -> String {
+    DISPLAY_PATH
+        .with(|shown| shown.borrow().clone())
+        .unwrap_or_else(|| current_dir.to_string())
+}
 
 /// Gets the current theme based on configuration.
 ///
@@ -15,6 +47,7 @@ use crate::utils::{calculate_context_usage, parse_duration, sanitize_for_termina
 /// 1. Config file: theme = "name"
 /// 2. Environment: CLAUDE_THEME or STATUSLINE_THEME
 /// 3. Default: "dark"
+#[cfg(feature = "themes")]
 fn get_current_theme() -> Theme {
     // Get theme name from config or environment
     let theme_name = config::get_theme();
@@ -28,17 +61,23 @@ fn get_current_theme() -> Theme {
         })
 }
 
+/// Without the `themes` feature every render uses the built-in default theme.
+#[cfg(not(feature = "themes"))]
+fn get_current_theme() -> Theme {
+    Theme::default()
+}
+
 /// ANSI color codes for terminal output.
 pub struct Colors;
 
 impl Colors {
-    /// Check if colors are enabled (respects NO_COLOR env var)
+    /// Check if colors are enabled (see [`crate::color`] for the precedence)
     pub fn enabled() -> bool {
-        std::env::var("NO_COLOR").is_err()
+        crate::color::enabled()
     }
 
     /// Get a color from theme, or empty string if colors are disabled
-    fn get_themed(color_name: &str) -> String {
+    pub fn get_themed(color_name: &str) -> String {
         if !Self::enabled() {
             return String::new();
         }
@@ -94,6 +133,7 @@ impl Colors {
         Self::get_themed("white")
     }
 
+    #[cfg_attr(not(any(feature = "git", feature = "sqlite")), allow(dead_code))]
     pub fn gray() -> String {
         Self::get_themed("gray")
     }
@@ -181,6 +221,15 @@ impl Colors {
         theme.resolve_color(&theme.colors.lines_removed)
     }
 
+    /// Get the theme's critical color, for states that need attention right away
+    pub fn critical() -> String {
+        if !Self::enabled() {
+            return String::new();
+        }
+        let theme = get_current_theme();
+        theme.resolve_color(&theme.colors.context_critical)
+    }
+
     /// Get cost color based on amount and theme thresholds
     pub fn cost_color(cost: f64) -> String {
         if !Self::enabled() {
@@ -204,20 +253,114 @@ impl Colors {
             return String::new();
         }
         let theme = get_current_theme();
-        let config = config::get_config();
 
-        if percentage > config.display.context_critical_threshold {
-            theme.resolve_color(&theme.colors.context_critical)
-        } else if percentage > config.display.context_warning_threshold {
-            theme.resolve_color(&theme.colors.context_warning)
-        } else if percentage > config.display.context_caution_threshold {
-            theme.resolve_color(&theme.colors.context_caution)
-        } else {
-            theme.resolve_color(&theme.colors.context_normal)
+        match context_level(percentage) {
+            3 => theme.resolve_color(&theme.colors.context_critical),
+            2 => theme.resolve_color(&theme.colors.context_warning),
+            1 => theme.resolve_color(&theme.colors.context_caution),
+            _ => theme.resolve_color(&theme.colors.context_normal),
         }
     }
 }
 
+/// Context severity level: 0 = normal, 1 = caution, 2 = warning, 3 = critical.
+pub(crate) fn context_level(percentage: f64) -> usize {
+    let config = config::get_config();
+
+    if percentage > config.display.context_critical_threshold {
+        3
+    } else if percentage > config.display.context_warning_threshold {
+        2
+    } else if percentage > config.display.context_caution_threshold {
+        1
+    } else {
+        0
+    }
+}
+
+/// Accessibility marker for a severity level (empty at level 0 or when disabled).
+fn severity_marker(level: usize) -> String {
+    let config = config::get_config();
+    if !config.display.severity_markers || level == 0 {
+        return String::new();
+    }
+    config
+        .display
+        .severity_symbols
+        .get(level - 1)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Accessibility marker for a cost: medium costs get the caution marker and
+/// high costs the critical one, mirroring their colors.
+fn cost_marker(cost: f64) -> String {
+    let config = config::get_config();
+    if cost >= config.cost.medium_threshold {
+        severity_marker(3)
+    } else if cost >= config.cost.low_threshold {
+        severity_marker(1)
+    } else {
+        String::new()
+    }
+}
+
+/// `▲` when today's cost is running well above the trailing week (see `cost.anomaly_factor`).
+fn anomaly_marker(daily_total: f64) -> String {
+    if guarded("anomaly", false, || {
+        crate::stats::daily_cost_anomaly(daily_total)
+    }) {
+        format!("{}▲", Colors::red())
+    } else {
+        String::new()
+    }
+}
+
+/// Marker for a working directory the user can't write to, where Claude Code's
+/// edits would fail (empty when writable or `display.readonly_marker` is "").
+fn readonly_marker(current_dir: &str, display_config: &config::DisplayConfig) -> String {
+    if display_config.readonly_marker.is_empty()
+        || crate::utils::is_dir_writable(Path::new(current_dir))
+    {
+        return String::new();
+    }
+    format!(
+        " {}{}{}",
+        Colors::orange(),
+        sanitize_for_terminal(&display_config.readonly_marker),
+        Colors::reset()
+    )
+}
+
+/// Formats a cost with the configured precision and rounding.
+fn format_cost(amount: f64) -> String {
+    crate::utils::format_cost(amount, &config::get_config().cost)
+}
+
+/// Whether the session cost is below `cost.hide_below`, hiding the session cost.
+fn below_cost_noise(cost: Option<&Cost>) -> bool {
+    let hide_below = config::get_config().cost.hide_below;
+    cost.and_then(|c| c.total_cost_usd)
+        .is_some_and(|total| total < hide_below)
+}
+
+/// First 8 characters of a session ID, enough to find it in the DB or transcripts.
+fn short_session_id(session_id: &str) -> String {
+    session_id.chars().take(8).collect()
+}
+
+/// Bar fill glyph for a context percentage, taken from the theme's shapes.
+///
+/// Applied even when colors are disabled, since the shape is what carries the
+/// severity for color-blind friendly themes.
+fn context_fill(percentage: f64) -> String {
+    get_current_theme()
+        .shapes
+        .context_fill(context_level(percentage))
+        .to_string()
+}
+
+#[allow(dead_code)] // Library API; the binary renders via format_output_to_string
 pub fn format_output(
     current_dir: &str,
     model_name: Option<&str>,
@@ -254,23 +397,56 @@ fn format_statusline_string(
         transcript_path,
         display_config.show_context
     );
+    if display_config.compact {
+        return format_compact_string(
+            current_dir,
+            model_name,
+            transcript_path,
+            cost,
+            session_id,
+            display_config,
+        );
+    }
+
+    let labels = &display_config.labels;
     let mut parts = Vec::new();
 
     // 1. Directory (always first if shown)
     if display_config.show_directory {
-        let short_dir = sanitize_for_terminal(&shorten_path(current_dir));
+        let short_dir = sanitize_for_terminal(&shorten_path(&display_path(current_dir)));
         parts.push(format!(
-            "{}{}{}",
+            "{}{}{}{}",
             Colors::directory(),
             short_dir,
-            Colors::reset()
+            Colors::reset(),
+            readonly_marker(current_dir, display_config)
         ));
     }
 
     // 2. Git status
+    #[cfg(feature = "git")]
     if display_config.show_git {
-        if let Some(git_status) = get_git_status(current_dir) {
-            let git_info = format_git_info(&git_status);
+        let git_status = crate::telemetry::timed("git", || {
+            guarded("git", None, || get_git_status(current_dir))
+        });
+        if let Some(git_status) = git_status {
+            let mut git_info = format_git_info(&git_status);
+            if config::get_config().git.show_tag {
+                if let Some(tag) = guarded("git tag", None, || {
+                    crate::git::get_nearest_tag(current_dir, &git_status)
+                }) {
+                    git_info.push(' ');
+                    git_info.push_str(&crate::git::format_tag(&tag));
+                }
+            }
+            if config::get_config().git.show_identity {
+                if let Some(identity) = guarded("git identity", None, || {
+                    crate::git::format_identity(current_dir)
+                }) {
+                    git_info.push(' ');
+                    git_info.push_str(&identity);
+                }
+            }
             if !git_info.is_empty() {
                 // Trim leading space from git_info (legacy format)
                 parts.push(git_info.trim_start().to_string());
@@ -278,19 +454,45 @@ fn format_statusline_string(
         }
     }
 
-    // 3. Context usage from transcript
+    // 3. Context usage (reported by Claude Code, or from the transcript)
     if display_config.show_context {
-        if let Some(transcript) = transcript_path {
-            if let Some(context) = calculate_context_usage(transcript, model_name, session_id, None)
-            {
-                let current_tokens = crate::utils::get_token_count_from_transcript(transcript);
-                let full_config = config::get_config();
-                let window_size = Some(crate::utils::get_context_window_for_model(
-                    model_name,
-                    full_config,
-                ));
-                parts.push(format_context_bar(&context, current_tokens, window_size));
+        let context = crate::telemetry::timed("context", || {
+            guarded("context", None, || {
+                calculate_current_context_usage(transcript_path, model_name, session_id, None)
+            })
+        });
+        if let Some(context) = context {
+            crate::telemetry::set_attribute("statusline.context.percentage", context.percentage);
+            let current_tokens = crate::utils::current_context_tokens(transcript_path);
+            let full_config = config::get_config();
+            let window_size = Some(crate::utils::context_window_size(model_name, &full_config));
+            let breakdown = if display_config.context_bar_style == "segmented" {
+                crate::utils::current_token_breakdown(transcript_path)
+            } else {
+                None
+            };
+            let mut context_part =
+                format_context_bar(&context, current_tokens, window_size, breakdown.as_ref());
+
+            // Optional sparkline of recent context usage for this session
+            if display_config.show_context_sparkline {
+                if let Some(sid) = session_id {
+                    let samples = guarded("context history", Vec::new(), || {
+                        crate::stats::get_context_samples(sid)
+                    });
+                    let sparkline = format_sparkline(&samples);
+                    if !sparkline.is_empty() {
+                        context_part.push_str(&format!(
+                            " {}{}{}",
+                            Colors::light_gray(),
+                            sparkline,
+                            Colors::reset()
+                        ));
+                    }
+                }
             }
+
+            parts.push(context_part);
         }
     }
 
@@ -299,72 +501,186 @@ fn format_statusline_string(
         if let Some(name) = model_name {
             let sanitized_name = sanitize_for_terminal(name);
             let model_type = ModelType::from_name(&sanitized_name);
+            let icon = model_type
+                .icon(&display_config.model_icons)
+                .filter(|icon| !icon.is_empty())
+                .map(|icon| format!("{} ", sanitize_for_terminal(icon)))
+                .unwrap_or_default();
             parts.push(format!(
-                "{}{}{}",
+                "{}{}{}{}",
                 Colors::model(),
+                icon,
                 sanitize_for_terminal(&model_type.abbreviation()),
                 Colors::reset()
             ));
         }
     }
 
-    // 5. Duration from transcript
-    if display_config.show_duration {
+    // 5. Duration from transcript (also read for the break reminder)
+    let reminder_secs = display_config.break_reminder_minutes * 60;
+    let times = transcript_path
+        .filter(|_| display_config.show_duration || reminder_secs > 0)
+        .and_then(|transcript| guarded("duration", None, || parse_session_times(transcript)));
+    if let Some(times) = times.filter(|_| display_config.show_duration) {
+        let style = &display_config.duration_format;
+        let duration = format_duration(times.wall_secs, style);
+        let text = if display_config.show_active_time {
+            format!(
+                "{} {} / {}",
+                labels.active,
+                format_duration(times.active_secs, style),
+                duration
+            )
+        } else {
+            duration
+        };
+        parts.push(format!("{}{}{}", Colors::duration(), text, Colors::reset()));
+    }
+
+    // 5a. Break reminder after a long stretch without idle gaps
+    if let Some(times) = times.filter(|_| reminder_secs > 0) {
+        let idle_threshold = config::get_config().transcript.idle_threshold_secs;
+        let now = crate::environment::current().now().timestamp().max(0) as u64;
+        if times.continuous_secs(now, idle_threshold) >= reminder_secs {
+            parts.push(format!(
+                "{}{}{}",
+                Colors::orange(),
+                sanitize_for_terminal(&display_config.break_reminder_text),
+                Colors::reset()
+            ));
+        }
+    }
+
+    // 5b. Transcript size (session weight beyond tokens)
+    if display_config.show_transcript_size {
         if let Some(transcript) = transcript_path {
-            if let Some(duration) = parse_duration(transcript) {
+            if let Some(size) = guarded("transcript size", None, || {
+                crate::utils::transcript_size(transcript)
+            }) {
                 parts.push(format!(
                     "{}{}{}",
                     Colors::duration(),
-                    format_duration(duration),
+                    format_transcript_size(&size, &display_config.labels.messages),
                     Colors::reset()
                 ));
             }
         }
     }
 
-    // 6. Lines changed
+    // 6. Lines changed: Claude Code's cumulative counts ("cost"), the uncommitted
+    // git diff ("git"), or both side by side to show how much is still unstaged
     if display_config.show_lines_changed {
-        if let Some(cost_data) = cost {
-            if let (Some(added), Some(removed)) =
-                (cost_data.total_lines_added, cost_data.total_lines_removed)
-            {
-                if added > 0 || removed > 0 {
-                    let mut lines_part = String::new();
-                    if added > 0 {
-                        lines_part.push_str(&format!(
-                            "{}+{}{}",
-                            Colors::lines_added(),
-                            added,
-                            Colors::reset()
-                        ));
-                    }
-                    if removed > 0 {
-                        if added > 0 {
-                            lines_part.push(' ');
-                        }
-                        lines_part.push_str(&format!(
-                            "{}-{}{}",
-                            Colors::lines_removed(),
-                            removed,
+        let source = display_config.lines_source.as_str();
+        let claude_lines = || cost.and_then(|c| c.total_lines_added.zip(c.total_lines_removed));
+        #[cfg(feature = "git")]
+        let git_lines = || {
+            guarded("git diff", None, || crate::git::get_diff_stats(current_dir))
+                .map(|stat| (stat.added, stat.removed))
+        };
+        #[cfg(not(feature = "git"))]
+        let git_lines = || None::<(u64, u64)>;
+
+        let lines_part = match source {
+            "git" => git_lines().and_then(|(added, removed)| format_lines(added, removed)),
+            "both" => {
+                let claude =
+                    claude_lines().and_then(|(added, removed)| format_lines(added, removed));
+                match claude {
+                    Some(claude) => {
+                        let (added, removed) = git_lines().unwrap_or((0, 0));
+                        let diff = format_lines(added, removed).unwrap_or_else(|| {
+                            format!(
+                                "{}{}{}",
+                                Colors::light_gray(),
+                                labels.clean,
+                                Colors::reset()
+                            )
+                        });
+                        Some(format!(
+                            "{} {}({}{} {}{}){}",
+                            claude,
+                            Colors::light_gray(),
+                            labels.git,
+                            Colors::reset(),
+                            diff,
+                            Colors::light_gray(),
                             Colors::reset()
-                        ));
+                        ))
                     }
-                    parts.push(lines_part);
+                    None => git_lines().and_then(|(added, removed)| {
+                        format_lines(added, removed).map(|diff| {
+                            format!(
+                                "{}{}{} {}",
+                                Colors::light_gray(),
+                                labels.git,
+                                Colors::reset(),
+                                diff
+                            )
+                        })
+                    }),
+                }
+            }
+            _ => claude_lines().and_then(|(added, removed)| format_lines(added, removed)),
+        };
+        if let Some(lines_part) = lines_part {
+            parts.push(lines_part);
+        }
+    }
+
+    // 6a. Recent change velocity: Claude Code's counts against the window start
+    if display_config.show_lines_delta {
+        let counts = cost.and_then(|c| c.total_lines_added.zip(c.total_lines_removed));
+        if let (Some(sid), Some((added, removed))) = (session_id, counts) {
+            let window_secs = display_config.lines_delta_window_minutes.max(1) * 60;
+            if let Some(delta) = guarded("lines delta", None, || {
+                crate::velocity::lines_delta(sid, added, removed, window_secs)
+            }) {
+                if let Some(lines) = format_lines(delta.added, delta.removed) {
+                    parts.push(format!(
+                        "{} {}{} {}{}",
+                        lines,
+                        Colors::light_gray(),
+                        labels.lines_in,
+                        format_duration(delta.span_secs, &display_config.duration_format),
+                        Colors::reset()
+                    ));
                 }
             }
         }
     }
 
-    // 7. Cost display with burn rate
+    // 6b. Distinct files edited in the session
+    if display_config.show_files_edited {
+        if let Some(count) = session_id.and_then(|sid| {
+            guarded("files edited", None, || {
+                crate::stats::get_edited_file_count(sid)
+            })
+        }) {
+            parts.push(format!(
+                "{}{} {}{}",
+                Colors::light_gray(),
+                count,
+                labels.files,
+                Colors::reset()
+            ));
+        }
+    }
+
+    // 7. Cost display with burn rate (session cost omitted when trivially cheap)
     if display_config.show_cost {
         if let Some(cost_data) = cost {
             if let Some(total_cost) = cost_data.total_cost_usd {
                 let cost_color = get_cost_color(total_cost);
 
-                // Calculate burn rate if we have duration
-                let duration = session_id
-                    .and_then(crate::stats::get_session_duration)
-                    .or_else(|| transcript_path.and_then(parse_duration));
+                // Calculate burn rate if we have duration, preferring the
+                // durations reported by Claude Code over stored/transcript times
+                let duration = cost_data.duration_secs().or_else(|| {
+                    guarded("burn rate", None, || {
+                        session_id
+                            .and_then(crate::stats::get_session_duration)
+                            .or_else(|| transcript_path.and_then(parse_duration))
+                    })
+                });
 
                 let burn_rate = duration.and_then(|d| {
                     if d > 60 {
@@ -374,40 +690,92 @@ fn format_statusline_string(
                     }
                 });
 
-                let mut cost_part = format!("{}${:.2}{}", cost_color, total_cost, Colors::reset());
+                // The anomaly marker goes on the day total, or on the session
+                // cost when that is all of today's spend
+                let anomaly = anomaly_marker(daily_total);
+                let show_session_cost = !below_cost_noise(cost);
+                let mut cost_part = String::new();
+                if show_session_cost {
+                    cost_part = format!(
+                        "{}{}{}{}{}",
+                        cost_color,
+                        format_cost(total_cost),
+                        cost_marker(total_cost),
+                        if daily_total > total_cost {
+                            ""
+                        } else {
+                            &anomaly
+                        },
+                        Colors::reset()
+                    );
+                }
+
+                // Rank among past sessions (e.g. "p92")
+                if show_session_cost && display_config.show_cost_percentile {
+                    let percentile = session_id.and_then(|sid| {
+                        guarded("cost percentile", None, || {
+                            crate::stats::session_cost_percentile(sid, total_cost)
+                        })
+                    });
+                    if let Some(percentile) = percentile {
+                        cost_part.push_str(&format!(
+                            " {}p{}{}",
+                            Colors::light_gray(),
+                            percentile,
+                            Colors::reset()
+                        ));
+                    }
+                }
 
                 // Add burn rate if available
                 if let Some(rate) = burn_rate {
                     if rate > 0.0 {
                         cost_part.push_str(&format!(
-                            " {}(${:.2}/hr){}",
+                            " {}({}{}){}",
                             Colors::light_gray(),
-                            rate,
+                            format_cost(rate),
+                            labels.per_hour,
                             Colors::reset()
                         ));
                     }
                 }
 
-                // Add daily total if different from session cost
+                // Add daily total if different from session cost, without
+                // parentheses when it stands in for a hidden session cost
                 if daily_total > total_cost {
                     let daily_color = get_cost_color(daily_total);
+                    let (open, close) = if show_session_cost {
+                        ("(", ")")
+                    } else {
+                        ("", "")
+                    };
                     cost_part.push_str(&format!(
-                        " {}(day: {}${:.2}){}",
+                        " {}{}{} {}{}{}{}{}{}",
                         Colors::reset(),
+                        open,
+                        labels.day,
                         daily_color,
-                        daily_total,
+                        format_cost(daily_total),
+                        cost_marker(daily_total),
+                        anomaly,
+                        close,
                         Colors::reset()
                     ));
                 }
 
-                parts.push(cost_part);
+                if !cost_part.is_empty() {
+                    parts.push(cost_part.trim_start().to_string());
+                }
             } else if daily_total > 0.0 {
                 // Show daily total even if no session cost
                 let daily_color = get_cost_color(daily_total);
                 parts.push(format!(
-                    "day: {}${:.2}{}",
+                    "{} {}{}{}{}{}",
+                    labels.day,
                     daily_color,
-                    daily_total,
+                    format_cost(daily_total),
+                    cost_marker(daily_total),
+                    anomaly_marker(daily_total),
                     Colors::reset()
                 ));
             }
@@ -415,9 +783,124 @@ fn format_statusline_string(
             // Show daily total even if no cost data
             let daily_color = get_cost_color(daily_total);
             parts.push(format!(
-                "day: {}${:.2}{}",
+                "{} {}{}{}{}{}",
+                labels.day,
                 daily_color,
-                daily_total,
+                format_cost(daily_total),
+                cost_marker(daily_total),
+                anomaly_marker(daily_total),
+                Colors::reset()
+            ));
+        }
+    }
+
+    // 7a. Budget consumption for the month or week (`cost.budget`)
+    if display_config.show_cost {
+        let cost_config = &config::get_config().cost;
+        if cost_config.budget > 0.0 {
+            let spent = guarded("budget", 0.0, crate::stats::budget_period_spend);
+            parts.push(format_budget_bar(
+                spent,
+                cost_config.budget,
+                cost_config.budget_bar_width,
+            ));
+        }
+    }
+
+    // 7b. Cost alerts, shown until acknowledged with `statusline alerts ack`
+    #[cfg(feature = "sqlite")]
+    if crate::stats::alerts_enabled() {
+        let alerts = guarded("alerts", Vec::new(), crate::stats::get_active_alerts);
+        for alert in alerts {
+            let label = if alert.kind == "session" {
+                &labels.alert_session
+            } else {
+                &labels.alert_daily
+            };
+            parts.push(format!(
+                "{}⚠ {} {}{}",
+                Colors::red(),
+                label,
+                format_cost(alert.threshold),
+                Colors::reset()
+            ));
+        }
+    }
+
+    // 7c. Low disk space: stats are not being saved
+    if let Err(crate::error::StatuslineError::LowDiskSpace { free_mb, .. }) =
+        crate::stats::check_disk_space()
+    {
+        parts.push(format!(
+            "{}⚠ {} {}MB{}",
+            Colors::red(),
+            labels.disk_low,
+            free_mb,
+            Colors::reset()
+        ));
+    }
+
+    // 7d. Session note (`statusline session note`)
+    if display_config.show_session_note {
+        if let Some(note) = session_id
+            .and_then(|sid| guarded("session note", None, || crate::stats::get_session_note(sid)))
+        {
+            parts.push(format!(
+                "{}{}{}",
+                Colors::light_gray(),
+                sanitize_for_terminal(&note),
+                Colors::reset()
+            ));
+        }
+    }
+
+    // 7e. Other Claude Code sessions running concurrently, with the combined burn rate
+    #[cfg(feature = "sqlite")]
+    if display_config.show_other_sessions {
+        let active = guarded("active sessions", None, || {
+            crate::stats::get_active_sessions(session_id)
+        });
+        if let Some(active) = active.filter(|a| a.others > 0) {
+            let mut part = format!(
+                "{}+{} {}{}",
+                Colors::light_gray(),
+                active.others,
+                labels.others,
+                Colors::reset()
+            );
+            if active.burn_rate > 0.0 {
+                part.push_str(&format!(
+                    " {}{}{}{}",
+                    get_cost_color(active.burn_rate),
+                    format_cost(active.burn_rate),
+                    labels.per_hour,
+                    Colors::reset()
+                ));
+            }
+            parts.push(part);
+        }
+    }
+
+    // 8. Short session ID (debug aid)
+    if display_config.show_session_id {
+        if let Some(sid) = session_id.filter(|sid| !sid.is_empty()) {
+            parts.push(format!(
+                "{}#{}{}",
+                Colors::light_gray(),
+                sanitize_for_terminal(&short_session_id(sid)),
+                Colors::reset()
+            ));
+        }
+    }
+
+    // 9. Update available (opt-in, cached check)
+    if config::get_config().update.check {
+        crate::update::refresh_if_stale();
+        if let Some(latest) = crate::update::newer_version() {
+            parts.push(format!(
+                "{}⬆ v{}{}",
+                Colors::light_gray(),
+                sanitize_for_terminal(&latest),
                 Colors::reset()
             ));
         }
@@ -425,10 +908,153 @@ fn format_statusline_string(
 
     // Join parts with separator
     let separator = format!(" {}•{} ", Colors::separator_color(), Colors::reset());
-    parts.join(&separator)
+    let mut output = parts.join(&separator);
+
+    // Flag omitted segments so failures don't go unnoticed
+    if is_degraded() {
+        output.push_str(&format!(" {}!{}", Colors::red(), Colors::reset()));
+    }
+    output
+}
+
+/// Minimal line for narrow panes (`display.compact`), e.g. "~/p/x ⌥main 62% $1.23".
+///
+/// Keeps only the directory, branch, context percentage and session cost, each
+/// still subject to its `show_*` toggle, separated by single spaces.
+fn format_compact_string(
+    current_dir: &str,
+    model_name: Option<&str>,
+    transcript_path: Option<&str>,
+    cost: Option<&Cost>,
+    session_id: Option<&str>,
+    display_config: &config::DisplayConfig,
+) -> String {
+    let mut parts = Vec::new();
+
+    if display_config.show_directory {
+        parts.push(format!(
+            "{}{}{}{}",
+            Colors::directory(),
+            sanitize_for_terminal(&abbreviate_path(&display_path(current_dir))),
+            Colors::reset(),
+            readonly_marker(current_dir, display_config)
+        ));
+    }
+
+    #[cfg(feature = "git")]
+    if display_config.show_git {
+        let git_status = crate::telemetry::timed("git", || {
+            guarded("git", None, || get_git_status(current_dir))
+        });
+        if let Some(git_status) = git_status.filter(|g| !g.branch.is_empty()) {
+            parts.push(format!(
+                "⌥{}",
+                crate::git::format_branch(&git_status.branch, Colors::git_branch())
+            ));
+        }
+    }
+
+    if display_config.show_context {
+        let context = crate::telemetry::timed("context", || {
+            guarded("context", None, || {
+                calculate_current_context_usage(transcript_path, model_name, session_id, None)
+            })
+        });
+        if let Some(context) = context {
+            crate::telemetry::set_attribute("statusline.context.percentage", context.percentage);
+            parts.push(format!(
+                "{}{}{}%{}{}",
+                Colors::context_color(context.percentage),
+                if context.approximate { "~" } else { "" },
+                context.percentage.round() as u32,
+                severity_marker(context_level(context.percentage)),
+                Colors::reset()
+            ));
+        }
+    }
+
+    if display_config.show_cost && !below_cost_noise(cost) {
+        if let Some(total_cost) = cost.and_then(|c| c.total_cost_usd) {
+            parts.push(format!(
+                "{}{}{}{}",
+                get_cost_color(total_cost),
+                format_cost(total_cost),
+                cost_marker(total_cost),
+                Colors::reset()
+            ));
+        }
+    }
+
+    let mut output = parts.join(" ");
+    if is_degraded() {
+        output.push_str(&format!(" {}!{}", Colors::red(), Colors::reset()));
+    }
+    output
+}
+
+/// Short right-prompt companion line (`--output zsh-rprompt`), e.g. "62% $1.23".
+///
+/// Only the context percentage and session cost, each subject to its `show_*`
+/// toggle. Returns an empty string when neither is available.
+pub fn format_rprompt_string(
+    model_name: Option<&str>,
+    transcript_path: Option<&str>,
+    cost: Option<&Cost>,
+    session_id: Option<&str>,
+) -> String {
+    let display_config = &config::get_config().display;
+    let mut parts = Vec::new();
+
+    if display_config.show_context {
+        let context = guarded("context", None, || {
+            calculate_current_context_usage(transcript_path, model_name, session_id, None)
+        });
+        if let Some(context) = context {
+            parts.push(format!(
+                "{}{}{}%{}",
+                Colors::context_color(context.percentage),
+                if context.approximate { "~" } else { "" },
+                context.percentage.round() as u32,
+                Colors::reset()
+            ));
+        }
+    }
+
+    if display_config.show_cost && !below_cost_noise(cost) {
+        if let Some(total_cost) = cost.and_then(|c| c.total_cost_usd) {
+            parts.push(format!(
+                "{}{}{}",
+                get_cost_color(total_cost),
+                format_cost(total_cost),
+                Colors::reset()
+            ));
+        }
+    }
+
+    parts.join(" ")
+}
+
+/// Escape a rendered line for a zsh prompt: `%` is doubled and ANSI sequences
+/// are wrapped in `%{...%}` so zsh doesn't count them toward the prompt width.
+pub fn zsh_prompt_escape(line: &str) -> String {
+    static ANSI_REGEX: OnceLock<regex::Regex> = OnceLock::new();
+
+    let ansi_regex = ANSI_REGEX.get_or_init(|| regex::Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+    let mut escaped = String::with_capacity(line.len());
+    let mut last = 0;
+    for m in ansi_regex.find_iter(line) {
+        escaped.push_str(&line[last..m.start()].replace('%', "%%"));
+        escaped.push_str("%{");
+        escaped.push_str(m.as_str());
+        escaped.push_str("%}");
+        last = m.end();
+    }
+    escaped.push_str(&line[last..].replace('%', "%%"));
+    escaped
 }
 
 /// Format output with explicit display configuration (prints to stdout)
+#[allow(dead_code)]
 fn format_output_with_config(
     current_dir: &str,
     model_name: Option<&str>,
@@ -454,7 +1080,6 @@ fn format_output_with_config(
 ///
 /// This is the library-friendly version of format_output that returns
 /// the formatted statusline as a String.
-#[allow(dead_code)]
 pub fn format_output_to_string(
     current_dir: &str,
     model_name: Option<&str>,
@@ -475,15 +1100,155 @@ pub fn format_output_to_string(
     )
 }
 
+/// Minimal line for an empty or unparseable payload: the shell's working
+/// directory (`PWD`) and a dimmed "no data" note, so the prompt is never blank
+/// while Claude Code is still starting up.
+pub fn format_no_data_line() -> String {
+    let dir = crate::environment::current()
+        .var("PWD")
+        .filter(|pwd| !pwd.is_empty())
+        .or_else(|| {
+            env::current_dir()
+                .ok()
+                .map(|p| p.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "~".to_string());
+    format!(
+        "{}{}{} {}{}{}",
+        Colors::directory(),
+        sanitize_for_terminal(&shorten_path(&dir)),
+        Colors::reset(),
+        Colors::gray(),
+        sanitize_for_terminal(&config::get_config().display.labels.no_data),
+        Colors::reset()
+    )
+}
+
+/// Path-only line for a working directory in `display.ignore_dirs` or under a
+/// `.statuslineignore` marker, showing `shown_dir`; None when `working_dir`
+/// isn't ignored. Callers skip git, transcript and database work when Some.
+pub fn format_ignored_dir_line(working_dir: &str, shown_dir: &str) -> Option<String> {
+    if !crate::utils::is_ignored_dir(working_dir, &config::get_config().display.ignore_dirs) {
+        return None;
+    }
+    Some(format!(
+        "{}{}{}",
+        Colors::directory(),
+        sanitize_for_terminal(&shorten_path(shown_dir)),
+        Colors::reset()
+    ))
+}
+
+/// Renders budget consumption as `[■■■□□] 61%`: green, yellow from 80%,
+/// and the critical color once the budget is used up.
+fn format_budget_bar(spent: f64, budget: f64, width: usize) -> String {
+    let percentage = spent / budget * 100.0;
+    let filled = ((percentage / 100.0 * width as f64).round() as usize).min(width);
+    let color = if percentage >= 100.0 {
+        Colors::critical()
+    } else if percentage >= 80.0 {
+        Colors::yellow()
+    } else {
+        Colors::green()
+    };
+    format!(
+        "{}[{}{}] {:.0}%{}",
+        color,
+        "■".repeat(filled),
+        "□".repeat(width - filled),
+        percentage,
+        Colors::reset()
+    )
+}
+
+/// Renders `[===>------]` in the severity color.
+///
+/// With a breakdown (`context_bar_style = "segmented"`), the filled part is split
+/// into cache-read, input (including cache writes) and output regions, each in
+/// its own color, while the brackets and remainder keep the severity color.
+fn render_bar(
+    percentage: f64,
+    bar_width: usize,
+    color: &str,
+    breakdown: Option<&TokenBreakdown>,
+) -> String {
+    let filled = ((percentage / 100.0 * bar_width as f64).round() as usize).min(bar_width);
+    let empty = bar_width - filled;
+    let fill = context_fill(percentage);
+    let rest = format!(
+        "{}{}",
+        if filled < bar_width { ">" } else { "" },
+        "-".repeat(empty.saturating_sub(if filled < bar_width { 1 } else { 0 }))
+    );
+
+    let Some(breakdown) = breakdown.filter(|b| b.total() > 0) else {
+        return format!(
+            "{}[{}{}]{}",
+            color,
+            fill.repeat(filled),
+            rest,
+            Colors::reset()
+        );
+    };
+
+    let widths = segment_widths(
+        filled,
+        [
+            breakdown.cache_read_tokens,
+            breakdown.input_tokens + breakdown.cache_creation_tokens,
+            breakdown.output_tokens,
+        ],
+    );
+    let segment_colors = [Colors::light_gray(), Colors::cyan(), Colors::magenta()];
+    let mut bar = format!("{}[", color);
+    for (width, segment_color) in widths.iter().zip(&segment_colors) {
+        if *width > 0 {
+            bar.push_str(&format!("{}{}", segment_color, fill.repeat(*width)));
+        }
+    }
+    bar.push_str(&format!("{}{}]{}", color, rest, Colors::reset()));
+    bar
+}
+
+/// Splits `filled` bar cells proportionally to `parts` (largest remainder), so
+/// the widths always add up to `filled`.
+fn segment_widths(filled: usize, parts: [u32; 3]) -> [usize; 3] {
+    let total: u64 = parts.iter().map(|&p| p as u64).sum();
+    if total == 0 {
+        return [0; 3];
+    }
+    let exact: Vec<f64> = parts
+        .iter()
+        .map(|&p| p as f64 / total as f64 * filled as f64)
+        .collect();
+    let mut widths = [0usize; 3];
+    for (width, value) in widths.iter_mut().zip(&exact) {
+        *width = value.floor() as usize;
+    }
+    let mut order: Vec<usize> = (0..3).collect();
+    order.sort_by(|&a, &b| {
+        let (ra, rb) = (exact[a] - exact[a].floor(), exact[b] - exact[b].floor());
+        rb.partial_cmp(&ra).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let assigned: usize = widths.iter().sum();
+    for &index in order.iter().take(filled - assigned) {
+        widths[index] += 1;
+    }
+    widths
+}
+
 fn format_context_bar(
     context: &ContextUsage,
     current_tokens: Option<u32>,
     window_size: Option<usize>,
+    breakdown: Option<&TokenBreakdown>,
 ) -> String {
     use crate::models::CompactionState;
 
     let config = config::get_config();
     let bar_width = config.display.progress_bar_width;
+    // Estimated from stored session tokens (no transcript)
+    let approx = if context.approximate { "~" } else { "" };
 
     // Format token counts if enabled and data available
     let token_display = if let (Some(current), Some(window)) = (current_tokens, window_size) {
@@ -507,8 +1272,9 @@ fn format_context_bar(
         CompactionState::InProgress => {
             // Simple static indicator - statusline doesn't update frequently enough for animation
             format!(
-                "{}Compacting...{}{}",
+                "{}{}{}{}",
                 Colors::yellow(),
+                config.display.labels.compacting,
                 Colors::reset(),
                 token_display
             )
@@ -519,27 +1285,16 @@ fn format_context_bar(
             let percentage = context.percentage;
             let color = Colors::context_color(percentage);
             let percentage_color = color.clone();
-
-            let filled_ratio = percentage / 100.0;
-            let filled = (filled_ratio * bar_width as f64).round() as usize;
-            let filled = filled.min(bar_width);
-            let empty = bar_width - filled;
-
-            let bar = format!(
-                "{}{}{}",
-                "=".repeat(filled),
-                if filled < bar_width { ">" } else { "" },
-                "-".repeat(empty.saturating_sub(if filled < bar_width { 1 } else { 0 }))
-            );
+            let bar = render_bar(percentage, bar_width, &color, breakdown);
 
             format!(
-                "{}{}%{} {}[{}]{} {}✓{}{}",
+                "{}{}{}%{}{} {} {}✓{}{}",
                 percentage_color,
+                approx,
                 percentage.round() as u32,
+                severity_marker(context_level(percentage)),
                 Colors::reset(),
-                color,
                 bar,
-                Colors::reset(),
                 Colors::green(),
                 Colors::reset(),
                 token_display
@@ -551,18 +1306,7 @@ fn format_context_bar(
             let percentage = context.percentage;
             let color = Colors::context_color(percentage);
             let percentage_color = color.clone();
-
-            let filled_ratio = percentage / 100.0;
-            let filled = (filled_ratio * bar_width as f64).round() as usize;
-            let filled = filled.min(bar_width);
-            let empty = bar_width - filled;
-
-            let bar = format!(
-                "{}{}{}",
-                "=".repeat(filled),
-                if filled < bar_width { ">" } else { "" },
-                "-".repeat(empty.saturating_sub(if filled < bar_width { 1 } else { 0 }))
-            );
+            let bar = render_bar(percentage, bar_width, &color, breakdown);
 
             // Add warning indicator if approaching auto-compact threshold
             let warning = if context.approaching_limit {
@@ -572,13 +1316,13 @@ fn format_context_bar(
             };
 
             format!(
-                "{}{}%{} {}[{}]{}{}{}",
+                "{}{}{}%{}{} {}{}{}",
                 percentage_color,
+                approx,
                 percentage.round() as u32,
+                severity_marker(context_level(percentage)),
                 Colors::reset(),
-                color,
                 bar,
-                Colors::reset(),
                 warning,
                 token_display
             )
@@ -586,18 +1330,74 @@ fn format_context_bar(
     }
 }
 
+/// Render context percentages (0-100) as a Unicode sparkline, e.g. "▂▃▅▇".
+///
+/// Uses an absolute 0-100% scale so the height reflects actual usage, not just
+/// the relative trend. Returns an empty string with fewer than two samples.
+fn format_sparkline(samples: &[f64]) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    if samples.len() < 2 {
+        return String::new();
+    }
+
+    samples
+        .iter()
+        .map(|pct| {
+            let idx = ((pct.clamp(0.0, 100.0) / 100.0) * (LEVELS.len() - 1) as f64).round();
+            LEVELS[idx as usize]
+        })
+        .collect()
+}
+
 fn get_cost_color(cost: f64) -> String {
     Colors::cost_color(cost)
 }
 
-fn format_duration(seconds: u64) -> String {
-    if seconds < 60 {
-        format!("{}s", seconds)
-    } else if seconds < 3600 {
-        format!("{}m", seconds / 60)
-    } else {
-        format!("{}h{}m", seconds / 3600, (seconds % 3600) / 60)
+/// Formats lines added/removed as "+12 -3", or None when both are zero.
+fn format_lines(added: u64, removed: u64) -> Option<String> {
+    if added == 0 && removed == 0 {
+        return None;
+    }
+    let mut lines_part = String::new();
+    if added > 0 {
+        lines_part.push_str(&format!(
+            "{}+{}{}",
+            Colors::lines_added(),
+            added,
+            Colors::reset()
+        ));
     }
+    if removed > 0 {
+        if added > 0 {
+            lines_part.push(' ');
+        }
+        lines_part.push_str(&format!(
+            "{}-{}{}",
+            Colors::lines_removed(),
+            removed,
+            Colors::reset()
+        ));
+    }
+    Some(lines_part)
+}
+
+/// Formats a transcript size as "1.2MB/3.4k msgs" ("~" marks an estimated count).
+fn format_transcript_size(size: &TranscriptSize, messages_label: &str) -> String {
+    let bytes = if size.bytes < 1024 {
+        format!("{}B", size.bytes)
+    } else if size.bytes < 1024 * 1024 {
+        format!("{}KB", size.bytes / 1024)
+    } else {
+        format!("{:.1}MB", size.bytes as f64 / (1024.0 * 1024.0))
+    };
+    let messages = if size.messages < 1000 {
+        size.messages.to_string()
+    } else {
+        format!("{:.1}k", size.messages as f64 / 1000.0)
+    };
+    let approx = if size.estimated { "~" } else { "" };
+    format!("{}/{}{} {}", bytes, approx, messages, messages_label)
 }
 
 #[cfg(test)]
@@ -623,6 +1423,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_display_path() {
+        assert_eq!(display_path("/work/repo/src"), "/work/repo/src");
+        set_display_path(Some("/work/repo"));
+        assert_eq!(display_path("/work/repo/src"), "/work/repo");
+        set_display_path(None);
+        assert_eq!(display_path("/work/repo/src"), "/work/repo/src");
+    }
+
+    #[test]
+    fn test_zsh_prompt_escape() {
+        assert_eq!(zsh_prompt_escape("62% $1.23"), "62%% $1.23");
+        assert_eq!(
+            zsh_prompt_escape("\x1b[32m62%\x1b[0m $1"),
+            "%{\x1b[32m%}62%%%{\x1b[0m%} $1"
+        );
+        assert_eq!(zsh_prompt_escape(""), "");
+    }
+
     #[test]
     fn test_get_cost_color() {
         // The test should work whether or not NO_COLOR is set
@@ -638,13 +1457,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_format_duration() {
-        assert_eq!(format_duration(45), "45s");
-        assert_eq!(format_duration(90), "1m");
-        assert_eq!(format_duration(3665), "1h1m");
-    }
-
     #[test]
     fn test_format_context_bar() {
         use crate::models::CompactionState;
@@ -653,8 +1465,9 @@ mod tests {
             approaching_limit: false,
             tokens_remaining: 180_000,
             compaction_state: CompactionState::Normal,
+            approximate: false,
         };
-        let bar = format_context_bar(&low, None, None);
+        let bar = format_context_bar(&low, None, None, None);
         assert!(bar.contains("10%"));
         assert!(bar.contains("[=>"));
         assert!(!bar.contains('•'));
@@ -665,11 +1478,60 @@ mod tests {
             approaching_limit: true,
             tokens_remaining: 10_000,
             compaction_state: CompactionState::Normal,
+            approximate: false,
         };
-        let bar = format_context_bar(&high, None, None);
+        let bar = format_context_bar(&high, None, None, None);
         assert!(bar.contains("95%"));
         assert!(!bar.contains('•'));
         assert!(bar.contains('⚠')); // Warning at 95%
+        assert!(!bar.contains('~'));
+
+        let estimated = ContextUsage {
+            approximate: true,
+            ..low
+        };
+        let bar = format_context_bar(&estimated, None, None, None);
+        assert!(bar.contains("~10%"));
+
+        // Segmented bars keep the layout, only the fill colors change
+        let breakdown = TokenBreakdown {
+            input_tokens: 1_000,
+            output_tokens: 1_000,
+            cache_read_tokens: 18_000,
+            cache_creation_tokens: 0,
+        };
+        let segmented = format_context_bar(&high, None, None, Some(&breakdown));
+        assert_eq!(
+            sanitize_for_terminal(&segmented),
+            sanitize_for_terminal(&format_context_bar(&high, None, None, None))
+        );
+    }
+
+    #[test]
+    fn test_segment_widths() {
+        assert_eq!(segment_widths(10, [80, 15, 5]), [8, 2, 0]);
+        assert_eq!(segment_widths(10, [1, 1, 1]), [4, 3, 3]);
+        assert_eq!(segment_widths(3, [0, 0, 0]), [0, 0, 0]);
+        assert_eq!(segment_widths(0, [5, 5, 5]), [0, 0, 0]);
+        assert_eq!(segment_widths(7, [100, 0, 0]), [7, 0, 0]);
+    }
+
+    #[test]
+    fn test_format_sparkline() {
+        assert_eq!(format_sparkline(&[]), "");
+        assert_eq!(format_sparkline(&[50.0]), "");
+        assert_eq!(format_sparkline(&[0.0, 100.0]), "▁█");
+        assert_eq!(format_sparkline(&[10.0, 30.0, 60.0, 90.0]), "▂▃▅▇");
+        // Out-of-range values are clamped
+        assert_eq!(format_sparkline(&[-5.0, 150.0]), "▁█");
+    }
+
+    #[test]
+    fn test_format_budget_bar() {
+        assert!(format_budget_bar(122.0, 200.0, 5).contains("[■■■□□] 61%"));
+        assert!(format_budget_bar(0.0, 200.0, 5).contains("[□□□□□] 0%"));
+        // Overspending keeps the bar full and shows the real percentage
+        assert!(format_budget_bar(260.0, 200.0, 4).contains("[■■■■] 130%"));
     }
 
     #[test]
@@ -688,6 +1550,7 @@ mod tests {
             total_cost_usd: Some(0.50),
             total_lines_added: None,
             total_lines_removed: None,
+            ..Default::default()
         };
 
         // The burn rate calculation happens in format_output
@@ -776,4 +1639,185 @@ mod tests {
         let sanitized_model = sanitize_for_terminal(model_with_control);
         assert_eq!(sanitized_model, "claude--opus");
     }
+
+    #[test]
+    fn test_compact_output() {
+        let display_config = config::DisplayConfig {
+            compact: true,
+            show_git: false,
+            show_context: false,
+            ..Default::default()
+        };
+        let cost = Cost {
+            total_cost_usd: Some(1.23),
+            total_lines_added: Some(10),
+            ..Default::default()
+        };
+
+        let output = format_statusline_string(
+            "/srv/projects/demo-app",
+            Some("Claude Opus 4.5"),
+            None,
+            Some(&cost),
+            25.0,
+            None,
+            &display_config,
+        );
+
+        assert!(output.contains("/s/p/demo-app"));
+        assert!(output.contains("$1.2"));
+        // No separators, line counts, model or day total
+        assert!(!output.contains('•'));
+        assert!(!output.contains("+10"));
+        assert!(!output.contains("Opus"));
+        assert!(!output.contains("day:"));
+    }
+
+    #[test]
+    fn test_session_id_segment() {
+        assert_eq!(
+            short_session_id("3f2a9c1e-77b4-4d2e-9f10-8a6b5c4d3e2f"),
+            "3f2a9c1e"
+        );
+        assert_eq!(short_session_id("abc"), "abc");
+
+        let display_config = config::DisplayConfig {
+            show_directory: false,
+            show_git: false,
+            show_context: false,
+            show_session_id: true,
+            ..Default::default()
+        };
+        let output = format_statusline_string(
+            "/tmp",
+            None,
+            None,
+            None,
+            0.0,
+            Some("3f2a9c1e-77b4-4d2e-9f10-8a6b5c4d3e2f"),
+            &display_config,
+        );
+        assert!(output.contains("#3f2a9c1e"));
+        assert!(!output.contains("77b4"));
+    }
+
+    #[test]
+    fn test_format_transcript_size() {
+        let size = TranscriptSize {
+            bytes: 1_258_291,
+            messages: 3_400,
+            estimated: true,
+        };
+        assert_eq!(format_transcript_size(&size, "msgs"), "1.2MB/~3.4k msgs");
+
+        let size = TranscriptSize {
+            bytes: 52_000,
+            messages: 42,
+            estimated: false,
+        };
+        assert_eq!(format_transcript_size(&size, "msgs"), "50KB/42 msgs");
+    }
+
+    #[test]
+    fn test_lines_source_both_outside_git() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let display_config = config::DisplayConfig {
+            show_directory: false,
+            show_git: false,
+            show_context: false,
+            show_cost: false,
+            lines_source: "both".to_string(),
+            ..Default::default()
+        };
+        let cost = Cost {
+            total_lines_added: Some(5),
+            total_lines_removed: Some(9),
+            ..Default::default()
+        };
+
+        let output = format_statusline_string(
+            dir.path().to_str().unwrap(),
+            None,
+            None,
+            Some(&cost),
+            0.0,
+            None,
+            &display_config,
+        );
+        // Claude's counts, with no uncommitted diff to compare against
+        assert!(output.contains("+5"));
+        assert!(output.contains("-9"));
+        assert!(output.contains("clean"));
+    }
+
+    #[test]
+    fn test_break_reminder() {
+        use std::io::Write;
+
+        // Entries every 2 minutes up to now, with an idle hour `gap_at` minutes ago
+        let transcript = |gap_at: i64| {
+            let mut file = tempfile::NamedTempFile::with_suffix(".jsonl").unwrap();
+            let now = chrono::Utc::now();
+            for minutes_ago in (0..=40).rev().step_by(2) {
+                let mut at = now - chrono::Duration::minutes(minutes_ago);
+                if minutes_ago >= gap_at {
+                    at -= chrono::Duration::hours(1);
+                }
+                let timestamp = at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+                writeln!(
+                    file,
+                    r#"{{"message":{{"role":"user","content":"x"}},"timestamp":"{}"}}"#,
+                    timestamp
+                )
+                .unwrap();
+            }
+            file
+        };
+        let display_config = config::DisplayConfig {
+            show_directory: false,
+            show_git: false,
+            show_context: false,
+            show_duration: false,
+            break_reminder_minutes: 30,
+            break_reminder_text: "stretch".to_string(),
+            ..Default::default()
+        };
+        let render = |file: &tempfile::NamedTempFile| {
+            format_statusline_string(
+                "/tmp",
+                None,
+                file.path().to_str(),
+                None,
+                0.0,
+                None,
+                &display_config,
+            )
+        };
+
+        // 40 continuous minutes
+        assert!(render(&transcript(100)).contains("stretch"));
+        // An idle hour 10 minutes ago resets the streak
+        assert!(!render(&transcript(10)).contains("stretch"));
+    }
+
+    #[test]
+    fn test_custom_labels() {
+        let mut display_config = config::DisplayConfig {
+            show_directory: false,
+            show_git: false,
+            show_context: false,
+            ..Default::default()
+        };
+        display_config.labels.day = "Tag:".to_string();
+        let cost = Cost {
+            total_cost_usd: Some(1.0),
+            ..Default::default()
+        };
+
+        let output =
+            format_statusline_string("/tmp", None, None, Some(&cost), 5.0, None, &display_config);
+        assert!(output.contains("(Tag: "));
+        assert!(output.contains("$5.00"));
+        assert!(!output.contains("day:"));
+    }
 }
@@ -0,0 +1,337 @@
+//! Injectable process environment: variables, home directory and clock.
+//!
+//! Path resolution (`common::get_data_dir`, `Config::find_config_file`, ...),
+//! config loading, home-relative paths, the date helpers and every wall-clock
+//! timestamp (git caches, learned windows, backups, the break reminder) read
+//! the environment through [`current`] instead of `std::env`, `dirs` and
+//! `chrono::Utc::now` / `Local::now` directly.
+//! By default that is the real [`SystemEnvironment`]. Hosts embedding the
+//! library and tests can run code against a [`FixedEnvironment`] with
+//! [`scoped`], which only affects the calling thread, so parallel callers stay
+//! isolated without mutating process-global environment variables.
+
+use crate::config::Config;
+use chrono::{DateTime, Local, Utc};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Source of environment variables, the home directory and the current time
+pub trait Environment: Send + Sync {
+    /// Value of an environment variable, None when unset or not valid UTF-8
+    fn var(&self, key: &str) -> Option<String>;
+
+    /// The user's home directory
+    fn home_dir(&self) -> Option<PathBuf>;
+
+    /// The current time
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Base data directory (`$XDG_DATA_HOME`, else `~/.local/share`)
+    fn data_dir(&self) -> Option<PathBuf> {
+        xdg_dir(self, "XDG_DATA_HOME", ".local/share")
+    }
+
+    /// Base config directory (`$XDG_CONFIG_HOME`, else `~/.config`)
+    fn config_dir(&self) -> Option<PathBuf> {
+        xdg_dir(self, "XDG_CONFIG_HOME", ".config")
+    }
+
+    /// Base cache directory (`$XDG_CACHE_HOME`, else `~/.cache`)
+    fn cache_dir(&self) -> Option<PathBuf> {
+        xdg_dir(self, "XDG_CACHE_HOME", ".cache")
+    }
+
+    /// Base state directory (`$XDG_STATE_HOME`, else `~/.local/state`); the
+    /// system environment has none on macOS and Windows
+    fn state_dir(&self) -> Option<PathBuf> {
+        xdg_dir(self, "XDG_STATE_HOME", ".local/state")
+    }
+
+    /// Runtime directory (an absolute `$XDG_RUNTIME_DIR`, no fallback)
+    fn runtime_dir(&self) -> Option<PathBuf> {
+        self.var("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+    }
+}
+
+/// An absolute `$VAR`, else `fallback` under the home directory
+fn xdg_dir<E: Environment + ?Sized>(env: &E, var: &str, fallback: &str) -> Option<PathBuf> {
+    env.var(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env.home_dir().map(|home| home.join(fallback)))
+}
+
+/// The real process environment, platform directories and system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemEnvironment;
+
+impl Environment for SystemEnvironment {
+    fn var(&self, key: &str) -> Option<String> {
+        std::env::var(key).ok()
+    }
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        dirs::home_dir()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn data_dir(&self) -> Option<PathBuf> {
+        dirs::data_dir()
+    }
+
+    fn config_dir(&self) -> Option<PathBuf> {
+        dirs::config_dir()
+    }
+
+    fn cache_dir(&self) -> Option<PathBuf> {
+        dirs::cache_dir()
+    }
+
+    fn state_dir(&self) -> Option<PathBuf> {
+        dirs::state_dir()
+    }
+
+    fn runtime_dir(&self) -> Option<PathBuf> {
+        dirs::runtime_dir()
+    }
+}
+
+/// A self-contained environment: only the variables set on it exist, and the
+/// clock can be pinned.
+///
+/// ```rust
+/// use statusline::environment::{self, FixedEnvironment};
+///
+/// let env = FixedEnvironment::new()
+///     .with_home("/tmp/statusline-test")
+///     .with_var("STATUSLINE_DATA_DIR", "/tmp/statusline-test/data");
+/// let data_dir = environment::scoped(env, statusline::common::get_data_dir);
+/// assert_eq!(data_dir, std::path::PathBuf::from("/tmp/statusline-test/data"));
+/// ```
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct FixedEnvironment {
+    vars: HashMap<String, String>,
+    home: Option<PathBuf>,
+    now: Option<DateTime<Utc>>,
+}
+
+#[allow(dead_code)]
+impl FixedEnvironment {
+    /// An environment without variables, home directory or pinned clock
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets an environment variable
+    pub fn with_var(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the home directory
+    pub fn with_home(mut self, home: impl Into<PathBuf>) -> Self {
+        self.home = Some(home.into());
+        self
+    }
+
+    /// Pins the clock; unpinned environments use the system clock
+    pub fn with_now(mut self, now: DateTime<Utc>) -> Self {
+        self.now = Some(now);
+        self
+    }
+}
+
+impl Environment for FixedEnvironment {
+    fn var(&self, key: &str) -> Option<String> {
+        self.vars.get(key).cloned()
+    }
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        self.home.clone()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.now.unwrap_or_else(Utc::now)
+    }
+}
+
+/// An environment installed with [`scoped`], the config loaded from it and
+/// the Claude Code profile reported within it
+struct Scope {
+    env: Arc<dyn Environment>,
+    config: Option<Arc<Config>>,
+    profile: Option<String>,
+}
+
+thread_local! {
+    // Innermost `scoped` environment of this thread, if any
+    static SCOPE: RefCell<Option<Scope>> = const { RefCell::new(None) };
+    // Profile reported on this thread outside any scope
+    static PROFILE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The environment in effect on this thread: the innermost [`scoped`] one, or
+/// the system environment.
+pub fn current() -> Arc<dyn Environment> {
+    SCOPE
+        .with(|scope| scope.borrow().as_ref().map(|s| Arc::clone(&s.env)))
+        .unwrap_or_else(|| Arc::new(SystemEnvironment))
+}
+
+/// The current time in the local timezone, from the current environment
+pub fn now_local() -> DateTime<Local> {
+    current().now().with_timezone(&Local)
+}
+
+/// Runs `f` with `env` as this thread's environment, restoring the previous
+/// one afterwards (also when `f` panics).
+///
+/// The config is loaded from `env` on first use within the scope, so each
+/// scope can point at its own config file. The scope starts without a
+/// reported profile (see [`crate::common::set_profile`]).
+#[allow(dead_code)]
+pub fn scoped<E, T>(env: E, f: impl FnOnce() -> T) -> T
+where
+    E: Environment + 'static,
+{
+    struct Restore(Option<Scope>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SCOPE.with(|scope| *scope.borrow_mut() = previous);
+        }
+    }
+
+    let scope = Scope {
+        env: Arc::new(env),
+        config: None,
+        profile: None,
+    };
+    let _restore = Restore(SCOPE.with(|s| s.borrow_mut().replace(scope)));
+    f()
+}
+
+/// Config of the current scope, loading it with `load` on first use. None
+/// outside [`scoped`], where the process-wide config applies.
+///
+/// The config is kept in the scope and dropped with it.
+pub(crate) fn scoped_config(load: impl FnOnce() -> Config) -> Option<Arc<Config>> {
+    let cached = SCOPE.with(|scope| Some(scope.borrow().as_ref()?.config.clone()))?;
+    if let Some(config) = cached {
+        return Some(config);
+    }
+    // Loading reads the environment, so the scope must not be borrowed meanwhile
+    let config = Arc::new(load());
+    SCOPE.with(|scope| {
+        if let Some(scope) = scope.borrow_mut().as_mut() {
+            scope.config = Some(Arc::clone(&config));
+        }
+    });
+    Some(config)
+}
+
+/// Profile reported in the input payload on this thread, within the current
+/// scope if there is one
+pub(crate) fn profile_override() -> Option<String> {
+    SCOPE.with(|scope| match scope.borrow().as_ref() {
+        Some(scope) => scope.profile.clone(),
+        None => PROFILE.with(|profile| profile.borrow().clone()),
+    })
+}
+
+/// Sets the profile returned by [`profile_override`]
+pub(crate) fn set_profile_override(profile: Option<String>) {
+    SCOPE.with(|scope| match scope.borrow_mut().as_mut() {
+        Some(scope) => scope.profile = profile,
+        None => PROFILE.with(|p| *p.borrow_mut() = profile),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_environment_isolated() {
+        let env = FixedEnvironment::new().with_home("/home/test");
+        assert_eq!(env.var("PATH"), None);
+        assert_eq!(
+            env.data_dir(),
+            Some(PathBuf::from("/home/test/.local/share"))
+        );
+
+        let env = env.with_var("XDG_CACHE_HOME", "/cache");
+        assert_eq!(env.cache_dir(), Some(PathBuf::from("/cache")));
+        // Relative XDG paths are invalid per the spec and ignored
+        let env = env.with_var("XDG_CONFIG_HOME", "relative");
+        assert_eq!(env.config_dir(), Some(PathBuf::from("/home/test/.config")));
+    }
+
+    #[test]
+    fn test_scoped_per_thread() {
+        let pinned = DateTime::parse_from_rfc3339("2025-03-07T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let env = FixedEnvironment::new()
+            .with_var("STATUSLINE_DATA_DIR", "/scoped/data")
+            .with_now(pinned);
+
+        scoped(env, || {
+            assert_eq!(crate::common::get_data_dir(), PathBuf::from("/scoped/data"));
+            assert_eq!(current().now(), pinned);
+
+            // Other threads keep the system environment
+            let other = std::thread::spawn(crate::common::get_data_dir)
+                .join()
+                .unwrap();
+            assert_ne!(other, PathBuf::from("/scoped/data"));
+        });
+        assert!(SCOPE.with(|scope| scope.borrow().is_none()));
+    }
+
+    #[test]
+    fn test_scoped_config() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("config.toml"),
+            "[display]\nshow_cost = false\n",
+        )
+        .unwrap();
+        let env = FixedEnvironment::new()
+            .with_var("STATUSLINE_CONFIG_DIR", dir.path().to_string_lossy())
+            .with_home(dir.path());
+
+        scoped(env, || {
+            assert!(!crate::config::get_config().display.show_cost);
+        });
+    }
+
+    #[test]
+    fn test_scoped_profile_override() {
+        let env = FixedEnvironment::new().with_var("XDG_RUNTIME_DIR", "/run/user/1000");
+        scoped(env, || {
+            assert_eq!(
+                current().runtime_dir(),
+                Some(PathBuf::from("/run/user/1000"))
+            );
+            set_profile_override(Some("work".to_string()));
+            assert_eq!(profile_override().as_deref(), Some("work"));
+
+            // A nested scope starts without one and doesn't leak its own
+            scoped(FixedEnvironment::new(), || {
+                assert_eq!(profile_override(), None);
+                set_profile_override(Some("personal".to_string()));
+            });
+            assert_eq!(profile_override().as_deref(), Some("work"));
+        });
+        assert_eq!(profile_override(), None);
+    }
+}
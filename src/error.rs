@@ -2,6 +2,10 @@
 //!
 //! This module provides a unified error type using the `thiserror` crate,
 //! consolidating all error types from various operations into a single enum.
+//!
+//! Every error maps to a stable code (e.g. `E012_DB_LOCKED`) and a one-line
+//! remediation hint. Codes never change meaning once released, so they are
+//! safe to search for and to match on in scripts.
 
 use std::io;
 use thiserror::Error;
@@ -20,16 +24,23 @@ pub enum StatuslineError {
     #[error("JSON parsing error: {0}")]
     JsonParse(#[from] serde_json::Error),
 
+    /// Well-formed input that is missing required data
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
     /// Database operation errors
     #[error("Database error: {0}")]
+    #[cfg(feature = "sqlite")]
     Database(#[from] rusqlite::Error),
 
     /// Connection pool errors
     #[error("Connection pool error: {0}")]
+    #[cfg(feature = "sqlite")]
     ConnectionPool(#[from] r2d2::Error),
 
     /// Git operation errors
     #[error("Git operation failed: {0}")]
+    #[cfg_attr(not(feature = "git"), allow(dead_code))]
     GitOperation(String),
 
     /// File validation errors
@@ -45,6 +56,10 @@ pub enum StatuslineError {
     #[error("Failed to acquire lock: {0}")]
     LockFailed(String),
 
+    /// The data directory's filesystem is below `database.min_free_space_mb`
+    #[error("Low disk space: {free_mb} MB free for the data directory (minimum {min_mb} MB)")]
+    LowDiskSpace { free_mb: u64, min_mb: u64 },
+
     /// Configuration errors
     #[error("Configuration error: {0}")]
     #[allow(dead_code)]
@@ -56,6 +71,10 @@ pub enum StatuslineError {
     #[allow(dead_code)]
     Sync(String),
 
+    /// A statusline was printed, but some segments failed and were omitted
+    #[error("Statusline rendered with omitted segments")]
+    PartialRender,
+
     /// Generic operation errors
     #[error("{0}")]
     Other(String),
@@ -64,9 +83,31 @@ pub enum StatuslineError {
 /// Result type alias for Statusline operations
 pub type Result<T> = std::result::Result<T, StatuslineError>;
 
+/// Process exit codes shared by all subcommands.
+///
+/// Scripts wrapping the CLI can branch on these instead of parsing stderr.
+#[allow(dead_code)]
+pub mod exit_code {
+    /// Success
+    pub const SUCCESS: i32 = 0;
+    /// Any failure without a more specific code
+    pub const FAILURE: i32 = 1;
+    /// Invalid command-line arguments (reported by clap)
+    pub const USAGE: i32 = 2;
+    /// Configuration could not be read, parsed or written
+    pub const CONFIG: i32 = 3;
+    /// Stats database or stats file error
+    pub const DATABASE: i32 = 4;
+    /// Input (JSON payload or hook event) could not be parsed
+    pub const INPUT: i32 = 5;
+    /// Statusline printed with omitted segments (`--strict-exit` and `render`)
+    pub const PARTIAL_RENDER: i32 = 6;
+}
+
 // Helper implementations for common conversions
 impl StatuslineError {
     /// Create a git operation error
+    #[cfg_attr(not(feature = "git"), allow(dead_code))]
     pub fn git(msg: impl Into<String>) -> Self {
         StatuslineError::GitOperation(msg.into())
     }
@@ -77,7 +118,7 @@ impl StatuslineError {
     }
 
     /// Create a stats file error
-    #[allow(dead_code)]
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
     pub fn stats(msg: impl Into<String>) -> Self {
         StatuslineError::StatsFile(msg.into())
     }
@@ -91,6 +132,114 @@ impl StatuslineError {
     pub fn other(msg: impl Into<String>) -> Self {
         StatuslineError::Other(msg.into())
     }
+
+    /// Create an invalid input error
+    pub fn input(msg: impl Into<String>) -> Self {
+        StatuslineError::InvalidInput(msg.into())
+    }
+
+    /// Stable error code, e.g. `E012_DB_LOCKED`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use statusline::error::StatuslineError;
+    ///
+    /// let err = StatuslineError::lock("stats.json is held by another process");
+    /// assert_eq!(err.code(), "E041_LOCK_FAILED");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            StatuslineError::Io(_) => "E001_IO",
+            StatuslineError::JsonParse(_) => "E002_JSON_PARSE",
+            StatuslineError::InvalidInput(_) => "E003_INPUT",
+            #[cfg(feature = "sqlite")]
+            StatuslineError::Database(e) => match e.sqlite_error_code() {
+                Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                    "E012_DB_LOCKED"
+                }
+                Some(rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase) => {
+                    "E013_DB_CORRUPT"
+                }
+                _ => "E010_DB",
+            },
+            #[cfg(feature = "sqlite")]
+            StatuslineError::ConnectionPool(_) => "E011_DB_POOL",
+            StatuslineError::GitOperation(_) => "E020_GIT",
+            StatuslineError::InvalidPath(_) => "E030_INVALID_PATH",
+            StatuslineError::StatsFile(_) => "E040_STATS_FILE",
+            StatuslineError::LockFailed(_) => "E041_LOCK_FAILED",
+            StatuslineError::LowDiskSpace { .. } => "E042_DISK_SPACE",
+            StatuslineError::Config(_) => "E050_CONFIG",
+            #[cfg(feature = "turso-sync")]
+            StatuslineError::Sync(_) => "E060_SYNC",
+            StatuslineError::PartialRender => "E070_PARTIAL_RENDER",
+            StatuslineError::Other(_) => "E099_OTHER",
+        }
+    }
+
+    /// Process exit code for this error (see [`exit_code`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use statusline::error::{exit_code, StatuslineError};
+    ///
+    /// let err = StatuslineError::input("missing session_id");
+    /// assert_eq!(err.exit_code(), exit_code::INPUT);
+    /// ```
+    pub fn exit_code(&self) -> i32 {
+        match self.code() {
+            "E050_CONFIG" => exit_code::CONFIG,
+            "E002_JSON_PARSE" | "E003_INPUT" => exit_code::INPUT,
+            "E070_PARTIAL_RENDER" => exit_code::PARTIAL_RENDER,
+            code if code.starts_with("E01") || code.starts_with("E04") => exit_code::DATABASE,
+            _ => exit_code::FAILURE,
+        }
+    }
+
+    /// One-line remediation hint for [`code`](Self::code).
+    pub fn hint(&self) -> &'static str {
+        match self.code() {
+            "E001_IO" => "Check that the path exists and its directory is readable and writable.",
+            "E002_JSON_PARSE" => {
+                "Check the JSON payload with `statusline input inspect`, or restore stats.json from a backup."
+            }
+            "E003_INPUT" => "Check the command's input (payload fields or arguments) against `--help`.",
+            "E010_DB" => "Run `statusline health` to check stats.db, then `statusline db-maintain`.",
+            "E011_DB_POOL" => {
+                "Too many connections to stats.db are open; retry or raise database.max_connections."
+            }
+            "E012_DB_LOCKED" => {
+                "Another process is writing stats.db; retry or raise database.busy_timeout_ms."
+            }
+            "E013_DB_CORRUPT" => {
+                "stats.db is damaged; move it aside so it is recreated, or restore a backup."
+            }
+            "E020_GIT" => "Check that git is installed and the directory is a readable repository.",
+            "E030_INVALID_PATH" => "Pass an existing file path without `..` components.",
+            "E040_STATS_FILE" => {
+                "Check stats.json in the data directory; corrupted files are backed up automatically."
+            }
+            "E041_LOCK_FAILED" => "Another statusline process holds the stats lock; retry shortly.",
+            "E042_DISK_SPACE" => {
+                "Stats are not saved until space is freed; or lower database.min_free_space_mb."
+            }
+            "E050_CONFIG" => {
+                "Fix config.toml; docs/CONFIGURATION.md lists every option and its type."
+            }
+            "E060_SYNC" => "Check the sync settings and auth token with `statusline sync --status`.",
+            "E070_PARTIAL_RENDER" => {
+                "Re-run with `--log-level warn` to see which segment failed."
+            }
+            _ => "Re-run with `--log-level debug` for details.",
+        }
+    }
+
+    /// The message followed by its code, for log lines: `Database error: ... [E010_DB]`.
+    pub fn coded(&self) -> String {
+        format!("{} [{}]", self, self.code())
+    }
 }
 
 // Allow conversion from string for convenience
@@ -105,3 +254,58 @@ impl From<&str> for StatuslineError {
         StatuslineError::Other(s.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let io = StatuslineError::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert_eq!(io.code(), "E001_IO");
+        assert_eq!(StatuslineError::Config("bad".into()).code(), "E050_CONFIG");
+        assert_eq!(StatuslineError::other("x").code(), "E099_OTHER");
+        assert!(io.coded().ends_with("[E001_IO]"));
+        assert!(!io.hint().is_empty());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(
+            StatuslineError::Config("bad".into()).exit_code(),
+            exit_code::CONFIG
+        );
+        assert_eq!(
+            StatuslineError::lock("busy").exit_code(),
+            exit_code::DATABASE
+        );
+        assert_eq!(StatuslineError::input("x").exit_code(), exit_code::INPUT);
+        assert_eq!(
+            StatuslineError::PartialRender.exit_code(),
+            exit_code::PARTIAL_RENDER
+        );
+        assert_eq!(StatuslineError::other("x").exit_code(), exit_code::FAILURE);
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert_eq!(StatuslineError::from(json).exit_code(), exit_code::INPUT);
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite")]
+    fn test_database_error_codes() {
+        let failure = |code| {
+            StatuslineError::from(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(code),
+                None,
+            ))
+        };
+        assert_eq!(failure(rusqlite::ffi::SQLITE_BUSY).code(), "E012_DB_LOCKED");
+        assert_eq!(
+            failure(rusqlite::ffi::SQLITE_CORRUPT).code(),
+            "E013_DB_CORRUPT"
+        );
+        assert_eq!(failure(rusqlite::ffi::SQLITE_READONLY).code(), "E010_DB");
+        assert!(failure(rusqlite::ffi::SQLITE_BUSY)
+            .hint()
+            .contains("busy_timeout_ms"));
+    }
+}
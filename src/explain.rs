@@ -0,0 +1,292 @@
+//! `--explain`: how each value on the statusline was derived.
+//!
+//! Formalizes the context calculation debug logs into a short report: which
+//! source the context window size came from, the buffer and percentage mode
+//! math, the effective thresholds and the resulting severity levels.
+
+use crate::config::{self, Config};
+use crate::models::Cost;
+use crate::utils::{self, WindowSource};
+
+const LEVELS: [&str; 4] = ["normal", "caution", "warning", "critical"];
+
+/// One explained value: what it is, its value and how it was derived
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainLine {
+    pub label: &'static str,
+    pub value: String,
+    pub reason: String,
+}
+
+/// Explains the context and cost values for a payload with the current config.
+pub fn explain(
+    model_name: Option<&str>,
+    transcript_path: Option<&str>,
+    session_id: Option<&str>,
+    cost: Option<&Cost>,
+    daily_total: f64,
+) -> Vec<ExplainLine> {
+    let config = config::get_config();
+    let mut lines = Vec::new();
+    let line = |label, value: String, reason: String| ExplainLine {
+        label,
+        value,
+        reason,
+    };
+
+    lines.push(line(
+        "model",
+        model_name.unwrap_or("(none)").to_string(),
+        "model.display_name".to_string(),
+    ));
+
+    let (base_window, source) = utils::context_window_size_with_source(model_name, &config);
+    lines.push(line("window", base_window.to_string(), source.to_string()));
+
+    let buffer_size = config.context.buffer_size_for(model_name);
+    let buffer_overridden = model_name
+        .and_then(|m| config.context.model_overrides.get(m))
+        .and_then(|o| o.buffer_size)
+        .is_some();
+    lines.push(line(
+        "buffer",
+        buffer_size.to_string(),
+        if buffer_overridden {
+            "context.model_overrides".to_string()
+        } else {
+            "context.buffer_size".to_string()
+        },
+    ));
+
+    lines.extend(explain_context(
+        &config,
+        model_name,
+        transcript_path,
+        session_id,
+        base_window,
+        buffer_size,
+        &source,
+    ));
+
+    if let Some(session_cost) = cost.and_then(|c| c.total_cost_usd) {
+        lines.push(line(
+            "session cost",
+            format!("${:.2}", session_cost),
+            cost_level(&config, session_cost),
+        ));
+    }
+    lines.push(line(
+        "day cost",
+        format!("${:.2}", daily_total),
+        cost_level(&config, daily_total),
+    ));
+
+    lines
+}
+
+/// Token source, percentage mode math, thresholds and severity level.
+fn explain_context(
+    config: &Config,
+    model_name: Option<&str>,
+    transcript_path: Option<&str>,
+    session_id: Option<&str>,
+    base_window: usize,
+    buffer_size: usize,
+    source: &WindowSource,
+) -> Vec<ExplainLine> {
+    let mut lines = Vec::new();
+    let reported = utils::reported_context();
+    let usage =
+        utils::calculate_current_context_usage(transcript_path, model_name, session_id, None);
+    let tokens_source = if reported.breakdown.is_some() {
+        "reported by Claude Code (context_window.current_usage)"
+    } else if usage.as_ref().is_some_and(|u| u.approximate) {
+        "last known for the session, no transcript (approximate)"
+    } else if usage.is_some() {
+        if reported.exceeds_200k_tokens {
+            "transcript, raised to 200K by exceeds_200k_tokens"
+        } else {
+            "last assistant message in the transcript"
+        }
+    } else {
+        "no token data, the context segment is hidden"
+    };
+    let tokens = utils::current_context_tokens(transcript_path);
+    lines.push(ExplainLine {
+        label: "tokens",
+        value: tokens.map_or("-".to_string(), |t| t.to_string()),
+        reason: tokens_source.to_string(),
+    });
+
+    // Same window split as the percentage calculation
+    let (full_window, working_window) = if config.context.adaptive_learning {
+        (base_window + buffer_size, base_window)
+    } else {
+        (base_window, base_window.saturating_sub(buffer_size))
+    };
+    let split = if config.context.adaptive_learning {
+        format!(
+            "full = window + buffer = {}, working = window = {}",
+            full_window, working_window
+        )
+    } else {
+        format!(
+            "full = window = {}, working = window - buffer = {}",
+            full_window, working_window
+        )
+    };
+    let divisor = match config.context.percentage_mode.as_str() {
+        "working" => working_window,
+        _ => full_window,
+    };
+    lines.push(ExplainLine {
+        label: "mode",
+        value: config.context.percentage_mode.clone(),
+        reason: split,
+    });
+
+    if let (Some(tokens), Some(usage)) = (tokens, &usage) {
+        let exact = tokens as f64 / divisor as f64 * 100.0;
+        lines.push(ExplainLine {
+            label: "context",
+            value: format!("{:.1}%", usage.percentage),
+            reason: if exact > 100.0 {
+                format!("{} / {} = {:.1}%, capped at 100%", tokens, divisor, exact)
+            } else {
+                format!("{} / {} = {:.1}%", tokens, divisor, exact)
+            },
+        });
+    }
+
+    let display = &config.display;
+    let level = usage
+        .as_ref()
+        .map(|u| crate::display::context_level(u.percentage))
+        .unwrap_or(0);
+    lines.push(ExplainLine {
+        label: "severity",
+        value: LEVELS[level].to_string(),
+        reason: format!(
+            "caution > {}%, warning > {}%, critical > {}% (display.context_*_threshold)",
+            display.context_caution_threshold,
+            display.context_warning_threshold,
+            display.context_critical_threshold
+        ),
+    });
+
+    let threshold = config.context.effective_threshold_for(model_name);
+    let threshold_source = if model_name
+        .and_then(|m| config.context.model_overrides.get(m))
+        .and_then(|o| o.auto_compact_threshold)
+        .is_some()
+    {
+        "context.model_overrides".to_string()
+    } else if (threshold - config.context.auto_compact_threshold).abs() < f64::EPSILON {
+        "context.auto_compact_threshold".to_string()
+    } else {
+        format!(
+            "default for \"{}\" mode, since context.auto_compact_threshold is at its default",
+            config.context.percentage_mode
+        )
+    };
+    let approaching = usage.as_ref().is_some_and(|u| u.approaching_limit);
+    lines.push(ExplainLine {
+        label: "compact warn",
+        value: format!(
+            "{}%{}",
+            threshold,
+            if approaching { " (reached)" } else { "" }
+        ),
+        reason: threshold_source,
+    });
+
+    if *source == WindowSource::Learned {
+        lines.push(ExplainLine {
+            label: "learning",
+            value: format!(
+                "{:.0}%",
+                config.context.learning_confidence_threshold * 100.0
+            ),
+            reason: "minimum confidence for learned windows".to_string(),
+        });
+    }
+
+    lines
+}
+
+/// Cost color level and the thresholds that decided it.
+fn cost_level(config: &Config, cost: f64) -> String {
+    let level = if cost >= config.cost.medium_threshold {
+        "high"
+    } else if cost >= config.cost.low_threshold {
+        "medium"
+    } else {
+        "low"
+    };
+    format!(
+        "{} (medium from ${:.2}, high from ${:.2}: cost.low/medium_threshold)",
+        level, config.cost.low_threshold, config.cost.medium_threshold
+    )
+}
+
+/// Renders explained values as aligned `label  value  (reason)` lines.
+pub fn render(lines: &[ExplainLine]) -> String {
+    let label_width = lines.iter().map(|l| l.label.len()).max().unwrap_or(0);
+    let value_width = lines.iter().map(|l| l.value.len()).max().unwrap_or(0);
+    lines
+        .iter()
+        .map(|l| {
+            format!(
+                "{:<lw$}  {:<vw$}  {}",
+                l.label,
+                l.value,
+                l.reason,
+                lw = label_width,
+                vw = value_width
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ReportedContext, TokenBreakdown};
+
+    fn value<'a>(lines: &'a [ExplainLine], label: &str) -> &'a ExplainLine {
+        lines.iter().find(|l| l.label == label).unwrap()
+    }
+
+    #[test]
+    fn test_explain_reported_context() {
+        utils::set_reported_context(ReportedContext {
+            breakdown: Some(TokenBreakdown {
+                input_tokens: 100_000,
+                ..Default::default()
+            }),
+            window_size: Some(200_000),
+            exceeds_200k_tokens: false,
+        });
+        let lines = explain(Some("Claude Sonnet 4.5"), None, None, None, 0.0);
+        utils::set_reported_context(ReportedContext::default());
+
+        assert_eq!(value(&lines, "window").value, "200000");
+        assert!(value(&lines, "window").reason.contains("reported"));
+        assert_eq!(value(&lines, "tokens").value, "100000");
+        assert_eq!(value(&lines, "context").reason, "100000 / 200000 = 50.0%");
+        assert!(render(&lines).lines().count() >= 6);
+    }
+
+    #[test]
+    fn test_explain_model_default_window() {
+        utils::set_reported_context(ReportedContext::default());
+        let lines = explain(Some("Claude Opus 3"), None, None, None, 0.0);
+        let window = value(&lines, "window");
+        assert_eq!(window.value, "160000");
+        assert_eq!(window.reason, "built-in default for Opus 3");
+        assert_eq!(value(&lines, "tokens").value, "-");
+    }
+}
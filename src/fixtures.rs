@@ -0,0 +1,167 @@
+//! Canned statusline states for `statusline render --fixture`.
+//!
+//! Each fixture is an input payload plus a generated transcript, so config and
+//! theme changes can be previewed in every state without driving a real
+//! session there.
+
+/// A canned payload and transcript.
+#[derive(Debug, Clone, Copy)]
+pub struct Fixture {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub model: &'static str,
+    pub cost: f64,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+    /// Day total shown next to the session cost
+    pub daily_total: f64,
+    /// Context tokens in the last assistant message
+    pub context_tokens: u32,
+    /// Session length covered by the transcript
+    pub duration_minutes: u32,
+    /// Whether a compaction is in progress (via hook state)
+    pub compacting: bool,
+}
+
+pub const FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "low-context",
+        description: "Fresh session, little context used",
+        model: "Claude Sonnet 4.5",
+        cost: 0.42,
+        lines_added: 12,
+        lines_removed: 3,
+        daily_total: 0.42,
+        context_tokens: 24_000,
+        duration_minutes: 8,
+        compacting: false,
+    },
+    Fixture {
+        name: "critical-context",
+        description: "Context close to the auto-compact threshold",
+        model: "Claude Sonnet 4.5",
+        cost: 6.80,
+        lines_added: 340,
+        lines_removed: 95,
+        daily_total: 11.25,
+        context_tokens: 188_000,
+        duration_minutes: 95,
+        compacting: false,
+    },
+    Fixture {
+        name: "compacting",
+        description: "Compaction in progress",
+        model: "Claude Opus 4.5",
+        cost: 9.10,
+        lines_added: 510,
+        lines_removed: 140,
+        daily_total: 14.60,
+        context_tokens: 176_000,
+        duration_minutes: 120,
+        compacting: true,
+    },
+    Fixture {
+        name: "expensive",
+        description: "Long, expensive session on a busy day",
+        model: "Claude Opus 4.1",
+        cost: 48.75,
+        lines_added: 2_480,
+        lines_removed: 915,
+        daily_total: 132.40,
+        context_tokens: 121_000,
+        duration_minutes: 215,
+        compacting: false,
+    },
+];
+
+/// Fixture names, for CLI value parsing.
+pub const FIXTURE_NAMES: [&str; 4] = ["low-context", "critical-context", "compacting", "expensive"];
+
+/// Looks up a fixture by name.
+pub fn find(name: &str) -> Option<&'static Fixture> {
+    FIXTURES.iter().find(|f| f.name == name)
+}
+
+impl Fixture {
+    /// Session ID used for the fixture (also keys its hook state).
+    pub fn session_id(&self) -> String {
+        format!("fixture-{}", self.name)
+    }
+
+    /// JSONL transcript: a user message and an assistant message spanning the session.
+    pub fn transcript(&self) -> String {
+        let start = chrono::DateTime::parse_from_rfc3339("2025-01-01T09:00:00Z")
+            .expect("valid fixture timestamp");
+        let end = start + chrono::Duration::minutes(self.duration_minutes as i64);
+        // Most of the context is cached, as in a real long-running session
+        let cache_read = self.context_tokens.saturating_sub(3_000);
+        let user = serde_json::json!({
+            "type": "user",
+            "timestamp": start.to_rfc3339(),
+            "message": { "role": "user", "content": "fixture" }
+        });
+        let assistant = serde_json::json!({
+            "type": "assistant",
+            "timestamp": end.to_rfc3339(),
+            "message": {
+                "role": "assistant",
+                "content": "fixture",
+                "usage": {
+                    "input_tokens": 2_000,
+                    "output_tokens": self.context_tokens - cache_read - 2_000,
+                    "cache_read_input_tokens": cache_read,
+                    "cache_creation_input_tokens": 0
+                }
+            }
+        });
+        format!("{}\n{}\n", user, assistant)
+    }
+
+    /// Input payload referencing `transcript_path`.
+    pub fn payload(&self, transcript_path: &str, current_dir: &str) -> serde_json::Value {
+        serde_json::json!({
+            "session_id": self.session_id(),
+            "transcript_path": transcript_path,
+            "workspace": { "current_dir": current_dir },
+            "model": { "display_name": self.model },
+            "cost": {
+                "total_cost_usd": self.cost,
+                "total_lines_added": self.lines_added,
+                "total_lines_removed": self.lines_removed
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{StatuslineInput, TranscriptEntry};
+
+    #[test]
+    fn test_fixture_names_match() {
+        let names: Vec<&str> = FIXTURES.iter().map(|f| f.name).collect();
+        assert_eq!(names, FIXTURE_NAMES);
+        assert!(find("compacting").unwrap().compacting);
+        assert!(find("missing").is_none());
+    }
+
+    #[test]
+    fn test_fixture_transcript_and_payload_parse() {
+        for fixture in FIXTURES {
+            let transcript = fixture.transcript();
+            let last = transcript.lines().last().unwrap();
+            let entry: TranscriptEntry = serde_json::from_str(last).unwrap();
+            let usage = entry.message.usage.unwrap();
+            let total = usage.input_tokens.unwrap()
+                + usage.output_tokens.unwrap()
+                + usage.cache_read_input_tokens.unwrap();
+            assert_eq!(total, fixture.context_tokens, "{}", fixture.name);
+
+            let input: StatuslineInput =
+                serde_json::from_value(fixture.payload("/tmp/t.jsonl", "/tmp")).unwrap();
+            assert_eq!(input.transcript.as_deref(), Some("/tmp/t.jsonl"));
+            assert_eq!(input.cost.unwrap().total_cost_usd, Some(fixture.cost));
+        }
+    }
+}
@@ -4,11 +4,19 @@
 //! their status information, including branch name and file change counts.
 
 use crate::common::validate_path_security;
+use crate::config::RepoRule;
 use crate::display::Colors;
 use crate::error::{Result, StatuslineError};
-use crate::git_utils;
+use crate::git_utils::{self, StatusPorcelain};
 use crate::utils::sanitize_for_terminal;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
 use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
 
 /// Git repository status information.
 ///
@@ -20,8 +28,51 @@ pub struct GitStatus {
     pub modified: usize,
     pub deleted: usize,
     pub untracked: usize,
+    /// Local commits not yet on the upstream branch
+    pub ahead: usize,
+    /// Files with unresolved merge conflicts (also counted in `modified`)
+    pub conflicted: usize,
+    /// Entries in the stash (porcelain v2 only)
+    pub stashed: usize,
+    /// Counts stopped at the repository's `max_status_entries`
+    pub truncated: bool,
+    /// Commit HEAD points at (porcelain v2 only; None before the first commit)
+    pub head_oid: Option<String>,
+    /// Seconds since the least recently modified uncommitted tracked file was
+    /// last written (only measured when `git.uncommitted_warn_minutes` is set)
+    pub oldest_change_secs: Option<u64>,
 }
 
+/// Uncommitted line changes in the working tree (from `git diff HEAD --numstat`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DiffStat {
+    pub added: u64,
+    pub removed: u64,
+}
+
+/// Cached `DiffStat`, so the diff isn't recomputed on every render.
+#[derive(Debug, Serialize, Deserialize)]
+struct DiffStatCache {
+    dir: PathBuf,
+    computed_at: DateTime<Utc>,
+    stat: DiffStat,
+}
+
+/// How long a cached diff stat is reused before running `git diff` again
+const DIFF_CACHE_SECONDS: i64 = 10;
+
+/// Cached nearest tag, reused while HEAD stays on the same commit.
+#[derive(Debug, Serialize, Deserialize)]
+struct TagCache {
+    dir: PathBuf,
+    head_oid: String,
+    computed_at: DateTime<Utc>,
+    tag: Option<String>,
+}
+
+/// How long a cached tag is reused for an unchanged HEAD (tags created since show up after this)
+const TAG_CACHE_SECONDS: i64 = 60;
+
 /// Validates that a path is a git repository directory
 fn validate_git_directory(dir: &str) -> Result<PathBuf> {
     // Use common validation first
@@ -68,18 +119,307 @@ pub fn get_git_status(dir: &str) -> Option<GitStatus> {
     // Validate and canonicalize the directory path
     let safe_dir = validate_git_directory(dir).ok()?;
 
-    // Get git status using the utility function
-    let status_text = git_utils::get_status_porcelain(&safe_dir)?;
+    // Per-repository options for large working trees
+    let config = crate::config::get_config();
+    let default_rule = RepoRule::default();
+    let rule = first_match(
+        &config.git.repos,
+        |rule| &rule.pattern,
+        &safe_dir.to_string_lossy(),
+        "git.repos",
+    )
+    .unwrap_or(&default_rule);
+
+    // Get git status using the utility function; in a repository, a failure or
+    // timeout drops the segment, so flag it
+    let Some(porcelain) = git_utils::get_status_porcelain_with(&safe_dir, rule) else {
+        crate::common::mark_degraded("git", "git status failed or timed out");
+        return None;
+    };
+    let (status_text, truncated) = cap_status_entries(porcelain.text(), rule.max_status_entries);
+
+    let is_v2 = matches!(porcelain, StatusPorcelain::V2(_));
+    let status = if is_v2 {
+        parse_git_status_v2(&status_text)
+    } else {
+        parse_git_status(&status_text)
+    };
+
+    let oldest_change_secs = if config.git.uncommitted_warn_minutes > 0 {
+        oldest_change_age(&safe_dir, &changed_paths(&status_text, is_v2))
+    } else {
+        None
+    };
 
-    #[cfg(feature = "git_porcelain_v2")]
-    return parse_git_status_v2(&status_text);
+    status.map(|status| GitStatus {
+        truncated,
+        oldest_change_secs,
+        ..status
+    })
+}
+
+/// Branch checked out in the repository containing `dir`, read from `HEAD`
+/// without running git. None when `HEAD` is detached or outside a repository.
+pub fn current_branch(dir: &str) -> Option<String> {
+    let dot_git = std::path::Path::new(dir)
+        .ancestors()
+        .map(|d| d.join(".git"))
+        .find(|p| p.exists())?;
+    let git_dir = if dot_git.is_file() {
+        // Worktrees and submodules: ".git" is a "gitdir: <path>" file
+        let content = fs::read_to_string(&dot_git).ok()?;
+        let target = PathBuf::from(content.strip_prefix("gitdir:")?.trim());
+        dot_git.parent()?.join(target)
+    } else {
+        dot_git
+    };
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .filter(|branch| !branch.is_empty())
+        .map(str::to_string)
+}
 
-    #[cfg(not(feature = "git_porcelain_v2"))]
-    return parse_git_status(&status_text);
+/// Paths of changed tracked files in porcelain output, relative to the
+/// repository root. Untracked and ignored files are skipped.
+fn changed_paths(status_text: &str, v2: bool) -> Vec<&str> {
+    status_text
+        .lines()
+        .filter_map(|line| {
+            if v2 {
+                // Fixed fields before the path: 8 ordinary, 9 renamed, 10 unmerged
+                let fields = match line.chars().next()? {
+                    '1' => 8,
+                    '2' => 9,
+                    'u' => 10,
+                    _ => return None,
+                };
+                let path = line.splitn(fields + 1, ' ').nth(fields)?;
+                // Renames end in "<tab><origPath>"
+                Some(path.split('\t').next().unwrap_or(path))
+            } else {
+                let (codes, path) = (line.get(..2)?, line.get(3..)?);
+                if codes.starts_with('#') || codes == "??" || codes == "!!" {
+                    return None;
+                }
+                // Renames are "<orig> -> <path>"
+                Some(path.rsplit(" -> ").next().unwrap_or(path))
+            }
+        })
+        .collect()
+}
+
+/// Age in seconds of the least recently written file among `paths`; deleted
+/// files have no modification time and are skipped.
+fn oldest_change_age(repo_root: &std::path::Path, paths: &[&str]) -> Option<u64> {
+    let oldest = paths
+        .iter()
+        .filter_map(|path| fs::metadata(repo_root.join(path)).ok()?.modified().ok())
+        .min()?;
+    Some(oldest.elapsed().map(|age| age.as_secs()).unwrap_or(0))
+}
+
+/// Keeps the header lines and the first `max_entries` file entries of porcelain
+/// output (0 = all), reporting whether entries were dropped.
+fn cap_status_entries(status_text: &str, max_entries: usize) -> (Cow<'_, str>, bool) {
+    if max_entries == 0 {
+        return (Cow::Borrowed(status_text), false);
+    }
+
+    let mut kept = String::new();
+    let mut entries = 0;
+    let mut truncated = false;
+    for line in status_text.lines() {
+        // Branch headers: "## main..." (v1) or "# branch.head main" (v2)
+        if !line.starts_with('#') {
+            if entries == max_entries {
+                truncated = true;
+                continue;
+            }
+            entries += 1;
+        }
+        kept.push_str(line);
+        kept.push('\n');
+    }
+
+    if truncated {
+        (Cow::Owned(kept), true)
+    } else {
+        (Cow::Borrowed(status_text), false)
+    }
+}
+
+/// Compiled `git.*` rule patterns, keyed by pattern text (`None` if invalid),
+/// so renders don't recompile them.
+static RULE_PATTERNS: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+
+/// Returns the first rule whose pattern matches `text`.
+///
+/// Invalid patterns are skipped, with a warning naming the config `section`
+/// the first time they are seen.
+fn first_match<'a, R>(
+    rules: &'a [R],
+    pattern: impl Fn(&R) -> &str,
+    text: &str,
+    section: &str,
+) -> Option<&'a R> {
+    let mut compiled = RULE_PATTERNS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    rules.iter().find(|rule| {
+        let pattern = pattern(rule);
+        if !compiled.contains_key(pattern) {
+            let regex = Regex::new(pattern)
+                .map_err(|e| log::warn!("Invalid {} pattern '{}': {}", section, pattern, e))
+                .ok();
+            compiled.insert(pattern.to_string(), regex);
+        }
+        compiled[pattern]
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(text))
+    })
+}
+
+/// Gets the uncommitted line changes of a repository.
+///
+/// Results are cached for a few seconds. If `git diff` fails or exceeds the git
+/// timeout, the last cached value for the same repository is returned instead.
+pub fn get_diff_stats(dir: &str) -> Option<DiffStat> {
+    let safe_dir = validate_git_directory(dir).ok()?;
+    let cache_path = crate::state::get_cache_dir()
+        .ok()
+        .map(|d| d.join("git-diff.json"));
+    let cached = cache_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<DiffStatCache>(&json).ok())
+        .filter(|cache| cache.dir == safe_dir);
+
+    if let Some(cache) = &cached {
+        if crate::environment::current().now() - cache.computed_at
+            < chrono::Duration::seconds(DIFF_CACHE_SECONDS)
+        {
+            return Some(cache.stat);
+        }
+    }
+
+    let Some(numstat) = git_utils::get_diff_numstat(&safe_dir) else {
+        return cached.map(|cache| cache.stat);
+    };
+    let stat = parse_numstat(&numstat);
+
+    if let Some(path) = cache_path {
+        let cache = DiffStatCache {
+            dir: safe_dir,
+            computed_at: crate::environment::current().now(),
+            stat,
+        };
+        if let Ok(json) = serde_json::to_string(&cache) {
+            let _ = fs::write(path, json);
+        }
+    }
+    Some(stat)
+}
+
+/// Gets the most recent tag reachable from HEAD, as `v2.3.1` on the tagged
+/// commit or `v2.3.1+4` four commits after it.
+///
+/// The result is cached per repository and HEAD commit (`GitStatus::head_oid`),
+/// so `git describe` only runs again once HEAD moves or the cache expires.
+/// Porcelain v1 status has no commit id, so there HEAD is resolved with
+/// `git rev-parse` first. Returns None without tags, or before the first commit.
+pub fn get_nearest_tag(dir: &str, status: &GitStatus) -> Option<String> {
+    let safe_dir = validate_git_directory(dir).ok()?;
+    let head_oid = match &status.head_oid {
+        Some(oid) => oid.clone(),
+        None => git_utils::rev_parse_head(&safe_dir)?,
+    };
+    let cache_path = crate::state::get_cache_dir()
+        .ok()
+        .map(|d| d.join("git-tag.json"));
+    let cached = cache_path
+        .as_ref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str::<TagCache>(&json).ok())
+        .filter(|cache| cache.dir == safe_dir && cache.head_oid == head_oid);
+
+    if let Some(cache) = &cached {
+        if crate::environment::current().now() - cache.computed_at
+            < chrono::Duration::seconds(TAG_CACHE_SECONDS)
+        {
+            return cache.tag.clone();
+        }
+    }
+
+    let tag = match git_utils::describe_tags(&safe_dir) {
+        Some(describe) => describe.and_then(|d| parse_describe(&d)),
+        // Failed or timed out: keep what we had for this commit
+        None => return cached.and_then(|cache| cache.tag),
+    };
+
+    if let Some(path) = cache_path {
+        let cache = TagCache {
+            dir: safe_dir,
+            head_oid,
+            computed_at: crate::environment::current().now(),
+            tag: tag.clone(),
+        };
+        if let Ok(json) = serde_json::to_string(&cache) {
+            let _ = fs::write(path, json);
+        }
+    }
+    tag
+}
+
+/// Turns `git describe --tags --long` output (`<tag>-<n>-g<hash>`) into
+/// `<tag>` when HEAD is tagged, or `<tag>+<n>` n commits later.
+fn parse_describe(describe: &str) -> Option<String> {
+    // Tags may contain dashes themselves, so split from the right
+    let mut parts = describe.trim().rsplitn(3, '-');
+    let hash = parts.next()?;
+    let distance: u64 = parts.next()?.parse().ok()?;
+    let tag = parts.next().filter(|tag| !tag.is_empty())?;
+    if !hash.starts_with('g') {
+        return None;
+    }
+    Some(if distance == 0 {
+        tag.to_string()
+    } else {
+        format!("{}+{}", tag, distance)
+    })
+}
+
+/// Formats the nearest tag for the git segment.
+pub fn format_tag(tag: &str) -> String {
+    format!(
+        "{}{}{}",
+        Colors::light_gray(),
+        sanitize_for_terminal(tag),
+        Colors::reset()
+    )
+}
+
+/// Sums `git diff --numstat` output (`<added>\t<removed>\t<path>`).
+///
+/// Binary files report `-` for both counts and are skipped.
+fn parse_numstat(numstat: &str) -> DiffStat {
+    let mut stat = DiffStat::default();
+    for line in numstat.lines() {
+        let mut fields = line.split('\t');
+        let added = fields.next().and_then(|f| f.parse::<u64>().ok());
+        let removed = fields.next().and_then(|f| f.parse::<u64>().ok());
+        if let (Some(added), Some(removed)) = (added, removed) {
+            stat.added += added;
+            stat.removed += removed;
+        }
+    }
+    stat
 }
 
 /// Parses git status output in porcelain v1 format.
 ///
+/// Used when git rejects the porcelain v2 arguments (`--show-stash` needs git 2.35).
 /// This function implements comprehensive parsing of git's porcelain v1 status format,
 /// handling all standard XY status codes including special cases like renames, type
 /// changes, and unmerged states.
@@ -107,7 +447,7 @@ pub fn get_git_status(dir: &str) -> Option<GitStatus> {
 /// - **Ignored**: `!!` (both positions are `!`, not counted)
 ///
 /// ### Unmerged/Conflict States
-/// All unmerged states are counted as modified:
+/// All unmerged states are counted as modified, and as conflicted:
 /// - `DD` - Both deleted
 /// - `AU` - Added by us
 /// - `UD` - Deleted by them
@@ -129,7 +469,6 @@ pub fn get_git_status(dir: &str) -> Option<GitStatus> {
 /// # Returns
 ///
 /// Returns `Some(GitStatus)` with parsed information, or `None` if parsing fails.
-#[cfg_attr(feature = "git_porcelain_v2", allow(dead_code))]
 fn parse_git_status(status_text: &str) -> Option<GitStatus> {
     let mut status = GitStatus::default();
 
@@ -140,8 +479,9 @@ fn parse_git_status(status_text: &str) -> Option<GitStatus> {
                 // Detached HEAD state - use the full string
                 status.branch = branch_info.to_string();
             } else if let Some(branch_end) = branch_info.find("...") {
-                // Branch with upstream tracking info
+                // Branch with upstream tracking info, e.g. "[ahead 2, behind 1]"
                 status.branch = branch_info[..branch_end].to_string();
+                status.ahead = parse_ahead_count(&branch_info[branch_end..]);
             } else {
                 // Simple branch name without tracking
                 status.branch = branch_info.to_string();
@@ -169,7 +509,10 @@ fn parse_git_status(status_text: &str) -> Option<GitStatus> {
                 | ('U', 'A')
                 | ('D', 'U')
                 | ('A', 'A')
-                | ('U', 'U') => status.modified += 1,
+                | ('U', 'U') => {
+                    status.modified += 1;
+                    status.conflicted += 1;
+                }
                 // Regular status codes
                 _ => {
                     // Check X (index) status
@@ -200,10 +543,22 @@ fn parse_git_status(status_text: &str) -> Option<GitStatus> {
     Some(status)
 }
 
+/// Extracts the ahead count from a v1 upstream suffix like "...origin/main [ahead 2, behind 1]".
+fn parse_ahead_count(tracking: &str) -> usize {
+    tracking
+        .split_once("ahead ")
+        .and_then(|(_, rest)| {
+            rest.split(|c: char| !c.is_ascii_digit())
+                .next()
+                .and_then(|n| n.parse().ok())
+        })
+        .unwrap_or(0)
+}
+
 /// Parses git status output in porcelain v2 format.
 ///
-/// This function is only available when the `git_porcelain_v2` feature is enabled.
-/// Porcelain v2 format provides machine-readable output with more structured information.
+/// Porcelain v2 format provides machine-readable output with more structured information,
+/// including ahead/behind counts and (with `--show-stash`) the stash size.
 ///
 /// # Porcelain v2 Format
 ///
@@ -221,7 +576,6 @@ fn parse_git_status(status_text: &str) -> Option<GitStatus> {
 /// - `! <path>` - Ignored file
 ///
 /// The XY status codes are the same as porcelain v1.
-#[cfg(feature = "git_porcelain_v2")]
 fn parse_git_status_v2(status_text: &str) -> Option<GitStatus> {
     let mut status = GitStatus::default();
 
@@ -230,6 +584,18 @@ fn parse_git_status_v2(status_text: &str) -> Option<GitStatus> {
             // Parse header lines
             if let Some(branch_name) = header.strip_prefix("branch.head ") {
                 status.branch = branch_name.to_string();
+            } else if let Some(oid) = header.strip_prefix("branch.oid ") {
+                // "(initial)" in a repository without commits
+                status.head_oid = (!oid.starts_with('(')).then(|| oid.to_string());
+            } else if let Some(ab) = header.strip_prefix("branch.ab ") {
+                // "+<ahead> -<behind>"
+                status.ahead = ab
+                    .split_whitespace()
+                    .next()
+                    .and_then(|a| a.trim_start_matches('+').parse().ok())
+                    .unwrap_or(0);
+            } else if let Some(count) = header.strip_prefix("stash ") {
+                status.stashed = count.trim().parse().unwrap_or(0);
             }
         } else if let Some(first_char) = line.chars().next() {
             match first_char {
@@ -279,7 +645,6 @@ fn parse_git_status_v2(status_text: &str) -> Option<GitStatus> {
 }
 
 /// Helper function to parse XY status string into two characters
-#[cfg(feature = "git_porcelain_v2")]
 fn parse_xy_status(xy: &str) -> Option<(char, char)> {
     let chars: Vec<char> = xy.chars().collect();
     if chars.len() >= 2 {
@@ -290,7 +655,6 @@ fn parse_xy_status(xy: &str) -> Option<(char, char)> {
 }
 
 /// Helper function to apply status codes to the GitStatus struct
-#[cfg(feature = "git_porcelain_v2")]
 fn apply_status_codes(status: &mut GitStatus, x: char, y: char) {
     // Handle special two-character codes first
     match (x, y) {
@@ -305,7 +669,10 @@ fn apply_status_codes(status: &mut GitStatus, x: char, y: char) {
         | ('U', 'A')
         | ('D', 'U')
         | ('A', 'A')
-        | ('U', 'U') => status.modified += 1,
+        | ('U', 'U') => {
+            status.modified += 1;
+            status.conflicted += 1;
+        }
         // Regular status codes
         _ => {
             // Check X (index) status
@@ -332,15 +699,110 @@ fn apply_status_codes(status: &mut GitStatus, x: char, y: char) {
     }
 }
 
+/// Formats the repository's commit identity as "@label" (`git.show_identity`).
+///
+/// The label comes from the first `git.identities` rule matching `user.email`;
+/// an unmatched or unset email shows "@?" so a wrong identity stands out.
+pub fn format_identity(dir: &str) -> Option<String> {
+    let safe_dir = validate_git_directory(dir).ok()?;
+    let email = git_utils::get_config_value(&safe_dir, "user.email");
+    let rules = &crate::config::get_config().git.identities;
+
+    Some(
+        match email
+            .and_then(|email| first_match(rules, |rule| &rule.pattern, &email, "git.identities"))
+        {
+            Some(rule) => format!(
+                "{}@{}{}",
+                rule.color
+                    .as_deref()
+                    .map(Colors::get_themed)
+                    .unwrap_or_else(Colors::light_gray),
+                sanitize_for_terminal(&rule.label),
+                Colors::reset()
+            ),
+            None => format!("{}@?{}", Colors::orange(), Colors::reset()),
+        },
+    )
+}
+
+/// Formats a branch name, styled by the first matching `git.branch_rules` entry.
+///
+/// Branches matching no rule use `default_color`. Invalid patterns are skipped.
+pub fn format_branch(branch: &str, default_color: String) -> String {
+    let config = crate::config::get_config();
+    let rule = first_match(
+        &config.git.branch_rules,
+        |rule| &rule.pattern,
+        branch,
+        "git.branch_rules",
+    );
+    let color = rule
+        .and_then(|rule| rule.color.as_deref())
+        .map(Colors::get_themed)
+        .unwrap_or(default_color);
+    let prefix = rule
+        .and_then(|rule| rule.prefix.as_deref())
+        .map(|prefix| format!("{} ", sanitize_for_terminal(prefix)))
+        .unwrap_or_default();
+
+    format!(
+        "{}{}{}{}",
+        color,
+        prefix,
+        sanitize_for_terminal(branch),
+        Colors::reset()
+    )
+}
+
 pub fn format_git_info(git_status: &GitStatus) -> String {
     let mut parts = Vec::new();
 
     // Add branch name (sanitized for terminal safety)
     if !git_status.branch.is_empty() {
+        parts.push(format_branch(&git_status.branch, Colors::green()));
+    }
+
+    // Unresolved merge conflicts come first so they can't be missed
+    if git_status.conflicted > 0 {
         parts.push(format!(
-            "{}{}{}",
-            Colors::green(),
-            sanitize_for_terminal(&git_status.branch),
+            "{}✖{}{}",
+            Colors::critical(),
+            git_status.conflicted,
+            Colors::reset()
+        ));
+    }
+
+    // Uncommitted work older than git.uncommitted_warn_minutes: time to commit
+    let git_config = &crate::config::get_config().git;
+    if let Some(age) = git_status.oldest_change_secs.filter(|age| {
+        git_config.uncommitted_warn_minutes > 0 && *age >= git_config.uncommitted_warn_minutes * 60
+    }) {
+        parts.push(format!(
+            "{}{}{}{}",
+            Colors::orange(),
+            sanitize_for_terminal(&git_config.uncommitted_marker),
+            crate::utils::format_duration(age, "compact"),
+            Colors::reset()
+        ));
+    }
+
+    // Unpushed commits, shown even when the tree is clean
+    if git_status.ahead > 0 {
+        parts.push(format!(
+            "{}⇡{}{}",
+            Colors::cyan(),
+            git_status.ahead,
+            Colors::reset()
+        ));
+    }
+
+    // Stashed changes, easy to forget about
+    if git_status.stashed > 0 {
+        parts.push(format!(
+            "{}*{}{}",
+            Colors::gray(),
+            git_status.stashed,
             Colors::reset()
         ));
     }
@@ -378,6 +840,10 @@ pub fn format_git_info(git_status: &GitStatus) -> String {
             Colors::reset()
         ));
     }
+    // More changes than max_status_entries counted
+    if git_status.truncated {
+        parts.push(format!("{}…{}", Colors::gray(), Colors::reset()));
+    }
 
     if parts.is_empty() {
         String::new()
@@ -389,6 +855,39 @@ pub fn format_git_info(git_status: &GitStatus) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{BranchRule, IdentityRule};
+
+    #[test]
+    fn test_current_branch() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("src/deep")).unwrap();
+        fs::write(repo.join(".git/HEAD"), "ref: refs/heads/feature/login\n").unwrap();
+        let dir = |p: &std::path::Path| p.to_str().unwrap().to_string();
+
+        assert_eq!(
+            current_branch(&dir(&repo.join("src/deep"))).as_deref(),
+            Some("feature/login")
+        );
+
+        // Linked worktree
+        let worktree = temp_dir.path().join("wt");
+        let worktree_git = repo.join(".git/worktrees/wt");
+        fs::create_dir_all(&worktree_git).unwrap();
+        fs::create_dir_all(&worktree).unwrap();
+        fs::write(worktree_git.join("HEAD"), "ref: refs/heads/fix\n").unwrap();
+        fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", worktree_git.display()),
+        )
+        .unwrap();
+        assert_eq!(current_branch(&dir(&worktree)).as_deref(), Some("fix"));
+
+        // Detached HEAD
+        fs::write(repo.join(".git/HEAD"), "0123456789abcdef\n").unwrap();
+        assert_eq!(current_branch(&dir(&repo)), None);
+    }
 
     #[test]
     fn test_validate_git_directory_security() {
@@ -444,6 +943,13 @@ mod tests {
         let status = parse_git_status(status_text).unwrap();
         assert_eq!(status.branch, "main");
 
+        assert_eq!(status.ahead, 1);
+
+        // Test behind only
+        let status_text = "## main...origin/main [behind 3]\n";
+        let status = parse_git_status(status_text).unwrap();
+        assert_eq!(status.ahead, 0);
+
         // Test feature branch
         let status_text = "## feature/cool\n";
         let status = parse_git_status(status_text).unwrap();
@@ -568,6 +1074,13 @@ mod tests {
         let status_text = "## main\nUU conflict.txt\n";
         let status = parse_git_status(status_text).unwrap();
         assert_eq!(status.modified, 1);
+        assert_eq!(status.conflicted, 1);
+
+        // Ordinary changes are not conflicts
+        let status_text = "## main\nMM file.txt\nA  new.txt\n";
+        let status = parse_git_status(status_text).unwrap();
+        assert_eq!(status.conflicted, 0);
+        assert!(!format_git_info(&status).contains('✖'));
     }
 
     #[test]
@@ -610,6 +1123,67 @@ mod tests {
         assert_eq!(status.untracked, 2); // ?? files
     }
 
+    #[test]
+    fn test_parse_numstat() {
+        let numstat = "10\t2\tsrc/main.rs\n3\t0\tREADME.md\n-\t-\tlogo.png\n";
+        assert_eq!(
+            parse_numstat(numstat),
+            DiffStat {
+                added: 13,
+                removed: 2
+            }
+        );
+        assert_eq!(parse_numstat(""), DiffStat::default());
+    }
+
+    #[test]
+    fn test_first_match_branch_rules() {
+        let rule = |pattern: &str, prefix: &str| BranchRule {
+            pattern: pattern.to_string(),
+            color: None,
+            prefix: Some(prefix.to_string()),
+        };
+        let rules = vec![
+            rule("[invalid", "x"),
+            rule("^(main|master)$", "main"),
+            rule("^feat(ure)?/", "feat"),
+            rule("^(fix|hotfix)/", "fix"),
+        ];
+        let prefix = |branch: &str| {
+            first_match(&rules, |rule| &rule.pattern, branch, "git.branch_rules")
+                .and_then(|r| r.prefix.clone())
+        };
+
+        assert_eq!(prefix("main").as_deref(), Some("main"));
+        assert_eq!(prefix("feature/login").as_deref(), Some("feat"));
+        assert_eq!(prefix("hotfix/crash").as_deref(), Some("fix"));
+        assert_eq!(prefix("maintenance"), None);
+    }
+
+    #[test]
+    fn test_first_match_identities() {
+        let rules = vec![
+            IdentityRule {
+                pattern: r"@acme\.com$".to_string(),
+                label: "work".to_string(),
+                color: None,
+            },
+            IdentityRule {
+                pattern: r"@gmail\.com$".to_string(),
+                label: "home".to_string(),
+                color: None,
+            },
+        ];
+        let label = |email: &str| {
+            first_match(&rules, |rule| &rule.pattern, email, "git.identities")
+                .map(|r| r.label.as_str())
+        };
+
+        assert_eq!(label("jane@acme.com"), Some("work"));
+        assert_eq!(label("jane@gmail.com"), Some("home"));
+        assert_eq!(label("jane@acme.com.evil.io"), None);
+    }
+
     #[test]
     fn test_format_git_info() {
         let status = GitStatus {
@@ -618,15 +1192,105 @@ mod tests {
             modified: 1,
             deleted: 0,
             untracked: 3,
+            ahead: 0,
+            conflicted: 0,
+            stashed: 0,
+            truncated: false,
+            head_oid: None,
+            oldest_change_secs: None,
         };
         let formatted = format_git_info(&status);
         assert!(formatted.contains("main"));
         assert!(formatted.contains("+2"));
         assert!(formatted.contains("~1"));
         assert!(formatted.contains("?3"));
+        assert!(!formatted.contains('⇡'));
+
+        // Unpushed commits show on a clean tree
+        let status = GitStatus {
+            branch: "main".to_string(),
+            ahead: 2,
+            ..Default::default()
+        };
+        assert!(format_git_info(&status).contains("⇡2"));
+
+        // Capped counts are marked
+        let status = GitStatus {
+            branch: "main".to_string(),
+            modified: 1000,
+            truncated: true,
+            ..Default::default()
+        };
+        assert!(format_git_info(&status).contains('…'));
+    }
+
+    #[test]
+    fn test_changed_paths() {
+        let v1 = "## main\n M src/a.rs\nA  new file.rs\nR  old.rs -> renamed.rs\n?? scratch.txt\n";
+        assert_eq!(
+            changed_paths(v1, false),
+            vec!["src/a.rs", "new file.rs", "renamed.rs"]
+        );
+
+        let v2 = "# branch.oid abc\n# branch.head main\n\
+                  1 .M N... 100644 100644 100644 a1 b1 src/a b.rs\n\
+                  2 R. N... 100644 100644 100644 a2 b2 R100 renamed.rs\told.rs\n\
+                  u UU N... 100644 100644 100644 100644 a3 b3 c3 conflict.rs\n\
+                  ? scratch.txt\n";
+        assert_eq!(
+            changed_paths(v2, true),
+            vec!["src/a b.rs", "renamed.rs", "conflict.rs"]
+        );
+    }
+
+    #[test]
+    fn test_oldest_change_age() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "a").unwrap();
+        assert!(oldest_change_age(dir.path(), &["a.rs"]).unwrap() < 60);
+        // Deleted files are skipped
+        assert_eq!(oldest_change_age(dir.path(), &["gone.rs"]), None);
+    }
+
+    #[test]
+    fn test_cap_status_entries() {
+        let v1 = "## main...origin/main [ahead 1]\n M a.rs\n M b.rs\n?? c.rs\n";
+        assert_eq!(cap_status_entries(v1, 0), (Cow::Borrowed(v1), false));
+        assert_eq!(cap_status_entries(v1, 3), (Cow::Borrowed(v1), false));
+
+        let (capped, truncated) = cap_status_entries(v1, 2);
+        assert!(truncated);
+        assert_eq!(
+            capped,
+            "## main...origin/main [ahead 1]\n M a.rs\n M b.rs\n"
+        );
+
+        // v2 headers are kept too
+        let v2 = "# branch.oid abc\n# branch.head main\n1 .M N... 100644 100644 100644 a b a.rs\n? c.rs\n";
+        let (capped, truncated) = cap_status_entries(v2, 1);
+        assert!(truncated);
+        assert_eq!(capped.lines().count(), 3);
+        assert!(capped.contains("branch.head main"));
+    }
+
+    #[test]
+    fn test_match_repo_rule() {
+        let rules = vec![
+            RepoRule {
+                pattern: "/monorepo$".to_string(),
+                untracked: false,
+                ..Default::default()
+            },
+            RepoRule {
+                pattern: "[".to_string(), // invalid, skipped
+                ..Default::default()
+            },
+        ];
+        let rule = |path: &str| first_match(&rules, |rule| &rule.pattern, path, "git.repos");
+        assert!(!rule("/home/user/monorepo").unwrap().untracked);
+        assert!(rule("/home/user/monorepo-tools").is_none());
     }
 
-    #[cfg(feature = "git_porcelain_v2")]
     #[test]
     fn test_parse_git_status_v2_branch() {
         // Test branch name parsing
@@ -638,9 +1302,47 @@ mod tests {
         let status_text = "# branch.oid 1234567890abcdef\n# branch.head (detached)\n";
         let status = parse_git_status_v2(status_text).unwrap();
         assert_eq!(status.branch, "(detached)");
+        // Test ahead/behind header
+        let status_text = "# branch.head main\n# branch.upstream origin/main\n# branch.ab +4 -0\n";
+        let status = parse_git_status_v2(status_text).unwrap();
+        assert_eq!(status.ahead, 4);
+
+        // Stash header from --show-stash
+        let status_text = "# branch.head main\n# stash 3\n";
+        let status = parse_git_status_v2(status_text).unwrap();
+        assert_eq!(status.stashed, 3);
+        assert!(format_git_info(&status).contains("*3"));
+    }
+
+    #[test]
+    fn test_parse_describe() {
+        assert_eq!(
+            parse_describe("v2.3.1-0-gabc1234\n").as_deref(),
+            Some("v2.3.1")
+        );
+        assert_eq!(
+            parse_describe("v2.3.1-4-gabc1234").as_deref(),
+            Some("v2.3.1+4")
+        );
+        // Dashes inside the tag name
+        assert_eq!(
+            parse_describe("release-2024-01-12-gabc1234").as_deref(),
+            Some("release-2024-01+12")
+        );
+        assert_eq!(parse_describe("abc1234"), None);
+        assert_eq!(parse_describe(""), None);
+    }
+
+    #[test]
+    fn test_parse_git_status_v2_head_oid() {
+        let status =
+            parse_git_status_v2("# branch.oid 1234567890abcdef\n# branch.head main\n").unwrap();
+        assert_eq!(status.head_oid.as_deref(), Some("1234567890abcdef"));
+
+        let status = parse_git_status_v2("# branch.oid (initial)\n# branch.head main\n").unwrap();
+        assert_eq!(status.head_oid, None);
     }
 
-    #[cfg(feature = "git_porcelain_v2")]
     #[test]
     fn test_parse_git_status_v2_files() {
         // Test various file statuses
@@ -662,7 +1364,6 @@ mod tests {
         assert_eq!(status.untracked, 1); // ?
     }
 
-    #[cfg(feature = "git_porcelain_v2")]
     #[test]
     fn test_parse_git_status_v2_unmerged() {
         // Test unmerged conflict states
@@ -672,6 +1373,8 @@ mod tests {
 
         let status = parse_git_status_v2(status_text).unwrap();
         assert_eq!(status.modified, 2); // All unmerged states count as modified
+        assert_eq!(status.conflicted, 2);
+        assert!(format_git_info(&status).contains("✖2"));
     }
 
     #[test]
@@ -686,6 +1389,12 @@ mod tests {
             modified: 0,
             deleted: 0,
             untracked: 0,
+            ahead: 0,
+            conflicted: 0,
+            stashed: 0,
+            truncated: false,
+            head_oid: None,
+            oldest_change_secs: None,
         };
         let formatted = format_git_info(&status);
         // Should not contain control characters (the escape codes from the malicious input)
@@ -3,13 +3,16 @@
 //! This module provides utilities for executing git commands
 //! safely and consistently.
 
-use crate::config;
+use crate::config::{self, RepoRule};
 use crate::error::StatuslineError;
 use crate::retry::retry_simple;
 use std::io::Read;
 use std::path::Path;
 use std::process::{Command, Output, Stdio};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long git is assumed to lack porcelain v2 after it rejected it once
+const PORCELAIN_V1_STAMP_TTL: Duration = Duration::from_secs(24 * 3600);
 
 /// Executes a git command with the given arguments in a directory.
 ///
@@ -28,16 +31,25 @@ use std::time::{Duration, Instant};
 ///
 /// Returns the command output if successful, or None if the command fails or times out.
 fn execute_git_command<P: AsRef<Path>>(dir: P, args: &[&str]) -> Option<Output> {
+    execute_git_command_with_locks(dir, args, false)
+}
+
+/// Executes a git command, letting git take optional locks when `optional_locks` is set.
+fn execute_git_command_with_locks<P: AsRef<Path>>(
+    dir: P,
+    args: &[&str],
+    optional_locks: bool,
+) -> Option<Output> {
     let config = config::get_config();
 
     // Support environment variable override for timeout
-    let timeout_ms = std::env::var("STATUSLINE_GIT_TIMEOUT_MS")
-        .ok()
+    let timeout_ms = crate::environment::current()
+        .var("STATUSLINE_GIT_TIMEOUT_MS")
         .and_then(|v| v.parse::<u32>().ok())
         .unwrap_or(config.git.timeout_ms);
 
     retry_simple(2, 100, || {
-        execute_git_with_timeout(dir.as_ref(), args, timeout_ms)
+        execute_git_with_timeout(dir.as_ref(), args, timeout_ms, optional_locks)
             .ok_or_else(|| StatuslineError::git("Git command timed out or failed"))
     })
     .ok()
@@ -50,13 +62,16 @@ fn execute_git_with_timeout<P: AsRef<Path>>(
     dir: P,
     args: &[&str],
     timeout_ms: u32,
+    optional_locks: bool,
 ) -> Option<Output> {
     let mut cmd = Command::new("git");
     cmd.args(args)
         .current_dir(dir.as_ref())
-        .env("GIT_OPTIONAL_LOCKS", "0")
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+    if !optional_locks {
+        cmd.env("GIT_OPTIONAL_LOCKS", "0");
+    }
 
     let mut child = cmd.spawn().ok()?;
 
@@ -117,11 +132,28 @@ fn execute_git_with_timeout<P: AsRef<Path>>(
     }
 }
 
+/// Output of `git status`, tagged with its porcelain format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusPorcelain {
+    /// `--porcelain=v2 --branch --show-stash`: ahead/behind and stash count in one call
+    V2(String),
+    /// `--porcelain=v1 --branch`, for git versions without `--show-stash` (before 2.35)
+    V1(String),
+}
+
+impl StatusPorcelain {
+    pub fn text(&self) -> &str {
+        match self {
+            StatusPorcelain::V2(text) | StatusPorcelain::V1(text) => text,
+        }
+    }
+}
+
 /// Gets the git status in porcelain format.
 ///
 /// This is the main function used by the statusline to get git information.
-/// When the `git_porcelain_v2` feature is enabled, it uses porcelain v2 format,
-/// otherwise it uses porcelain v1 format.
+/// It uses porcelain v2, falling back to porcelain v1 on git versions that
+/// reject the v2 arguments.
 ///
 /// # Arguments
 ///
@@ -130,14 +162,155 @@ fn execute_git_with_timeout<P: AsRef<Path>>(
 /// # Returns
 ///
 /// Returns the porcelain status output if successful.
+#[allow(dead_code)]
 pub fn get_status_porcelain<P: AsRef<Path>>(dir: P) -> Option<String> {
-    #[cfg(feature = "git_porcelain_v2")]
-    let args = &["status", "--porcelain=v2", "--branch"];
+    get_status_porcelain_with(dir, &RepoRule::default()).map(|status| status.text().to_string())
+}
+
+/// Gets the git status with a repository's `git.repos` options.
+///
+/// Without an explicit `no_optional_locks`, optional locks are only allowed when
+/// the repository uses fsmonitor, so git can save the fsmonitor token (and
+/// refreshed index) instead of rescanning the whole tree on every render.
+pub fn get_status_porcelain_with<P: AsRef<Path>>(
+    dir: P,
+    rule: &RepoRule,
+) -> Option<StatusPorcelain> {
+    let dir = dir.as_ref();
+    let no_optional_locks = rule
+        .no_optional_locks
+        .unwrap_or_else(|| !fsmonitor_enabled(dir));
+
+    let status_args = |porcelain: &[&'static str]| {
+        let mut args = Vec::new();
+        if no_optional_locks {
+            args.push("--no-optional-locks");
+        }
+        args.push("status");
+        args.extend_from_slice(porcelain);
+        if !rule.untracked {
+            args.push("-uno");
+        }
+        args
+    };
+
+    // Git that rejected v2 recently goes straight to v1, instead of running
+    // git status twice on every render
+    let stamp = porcelain_v1_stamp_path();
+    let v2_unsupported = stamp.as_deref().is_some_and(|stamp| {
+        std::fs::metadata(stamp)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age < PORCELAIN_V1_STAMP_TTL)
+    });
+
+    if !v2_unsupported {
+        let v2 = status_args(&["--porcelain=v2", "--branch", "--show-stash"]);
+        let output = execute_git_command_with_locks(dir, &v2, !no_optional_locks)?;
+        if output.status.success() {
+            return Some(StatusPorcelain::V2(
+                String::from_utf8_lossy(&output.stdout).to_string(),
+            ));
+        }
+
+        // Older git: retry with the v1 format (timeouts already returned None above)
+        log::debug!(
+            "git status --porcelain=v2 failed, falling back to v1: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let v1 = status_args(&["--porcelain=v1", "--branch"]);
+    let output = execute_git_command_with_locks(dir, &v1, !no_optional_locks)?;
+    if !output.status.success() {
+        return None;
+    }
+    // v1 worked where v2 didn't, so it's git itself rather than the repository
+    if let (false, Some(stamp)) = (v2_unsupported, stamp) {
+        let _ = std::fs::write(stamp, "");
+    }
+    Some(StatusPorcelain::V1(
+        String::from_utf8_lossy(&output.stdout).to_string(),
+    ))
+}
+
+/// Touched when git rejects porcelain v2 but accepts v1
+fn porcelain_v1_stamp_path() -> Option<std::path::PathBuf> {
+    crate::state::get_cache_dir()
+        .ok()
+        .map(|dir| dir.join("git-porcelain-v1.stamp"))
+}
+
+/// Whether the repository uses fsmonitor: the built-in daemon is running, or
+/// `core.fsmonitor` is set in any config git reads (system, global, includes,
+/// the repository or worktree).
+pub fn fsmonitor_enabled(dir: &Path) -> bool {
+    if dir.join(".git").join("fsmonitor--daemon.ipc").exists() {
+        return true;
+    }
+    get_config_value(dir, "core.fsmonitor").is_some_and(|value| fsmonitor_value_enabled(&value))
+}
+
+/// Whether a `core.fsmonitor` value enables fsmonitor: a boolean true, or the
+/// path of a hook such as `.git/hooks/query-watchman`.
+fn fsmonitor_value_enabled(value: &str) -> bool {
+    !matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "false" | "no" | "off" | "0" | ""
+    )
+}
+
+/// Gets a git config value as seen from the repository (`git config --get <key>`).
+///
+/// Returns None when the key is unset or the command fails.
+pub fn get_config_value<P: AsRef<Path>>(dir: P, key: &str) -> Option<String> {
+    let output = execute_git_command(dir, &["config", "--get", key])?;
+
+    if output.status.success() {
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!value.is_empty()).then_some(value)
+    } else {
+        None
+    }
+}
+
+/// Resolves the commit HEAD points at (`git rev-parse --verify HEAD`).
+///
+/// Returns None before the first commit or when the command fails.
+pub fn rev_parse_head<P: AsRef<Path>>(dir: P) -> Option<String> {
+    let output = execute_git_command(dir, &["rev-parse", "--verify", "--quiet", "HEAD"])?;
 
-    #[cfg(not(feature = "git_porcelain_v2"))]
-    let args = &["status", "--porcelain=v1", "--branch"];
+    if output.status.success() {
+        let oid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!oid.is_empty()).then_some(oid)
+    } else {
+        None
+    }
+}
 
-    let output = execute_git_command(dir, args)?;
+/// Describes HEAD relative to the most recent reachable tag
+/// (`git describe --tags --long`, e.g. `v2.3.1-4-gabc1234`).
+///
+/// Returns `Some(None)` when git ran but found no tag, and None when the
+/// command failed to run or timed out.
+pub fn describe_tags<P: AsRef<Path>>(dir: P) -> Option<Option<String>> {
+    let output = execute_git_command(dir, &["describe", "--tags", "--long"])?;
+
+    Some(
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()),
+    )
+}
+
+/// Gets per-file line counts of uncommitted changes (`git diff HEAD --numstat`).
+///
+/// Covers both staged and unstaged changes to tracked files. Returns None when the
+/// command fails (e.g. a repository without commits) or times out.
+pub fn get_diff_numstat<P: AsRef<Path>>(dir: P) -> Option<String> {
+    let output = execute_git_command(dir, &["diff", "HEAD", "--numstat"])?;
 
     if output.status.success() {
         Some(String::from_utf8_lossy(&output.stdout).to_string())
@@ -189,6 +362,90 @@ mod tests {
         assert!(result.is_some());
     }
 
+    #[test]
+    fn test_status_without_untracked_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        Command::new("git")
+            .args(["init"])
+            .current_dir(temp_dir.path())
+            .output()
+            .ok();
+        std::fs::write(temp_dir.path().join("new.txt"), "x").unwrap();
+
+        let full = get_status_porcelain(temp_dir.path()).unwrap();
+        assert!(full.contains("new.txt"));
+
+        let rule = RepoRule {
+            untracked: false,
+            no_optional_locks: Some(false),
+            ..Default::default()
+        };
+        let fast = get_status_porcelain_with(temp_dir.path(), &rule).unwrap();
+        assert!(matches!(fast, StatusPorcelain::V2(_)));
+        assert!(fast.text().contains("# branch.head"));
+        assert!(!fast.text().contains("new.txt"));
+    }
+
+    #[test]
+    fn test_rev_parse_head() {
+        let temp_dir = TempDir::new().unwrap();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .current_dir(temp_dir.path())
+                .output()
+                .unwrap()
+        };
+        git(&["init"]);
+        // No commit yet
+        assert_eq!(rev_parse_head(temp_dir.path()), None);
+
+        git(&[
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@example.com",
+            "commit",
+            "--allow-empty",
+            "-m",
+            "first",
+        ]);
+        let oid = rev_parse_head(temp_dir.path()).unwrap();
+        assert_eq!(oid.len(), 40);
+    }
+
+    #[test]
+    fn test_fsmonitor_value_enabled() {
+        assert!(fsmonitor_value_enabled("true"));
+        assert!(fsmonitor_value_enabled(".git/hooks/query-watchman"));
+        assert!(!fsmonitor_value_enabled("false"));
+        assert!(!fsmonitor_value_enabled("Off"));
+    }
+
+    #[test]
+    fn test_fsmonitor_enabled_reads_git_config() {
+        let temp_dir = TempDir::new().unwrap();
+        Command::new("git")
+            .args(["init"])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+        assert!(!fsmonitor_enabled(temp_dir.path()));
+
+        // Set through an included file, which a plain .git/config parse misses
+        std::fs::write(
+            temp_dir.path().join("fsmonitor.inc"),
+            "[core]\n\tfsmonitor = true\n",
+        )
+        .unwrap();
+        Command::new("git")
+            .args(["config", "include.path", "../fsmonitor.inc"])
+            .current_dir(temp_dir.path())
+            .output()
+            .unwrap();
+        assert!(fsmonitor_enabled(temp_dir.path()));
+    }
+
     #[test]
     fn test_timeout_kills_process() {
         // Test that timeout actually kills long-running processes
@@ -200,6 +457,7 @@ mod tests {
             temp_dir.path(),
             &["--version"], // Quick command that should succeed
             200,            // 200ms timeout
+            false,
         );
 
         // Should complete quickly and successfully
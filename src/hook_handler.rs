@@ -1,12 +1,10 @@
-// Hook handler for Claude Code PreCompact and Stop events
+// Hook handler for Claude Code PreCompact, Stop and SessionEnd events
 //
 // This module provides handlers for Claude Code's hook system to track
 // compaction state in real-time via file-based state management.
 
-use chrono::Utc;
-
 use crate::error::Result;
-use crate::state::{clear_state, write_state, HookState};
+use crate::state::{cleanup_stale_states, clear_state, write_state, HookState};
 
 /// Handle PreCompact hook event
 ///
@@ -26,7 +24,7 @@ pub fn handle_precompact(session_id: &str, trigger: &str) -> Result<()> {
         state: "compacting".to_string(),
         trigger: trigger.to_string(),
         session_id: session_id.to_string(),
-        started_at: Utc::now(),
+        started_at: crate::environment::current().now(),
         pid: Some(std::process::id()),
     };
 
@@ -61,6 +59,32 @@ pub fn handle_stop(session_id: &str) -> Result<()> {
     Ok(())
 }
 
+/// Handle SessionEnd hook event
+///
+/// Called when a Claude session ends. Clears any compaction state left
+/// behind (e.g. a session closed mid-compaction) so it cannot go stale, sweeps
+/// stale state files of other sessions, and records the end time so the
+/// session's duration stops there.
+///
+/// # Arguments
+///
+/// * `session_id` - Ending Claude session ID
+///
+/// # Returns
+///
+/// Ok(()) on success, error on file deletion failure
+pub fn handle_session_end(session_id: &str) -> Result<()> {
+    clear_state(session_id)?;
+    if let Err(e) = cleanup_stale_states() {
+        log::debug!("Failed to clean up stale state files: {}", e);
+    }
+    crate::stats::record_session_end(session_id);
+
+    log::info!("SessionEnd hook: session={}", session_id);
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,6 +127,18 @@ mod tests {
         assert!(read_state(&session_id).is_none());
     }
 
+    #[test]
+    fn test_handle_session_end() {
+        let session_id = format!("{}-session-end", test_session_id());
+
+        handle_precompact(&session_id, "auto").unwrap();
+        handle_session_end(&session_id).unwrap();
+        assert!(read_state(&session_id).is_none());
+
+        // Ending a session without compaction state is fine
+        handle_session_end(&session_id).unwrap();
+    }
+
     #[test]
     fn test_handle_precompact_manual_trigger() {
         let session_id = format!("{}-manual", test_session_id());
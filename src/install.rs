@@ -0,0 +1,187 @@
+//! Claude Code integration setup (`statusline install --hooks`).
+//!
+//! Adds the hook entries that drive hook-based compaction detection to Claude
+//! Code's settings file. Existing settings and hooks are preserved, and entries
+//! that already invoke `statusline hook ...` are left alone, so re-running the
+//! installer is a no-op.
+
+use crate::error::{Result, StatuslineError};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Claude Code hook events and the `statusline hook` subcommand each one runs
+pub const HOOK_EVENTS: [(&str, &str); 2] =
+    [("PreCompact", "precompact"), ("SessionEnd", "session-end")];
+
+/// Settings file to update, preferring an existing `settings.local.json`
+/// like `scripts/install-statusline.sh` does.
+pub fn settings_path(config_dir: &Path) -> PathBuf {
+    let local = config_dir.join("settings.local.json");
+    let shared = config_dir.join("settings.json");
+    if local.exists() || !shared.exists() {
+        local
+    } else {
+        shared
+    }
+}
+
+/// Command prefix for hook entries: the running binary, quoted if needed.
+pub fn statusline_command() -> String {
+    let exe = std::env::current_exe()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "statusline".to_string());
+    if exe.contains(char::is_whitespace) {
+        format!("\"{}\"", exe)
+    } else {
+        exe
+    }
+}
+
+/// Whether an event's hook groups already run `statusline hook <subcommand>`.
+fn has_hook(groups: &[Value], subcommand: &str) -> bool {
+    let suffix = format!(" hook {}", subcommand);
+    groups
+        .iter()
+        .filter_map(|group| group.get("hooks").and_then(Value::as_array))
+        .flatten()
+        .filter_map(|hook| hook.get("command").and_then(Value::as_str))
+        .any(|command| command.contains("statusline") && command.trim_end().ends_with(&suffix))
+}
+
+/// Adds missing statusline hook entries to parsed settings.
+///
+/// Returns the events that were added (empty when everything was installed).
+pub fn add_hooks(settings: &mut Value, command: &str) -> Result<Vec<&'static str>> {
+    let root = settings.as_object_mut().ok_or_else(|| {
+        StatuslineError::Config("Claude settings must be a JSON object".to_string())
+    })?;
+    let hooks = root
+        .entry("hooks")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or_else(|| {
+            StatuslineError::Config("\"hooks\" in Claude settings must be an object".to_string())
+        })?;
+
+    let mut added = Vec::new();
+    for (event, subcommand) in HOOK_EVENTS {
+        let groups = hooks
+            .entry(event)
+            .or_insert_with(|| json!([]))
+            .as_array_mut()
+            .ok_or_else(|| {
+                StatuslineError::Config(format!("\"hooks.{}\" must be an array", event))
+            })?;
+        if has_hook(groups, subcommand) {
+            continue;
+        }
+        groups.push(json!({
+            "hooks": [{
+                "type": "command",
+                "command": format!("{} hook {}", command, subcommand)
+            }]
+        }));
+        added.push(event);
+    }
+    Ok(added)
+}
+
+/// Installs the hooks into `path`, backing up an existing file first.
+///
+/// With `dry_run`, reports what would be added without writing anything.
+pub fn install_hooks(path: &Path, command: &str, dry_run: bool) -> Result<Vec<&'static str>> {
+    let existing = match fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let mut settings = match existing.as_deref() {
+        Some(content) if !content.trim().is_empty() => serde_json::from_str(content)?,
+        _ => json!({}),
+    };
+
+    let added = add_hooks(&mut settings, command)?;
+    if added.is_empty() || dry_run {
+        return Ok(added);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if existing.is_some() {
+        fs::copy(path, path.with_extension("json.backup"))?;
+    }
+    // Write to a temp file and rename so Claude Code never sees a partial file
+    let temp = path.with_extension("json.tmp");
+    fs::write(
+        &temp,
+        format!("{}\n", serde_json::to_string_pretty(&settings)?),
+    )?;
+    fs::rename(&temp, path)?;
+    Ok(added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_add_hooks_preserves_and_is_idempotent() {
+        let mut settings = json!({
+            "statusLine": { "type": "command", "command": "/usr/local/bin/statusline" },
+            "hooks": {
+                "SessionEnd": [{ "hooks": [{ "type": "command", "command": "notify-send done" }] }]
+            }
+        });
+
+        let added = add_hooks(&mut settings, "/usr/local/bin/statusline").unwrap();
+        assert_eq!(added, vec!["PreCompact", "SessionEnd"]);
+        assert_eq!(
+            settings["statusLine"]["command"],
+            "/usr/local/bin/statusline"
+        );
+        assert!(settings["hooks"].get("Stop").is_none());
+        let session_end = settings["hooks"]["SessionEnd"].as_array().unwrap();
+        assert_eq!(session_end.len(), 2);
+        assert_eq!(
+            session_end[1]["hooks"][0]["command"],
+            "/usr/local/bin/statusline hook session-end"
+        );
+
+        assert!(add_hooks(&mut settings, "statusline").unwrap().is_empty());
+        assert!(add_hooks(&mut json!([]), "statusline").is_err());
+    }
+
+    #[test]
+    fn test_install_hooks_writes_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("settings.json");
+        fs::write(&path, r#"{"model": "opus"}"#).unwrap();
+
+        assert_eq!(install_hooks(&path, "statusline", true).unwrap().len(), 2);
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"{"model": "opus"}"#);
+
+        assert_eq!(install_hooks(&path, "statusline", false).unwrap().len(), 2);
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["model"], "opus");
+        assert!(written["hooks"]["SessionEnd"].is_array());
+        assert!(temp_dir.path().join("settings.json.backup").exists());
+
+        assert!(install_hooks(&path, "statusline", false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_settings_path_prefers_local() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        assert_eq!(settings_path(dir), dir.join("settings.local.json"));
+        fs::write(dir.join("settings.json"), "{}").unwrap();
+        assert_eq!(settings_path(dir), dir.join("settings.json"));
+        fs::write(dir.join("settings.local.json"), "{}").unwrap();
+        assert_eq!(settings_path(dir), dir.join("settings.local.json"));
+    }
+}
@@ -0,0 +1,135 @@
+//! OS keyring access for secrets such as the database encryption key and
+//! the sync auth token.
+//!
+//! Uses the platform's command-line tools so no extra dependencies are needed:
+//! - macOS: `security find-generic-password -s <service> -a <account> -w`
+//! - Linux: `secret-tool lookup service <service> account <account>` (libsecret)
+//!
+//! Other platforms have no keyring support: lookups return `None` and stores fail.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Keyring service name under which all statusline secrets are stored.
+pub const SERVICE: &str = "claudia-statusline";
+
+/// Reads the secret stored for `account` under [`SERVICE`], if any.
+pub fn get_secret(account: &str) -> Option<String> {
+    let output = lookup_command(account)?
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        log::debug!("No keyring entry for {}/{}", SERVICE, account);
+        return None;
+    }
+
+    let secret = String::from_utf8(output.stdout).ok()?;
+    let secret = secret.trim_end_matches(['\r', '\n']);
+    if secret.is_empty() {
+        None
+    } else {
+        Some(secret.to_string())
+    }
+}
+
+/// Stores `secret` for `account` under [`SERVICE`], replacing any existing entry.
+#[cfg_attr(not(feature = "turso-sync"), allow(dead_code))]
+pub fn store_secret(account: &str, secret: &str) -> io::Result<()> {
+    let mut cmd = store_command(account).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "no OS keyring support on this platform",
+        )
+    })?;
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot run {}: {}", program, e)))?;
+
+    // Both tools read the secret from stdin, which keeps it out of the process list
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(store_input(secret).as_bytes())?;
+    }
+
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "keyring rejected the entry for {}/{}",
+            SERVICE, account
+        )))
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[cfg_attr(not(feature = "turso-sync"), allow(dead_code))]
+fn store_command(account: &str) -> Option<Command> {
+    let mut cmd = Command::new("security");
+    // -U updates an existing item instead of failing; -w without a value (it must
+    // come last) makes security prompt for the password on stdin
+    cmd.args([
+        "add-generic-password",
+        "-U",
+        "-s",
+        SERVICE,
+        "-a",
+        account,
+        "-w",
+    ]);
+    Some(cmd)
+}
+
+/// security asks for the password and then to retype it
+#[cfg(target_os = "macos")]
+#[cfg_attr(not(feature = "turso-sync"), allow(dead_code))]
+fn store_input(secret: &str) -> String {
+    format!("{secret}\n{secret}\n")
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[cfg_attr(not(feature = "turso-sync"), allow(dead_code))]
+fn store_command(account: &str) -> Option<Command> {
+    let mut cmd = Command::new("secret-tool");
+    cmd.args([
+        "store", "--label", SERVICE, "service", SERVICE, "account", account,
+    ]);
+    Some(cmd)
+}
+
+/// secret-tool stores everything read from stdin, so no trailing newline
+#[cfg(not(target_os = "macos"))]
+#[cfg_attr(not(feature = "turso-sync"), allow(dead_code))]
+fn store_input(secret: &str) -> String {
+    secret.to_string()
+}
+
+#[cfg(not(unix))]
+#[cfg_attr(not(feature = "turso-sync"), allow(dead_code))]
+fn store_command(_account: &str) -> Option<Command> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn lookup_command(account: &str) -> Option<Command> {
+    let mut cmd = Command::new("security");
+    cmd.args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"]);
+    Some(cmd)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn lookup_command(account: &str) -> Option<Command> {
+    let mut cmd = Command::new("secret-tool");
+    cmd.args(["lookup", "service", SERVICE, "account", account]);
+    Some(cmd)
+}
+
+#[cfg(not(unix))]
+fn lookup_command(_account: &str) -> Option<Command> {
+    None
+}
@@ -0,0 +1,140 @@
+//! Last rendered statusline for external consumers (`statusline last`).
+//!
+//! After each render the line is written to `$XDG_RUNTIME_DIR/claudia-statusline/`
+//! as `last` (plain text) and `last.json` (text, ANSI form and the session it
+//! came from), so window managers and status bars can show the latest state
+//! without triggering a fresh computation. Both files are replaced atomically;
+//! platforms without a runtime directory use the cache directory instead.
+
+use crate::error::Result;
+use crate::utils::sanitize_for_terminal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Plain-text file name
+const TEXT_FILE: &str = "last";
+
+/// JSON file name
+const JSON_FILE: &str = "last.json";
+
+/// The last rendered statusline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastRender {
+    /// Statusline without color codes
+    pub text: String,
+    /// Statusline as printed, including ANSI color codes
+    pub ansi: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    pub directory: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    pub rendered_at: DateTime<Utc>,
+}
+
+impl LastRender {
+    pub fn new(
+        line: &str,
+        session_id: Option<&str>,
+        model: Option<&str>,
+        directory: &str,
+        cost_usd: Option<f64>,
+    ) -> Self {
+        LastRender {
+            text: sanitize_for_terminal(line),
+            ansi: line.to_string(),
+            session_id: session_id.map(str::to_string),
+            model: model.map(str::to_string),
+            directory: directory.to_string(),
+            cost_usd,
+            rendered_at: crate::environment::current().now(),
+        }
+    }
+}
+
+/// Directory holding the last render (runtime dir, falling back to the cache dir)
+pub fn last_dir() -> Option<PathBuf> {
+    let env = crate::environment::current();
+    env.runtime_dir()
+        .or_else(|| env.cache_dir())
+        .map(|dir| dir.join("claudia-statusline"))
+}
+
+/// Writes the render to the default location
+pub fn save(render: &LastRender) -> Result<()> {
+    let dir = last_dir().ok_or_else(|| {
+        crate::error::StatuslineError::Config("Cannot determine runtime directory".to_string())
+    })?;
+    save_to(&dir, render)
+}
+
+/// Reads the last render from the default location (None if nothing was rendered yet)
+pub fn load() -> Result<Option<LastRender>> {
+    match last_dir() {
+        Some(dir) => load_from(&dir),
+        None => Ok(None),
+    }
+}
+
+fn save_to(dir: &Path, render: &LastRender) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    write_atomic(&dir.join(TEXT_FILE), &format!("{}\n", render.text))?;
+    write_atomic(&dir.join(JSON_FILE), &serde_json::to_string(render)?)
+}
+
+fn load_from(dir: &Path) -> Result<Option<LastRender>> {
+    match fs::read_to_string(dir.join(JSON_FILE)) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write to a per-process temp file, then rename over the target, so readers
+/// never see a partial file even when sessions render concurrently
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let temp = path.with_extension(format!("tmp.{}", std::process::id()));
+    fs::write(&temp, contents)?;
+    if let Err(e) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().join("claudia-statusline");
+        assert_eq!(load_from(&dir).unwrap(), None);
+
+        let render = LastRender::new(
+            "\x1b[36m~/repo\x1b[0m \x1b[35mOpus\x1b[0m",
+            Some("abc-123"),
+            Some("Opus"),
+            "/home/user/repo",
+            Some(1.25),
+        );
+        save_to(&dir, &render).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.join(TEXT_FILE)).unwrap(),
+            "~/repo Opus\n"
+        );
+        let loaded = load_from(&dir).unwrap().unwrap();
+        assert_eq!(loaded, render);
+        assert_eq!(loaded.text, "~/repo Opus");
+
+        // No temp files are left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+    }
+}
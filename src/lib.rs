@@ -30,22 +30,50 @@
 // TODO: Re-enable html_root_url once the crate is published on docs.rs
 // #![doc(html_root_url = "https://docs.rs/statusline/2.7.0")]
 
+/// Database and config snapshots (`statusline backup`)
+#[cfg(feature = "sqlite")]
+pub mod backup;
+/// Color decision honoring `--color`, NO_COLOR and CLICOLOR_FORCE
+pub mod color;
 pub mod common;
 /// Configuration management module for loading and saving settings
 pub mod config;
 /// Adaptive context window learning from usage patterns
+#[cfg(feature = "adaptive-learning")]
 pub mod context_learning;
 /// SQLite database backend for persistent statistics
+#[cfg(feature = "sqlite")]
 pub mod database;
 pub mod display;
+/// Injectable environment variables, home directory and clock
+pub mod environment;
 pub mod error;
+/// `--explain` report of how statusline values were derived
+pub mod explain;
+/// Canned payloads for `statusline render --fixture`
+pub mod fixtures;
+#[cfg(feature = "git")]
 pub mod git;
+#[cfg(feature = "git")]
 pub mod git_utils;
-/// Hook handlers for Claude Code PreCompact and Stop events
+/// Hook handlers for Claude Code PreCompact, Stop and SessionEnd events
 pub mod hook_handler;
+/// Claude Code settings integration (`statusline install --hooks`)
+pub mod install;
+/// OS keyring access for secrets (requires sqlcipher or turso-sync feature)
+#[cfg(any(feature = "sqlcipher", feature = "turso-sync"))]
+pub mod keyring;
+/// Last rendered statusline for window managers and bars (`statusline last`)
+pub mod last;
 /// Database schema migration system
+#[cfg(feature = "sqlite")]
 pub mod migrations;
 pub mod models;
+/// API pricing table for derived cost estimates
+pub mod pricing;
+/// Statistics reports and terminal charts for the stats command
+#[cfg(feature = "sqlite")]
+pub mod reports;
 /// Retry logic with exponential backoff for transient failures
 pub mod retry;
 /// Hook-based state management for real-time event tracking
@@ -54,18 +82,32 @@ pub mod stats;
 /// Cloud synchronization module (requires turso-sync feature)
 #[cfg(feature = "turso-sync")]
 pub mod sync;
+/// OpenTelemetry render spans (exporter requires the otel feature)
+pub mod telemetry;
 /// Theme system for customizable statusline colors
 pub mod theme;
+/// Transcript discovery from the session id
+pub mod transcripts;
+/// Opt-in check for newer GitHub releases
+pub mod update;
 pub mod utils;
+/// Recent lines-changed velocity for the lines segment
+pub mod velocity;
 pub mod version;
+/// Live re-rendering for `statusline watch`
+#[cfg(feature = "sqlite")]
+pub mod watch;
 
 pub use config::Config;
 pub use display::{format_output, format_output_to_string};
 pub use error::{Result, StatuslineError};
+#[cfg(feature = "git")]
 pub use git::get_git_status;
 pub use models::{Cost, Model, StatuslineInput, Workspace};
 pub use stats::{get_daily_total, get_or_load_stats_data, update_stats_data, StatsData};
-pub use theme::{get_theme_manager, Theme, ThemeManager};
+pub use theme::Theme;
+#[cfg(feature = "themes")]
+pub use theme::{get_theme_manager, ThemeManager};
 pub use version::{short_version, version_string};
 
 // ============================================================================
@@ -95,6 +137,7 @@ pub use version::{short_version, version_string};
 /// let input = StatuslineInput {
 ///     workspace: Some(Workspace {
 ///         current_dir: Some("/home/user/project".to_string()),
+///         project_dir: None,
 ///     }),
 ///     model: Some(Model {
 ///         display_name: Some("Claude 3.5 Sonnet".to_string()),
@@ -106,18 +149,40 @@ pub use version::{short_version, version_string};
 /// println!("{}", output);
 /// ```
 pub fn render_statusline(input: &StatuslineInput, update_stats: bool) -> Result<String> {
-    // Get workspace directory
+    // Namespace stats by the profile named in the input (if any)
+    common::set_profile(input.profile.as_deref());
+    // Prefer token/context data reported in the input over transcript parsing
+    utils::set_reported_context(input.reported_context());
+
+    // Get the directory to display (cwd or project root, per display.path_style)
+    let path_style = config::get_config().display.path_style.clone();
     let current_dir = input
         .workspace
         .as_ref()
-        .and_then(|w| w.current_dir.as_deref())
+        .and_then(|w| w.display_dir(&path_style))
         .unwrap_or("~");
 
+    // Ignored directories (slow mounts): the path only, no git, transcript or database work
+    let working_dir = input
+        .workspace
+        .as_ref()
+        .and_then(|w| w.current_dir.as_deref())
+        .unwrap_or(current_dir);
+    if let Some(line) = display::format_ignored_dir_line(working_dir, current_dir) {
+        return Ok(line);
+    }
+
     // Get model name
     let model_name = input.model.as_ref().and_then(|m| m.display_name.as_deref());
 
-    // Get transcript path
-    let transcript_path = input.transcript.as_deref();
+    // Get transcript path, discovering it from the session id if not provided
+    let discovered = match (&input.transcript, &input.session_id) {
+        (None, Some(session_id)) => {
+            transcripts::discover(session_id).map(|path| path.to_string_lossy().into_owned())
+        }
+        _ => None,
+    };
+    let transcript_path = input.transcript.as_deref().or(discovered.as_deref());
 
     // Get cost data
     let cost = input.cost.as_ref();
@@ -125,127 +190,165 @@ pub fn render_statusline(input: &StatuslineInput, update_stats: bool) -> Result<
     // Get session ID
     let session_id = input.session_id.as_deref();
 
-    // Load or update stats
-    let daily_total = if update_stats && session_id.is_some() {
-        // Update stats with new data
-        if let Some(ref cost) = input.cost {
-            if let Some(total_cost) = cost.total_cost_usd {
-                // Extract model name and workspace directory
-                let model_name = input
-                    .model
-                    .as_ref()
-                    .and_then(|m| m.display_name.as_ref())
-                    .map(|s| s.as_str());
-                let workspace_dir = input
-                    .workspace
-                    .as_ref()
-                    .and_then(|w| w.current_dir.as_ref())
-                    .map(|s| s.as_str());
+    // Each render reports its own failures
+    common::reset_degraded();
+
+    // Load or update stats (a failing stats backend must not blank the statusline)
+    let daily_total = common::guarded("stats", 0.0, || {
+        if update_stats && session_id.is_some() {
+            // Update stats with new data
+            if let Some(ref cost) = input.cost {
+                if let Some(total_cost) = cost.total_cost_usd {
+                    // Extract model name and workspace directory
+                    let model_name = input
+                        .model
+                        .as_ref()
+                        .and_then(|m| m.display_name.as_ref())
+                        .map(|s| s.as_str());
+                    let workspace_dir = input.workspace.as_ref().and_then(|w| w.project_root());
+
+                    // Token breakdown from the input, or the transcript if not reported
+                    let token_breakdown = utils::current_token_breakdown(transcript_path);
 
-                // Extract token breakdown from transcript if available
-                let token_breakdown = input
-                    .transcript
-                    .as_ref()
-                    .and_then(|path| utils::get_token_breakdown_from_transcript(path));
+                    // Get device ID for audit trail
+                    let device_id = common::get_device_id();
 
-                // Get device ID for audit trail
-                let device_id = common::get_device_id();
+                    // Branch checked out in the working directory, for per-branch costs
+                    #[cfg(feature = "git")]
+                    let git_branch = input
+                        .workspace
+                        .as_ref()
+                        .and_then(|w| w.current_dir.as_deref())
+                        .and_then(git::current_branch);
+                    #[cfg(not(feature = "git"))]
+                    let git_branch = None;
 
-                use crate::database::SessionUpdate;
-                let (daily_total, _monthly_total) = stats::update_stats_data(|data| {
-                    data.update_session(
-                        session_id.unwrap(),
-                        SessionUpdate {
-                            cost: total_cost,
-                            lines_added: cost.total_lines_added.unwrap_or(0),
-                            lines_removed: cost.total_lines_removed.unwrap_or(0),
-                            model_name: model_name.map(|s| s.to_string()),
-                            workspace_dir: workspace_dir.map(|s| s.to_string()),
-                            device_id: Some(device_id),
-                            token_breakdown,
-                            max_tokens_observed: None, // updated separately
-                        },
-                    )
-                });
-                daily_total
+                    use crate::stats::SessionUpdate;
+                    let (daily_total, _monthly_total) = stats::update_stats_data(|data| {
+                        data.update_session(
+                            session_id.unwrap(),
+                            SessionUpdate {
+                                cost: total_cost,
+                                lines_added: cost.total_lines_added.unwrap_or(0),
+                                lines_removed: cost.total_lines_removed.unwrap_or(0),
+                                model_name: model_name.map(|s| s.to_string()),
+                                workspace_dir: workspace_dir.map(|s| s.to_string()),
+                                device_id: Some(device_id),
+                                token_breakdown,
+                                max_tokens_observed: None, // updated separately
+                                git_branch,
+                            },
+                        )
+                    });
+                    daily_total
+                } else {
+                    // Have session but no cost data - still load existing daily totals
+                    let data = stats::get_or_load_stats_data();
+                    stats::get_daily_total(&data)
+                }
             } else {
-                // Have session but no cost data - still load existing daily totals
+                // No cost data - just get current daily total
                 let data = stats::get_or_load_stats_data();
                 stats::get_daily_total(&data)
             }
         } else {
-            // No cost data - just get current daily total
-            let data = stats::get_or_load_stats_data();
-            stats::get_daily_total(&data)
+            // Just get current daily total without updating
+            let stats_data = stats::get_or_load_stats_data();
+            stats::get_daily_total(&stats_data)
         }
-    } else {
-        // Just get current daily total without updating
-        let stats_data = stats::get_or_load_stats_data();
-        stats::get_daily_total(&stats_data)
-    };
+    });
+
+    // Fire cost alerts; they stay on the statusline until `statusline alerts ack`
+    if update_stats && stats::alerts_enabled() {
+        let session_cost = cost.and_then(|c| c.total_cost_usd);
+        common::guarded("alerts", (), || {
+            stats::check_cost_alerts(session_id, session_cost, daily_total)
+        });
+    }
 
     // Track max_tokens_observed for compaction detection
     // This runs regardless of adaptive_learning setting
     if update_stats {
-        if let (Some(transcript), Some(session)) = (transcript_path, session_id) {
-            if let Some(current_tokens) = utils::get_token_count_from_transcript(transcript) {
-                // Update session's max_tokens_observed
-                // This updates both in-memory stats and SQLite database
-                stats::update_stats_data(|data| {
-                    data.update_max_tokens(session, current_tokens);
-                    // Return unchanged totals (we're just updating token tracking)
-                    use crate::common::{current_date, current_month};
-                    let today = current_date();
-                    let month = current_month();
-                    let daily_total = data.daily.get(&today).map(|d| d.total_cost).unwrap_or(0.0);
-                    let monthly_total = data
-                        .monthly
-                        .get(&month)
-                        .map(|m| m.total_cost)
-                        .unwrap_or(0.0);
-                    (daily_total, monthly_total)
-                });
+        common::guarded("token tracking", (), || {
+            if let Some(session) = session_id {
+                // Active time, API call usage and edited files from the transcript
+                if let Some(transcript) = transcript_path {
+                    stats::record_transcript_activity(session, transcript);
+                }
 
-                // Adaptive context learning: observe token usage if enabled
-                if let Some(model) = model_name {
-                    let config = config::get_config();
-                    if config.context.adaptive_learning {
-                        // Get previous token count from session stats
-                        let stats_data = stats::get_or_load_stats_data();
-                        let previous_tokens = stats_data
-                            .sessions
-                            .get(session)
-                            .and_then(|s| s.max_tokens_observed)
-                            .map(|t| t as usize);
+                if let Some(current_tokens) = utils::current_context_tokens(transcript_path) {
+                    // Update session's max_tokens_observed
+                    // This updates both in-memory stats and SQLite database
+                    stats::update_stats_data(|data| {
+                        data.update_max_tokens(session, current_tokens);
+                        // Return unchanged totals (we're just updating token tracking)
+                        use crate::common::{current_date, current_month};
+                        let today = current_date();
+                        let month = current_month();
+                        let daily_total =
+                            data.daily.get(&today).map(|d| d.total_cost).unwrap_or(0.0);
+                        let monthly_total = data
+                            .monthly
+                            .get(&month)
+                            .map(|m| m.total_cost)
+                            .unwrap_or(0.0);
+                        (daily_total, monthly_total)
+                    });
 
-                        // Create context learner and observe usage
-                        use crate::common::get_data_dir;
-                        use crate::context_learning::ContextLearner;
-                        use crate::database::SqliteDatabase;
+                    // Record context usage history for the sparkline
+                    if config::get_config().display.show_context_sparkline {
+                        if let Some(context) = utils::calculate_current_context_usage(
+                            transcript_path,
+                            model_name,
+                            Some(session),
+                            None,
+                        )
+                        .filter(|context| !context.approximate)
+                        {
+                            stats::record_context_sample(session, context.percentage);
+                        }
+                    }
+
+                    // Adaptive context learning: observe token usage if enabled
+                    #[cfg(feature = "adaptive-learning")]
+                    if let Some(model) = model_name {
+                        let config = config::get_config();
+                        if config.context.adaptive_learning && stats::check_disk_space().is_ok() {
+                            // Get previous token count from session stats
+                            let stats_data = stats::get_or_load_stats_data();
+                            let previous_tokens = stats_data
+                                .sessions
+                                .get(session)
+                                .and_then(|s| s.max_tokens_observed)
+                                .map(|t| t as usize);
 
-                        let db_path = get_data_dir().join("stats.db");
-                        if let Ok(db) = SqliteDatabase::new(&db_path) {
-                            let learner = ContextLearner::new(db);
-                            // Extract workspace_dir and device_id for audit trail
-                            let workspace_dir = input
-                                .workspace
-                                .as_ref()
-                                .and_then(|w| w.current_dir.as_deref());
-                            let device_id = crate::common::get_device_id();
-                            // Ignore errors from adaptive learning - it's experimental and shouldn't block statusline
-                            let _ = learner.observe_usage(
-                                model,
-                                current_tokens as usize,
-                                previous_tokens,
-                                Some(transcript),
-                                workspace_dir,
-                                Some(&device_id),
-                            );
+                            // Create context learner and observe usage
+                            use crate::common::get_database_path;
+                            use crate::context_learning::ContextLearner;
+                            use crate::database::SqliteDatabase;
+
+                            let db_path = get_database_path();
+                            if let Ok(db) = SqliteDatabase::new(&db_path) {
+                                let learner = ContextLearner::new(db);
+                                // Extract workspace_dir and device_id for audit trail
+                                let workspace_dir =
+                                    input.workspace.as_ref().and_then(|w| w.project_root());
+                                let device_id = crate::common::get_device_id();
+                                // Ignore errors from adaptive learning - it's experimental and shouldn't block statusline
+                                let _ = learner.observe_usage(
+                                    model,
+                                    current_tokens as usize,
+                                    previous_tokens,
+                                    transcript_path,
+                                    workspace_dir,
+                                    Some(&device_id),
+                                );
+                            }
                         }
                     }
                 }
             }
-        }
+        });
     }
 
     // Format the output to string
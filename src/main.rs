@@ -23,30 +23,55 @@
 use clap::{Parser, Subcommand};
 use log::warn;
 use std::env;
-use std::io::{self, Read};
+use std::io::{self, BufRead, Read, Write};
 use std::path::PathBuf;
 
+#[cfg(feature = "sqlite")]
+mod backup;
+mod color;
 mod common;
 mod config;
+#[cfg(feature = "adaptive-learning")]
 mod context_learning;
+#[cfg(feature = "sqlite")]
 mod database;
 mod display;
+mod environment;
 mod error;
+mod explain;
+mod fixtures;
+#[cfg(feature = "git")]
 mod git;
+#[cfg(feature = "git")]
 mod git_utils;
 mod hook_handler;
+mod install;
+#[cfg(any(feature = "sqlcipher", feature = "turso-sync"))]
+mod keyring;
+mod last;
+#[cfg(feature = "sqlite")]
 mod migrations;
 mod models;
+#[cfg(feature = "sqlite")]
+mod pricing;
+#[cfg(feature = "sqlite")]
+mod reports;
 mod retry;
 mod state;
 mod stats;
 #[cfg(feature = "turso-sync")]
 mod sync;
+mod telemetry;
 mod theme;
+mod transcripts;
+mod update;
 mod utils;
+mod velocity;
 mod version;
+#[cfg(feature = "sqlite")]
+mod watch;
 
-use display::{format_output, Colors};
+use display::{format_output_to_string, Colors};
 use error::Result;
 use models::StatuslineInput;
 use stats::{get_or_load_stats_data, update_stats_data};
@@ -65,22 +90,55 @@ struct Cli {
     #[arg(long = "version-full")]
     version_full: bool,
 
-    /// Disable colored output
+    /// Disable colored output (same as --color never)
     #[arg(long)]
     no_color: bool,
 
+    /// When to use colors: auto honors NO_COLOR, CLICOLOR_FORCE and, for
+    /// commands other than the statusline, whether stdout is a terminal
+    #[arg(long, value_name = "WHEN", default_value = "auto", value_parser = color::COLOR_CHOICES)]
+    color: String,
+
     /// Set color theme (light or dark)
     #[arg(long, value_name = "THEME")]
     theme: Option<String>,
 
-    /// Path to configuration file
+    /// Path to configuration file, or a directory used instead of
+    /// ~/.config/claudia-statusline (config.toml, themes)
     #[arg(long, value_name = "PATH")]
     config: Option<PathBuf>,
 
+    /// Apply the [variants.NAME] overrides from the config file
+    #[arg(long, value_name = "NAME")]
+    variant: Option<String>,
+
+    /// Directory for stats and runtime data instead of ~/.local/share/claudia-statusline
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+
     /// Set log level
     #[arg(long, value_name = "LEVEL", value_parser = ["error", "warn", "info", "debug", "trace"])]
     log_level: Option<String>,
 
+    /// Stay running, rendering one line per newline-delimited JSON payload on stdin
+    #[arg(long)]
+    stream: bool,
+
+    /// Exit non-zero when the payload can't be parsed (5) or segments were omitted (6)
+    #[arg(long)]
+    strict_exit: bool,
+
+    /// After the line, print to stderr how each value was derived (context window
+    /// source, buffer and percentage math, effective thresholds)
+    #[arg(long)]
+    explain: bool,
+
+    /// Output format: "zsh-rprompt" escapes the line for a zsh prompt and writes a
+    /// short cost + context string for RPROMPT to stderr (logging is then off
+    /// unless --log-level or RUST_LOG is set)
+    #[arg(long, value_name = "FORMAT", default_value = "statusline", value_parser = ["statusline", "zsh-rprompt"])]
+    output: String,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -91,6 +149,7 @@ enum Commands {
     GenerateConfig,
 
     /// Migration utilities for the SQLite database
+    #[cfg(feature = "sqlite")]
     Migrate {
         /// Finalize migration from JSON to SQLite-only mode
         #[arg(long)]
@@ -110,6 +169,7 @@ enum Commands {
     },
 
     /// Database maintenance operations (suitable for cron)
+    #[cfg(feature = "sqlite")]
     DbMaintain {
         /// Force VACUUM even if not needed
         #[arg(long)]
@@ -122,6 +182,100 @@ enum Commands {
         /// Run in quiet mode (only errors)
         #[arg(short, long)]
         quiet: bool,
+
+        /// List the rows retention pruning would delete and the space it would
+        /// reclaim, without changing the database
+        #[arg(long, conflicts_with_all = ["force_vacuum", "no_prune", "quiet"])]
+        dry_run: bool,
+    },
+
+    /// Show usage statistics and reports
+    #[cfg(feature = "sqlite")]
+    Stats {
+        /// Render charts of daily cost (last 30 days) and today's hourly distribution
+        #[arg(long)]
+        chart: bool,
+
+        /// List the N most expensive projects (workspaces) for the selected period
+        #[arg(long, value_name = "N")]
+        top_projects: Option<usize>,
+
+        /// Show the share of sessions, tokens and cost per model
+        #[arg(long)]
+        by_model: bool,
+
+        /// Show sessions, tokens and cost per user (shared databases)
+        #[arg(long)]
+        by_user: bool,
+
+        /// Show cost and line changes per git branch, grouped by repository
+        #[arg(long)]
+        by_branch: bool,
+
+        /// Show a weekday × hour activity heatmap (by session start time)
+        #[arg(long)]
+        heatmap: bool,
+
+        /// Show each day's cost split into input, output, cache write and cache read
+        #[arg(long)]
+        cost_split: bool,
+
+        /// Forecast this month's total spend from the month-to-date daily totals
+        #[arg(long)]
+        forecast: bool,
+
+        /// Metric shaded in the heatmap
+        #[arg(long, default_value = "sessions", value_parser = ["sessions", "cost"])]
+        metric: String,
+
+        /// Period for project, model and heatmap reports
+        #[arg(long, default_value = "month", value_parser = reports::PERIODS)]
+        period: String,
+    },
+
+    /// Generate a shareable usage report
+    #[cfg(feature = "sqlite")]
+    Report {
+        /// Report on the last 7 days (default)
+        #[arg(long, conflicts_with = "monthly")]
+        weekly: bool,
+
+        /// Report on the current month to date
+        #[arg(long)]
+        monthly: bool,
+
+        /// Output format
+        #[arg(long, default_value = "markdown", value_parser = ["markdown"])]
+        format: String,
+    },
+
+    /// Export usage history for other tools
+    #[cfg(feature = "sqlite")]
+    Export {
+        /// Export format (ccusage: the `ccusage daily --json` structure;
+        /// timesheet: CSV of sessions as time blocks per project; toggl: CSV
+        /// for the Toggl Track importer)
+        #[arg(long, value_parser = ["ccusage", "timesheet", "toggl"])]
+        format: String,
+
+        /// Only export sessions last active in this period
+        #[arg(long, default_value = "all", value_parser = reports::PERIODS)]
+        period: String,
+
+        /// Email of the Toggl workspace member the entries belong to
+        #[arg(long, required_if_eq("format", "toggl"))]
+        email: Option<String>,
+    },
+
+    /// Import usage history exported by other tools
+    #[cfg(feature = "sqlite")]
+    Import {
+        /// Import format (ccusage: `ccusage daily --json` output)
+        #[arg(long, value_parser = ["ccusage"])]
+        format: String,
+
+        /// File to read ("-" for stdin)
+        path: PathBuf,
     },
 
     /// Show diagnostic information about the statusline
@@ -131,9 +285,34 @@ enum Commands {
         json: bool,
     },
 
+    /// Inspect and repair the stats database
+    #[cfg(feature = "sqlite")]
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+
+    /// Re-derive session statistics lost to corruption or added by newer
+    /// schema columns
+    #[cfg(feature = "sqlite")]
+    Rebuild {
+        /// Directory of Claude Code transcripts (e.g. ~/.claude/projects);
+        /// start time, active time and token counts of known sessions are
+        /// recomputed from their transcripts
+        #[arg(long, value_name = "DIR")]
+        from_transcripts: PathBuf,
+
+        /// Only report which sessions would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Cloud sync operations (requires turso-sync feature)
     #[cfg(feature = "turso-sync")]
     Sync {
+        #[command(subcommand)]
+        action: Option<SyncAction>,
+
         /// Show sync status
         #[arg(long)]
         status: bool,
@@ -152,7 +331,12 @@ enum Commands {
     },
 
     /// Adaptive context window learning (experimental)
+    #[cfg(feature = "adaptive-learning")]
+    #[command(alias = "learning")]
     ContextLearning {
+        #[command(subcommand)]
+        action: Option<LearningAction>,
+
         /// Show learned context windows for all models
         #[arg(long)]
         status: bool,
@@ -179,6 +363,210 @@ enum Commands {
         #[command(subcommand)]
         action: HookAction,
     },
+
+    /// Set up Claude Code integration
+    Install {
+        /// Add PreCompact/SessionEnd hooks to Claude Code settings
+        #[arg(long)]
+        hooks: bool,
+
+        /// Settings file to update (default: settings.local.json or settings.json in CLAUDE_CONFIG_DIR or ~/.claude)
+        #[arg(long)]
+        settings: Option<PathBuf>,
+
+        /// Show what would be added without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Render canned states to preview config and theme changes
+    Render {
+        /// Fixture to render (all fixtures when omitted)
+        #[arg(long, value_parser = fixtures::FIXTURE_NAMES)]
+        fixture: Option<String>,
+
+        /// Theme to render with instead of the configured one (built-in or
+        /// themes/<NAME>.toml in the config directory, re-read on every run)
+        #[arg(long, value_name = "NAME")]
+        theme: Option<String>,
+    },
+
+    /// Re-render the statusline live whenever the transcript or stats change
+    #[cfg(feature = "sqlite")]
+    Watch {
+        /// Transcript file of the session to follow
+        #[arg(long, value_name = "PATH")]
+        transcript: PathBuf,
+
+        /// Session ID (defaults to the transcript file name)
+        #[arg(long)]
+        session_id: Option<String>,
+
+        /// Milliseconds to batch file changes before redrawing (the polling
+        /// interval if file watching is unavailable)
+        #[arg(long, default_value_t = 200)]
+        interval_ms: u64,
+    },
+
+    /// Input payload diagnostics
+    Input {
+        #[command(subcommand)]
+        action: InputAction,
+    },
+
+    /// Print the last rendered statusline without computing a new one
+    Last {
+        /// Output as JSON (text, ANSI form, session, model, directory, cost)
+        #[arg(long)]
+        json: bool,
+
+        /// Keep the color codes
+        #[arg(long, conflicts_with = "json")]
+        ansi: bool,
+    },
+
+    /// Show cost alerts that have not been acknowledged
+    #[cfg(feature = "sqlite")]
+    Alerts {
+        #[command(subcommand)]
+        action: Option<AlertsAction>,
+    },
+
+    /// Label sessions for later review
+    #[cfg(feature = "sqlite")]
+    Session {
+        #[command(subcommand)]
+        action: SessionAction,
+    },
+
+    /// Snapshot or restore the stats database and config
+    #[cfg(feature = "sqlite")]
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+}
+
+#[cfg(feature = "turso-sync")]
+#[derive(Subcommand)]
+enum SyncAction {
+    /// Show remote rows and storage used against the plan limits
+    Quota,
+
+    /// Store the sync auth token in the OS keyring (use with auth_token = "keyring")
+    Login {
+        /// Token to store (prompted for on stdin when omitted, keeping it out of shell history)
+        #[arg(long)]
+        token: Option<String>,
+    },
+
+    /// Push pending changes and update the backoff state (spawned by renders)
+    #[command(hide = true)]
+    Flush,
+}
+
+#[cfg(feature = "adaptive-learning")]
+#[derive(Subcommand)]
+enum LearningAction {
+    /// Export learned context windows as JSON
+    Export {
+        /// File to write (stdout when omitted)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import learned context windows from a JSON export
+    Import {
+        /// Export file to read ("-" for stdin)
+        path: PathBuf,
+
+        /// Clear all local learning data before importing
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Subcommand)]
+enum DbAction {
+    /// Show tables, indices, views and applied migrations
+    Schema {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Run a read-only SELECT against the stats database
+    Query {
+        /// SQL statement (SELECT or WITH ... SELECT only)
+        sql: String,
+
+        /// Output rows as JSON objects
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find daily/monthly aggregates that disagree with the sessions table
+    /// (e.g. sessions counted twice) and recompute them from it
+    Repair {
+        /// Only report the anomalies
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Subcommand)]
+enum AlertsAction {
+    /// Acknowledge all active alerts so they stop showing on the statusline
+    Ack,
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Subcommand)]
+enum SessionAction {
+    /// Set a short note for the session, e.g. `statusline session note "migrating auth"`
+    /// (prints the current note when TEXT is omitted)
+    Note {
+        /// Note text
+        text: Option<String>,
+
+        /// Session to label (defaults to the session of the last rendered line)
+        #[arg(long)]
+        session_id: Option<String>,
+
+        /// Remove the note
+        #[arg(long, conflicts_with = "text")]
+        clear: bool,
+    },
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Write a consistent snapshot of stats.db plus the config file
+    Create {
+        /// Backup file, or directory to put it in (default: <data dir>/backups/)
+        #[arg(long, value_name = "PATH")]
+        to: Option<PathBuf>,
+    },
+
+    /// Replace stats.db (and the config file) with a backup; the current files
+    /// are kept with a .pre-restore suffix
+    Restore {
+        /// Backup file written by `statusline backup create`
+        file: PathBuf,
+
+        /// Leave the current config file untouched
+        #[arg(long)]
+        skip_config: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum InputAction {
+    /// Show what was understood from a JSON payload piped on stdin
+    Inspect,
 }
 
 #[derive(Subcommand)]
@@ -200,9 +588,24 @@ enum HookAction {
         #[arg(long)]
         session_id: Option<String>,
     },
+
+    /// SessionEnd hook - called when a Claude session ends
+    SessionEnd {
+        /// Session ID from Claude (if not provided, reads from stdin JSON)
+        #[arg(long)]
+        session_id: Option<String>,
+    },
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error [{}]: {}", e.code(), e);
+        eprintln!("  hint: {}", e.hint());
+        std::process::exit(e.exit_code());
+    }
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
     let cli = Cli::parse();
 
     // Handle log level with precedence: CLI > env > default
@@ -213,13 +616,22 @@ fn main() -> Result<()> {
     }
 
     // Initialize logger with RUST_LOG env var (which may have been set above)
-    // Default to "warn" if RUST_LOG is not set
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
-
-    // Handle NO_COLOR with precedence: CLI > env
-    if cli.no_color {
-        env::set_var("NO_COLOR", "1");
-    }
+    // Default to "warn" if RUST_LOG is not set, or "off" when stderr carries the right prompt
+    let zsh_rprompt = cli.output == "zsh-rprompt";
+    let default_level = if zsh_rprompt { "off" } else { "warn" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_level))
+        .init();
+
+    // Handle colors with precedence: CLI > NO_COLOR > CLICOLOR_FORCE > terminal check.
+    // Only the statusline and its fixture preview skip the terminal check,
+    // since Claude Code reads them through a pipe
+    let color_choice = if cli.no_color {
+        color::ColorChoice::Never
+    } else {
+        color::ColorChoice::parse(&cli.color)
+    };
+    let check_tty = !matches!(cli.command, None | Some(Commands::Render { .. }));
+    color::init(color_choice, check_tty);
 
     // Handle theme with precedence: CLI > env > config
     if let Some(ref theme) = cli.theme {
@@ -229,9 +641,23 @@ fn main() -> Result<()> {
         env::set_var("STATUSLINE_THEME", theme);
     }
 
-    // Handle config path if provided
+    // Handle config path if provided: a directory replaces the whole config directory
     if let Some(ref config_path) = cli.config {
-        env::set_var("STATUSLINE_CONFIG_PATH", config_path.display().to_string());
+        if config_path.is_dir() {
+            env::set_var("STATUSLINE_CONFIG_DIR", config_path);
+        } else {
+            env::set_var("STATUSLINE_CONFIG_PATH", config_path.display().to_string());
+        }
+    }
+
+    // Handle variant if provided (CLI > STATUSLINE_VARIANT)
+    if let Some(ref variant) = cli.variant {
+        env::set_var("STATUSLINE_VARIANT", variant);
+    }
+
+    // Handle data directory if provided (CLI > STATUSLINE_DATA_DIR > XDG_DATA_HOME)
+    if let Some(ref data_dir) = cli.data_dir {
+        env::set_var("STATUSLINE_DATA_DIR", data_dir);
     }
 
     // Handle version-full flag
@@ -258,6 +684,7 @@ fn main() -> Result<()> {
                 println!("Edit {} to customize settings", config_path.display());
                 return Ok(());
             }
+            #[cfg(feature = "sqlite")]
             Commands::Migrate {
                 finalize,
                 delete_json,
@@ -274,128 +701,371 @@ fn main() -> Result<()> {
                     return show_migration_roadmap();
                 }
             }
+            #[cfg(feature = "sqlite")]
             Commands::DbMaintain {
                 force_vacuum,
                 no_prune,
                 quiet,
+                dry_run,
             } => {
+                if dry_run {
+                    return show_retention_preview();
+                }
                 return perform_database_maintenance(force_vacuum, no_prune, quiet);
             }
+            #[cfg(feature = "sqlite")]
+            Commands::Stats {
+                chart,
+                top_projects,
+                by_model,
+                by_user,
+                by_branch,
+                heatmap,
+                cost_split,
+                forecast,
+                metric,
+                period,
+            } => {
+                return handle_stats_command(
+                    StatsSections {
+                        chart,
+                        top_projects,
+                        by_model,
+                        by_user,
+                        by_branch,
+                        heatmap_metric: heatmap.then_some(metric.as_str()),
+                        cost_split,
+                        forecast,
+                    },
+                    &period,
+                );
+            }
+            #[cfg(feature = "sqlite")]
+            Commands::Report {
+                weekly: _,
+                monthly,
+                format: _,
+            } => {
+                return handle_report_command(monthly);
+            }
+            #[cfg(feature = "sqlite")]
+            Commands::Export {
+                format,
+                period,
+                email,
+            } => {
+                return handle_export_command(&format, &period, email.as_deref());
+            }
+            #[cfg(feature = "sqlite")]
+            Commands::Import { format: _, path } => {
+                return handle_import_command(&path);
+            }
             Commands::Health { json } => {
                 return show_health_report(json);
             }
+            #[cfg(feature = "sqlite")]
+            Commands::Db { action } => {
+                return handle_db_command(action);
+            }
+            #[cfg(feature = "sqlite")]
+            Commands::Rebuild {
+                from_transcripts,
+                dry_run,
+            } => {
+                return handle_rebuild_command(&from_transcripts, dry_run);
+            }
 
             #[cfg(feature = "turso-sync")]
             Commands::Sync {
+                action,
                 status,
                 push,
                 pull,
                 dry_run,
             } => {
-                return handle_sync_command(status, push, pull, dry_run);
+                return handle_sync_command(action, status, push, pull, dry_run);
             }
 
+            #[cfg(feature = "adaptive-learning")]
             Commands::ContextLearning {
+                action,
                 status,
                 reset,
                 details,
                 reset_all,
                 rebuild,
             } => {
+                if let Some(action) = action {
+                    return handle_learning_action(action);
+                }
                 return handle_context_learning_command(status, reset, details, reset_all, rebuild);
             }
 
             Commands::Hook { action } => {
                 return handle_hook_command(action);
             }
+
+            Commands::Install {
+                hooks,
+                settings,
+                dry_run,
+            } => {
+                return handle_install_command(hooks, settings, dry_run);
+            }
+
+            Commands::Render { fixture, theme } => {
+                return handle_render_command(fixture.as_deref(), theme.as_deref());
+            }
+
+            #[cfg(feature = "sqlite")]
+            Commands::Watch {
+                transcript,
+                session_id,
+                interval_ms,
+            } => {
+                return watch::run(
+                    &transcript,
+                    session_id.as_deref(),
+                    std::time::Duration::from_millis(interval_ms.max(50)),
+                );
+            }
+
+            Commands::Input { action } => {
+                return handle_input_command(action);
+            }
+
+            Commands::Last { json, ansi } => {
+                return handle_last_command(json, ansi);
+            }
+
+            #[cfg(feature = "sqlite")]
+            Commands::Alerts { action } => {
+                return handle_alerts_command(action);
+            }
+
+            #[cfg(feature = "sqlite")]
+            Commands::Session { action } => {
+                return handle_session_command(action);
+            }
+
+            #[cfg(feature = "sqlite")]
+            Commands::Backup { action } => {
+                return handle_backup_command(action);
+            }
         }
     }
 
-    // Read JSON from stdin
-    let mut buffer = String::new();
-    io::stdin().read_to_string(&mut buffer)?;
+    // Check for migration opportunity (warn once per run)
+    check_migration_status();
+
+    if cli.stream {
+        return stream_payloads(zsh_rprompt, cli.explain);
+    }
+
+    // Read JSON from stdin (invalid UTF-8 is left to the JSON parser to reject)
+    let mut buffer = Vec::new();
+    io::stdin().read_to_end(&mut buffer)?;
+    let outcome = render_payload(&String::from_utf8_lossy(&buffer), zsh_rprompt, cli.explain);
+
+    // Claude Code may hide the line of a failing command, so the render always
+    // succeeds unless a wrapper asks for the outcome
+    if cli.strict_exit {
+        io::stdout().flush()?;
+        outcome?;
+    }
+    Ok(())
+}
+
+/// Renders one statusline per newline-delimited JSON payload until stdin closes.
+///
+/// Each line is terminated with `\n` and flushed immediately, so a wrapper can
+/// keep one warm process and read a reply for every payload it writes. With
+/// `zsh_rprompt` the right-prompt string goes to stderr, one line per payload too.
+fn stream_payloads(zsh_rprompt: bool, explain: bool) -> Result<()> {
+    let mut stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        common::reset_degraded();
+        let _ = render_payload(&line, zsh_rprompt, explain);
+        println!();
+        stdout.flush()?;
+        if zsh_rprompt {
+            eprintln!();
+        }
+    }
+    Ok(())
+}
 
-    // Parse input
-    let input: StatuslineInput = match serde_json::from_str(&buffer) {
+/// Parses one JSON payload, updates stats and prints the statusline (without a newline).
+///
+/// With `zsh_rprompt` the line is escaped for a zsh prompt and the short
+/// right-prompt string is written to stderr. A line is always printed, with only
+/// the directory and a "no data" note when the payload can't be parsed. The
+/// result reports an unparseable payload or omitted segments for `--strict-exit`.
+/// With `explain` the derivation of the context and cost values follows on stderr.
+fn render_payload(buffer: &str, zsh_rprompt: bool, explain: bool) -> Result<()> {
+    telemetry::start_render();
+
+    // Parse input; an empty, truncated or non-JSON payload (e.g. during Claude Code
+    // startup) gets a minimal line instead of a blank or misleading one
+    let mut input: StatuslineInput = match serde_json::from_str(buffer) {
         Ok(input) => input,
         Err(e) => {
             // Log parse error to stderr (won't interfere with statusline output)
-            warn!("Failed to parse JSON input: {}. Using defaults.", e);
-            StatuslineInput::default()
+            let e = error::StatuslineError::from(e);
+            if buffer.trim().is_empty() {
+                warn!("No JSON input on stdin: {}", e.coded());
+            } else {
+                warn!("Failed to parse JSON input: {}", e.coded());
+            }
+            let line = display::format_no_data_line();
+            if zsh_rprompt {
+                print!("{}", display::zsh_prompt_escape(&line));
+            } else {
+                print!("{}", line);
+            }
+            return Err(e);
         }
     };
 
-    // Check for migration opportunity (warn once per run)
-    check_migration_status();
+    // Report schema drift (e.g. after a Claude Code update) when debugging
+    if log::log_enabled!(log::Level::Debug) {
+        if let Ok(raw) = serde_json::from_str::<serde_json::Value>(buffer) {
+            let diagnostics = models::diagnose_input(&raw);
+            if !diagnostics.missing.is_empty() {
+                log::debug!(
+                    "Input is missing expected fields: {}",
+                    diagnostics.missing.join(", ")
+                );
+            }
+            if !diagnostics.unknown.is_empty() {
+                log::debug!(
+                    "Input has unrecognized fields: {}",
+                    diagnostics.unknown.join(", ")
+                );
+            }
+        }
+    }
+    common::set_profile(input.profile.as_deref());
+    utils::set_reported_context(input.reported_context());
 
-    // Get current directory
+    // Working directory for git, the read-only marker and ignore checks
     let current_dir = input
         .workspace
         .as_ref()
-        .and_then(|w| w.current_dir.as_ref())
-        .cloned()
+        .and_then(|w| w.current_dir.clone())
         .unwrap_or_else(|| {
             env::current_dir()
                 .ok()
                 .and_then(|p| p.to_str().map(|s| s.to_string()))
                 .unwrap_or_else(|| "~".to_string())
         });
+    // Path shown in the directory segment (cwd or project root, per display.path_style)
+    let path_style = config::get_config().display.path_style.clone();
+    display::set_display_path(
+        input
+            .workspace
+            .as_ref()
+            .and_then(|w| w.display_dir(&path_style)),
+    );
 
     // Early exit for empty or home directory only
     if current_dir.is_empty() || current_dir == "~" {
-        print!("{}~{}", Colors::directory(), Colors::reset());
+        let line = format!("{}~{}", Colors::directory(), Colors::reset());
+        if zsh_rprompt {
+            print!("{}", display::zsh_prompt_escape(&line));
+        } else {
+            print!("{}", line);
+        }
+        return Ok(());
+    }
+
+    // Ignored directories (slow mounts): the path only, no git, transcript or database work
+    if let Some(line) =
+        display::format_ignored_dir_line(&current_dir, &display::display_path(&current_dir))
+    {
+        if zsh_rprompt {
+            print!("{}", display::zsh_prompt_escape(&line));
+        } else {
+            print!("{}", line);
+        }
         return Ok(());
     }
 
+    // Older or unusual Claude Code setups omit transcript_path; look it up by session
+    if input.transcript.is_none() {
+        if let Some(session_id) = input.session_id.as_deref() {
+            input.transcript =
+                transcripts::discover(session_id).map(|path| path.to_string_lossy().into_owned());
+        }
+    }
+
+    if let Some(model_name) = input.model.as_ref().and_then(|m| m.display_name.as_deref()) {
+        telemetry::set_attribute("statusline.model", model_name);
+    }
+
     // Update stats tracking if we have session and cost data
-    let (daily_total, _monthly_total) =
-        if let (Some(session_id), Some(ref cost)) = (&input.session_id, &input.cost) {
-            if let Some(total_cost) = cost.total_cost_usd {
-                // Extract model name and workspace directory
-                let model_name = input
-                    .model
-                    .as_ref()
-                    .and_then(|m| m.display_name.as_ref())
-                    .map(|s| s.as_str());
-                let workspace_dir = input
-                    .workspace
-                    .as_ref()
-                    .and_then(|w| w.current_dir.as_ref())
-                    .map(|s| s.as_str());
+    // A failing stats backend must not blank the statusline
+    let (daily_total, _monthly_total) = telemetry::timed("update_stats", || {
+        common::guarded("stats", (0.0, 0.0), || {
+            if let (Some(session_id), Some(ref cost)) = (&input.session_id, &input.cost) {
+                if let Some(total_cost) = cost.total_cost_usd {
+                    // Extract model name and workspace directory
+                    let model_name = input
+                        .model
+                        .as_ref()
+                        .and_then(|m| m.display_name.as_ref())
+                        .map(|s| s.as_str());
+                    let workspace_dir = input.workspace.as_ref().and_then(|w| w.project_root());
+
+                    // Token breakdown from the input, or the transcript if not reported
+                    let token_breakdown =
+                        utils::current_token_breakdown(input.transcript.as_deref());
+
+                    // Get device ID for audit trail
+                    let device_id = common::get_device_id();
+
+                    // Branch checked out in the working directory, for per-branch costs
+                    #[cfg(feature = "git")]
+                    let git_branch = input
+                        .workspace
+                        .as_ref()
+                        .and_then(|w| w.current_dir.as_deref())
+                        .and_then(git::current_branch);
+                    #[cfg(not(feature = "git"))]
+                    let git_branch = None;
+
+                    // Update stats with new cost data
+                    use stats::SessionUpdate;
+                    let result = update_stats_data(|data| {
+                        data.update_session(
+                            session_id,
+                            SessionUpdate {
+                                cost: total_cost,
+                                lines_added: cost.total_lines_added.unwrap_or(0),
+                                lines_removed: cost.total_lines_removed.unwrap_or(0),
+                                model_name: model_name.map(|s| s.to_string()),
+                                workspace_dir: workspace_dir.map(|s| s.to_string()),
+                                device_id: Some(device_id.clone()),
+                                token_breakdown,
+                                max_tokens_observed: None, // updated separately
+                                git_branch,
+                            },
+                        )
+                    });
 
-                // Extract token breakdown from transcript if available
-                let token_breakdown = input
-                    .transcript
-                    .as_ref()
-                    .and_then(|path| utils::get_token_breakdown_from_transcript(path));
-
-                // Get device ID for audit trail
-                let device_id = common::get_device_id();
-
-                // Update stats with new cost data
-                use database::SessionUpdate;
-                let result = update_stats_data(|data| {
-                    data.update_session(
-                        session_id,
-                        SessionUpdate {
-                            cost: total_cost,
-                            lines_added: cost.total_lines_added.unwrap_or(0),
-                            lines_removed: cost.total_lines_removed.unwrap_or(0),
-                            model_name: model_name.map(|s| s.to_string()),
-                            workspace_dir: workspace_dir.map(|s| s.to_string()),
-                            device_id: Some(device_id.clone()),
-                            token_breakdown,
-                            max_tokens_observed: None, // updated separately
-                        },
-                    )
-                });
-
-                // Track max_tokens_observed for compaction detection
-                // This runs regardless of adaptive_learning setting
-                if let Some(ref transcript_path) = input.transcript {
-                    if let Some(current_tokens) =
-                        utils::get_token_count_from_transcript(transcript_path)
-                    {
+                    // Track max_tokens_observed for compaction detection
+                    // This runs regardless of adaptive_learning setting
+                    let transcript_path = input.transcript.as_deref();
+
+                    // Active time, API call usage and edited files from the transcript
+                    if let Some(transcript) = transcript_path {
+                        stats::record_transcript_activity(session_id, transcript);
+                    }
+                    if let Some(current_tokens) = utils::current_context_tokens(transcript_path) {
                         // Update session's max_tokens_observed
                         // This updates both in-memory stats and SQLite database
                         update_stats_data(|data| {
@@ -414,12 +1084,30 @@ fn main() -> Result<()> {
                             (daily_total, monthly_total)
                         });
 
+                        // Record context usage history for the sparkline
+                        if config::get_config().display.show_context_sparkline {
+                            let model_name =
+                                input.model.as_ref().and_then(|m| m.display_name.as_deref());
+                            if let Some(context) = utils::calculate_current_context_usage(
+                                transcript_path,
+                                model_name,
+                                Some(session_id),
+                                None,
+                            )
+                            .filter(|context| !context.approximate)
+                            {
+                                stats::record_context_sample(session_id, context.percentage);
+                            }
+                        }
+
                         // Adaptive context learning: observe token usage if enabled
+                        #[cfg(feature = "adaptive-learning")]
                         if let Some(model_name) =
                             input.model.as_ref().and_then(|m| m.display_name.as_ref())
                         {
                             let config = config::get_config();
-                            if config.context.adaptive_learning {
+                            if config.context.adaptive_learning && stats::check_disk_space().is_ok()
+                            {
                                 // Get previous token count from session stats
                                 let stats_data = get_or_load_stats_data();
                                 let previous_tokens = stats_data
@@ -429,11 +1117,11 @@ fn main() -> Result<()> {
                                     .map(|t| t as usize);
 
                                 // Create context learner and observe usage
-                                use common::get_data_dir;
+                                use common::get_database_path;
                                 use context_learning::ContextLearner;
                                 use database::SqliteDatabase;
 
-                                let db_path = get_data_dir().join("stats.db");
+                                let db_path = get_database_path();
                                 if let Ok(db) = SqliteDatabase::new(&db_path) {
                                     let learner = ContextLearner::new(db);
                                     // Ignore errors from adaptive learning - it's experimental
@@ -442,7 +1130,7 @@ fn main() -> Result<()> {
                                         model_name,
                                         current_tokens as usize,
                                         previous_tokens,
-                                        Some(transcript_path),
+                                        transcript_path,
                                         workspace_dir,
                                         Some(&device_id),
                                     );
@@ -450,14 +1138,27 @@ fn main() -> Result<()> {
                             }
                         }
                     }
-                }
 
-                result
+                    result
+                } else {
+                    // Have session but no cost data - still load existing daily totals
+                    let data = get_or_load_stats_data();
+                    let today = common::current_date();
+                    let month = common::current_month();
+
+                    let daily_total = data.daily.get(&today).map(|d| d.total_cost).unwrap_or(0.0);
+                    let monthly_total = data
+                        .monthly
+                        .get(&month)
+                        .map(|m| m.total_cost)
+                        .unwrap_or(0.0);
+                    (daily_total, monthly_total)
+                }
             } else {
-                // Have session but no cost data - still load existing daily totals
+                // No session_id - still load stats data to show accumulated totals
                 let data = get_or_load_stats_data();
-                let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-                let month = chrono::Local::now().format("%Y-%m").to_string();
+                let today = common::current_date();
+                let month = common::current_month();
 
                 let daily_total = data.daily.get(&today).map(|d| d.total_cost).unwrap_or(0.0);
                 let monthly_total = data
@@ -467,40 +1168,100 @@ fn main() -> Result<()> {
                     .unwrap_or(0.0);
                 (daily_total, monthly_total)
             }
-        } else {
-            // No session_id - still load stats data to show accumulated totals
-            let data = get_or_load_stats_data();
-            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-            let month = chrono::Local::now().format("%Y-%m").to_string();
-
-            let daily_total = data.daily.get(&today).map(|d| d.total_cost).unwrap_or(0.0);
-            let monthly_total = data
-                .monthly
-                .get(&month)
-                .map(|m| m.total_cost)
-                .unwrap_or(0.0);
-            (daily_total, monthly_total)
-        };
+        })
+    });
+
+    // Fire cost alerts; they stay on the statusline until `statusline alerts ack`
+    if stats::alerts_enabled() {
+        let session_cost = input.cost.as_ref().and_then(|c| c.total_cost_usd);
+        common::guarded("alerts", (), || {
+            stats::check_cost_alerts(input.session_id.as_deref(), session_cost, daily_total)
+        });
+    }
 
-    // Format and print output
-    format_output(
-        &current_dir,
-        input.model.as_ref().and_then(|m| m.display_name.as_deref()),
-        input.transcript.as_deref(),
-        input.cost.as_ref(),
-        daily_total,
+    // Queue a background push of the updated stats (never blocks the render)
+    #[cfg(feature = "turso-sync")]
+    if input.session_id.is_some() && config::get_config().sync.enabled {
+        common::guarded("sync", (), sync::schedule_push);
+    }
+
+    // Format and print output, falling back to the bare directory if formatting fails
+    // (nothing is printed until the full line has been built)
+    let model_name = input.model.as_ref().and_then(|m| m.display_name.as_deref());
+    let line = telemetry::timed("format", || {
+        common::guarded("format", None, || {
+            Some(format_output_to_string(
+                &current_dir,
+                model_name,
+                input.transcript.as_deref(),
+                input.cost.as_ref(),
+                daily_total,
+                input.session_id.as_deref(),
+            ))
+        })
+    })
+    .unwrap_or_else(|| {
+        format!(
+            "{}{}{} {}!{}",
+            Colors::directory(),
+            utils::sanitize_for_terminal(&utils::shorten_path(&display::display_path(
+                &current_dir
+            ))),
+            Colors::reset(),
+            Colors::red(),
+            Colors::reset()
+        )
+    });
+    if zsh_rprompt {
+        // Both strings come from the same payload, so the two prompts always agree
+        let rprompt = display::format_rprompt_string(
+            model_name,
+            input.transcript.as_deref(),
+            input.cost.as_ref(),
+            input.session_id.as_deref(),
+        );
+        print!("{}", display::zsh_prompt_escape(&line));
+        eprint!("{}", display::zsh_prompt_escape(&rprompt));
+    } else {
+        print!("{}", line);
+    }
+    if explain {
+        let lines = explain::explain(
+            model_name,
+            input.transcript.as_deref(),
+            input.session_id.as_deref(),
+            input.cost.as_ref(),
+            daily_total,
+        );
+        eprintln!("\n{}", explain::render(&lines));
+    }
+    telemetry::export_render();
+
+    // Persist the line for window managers and bars (`statusline last`)
+    let last_render = last::LastRender::new(
+        &line,
         input.session_id.as_deref(),
+        model_name,
+        &current_dir,
+        input.cost.as_ref().and_then(|c| c.total_cost_usd),
     );
+    if let Err(e) = last::save(&last_render) {
+        log::debug!("Failed to save last render: {}", e.coded());
+    }
 
-    Ok(())
+    if common::is_degraded() {
+        Err(error::StatuslineError::PartialRender)
+    } else {
+        Ok(())
+    }
 }
 
 /// Check if migration is needed and warn the user
 fn check_migration_status() {
     let config = config::get_config();
 
-    // Only warn if json_backup is enabled
-    if config.database.json_backup {
+    // Only warn if json_backup is enabled (JSON is the only store without SQLite)
+    if cfg!(feature = "sqlite") && config.database.json_backup {
         let json_path = stats::StatsData::get_stats_file_path();
 
         // Check if JSON file exists
@@ -520,8 +1281,8 @@ fn check_migration_status() {
 }
 
 /// Show migration roadmap and current status
+#[cfg(feature = "sqlite")]
 fn show_migration_roadmap() -> Result<()> {
-    use crate::common::get_data_dir;
     use crate::config::Config;
 
     println!("═══════════════════════════════════════════════════════════════");
@@ -529,9 +1290,8 @@ fn show_migration_roadmap() -> Result<()> {
     println!("═══════════════════════════════════════════════════════════════\n");
 
     // Detect current state
-    let data_dir = get_data_dir();
-    let json_path = data_dir.join("stats.json");
-    let db_path = data_dir.join("stats.db");
+    let json_path = stats::StatsData::get_stats_file_path();
+    let db_path = crate::common::get_database_path();
 
     let config = Config::load().ok();
     let json_backup_enabled = config
@@ -623,8 +1383,9 @@ fn show_migration_roadmap() -> Result<()> {
 }
 
 /// Finalize the migration from JSON to SQLite-only mode
+#[cfg(feature = "sqlite")]
 fn run_schema_migrations() -> Result<()> {
-    use crate::common::get_data_dir;
+    use crate::common::get_database_path;
     use crate::display::Colors;
     use crate::migrations::MigrationRunner;
 
@@ -635,7 +1396,7 @@ fn run_schema_migrations() -> Result<()> {
     );
     println!();
 
-    let db_path = get_data_dir().join("stats.db");
+    let db_path = get_database_path();
     let mut runner =
         MigrationRunner::new(&db_path).map_err(crate::error::StatuslineError::Database)?;
 
@@ -675,6 +1436,7 @@ fn run_schema_migrations() -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "sqlite")]
 fn dump_database_schema() -> Result<()> {
     use crate::display::Colors;
 
@@ -714,7 +1476,7 @@ fn dump_database_schema() -> Result<()> {
     println!("-- Turso Database Schema Setup for Claudia Statusline");
     println!(
         "-- Auto-generated from migrations on {}",
-        chrono::Local::now().format("%Y-%m-%d")
+        environment::now_local().format("%Y-%m-%d")
     );
     println!("-- This script creates the necessary tables for cloud sync");
     println!();
@@ -734,8 +1496,8 @@ fn dump_database_schema() -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "sqlite")]
 fn finalize_migration(delete_json: bool) -> Result<()> {
-    use chrono::Utc;
     use std::fs;
 
     println!("🔄 Finalizing migration to SQLite-only mode...\n");
@@ -762,7 +1524,7 @@ fn finalize_migration(delete_json: bool) -> Result<()> {
 
     let json_data = if json_path.exists() {
         let contents = fs::read_to_string(&json_path)?;
-        serde_json::from_str::<stats::StatsData>(&contents).ok()
+        stats::StatsData::from_json(&contents).ok()
     } else {
         None
     };
@@ -800,7 +1562,7 @@ fn finalize_migration(delete_json: bool) -> Result<()> {
         println!("✅ JSON file deleted: {}", json_path.display());
     } else {
         // Archive with timestamp
-        let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
+        let timestamp = environment::current().now().format("%Y%m%d_%H%M%S");
         let archive_path = json_path.with_file_name(format!("stats.json.migrated.{}", timestamp));
         println!("\n📦 Archiving JSON file...");
         fs::rename(&json_path, &archive_path)?;
@@ -818,7 +1580,7 @@ fn finalize_migration(delete_json: bool) -> Result<()> {
 
     // Load existing config or create new one
     let mut config = if config_path.exists() {
-        config::Config::load_from_file(&config_path).unwrap_or_default()
+        config::Config::load_from_file_unexpanded(&config_path).unwrap_or_default()
     } else {
         config::Config::default()
     };
@@ -838,6 +1600,7 @@ fn finalize_migration(delete_json: bool) -> Result<()> {
 }
 
 /// Perform database maintenance operations
+#[cfg(feature = "sqlite")]
 fn perform_database_maintenance(force_vacuum: bool, no_prune: bool, quiet: bool) -> Result<()> {
     if !quiet {
         println!("🔧 Starting database maintenance...\n");
@@ -849,10 +1612,7 @@ fn perform_database_maintenance(force_vacuum: bool, no_prune: bool, quiet: bool)
         if !quiet {
             println!("❌ Database not found at: {}", db_path.display());
         }
-        return Err(error::StatuslineError::Io(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "Database file not found",
-        )));
+        return Err(error::StatuslineError::stats("Database file not found"));
     }
 
     // Get initial size
@@ -911,6 +1671,13 @@ fn perform_database_maintenance(force_vacuum: bool, no_prune: bool, quiet: bool)
                 "not needed".to_string()
             }
         );
+        println!(
+            "  ✅ Idle sessions: {}",
+            match maintenance_result.sessions_closed {
+                0 => "none to close".to_string(),
+                closed => format!("closed {}", closed),
+            }
+        );
         println!(
             "  ✅ Integrity check: {}",
             if maintenance_result.integrity_ok {
@@ -935,9 +1702,706 @@ fn perform_database_maintenance(force_vacuum: bool, no_prune: bool, quiet: bool)
     Ok(())
 }
 
+/// `db-maintain --dry-run`: what retention pruning would delete
+#[cfg(feature = "sqlite")]
+fn show_retention_preview() -> Result<()> {
+    let db_path = stats::StatsData::get_sqlite_path()?;
+    if !db_path.exists() {
+        println!("❌ Database not found at: {}", db_path.display());
+        return Err(error::StatuslineError::stats("Database file not found"));
+    }
+    let preview = database::preview_retention()?;
+    let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+
+    println!("🔍 Retention dry run (nothing is deleted)\n");
+
+    let cutoffs = &preview.cutoffs;
+    match &cutoffs.sessions {
+        Some(cutoff) => println!(
+            "Sessions last updated before {}: {}",
+            cutoff.replace('T', " "),
+            preview.sessions.len()
+        ),
+        None => println!("Sessions: kept forever (retention_days_sessions = 0)"),
+    }
+    for session in &preview.sessions {
+        println!(
+            "  {}  {}  ${:.2}",
+            session.session_id,
+            session.last_updated.replace('T', " "),
+            session.cost
+        );
+    }
+
+    let periods = [
+        (
+            "Daily stats",
+            "retention_days_daily",
+            &cutoffs.daily,
+            &preview.daily,
+        ),
+        (
+            "Monthly stats",
+            "retention_days_monthly",
+            &cutoffs.monthly,
+            &preview.monthly,
+        ),
+    ];
+    for (name, setting, cutoff, rows) in periods {
+        match cutoff {
+            Some(cutoff) => println!("{} before {}: {}", name, cutoff, rows.len()),
+            None => println!("{}: kept forever ({} = 0)", name, setting),
+        }
+        for row in rows {
+            if row.user.is_empty() {
+                println!("  {}  ${:.2}", row.period, row.cost);
+            } else {
+                println!("  {}  {}  ${:.2}", row.period, row.user, row.cost);
+            }
+        }
+    }
+
+    let listed = preview.sessions.len() + preview.daily.len() + preview.monthly.len();
+    println!(
+        "Branch costs, token usage, edited files, line samples, learning data and alert log: {}",
+        preview.total_rows.saturating_sub(listed)
+    );
+
+    println!("\n📋 Total: {} rows", preview.total_rows);
+    if preview.sessions_closed > 0 {
+        println!(
+            "💤 Idle sessions marked ended (session_idle_hours = {}): {}",
+            config::get_config().database.session_idle_hours,
+            preview.sessions_closed
+        );
+    }
+    println!(
+        "💾 Reclaimable: at least {:.2} MB after VACUUM ({:.2} MB already free)",
+        mb(preview.reclaimable_bytes),
+        mb(preview.free_bytes)
+    );
+    Ok(())
+}
+
+/// Optional sections of `statusline stats`, printed after the summary
+#[cfg(feature = "sqlite")]
+struct StatsSections<'a> {
+    chart: bool,
+    top_projects: Option<usize>,
+    by_model: bool,
+    by_user: bool,
+    by_branch: bool,
+    heatmap_metric: Option<&'a str>,
+    cost_split: bool,
+    forecast: bool,
+}
+
+/// Show usage statistics, optionally with terminal charts
+#[cfg(feature = "sqlite")]
+fn handle_stats_command(sections: StatsSections, period: &str) -> Result<()> {
+    use crate::common::{current_date, current_month};
+
+    let StatsSections {
+        chart,
+        top_projects,
+        by_model,
+        by_user,
+        by_branch,
+        heatmap_metric,
+        cost_split,
+        forecast,
+    } = sections;
+
+    let db_path = stats::StatsData::get_sqlite_path()?;
+    if !db_path.exists() {
+        println!(
+            "{}No statistics recorded yet{}",
+            Colors::yellow(),
+            Colors::reset()
+        );
+        return Ok(());
+    }
+    let db = database::SqliteDatabase::new(&db_path)?;
+
+    println!();
+    println!("{}📊 Usage Statistics{}", Colors::cyan(), Colors::reset());
+    println!("{}", "=".repeat(60));
+    println!();
+    println!(
+        "  {:<26} ${:.2}",
+        format!("Today ({}):", current_date()),
+        db.get_today_total()?
+    );
+    println!(
+        "  {:<26} ${:.2}",
+        format!("This month ({}):", current_month()),
+        db.get_month_total()?
+    );
+    let (all_time_total, all_time_sessions) = db.get_user_all_time_totals()?;
+    println!("  {:<26} ${:.2}", "All time:", all_time_total);
+    println!("  {:<26} {}", "Sessions (all time):", all_time_sessions);
+
+    let today = environment::now_local().date_naive();
+    for (label, start) in [
+        ("Tokens today:", reports::period_start("today", today)),
+        ("Tokens this month:", reports::period_start("month", today)),
+    ] {
+        let start = start.unwrap_or(today).format("%Y-%m-%d").to_string();
+        let tokens = db.get_token_totals(&start, &current_date())?;
+        println!(
+            "  {:<26} {} in · {} out · {} cache rd · {} cache wr",
+            label,
+            utils::format_token_count(tokens.input as usize),
+            utils::format_token_count(tokens.output as usize),
+            utils::format_token_count(tokens.cache_read as usize),
+            utils::format_token_count(tokens.cache_creation as usize)
+        );
+    }
+
+    for (label, start) in [
+        (
+            "Cache savings (7 days):",
+            reports::period_start("week", today),
+        ),
+        (
+            "Cache savings (month):",
+            reports::period_start("month", today),
+        ),
+    ] {
+        let since = start.map(|d| d.format("%Y-%m-%d").to_string());
+        let saved: f64 = db
+            .get_cache_read_tokens_by_model(since.as_deref())?
+            .iter()
+            .map(|(model, tokens)| pricing::cache_savings(model, *tokens))
+            .sum();
+        println!(
+            "  {:<26} {}~${:.2}{}",
+            label,
+            Colors::green(),
+            saved,
+            Colors::reset()
+        );
+    }
+    println!();
+
+    for period in reports::comparison_periods(today) {
+        let range = |(start, end): (chrono::NaiveDate, chrono::NaiveDate)| {
+            db.get_period_totals(
+                &start.format("%Y-%m-%d").to_string(),
+                &end.format("%Y-%m-%d").to_string(),
+            )
+        };
+        let current = range(period.current)?;
+        let previous = range(period.previous)?;
+
+        println!("{}{}{}", Colors::cyan(), period.label, Colors::reset());
+        println!("{}", "-".repeat(60));
+        let rows = [
+            (
+                "Cost",
+                format!("${:.2}", current.cost),
+                format!("${:.2}", previous.cost),
+                current.cost,
+                previous.cost,
+                false,
+            ),
+            (
+                "Sessions",
+                current.sessions.to_string(),
+                previous.sessions.to_string(),
+                current.sessions as f64,
+                previous.sessions as f64,
+                true,
+            ),
+            (
+                "Lines changed",
+                current.lines_changed.to_string(),
+                previous.lines_changed.to_string(),
+                current.lines_changed as f64,
+                previous.lines_changed as f64,
+                true,
+            ),
+            (
+                "Tokens",
+                utils::format_token_count(current.tokens as usize),
+                utils::format_token_count(previous.tokens as usize),
+                current.tokens as f64,
+                previous.tokens as f64,
+                true,
+            ),
+        ];
+        for (label, now, before, now_value, before_value, higher_is_better) in rows {
+            println!(
+                "  {:<14} {:>10}  (was {:>8})  {}",
+                label,
+                now,
+                before,
+                colored_change(now_value, before_value, higher_is_better)
+            );
+        }
+        println!();
+    }
+
+    if chart {
+        const CHART_DAYS: u32 = 30;
+        const CHART_WIDTH: usize = 40;
+
+        let start = (today - chrono::Duration::days(CHART_DAYS as i64 - 1))
+            .format("%Y-%m-%d")
+            .to_string();
+        let daily =
+            reports::fill_daily_series(&db.get_daily_costs_since(&start)?, today, CHART_DAYS);
+
+        println!(
+            "{}Daily cost (last {} days){}",
+            Colors::cyan(),
+            CHART_DAYS,
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        for line in reports::render_bar_chart(&daily, CHART_WIDTH) {
+            println!("  {}", line);
+        }
+        println!();
+
+        let hourly = reports::fill_hourly_series(&db.get_hourly_costs(&current_date())?);
+        println!(
+            "{}Today by hour (session start){}",
+            Colors::cyan(),
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        for line in reports::render_bar_chart(&hourly, CHART_WIDTH) {
+            println!("  {}", line);
+        }
+        println!();
+    }
+
+    if forecast {
+        let start = (today - chrono::Duration::days(reports::FORECAST_HISTORY_WEEKS * 7))
+            .format("%Y-%m-%d")
+            .to_string();
+        let forecast = reports::forecast_month(&db.get_daily_costs_since(&start)?, today);
+
+        println!(
+            "{}Month-end forecast ({}){}",
+            Colors::cyan(),
+            current_month(),
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        println!(
+            "  {:<26} ${:.2}  (day {} of {})",
+            "Spent so far:", forecast.spent, forecast.days_elapsed, forecast.days_in_month
+        );
+        println!(
+            "  {:<26} ${:.2}  (${:.2}/day)",
+            "Linear:",
+            forecast.linear,
+            forecast.spent / forecast.days_elapsed as f64
+        );
+        match forecast.seasonal {
+            Some(seasonal) => println!(
+                "  {:<26} ${:.2}  (weekday averages, last {} weeks)",
+                "Weekday-adjusted:",
+                seasonal,
+                reports::FORECAST_HISTORY_WEEKS
+            ),
+            None => println!(
+                "  {:<26} {}not enough history{}",
+                "Weekday-adjusted:",
+                Colors::light_gray(),
+                Colors::reset()
+            ),
+        }
+
+        let cost_config = &config::get_config().cost;
+        if cost_config.budget > 0.0 && cost_config.budget_period == "month" {
+            let projected = forecast.seasonal.unwrap_or(forecast.linear);
+            let verdict = if projected > cost_config.budget {
+                format!(
+                    "{}▲ over by ${:.2}{}",
+                    Colors::red(),
+                    projected - cost_config.budget,
+                    Colors::reset()
+                )
+            } else {
+                format!(
+                    "{}on track, ${:.2} to spare{}",
+                    Colors::green(),
+                    cost_config.budget - projected,
+                    Colors::reset()
+                )
+            };
+            println!(
+                "  {:<26} {}",
+                format!("Budget ${:.2}:", cost_config.budget),
+                verdict
+            );
+        }
+        println!();
+    }
+
+    let since = reports::period_start(period, today).map(|d| d.format("%Y-%m-%d").to_string());
+
+    if let Some(limit) = top_projects {
+        let projects = db.get_project_usage(since.as_deref(), limit)?;
+
+        println!(
+            "{}Top {} projects ({}){}",
+            Colors::cyan(),
+            limit,
+            reports::period_label(period),
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        if projects.is_empty() {
+            println!("  No sessions in this period");
+        }
+        for (rank, project) in projects.iter().enumerate() {
+            let name = if project.key.is_empty() {
+                "(unknown)".to_string()
+            } else {
+                utils::shorten_path(&project.key)
+            };
+            println!(
+                "  {:>2}. {:<32} {:>9}  {:>6} tokens  {:>4} sessions",
+                rank + 1,
+                name,
+                format!("${:.2}", project.cost),
+                utils::format_token_count(project.tokens as usize),
+                project.sessions
+            );
+        }
+        println!();
+    }
+
+    if by_model {
+        let models = reports::merge_model_usage(&db.get_model_usage(since.as_deref())?);
+        let total_sessions: u64 = models.iter().map(|m| m.sessions).sum();
+        let total_tokens: u64 = models.iter().map(|m| m.tokens).sum();
+        let total_cost: f64 = models.iter().map(|m| m.cost).sum();
+
+        println!(
+            "{}Usage by model ({}){}",
+            Colors::cyan(),
+            reports::period_label(period),
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        if models.is_empty() {
+            println!("  No sessions in this period");
+        } else {
+            println!(
+                "  {:<16} {:>9} {:>9} {:>9} {:>10}",
+                "Model", "Sessions", "Tokens", "Cost", "Total"
+            );
+        }
+        for model in &models {
+            println!(
+                "  {:<16} {:>8.1}% {:>8.1}% {:>8.1}% {:>10}",
+                model.key,
+                reports::share(model.sessions as f64, total_sessions as f64),
+                reports::share(model.tokens as f64, total_tokens as f64),
+                reports::share(model.cost, total_cost),
+                format!("${:.2}", model.cost)
+            );
+        }
+        println!();
+    }
+
+    if by_user {
+        let users = db.get_user_usage(since.as_deref())?;
+
+        println!(
+            "{}Usage by user ({}){}",
+            Colors::cyan(),
+            reports::period_label(period),
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        if users.is_empty() {
+            println!("  No sessions in this period");
+        } else {
+            println!(
+                "  {:<20} {:>9} {:>12} {:>10}",
+                "User", "Sessions", "Tokens", "Cost"
+            );
+        }
+        for user in &users {
+            let name = if user.key.is_empty() {
+                "(unknown)"
+            } else {
+                user.key.as_str()
+            };
+            println!(
+                "  {:<20} {:>9} {:>12} {:>10}",
+                name,
+                user.sessions,
+                user.tokens,
+                format!("${:.2}", user.cost)
+            );
+        }
+        println!();
+    }
+
+    if by_branch {
+        let branches = db.get_branch_usage(since.as_deref())?;
+
+        println!(
+            "{}Cost by branch ({}){}",
+            Colors::cyan(),
+            reports::period_label(period),
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        if branches.is_empty() {
+            println!("  No branch activity in this period");
+        }
+        // Repositories in order of their most expensive branch
+        let mut repos: Vec<&str> = Vec::new();
+        for branch in &branches {
+            if !repos.contains(&branch.workspace_dir.as_str()) {
+                repos.push(&branch.workspace_dir);
+            }
+        }
+        for repo in repos {
+            let name = if repo.is_empty() {
+                "(unknown)".to_string()
+            } else {
+                utils::shorten_path(repo)
+            };
+            println!("  {}", name);
+            for branch in branches.iter().filter(|b| b.workspace_dir == repo) {
+                println!(
+                    "    {:<30} {:>9}  {:>14}  last {}",
+                    branch.branch,
+                    format!("${:.2}", branch.cost),
+                    format!("+{} -{}", branch.lines_added, branch.lines_removed),
+                    branch.last_date
+                );
+            }
+        }
+        println!();
+    }
+
+    if let Some(metric) = heatmap_metric {
+        let rows: Vec<(u32, u32, f64)> = db
+            .get_weekday_hour_activity(since.as_deref())?
+            .into_iter()
+            .map(|(weekday, hour, sessions, cost)| {
+                let value = if metric == "cost" {
+                    cost
+                } else {
+                    sessions as f64
+                };
+                (weekday, hour, value)
+            })
+            .collect();
+
+        println!(
+            "{}Activity heatmap: {} by weekday and hour ({}){}",
+            Colors::cyan(),
+            metric,
+            reports::period_label(period),
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        for line in reports::render_heatmap(&reports::build_heatmap_grid(&rows)) {
+            println!("  {}", line);
+        }
+        println!();
+    }
+
+    if cost_split {
+        let days = reports::daily_cost_split(&db.get_daily_model_usage(since.as_deref())?);
+
+        println!(
+            "{}Cost by token type ({}){}",
+            Colors::cyan(),
+            reports::period_label(period),
+            Colors::reset()
+        );
+        println!("{}", "-".repeat(60));
+        if days.is_empty() {
+            println!("  No sessions in this period");
+        } else {
+            println!(
+                "  {:<10} {:>9} {:>8} {:>8} {:>10} {:>10}",
+                "Date", "Cost", "Input", "Output", "Cache wr", "Cache rd"
+            );
+        }
+        let mut total = pricing::CostSplit::default();
+        let mut total_cost = 0.0;
+        let print_row = |label: &str, cost: f64, split: &pricing::CostSplit| {
+            let pct = |part: f64| {
+                if split.total() > 0.0 {
+                    format!("{:.1}%", reports::share(part, split.total()))
+                } else {
+                    "-".to_string()
+                }
+            };
+            println!(
+                "  {:<10} {:>9} {:>8} {:>8} {:>10} {:>10}",
+                label,
+                format!("${:.2}", cost),
+                pct(split.input),
+                pct(split.output),
+                pct(split.cache_write),
+                pct(split.cache_read)
+            );
+        };
+        for day in &days {
+            print_row(&day.date, day.cost, &day.split);
+            total.add(&day.split);
+            total_cost += day.cost;
+        }
+        if days.len() > 1 {
+            print_row("Total", total_cost, &total);
+        }
+        if !days.is_empty() {
+            println!(
+                "  {}Shares estimated from API rates per model{}",
+                Colors::light_gray(),
+                Colors::reset()
+            );
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Print a markdown usage report for the last 7 days (or month to date)
+#[cfg(feature = "sqlite")]
+fn handle_report_command(monthly: bool) -> Result<()> {
+    const REPORT_LIMIT: usize = 5;
+
+    let db_path = stats::StatsData::get_sqlite_path()?;
+    if !db_path.exists() {
+        eprintln!("No statistics recorded yet");
+        return Ok(());
+    }
+    let db = database::SqliteDatabase::new(&db_path)?;
+
+    let today = environment::now_local().date_naive();
+    let (title, period) = {
+        let mut periods = reports::comparison_periods(today);
+        // comparison_periods returns [today, last 7 days, month to date]
+        if monthly {
+            ("Monthly usage report", periods.remove(2))
+        } else {
+            ("Weekly usage report", periods.remove(1))
+        }
+    };
+    let date = |d: chrono::NaiveDate| d.format("%Y-%m-%d").to_string();
+    let (start, end) = (date(period.current.0), date(period.current.1));
+
+    let report = reports::PeriodReport {
+        title: title.to_string(),
+        start: period.current.0,
+        end: period.current.1,
+        current: db.get_period_totals(&start, &end)?,
+        previous: db.get_period_totals(&date(period.previous.0), &date(period.previous.1))?,
+        projects: db.get_project_usage(Some(&start), REPORT_LIMIT)?,
+        models: reports::merge_model_usage(&db.get_model_usage(Some(&start))?),
+        sessions: db.get_top_sessions(&start, &end, REPORT_LIMIT)?,
+        duration_format: config::get_config().display.duration_format.clone(),
+    };
+
+    print!("{}", report.to_markdown());
+    Ok(())
+}
+
+/// Print usage history as ccusage-compatible JSON, or sessions as a
+/// timesheet / Toggl CSV
+#[cfg(feature = "sqlite")]
+fn handle_export_command(format: &str, period: &str, email: Option<&str>) -> Result<()> {
+    let db_path = stats::StatsData::get_sqlite_path()?;
+    let db = if db_path.exists() {
+        Some(database::SqliteDatabase::new(&db_path)?)
+    } else {
+        None
+    };
+    let today = environment::now_local().date_naive();
+    let since = reports::period_start(period, today).map(|d| d.format("%Y-%m-%d").to_string());
+
+    if format == "ccusage" {
+        let rows = match &db {
+            Some(db) => db.get_daily_model_usage(since.as_deref())?,
+            None => Vec::new(),
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&reports::to_ccusage_json(&rows))?
+        );
+        return Ok(());
+    }
+
+    let entries = match &db {
+        Some(db) => db.get_timesheet_entries(since.as_deref())?,
+        None => Vec::new(),
+    };
+    if format == "toggl" {
+        print!(
+            "{}",
+            reports::to_toggl_csv(&entries, email.unwrap_or_default())
+        );
+    } else {
+        print!("{}", reports::to_timesheet_csv(&entries));
+    }
+    Ok(())
+}
+
+/// Import `ccusage daily --json` output into the stats database
+#[cfg(feature = "sqlite")]
+fn handle_import_command(path: &std::path::Path) -> Result<()> {
+    let content = if path.as_os_str() == "-" {
+        let mut buffer = String::new();
+        io::stdin().read_to_string(&mut buffer)?;
+        buffer
+    } else {
+        std::fs::read_to_string(path)?
+    };
+    let rows = reports::from_ccusage_json(&serde_json::from_str(&content)?)?;
+
+    let db = database::SqliteDatabase::new(&stats::StatsData::get_sqlite_path()?)?;
+    let imported = db.import_daily_model_usage(&rows)?;
+    println!(
+        "{}✓ Imported {} of {} daily model rows{}",
+        Colors::green(),
+        imported,
+        rows.len(),
+        Colors::reset()
+    );
+    if imported < rows.len() {
+        println!("  Rows imported before were skipped");
+    }
+    Ok(())
+}
+
+/// Format a period-over-period change, green when it moves in the good direction
+///
+/// Cost increases are shown in red; for activity metrics (sessions, lines,
+/// tokens) increases are shown in green.
+#[cfg(feature = "sqlite")]
+fn colored_change(current: f64, previous: f64, higher_is_better: bool) -> String {
+    let change = reports::format_change(current, previous);
+    let color = match reports::percent_change(current, previous) {
+        Some(pct) if pct.abs() > 0.05 => {
+            if (pct > 0.0) == higher_is_better {
+                Colors::green()
+            } else {
+                Colors::red()
+            }
+        }
+        _ => Colors::gray(),
+    };
+    format!("{}{}{}", color, change, Colors::reset())
+}
+
 /// Show diagnostic health information
 fn show_health_report(json_output: bool) -> Result<()> {
-    use rusqlite::{Connection, OpenFlags};
     use serde_json::json;
 
     // Get paths
@@ -948,6 +2412,169 @@ fn show_health_report(json_output: bool) -> Result<()> {
     // Check if files exist
     let db_exists = db_path.exists();
     let json_exists = json_path.exists();
+    let (db_size, wal_size) = common::database_file_sizes(&db_path);
+
+    // Before reading totals, which may back up and replace a corrupted stats file
+    let problems = health_problems(&db_path, &json_path);
+    let (today_total, month_total, all_time_total, session_count, earliest_session) =
+        health_totals(&db_path);
+
+    if json_output {
+        // Output as JSON
+        let problems: Vec<_> = problems
+            .iter()
+            .map(|(component, e)| {
+                json!({
+                    "component": component,
+                    "code": e.code(),
+                    "message": e.to_string().trim_end(),
+                    "hint": e.hint(),
+                })
+            })
+            .collect();
+        let health = json!({
+            "database_path": db_path.display().to_string(),
+            "database_exists": db_exists,
+            "database_size_bytes": db_size,
+            "wal_size_bytes": wal_size,
+            "max_size_mb": config.database.max_size_mb,
+            "json_path": json_path.display().to_string(),
+            "json_exists": json_exists,
+            "json_backup": config.database.json_backup,
+            "today_total": today_total,
+            "month_total": month_total,
+            "all_time_total": all_time_total,
+            "session_count": session_count,
+            "earliest_session": earliest_session,
+            "update_check": config.update.check,
+            "latest_version": update::latest_version(),
+            "update_available": update::newer_version(),
+            "update_last_checked": update::last_checked().map(|t| t.to_rfc3339()),
+            "problems": problems,
+        });
+        println!("{}", serde_json::to_string(&health)?);
+    } else {
+        // Output as human-readable text
+        println!("Claudia Statusline Health Report");
+        println!("================================");
+        println!();
+        println!("Configuration:");
+        println!("  Database path: {}", db_path.display());
+        println!("  Database exists: {}", if db_exists { "✅" } else { "❌" });
+        if db_exists {
+            let mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+            let limit = match config.database.max_size_mb {
+                0 => "no limit".to_string(),
+                max => format!("limit {} MB", max),
+            };
+            println!(
+                "  Database size: {:.1} MB + {:.1} MB WAL ({})",
+                mb(db_size),
+                mb(wal_size),
+                limit
+            );
+        }
+        println!("  JSON path: {}", json_path.display());
+        println!("  JSON exists: {}", if json_exists { "✅" } else { "❌" });
+        println!(
+            "  JSON backup enabled: {}",
+            if config.database.json_backup {
+                "✅"
+            } else {
+                "❌"
+            }
+        );
+        println!();
+        println!("Statistics:");
+        println!("  Today's total: ${:.2}", today_total);
+        println!("  Month total: ${:.2}", month_total);
+        println!("  All-time total: ${:.2}", all_time_total);
+        println!("  Session count: {}", session_count);
+        if let Some(earliest) = earliest_session {
+            println!("  Earliest session: {}", earliest);
+        } else {
+            println!("  Earliest session: N/A");
+        }
+        println!();
+        println!("Problems:");
+        if problems.is_empty() {
+            println!("  None");
+        }
+        for (component, e) in &problems {
+            println!(
+                "  ❌ {}: {} [{}]",
+                component,
+                e.to_string().trim_end(),
+                e.code()
+            );
+            println!("     hint: {}", e.hint());
+        }
+        println!();
+        println!("Updates:");
+        if config.update.check {
+            match update::newer_version() {
+                Some(latest) => println!(
+                    "  ⬆ v{} available (running v{})",
+                    latest,
+                    version::VersionInfo::current().version
+                ),
+                None => match update::latest_version() {
+                    Some(latest) => println!("  Up to date (latest release v{})", latest),
+                    None => println!("  No release information yet"),
+                },
+            }
+            match update::last_checked() {
+                Some(checked) => println!(
+                    "  Last checked: {}",
+                    checked
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                ),
+                None => println!("  Last checked: never"),
+            }
+        } else {
+            println!("  Update check: disabled (set update.check = true to enable)");
+        }
+    }
+
+    Ok(())
+}
+
+/// Errors hit while loading the config, stats database and stats file, by component.
+fn health_problems(
+    db_path: &std::path::Path,
+    json_path: &std::path::Path,
+) -> Vec<(&'static str, error::StatuslineError)> {
+    let mut problems = Vec::new();
+    if let Err(e) = config::Config::load() {
+        problems.push(("config", e));
+    }
+    if let Err(e) = stats::check_disk_space() {
+        problems.push(("disk", e));
+    }
+    #[cfg(feature = "sqlite")]
+    if db_path.exists() {
+        if let Err(e) = database::SqliteDatabase::new(db_path) {
+            problems.push(("database", e.into()));
+        }
+    }
+    #[cfg(not(feature = "sqlite"))]
+    let _ = db_path;
+    if json_path.exists() {
+        let parsed = std::fs::read_to_string(json_path)
+            .map_err(error::StatuslineError::from)
+            .and_then(|contents| stats::StatsData::from_json(&contents));
+        if let Err(e) = parsed {
+            problems.push(("stats file", e));
+        }
+    }
+    problems
+}
+
+/// Today, month and all-time totals, session count and earliest session for `health`.
+#[cfg(feature = "sqlite")]
+fn health_totals(db_path: &std::path::Path) -> (f64, f64, f64, usize, Option<String>) {
+    use rusqlite::{Connection, OpenFlags};
 
     // Get stats from database using aggregate helpers
     let mut today_total = 0.0;
@@ -956,9 +2583,9 @@ fn show_health_report(json_output: bool) -> Result<()> {
     let mut session_count = 0;
     let mut earliest_session: Option<String> = None;
 
-    if db_exists {
+    if db_path.exists() {
         // Prefer normal DB API first; fall back to read-only if environment is read-only (e.g., CI sandbox)
-        match database::SqliteDatabase::new(&db_path) {
+        match database::SqliteDatabase::new(db_path) {
             Ok(db) => {
                 today_total = db.get_today_total().unwrap_or(0.0);
                 month_total = db.get_month_total().unwrap_or(0.0);
@@ -969,8 +2596,9 @@ fn show_health_report(json_output: bool) -> Result<()> {
             Err(_) => {
                 // Read-only fallback: open without attempting schema creation/WAL
                 if let Ok(conn) =
-                    Connection::open_with_flags(&db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                    Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
                 {
+                    let _ = database::apply_encryption_key(&conn);
                     // Today total
                     let _ = conn
                         .query_row(
@@ -1009,64 +2637,61 @@ fn show_health_report(json_output: bool) -> Result<()> {
         }
     }
 
-    if json_output {
-        // Output as JSON
-        let health = json!({
-            "database_path": db_path.display().to_string(),
-            "database_exists": db_exists,
-            "json_path": json_path.display().to_string(),
-            "json_exists": json_exists,
-            "json_backup": config.database.json_backup,
-            "today_total": today_total,
-            "month_total": month_total,
-            "all_time_total": all_time_total,
-            "session_count": session_count,
-            "earliest_session": earliest_session,
-        });
-        println!("{}", serde_json::to_string(&health)?);
-    } else {
-        // Output as human-readable text
-        println!("Claudia Statusline Health Report");
-        println!("================================");
-        println!();
-        println!("Configuration:");
-        println!("  Database path: {}", db_path.display());
-        println!("  Database exists: {}", if db_exists { "✅" } else { "❌" });
-        println!("  JSON path: {}", json_path.display());
-        println!("  JSON exists: {}", if json_exists { "✅" } else { "❌" });
-        println!(
-            "  JSON backup enabled: {}",
-            if config.database.json_backup {
-                "✅"
-            } else {
-                "❌"
-            }
-        );
-        println!();
-        println!("Statistics:");
-        println!("  Today's total: ${:.2}", today_total);
-        println!("  Month total: ${:.2}", month_total);
-        println!("  All-time total: ${:.2}", all_time_total);
-        println!("  Session count: {}", session_count);
-        if let Some(earliest) = earliest_session {
-            println!("  Earliest session: {}", earliest);
-        } else {
-            println!("  Earliest session: N/A");
-        }
-    }
+    (
+        today_total,
+        month_total,
+        all_time_total,
+        session_count,
+        earliest_session,
+    )
+}
 
-    Ok(())
+/// Without the SQLite backend the totals come from stats.json.
+#[cfg(not(feature = "sqlite"))]
+fn health_totals(_db_path: &std::path::Path) -> (f64, f64, f64, usize, Option<String>) {
+    let data = get_or_load_stats_data();
+    let month_total = data
+        .monthly
+        .get(&common::current_month())
+        .map(|m| m.total_cost)
+        .unwrap_or(0.0);
+    (
+        stats::get_daily_total(&data),
+        month_total,
+        data.all_time.total_cost,
+        data.all_time.sessions,
+        Some(data.all_time.since),
+    )
 }
 
 /// Handle sync commands (status, push, pull)
 #[cfg(feature = "turso-sync")]
-fn handle_sync_command(status: bool, push: bool, pull: bool, dry_run: bool) -> Result<()> {
+fn handle_sync_command(
+    action: Option<SyncAction>,
+    status: bool,
+    push: bool,
+    pull: bool,
+    dry_run: bool,
+) -> Result<()> {
     use crate::config::Config;
 
     // Load configuration
     let config = Config::load()?;
     let mut sync_manager = crate::sync::SyncManager::new(config.sync.clone());
 
+    match action {
+        Some(SyncAction::Quota) => {
+            return show_sync_quota(&mut sync_manager, config.sync.soft_quota_fraction);
+        }
+        Some(SyncAction::Login { token }) => {
+            return handle_sync_login(&sync_manager, token);
+        }
+        Some(SyncAction::Flush) => {
+            return crate::sync::flush(&mut sync_manager).map(|_| ());
+        }
+        None => {}
+    }
+
     // Determine which action to take
     if status || (!push && !pull) {
         // Show status (default if no flags specified)
@@ -1095,7 +2720,12 @@ fn handle_sync_push(sync_manager: &mut crate::sync::SyncManager, dry_run: bool)
     }
     println!();
 
-    match sync_manager.push(dry_run) {
+    let result = if dry_run {
+        sync_manager.push(true)
+    } else {
+        crate::sync::flush(sync_manager)
+    };
+    match result {
         Ok(result) => {
             println!("{}✅ Push completed{}", Colors::green(), Colors::reset());
             println!();
@@ -1163,9 +2793,101 @@ fn handle_sync_pull(sync_manager: &mut crate::sync::SyncManager, dry_run: bool)
     Ok(())
 }
 
+/// Store the sync auth token in the OS keyring
+#[cfg(feature = "turso-sync")]
+fn handle_sync_login(sync_manager: &crate::sync::SyncManager, token: Option<String>) -> Result<()> {
+    use std::io::Write;
+
+    let token = match token {
+        Some(token) => token,
+        None => {
+            eprint!("Auth token: ");
+            io::stderr().flush()?;
+            let mut line = String::new();
+            io::stdin().read_line(&mut line)?;
+            line
+        }
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(error::StatuslineError::Sync("No token given".to_string()));
+    }
+
+    let account = sync_manager.keyring_account();
+    keyring::store_secret(&account, token)
+        .map_err(|e| error::StatuslineError::Sync(format!("Failed to store token: {}", e)))?;
+
+    println!(
+        "{}✅ Token stored in the OS keyring ({}/{}){}",
+        Colors::green(),
+        keyring::SERVICE,
+        account,
+        Colors::reset()
+    );
+    println!();
+    println!("Reference it from config.toml:");
+    println!("  [sync.turso]");
+    println!("  auth_token = \"keyring\"");
+    Ok(())
+}
+
+/// Show remote usage against plan limits
+#[cfg(feature = "turso-sync")]
+fn show_sync_quota(
+    sync_manager: &mut crate::sync::SyncManager,
+    soft_quota_fraction: f64,
+) -> Result<()> {
+    fn percent(fraction: Option<f64>) -> String {
+        fraction.map_or_else(String::new, |f| format!(" ({:.1}%)", f * 100.0))
+    }
+
+    let usage = sync_manager.quota()?;
+
+    println!("{}Sync Quota{}", Colors::cyan(), Colors::reset());
+    println!("==========");
+    println!();
+    if usage.row_limit > 0 {
+        println!(
+            "  Rows: {} / {}{}",
+            usage.rows,
+            usage.row_limit,
+            percent(usage.row_fraction())
+        );
+    } else {
+        println!("  Rows: {} (no plan limit)", usage.rows);
+    }
+    match usage.storage_bytes {
+        Some(bytes) => println!(
+            "  Storage: {:.2} MB / {} MB{}",
+            bytes as f64 / (1024.0 * 1024.0),
+            usage.storage_limit_bytes / (1024 * 1024),
+            percent(usage.storage_fraction())
+        ),
+        None => println!("  Storage: unknown (server does not report page counts)"),
+    }
+    println!("  Warning threshold: {:.0}%", soft_quota_fraction * 100.0);
+    print_quota_warning(&usage, soft_quota_fraction);
+
+    Ok(())
+}
+
+/// Warn when remote usage is above the soft quota fraction
+#[cfg(feature = "turso-sync")]
+fn print_quota_warning(usage: &crate::sync::QuotaUsage, soft_quota_fraction: f64) {
+    if usage.exceeds(soft_quota_fraction) {
+        println!();
+        println!(
+            "{}⚠️  Remote usage is above {:.0}% of the plan limit. Prune old data or upgrade the plan.{}",
+            Colors::yellow(),
+            soft_quota_fraction * 100.0,
+            Colors::reset()
+        );
+    }
+}
+
 /// Show sync status and configuration
 #[cfg(feature = "turso-sync")]
-fn show_sync_status(_sync_manager: &crate::sync::SyncManager) -> Result<()> {
+fn show_sync_status(sync_manager: &crate::sync::SyncManager) -> Result<()> {
     use crate::config::Config;
 
     let config = Config::load()?;
@@ -1187,6 +2909,39 @@ fn show_sync_status(_sync_manager: &crate::sync::SyncManager) -> Result<()> {
     );
     println!();
 
+    let state = crate::sync::SyncState::load();
+    if config.sync.enabled {
+        println!("Background push:");
+        println!(
+            "  Pending changes: {}",
+            if state.pending { "yes" } else { "no" }
+        );
+        if let Some(last) = state
+            .last_success
+            .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+        {
+            println!(
+                "  Last success: {}",
+                last.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        if state.failures > 0 {
+            let wait = state.next_attempt - environment::current().now().timestamp();
+            println!(
+                "  {}Failed {} time(s), next attempt in {}s{}",
+                Colors::yellow(),
+                state.failures,
+                wait.max(0),
+                Colors::reset()
+            );
+            if let Some(err) = &state.last_error {
+                println!("  Last error: {}", err);
+            }
+        }
+        println!();
+    }
+
     if config.sync.enabled {
         println!("Turso Configuration:");
         if !config.sync.turso.database_url.is_empty() {
@@ -1200,7 +2955,20 @@ fn show_sync_status(_sync_manager: &crate::sync::SyncManager) -> Result<()> {
         }
 
         if !config.sync.turso.auth_token.is_empty() {
-            if config.sync.turso.auth_token.starts_with('$') {
+            if config.sync.turso.auth_token == "keyring" {
+                let account = sync_manager.keyring_account();
+                let found = keyring::get_secret(&account).is_some();
+                println!(
+                    "  Auth token: OS keyring ({}/{}) {}",
+                    keyring::SERVICE,
+                    account,
+                    if found {
+                        "✅"
+                    } else {
+                        "❌ missing, run `statusline sync login`"
+                    }
+                );
+            } else if config.sync.turso.auth_token.starts_with('$') {
                 println!("  Auth token: {} (env var)", config.sync.turso.auth_token);
             } else {
                 println!("  Auth token: *** (configured)");
@@ -1228,6 +2996,9 @@ fn show_sync_status(_sync_manager: &crate::sync::SyncManager) -> Result<()> {
                         Colors::green(),
                         Colors::reset()
                     );
+                    if let Ok(usage) = temp_manager.quota() {
+                        print_quota_warning(&usage, config.sync.soft_quota_fraction);
+                    }
                 } else {
                     println!(
                         "  Connection: {}❌ Not connected{}",
@@ -1261,6 +3032,7 @@ fn show_sync_status(_sync_manager: &crate::sync::SyncManager) -> Result<()> {
 }
 
 /// Handle context learning command
+#[cfg(feature = "adaptive-learning")]
 fn handle_context_learning_command(
     status: bool,
     reset: Option<String>,
@@ -1268,13 +3040,13 @@ fn handle_context_learning_command(
     reset_all: bool,
     rebuild: bool,
 ) -> Result<()> {
-    use crate::common::get_data_dir;
+    use crate::common::get_database_path;
     use crate::context_learning::ContextLearner;
     use crate::database::SqliteDatabase;
     use crate::display::Colors;
 
     // Create context learner
-    let db_path = get_data_dir().join("stats.db");
+    let db_path = get_database_path();
     let db = SqliteDatabase::new(&db_path)?;
     let learner = ContextLearner::new(db);
 
@@ -1489,6 +3261,7 @@ fn handle_context_learning_command(
             "  Confidence threshold: {:.1}%",
             config.context.learning_confidence_threshold * 100.0
         );
+        println!("  Strategy: {}", config.context.learning_strategy);
         println!();
         println!(
             "{}Use --details <model> to see audit trail (workspace/device){}",
@@ -1520,7 +3293,112 @@ fn handle_context_learning_command(
     println!("  statusline context-learning --reset-all");
     println!("    Reset all learning data");
     println!();
+    println!("  statusline learning export [--output <file>]");
+    println!("    Export learned windows as JSON");
+    println!();
+    println!("  statusline learning import <file> [--replace]");
+    println!("    Import learned windows from an export");
+    println!();
+
+    Ok(())
+}
+
+/// Handle `learning export` / `learning import`
+#[cfg(feature = "adaptive-learning")]
+fn handle_learning_action(action: LearningAction) -> Result<()> {
+    use crate::common::get_database_path;
+    use crate::context_learning::{ContextLearner, LearningExport};
+    use crate::database::SqliteDatabase;
+    use crate::display::Colors;
+
+    let db = SqliteDatabase::new(&get_database_path())?;
+    let learner = ContextLearner::new(db);
+
+    match action {
+        LearningAction::Export { output } => {
+            let export = learner.export()?;
+            let json = serde_json::to_string_pretty(&export)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, format!("{}\n", json))?;
+                    eprintln!(
+                        "{}✓ Exported {} learned context windows to {}{}",
+                        Colors::green(),
+                        export.windows.len(),
+                        path.display(),
+                        Colors::reset()
+                    );
+                }
+                None => println!("{}", json),
+            }
+        }
+        LearningAction::Import { path, replace } => {
+            let content = if path.as_os_str() == "-" {
+                let mut buffer = String::new();
+                io::stdin().read_to_string(&mut buffer)?;
+                buffer
+            } else {
+                std::fs::read_to_string(&path)?
+            };
+            let export: LearningExport = serde_json::from_str(&content)?;
+            let imported = learner.import(&export, replace)?;
+            println!(
+                "{}✓ Imported {} learned context windows{}{}",
+                Colors::green(),
+                imported,
+                if replace {
+                    " (replaced local data)"
+                } else {
+                    ""
+                },
+                Colors::reset()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Handle `statusline install`
+fn handle_install_command(hooks: bool, settings: Option<PathBuf>, dry_run: bool) -> Result<()> {
+    use crate::display::Colors;
+
+    if !hooks {
+        return Err(error::StatuslineError::other(
+            "Nothing to install: pass --hooks (use scripts/install-statusline.sh to install the binary)",
+        ));
+    }
+
+    let path = match settings {
+        Some(path) => path,
+        None => install::settings_path(&common::claude_config_dir().ok_or_else(|| {
+            error::StatuslineError::other("Cannot locate the Claude Code config directory")
+        })?),
+    };
+    let added = install::install_hooks(&path, &install::statusline_command(), dry_run)?;
 
+    if added.is_empty() {
+        println!(
+            "{}✓ Hooks already installed in {}{}",
+            Colors::green(),
+            path.display(),
+            Colors::reset()
+        );
+    } else if dry_run {
+        println!(
+            "Would add hooks to {}: {}",
+            path.display(),
+            added.join(", ")
+        );
+    } else {
+        println!(
+            "{}✓ Added hooks to {}: {}{}",
+            Colors::green(),
+            path.display(),
+            added.join(", "),
+            Colors::reset()
+        );
+        println!("  Restart Claude Code to pick up the new hooks");
+    }
     Ok(())
 }
 
@@ -1553,10 +3431,623 @@ fn handle_hook_command(action: HookAction) -> Result<()> {
             hook_handler::handle_stop(&sid)?;
             println!("Stop hook processed for session: {}", sid);
         }
+        HookAction::SessionEnd { session_id } => {
+            let sid = if let Some(s) = session_id {
+                s
+            } else {
+                let (s, _) = read_hook_json_from_stdin()?;
+                s
+            };
+
+            hook_handler::handle_session_end(&sid)?;
+            println!("SessionEnd hook processed for session: {}", sid);
+        }
+    }
+    Ok(())
+}
+
+/// Render one or all fixtures with the current config, using a throwaway data dir
+fn handle_render_command(fixture: Option<&str>, theme: Option<&str>) -> Result<()> {
+    use std::fs;
+
+    let selected: Vec<&fixtures::Fixture> = match fixture {
+        Some(name) => vec![fixtures::find(name)
+            .ok_or_else(|| error::StatuslineError::other(format!("Unknown fixture: {}", name)))?],
+        None => fixtures::FIXTURES.iter().collect(),
+    };
+
+    // Same override as the global --theme, but a theme that fails to load is an
+    // error here rather than a silent fallback to the default
+    if let Some(theme) = theme {
+        #[cfg(feature = "themes")]
+        theme::get_theme_manager()
+            .load_theme(theme)
+            .map_err(error::StatuslineError::input)?;
+        env::set_var("CLAUDE_THEME", theme);
+        env::set_var("STATUSLINE_THEME", theme);
+    }
+
+    // Fixtures must never read or write real stats, state, caches or runtime files.
+    // The guard removes the sandbox on every exit, including early `?` returns
+    struct Sandbox(std::path::PathBuf);
+    impl Drop for Sandbox {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    let guard =
+        Sandbox(env::temp_dir().join(format!("statusline-fixtures-{}", std::process::id())));
+    let sandbox = &guard.0;
+    for (var, dir) in [
+        ("STATUSLINE_DATA_DIR", "data"),
+        ("XDG_STATE_HOME", "state"),
+        ("XDG_CACHE_HOME", "cache"),
+        ("XDG_RUNTIME_DIR", "run"),
+    ] {
+        let dir = sandbox.join(dir);
+        fs::create_dir_all(&dir)?;
+        env::set_var(var, dir);
+    }
+
+    let current_dir = dirs::home_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("projects")
+        .join("demo-app");
+    let current_dir = current_dir.to_string_lossy();
+
+    for fixture in &selected {
+        log::debug!(
+            "Rendering fixture {}: {}",
+            fixture.name,
+            fixture.description
+        );
+        let session_id = fixture.session_id();
+        let transcript = sandbox.join(format!("{}.jsonl", session_id));
+        fs::write(&transcript, fixture.transcript())?;
+        let transcript = transcript.to_string_lossy();
+
+        if fixture.compacting {
+            state::write_state(&state::HookState {
+                state: "compacting".to_string(),
+                trigger: "auto".to_string(),
+                session_id: session_id.clone(),
+                started_at: environment::current().now(),
+                pid: Some(std::process::id()),
+            })?;
+        }
+
+        let input: StatuslineInput =
+            serde_json::from_value(fixture.payload(&transcript, &current_dir))?;
+        utils::set_reported_context(input.reported_context());
+        let line = display::format_output_to_string(
+            &current_dir,
+            input.model.as_ref().and_then(|m| m.display_name.as_deref()),
+            input.transcript.as_deref(),
+            input.cost.as_ref(),
+            fixture.daily_total,
+            input.session_id.as_deref(),
+        );
+
+        if fixture.compacting {
+            let _ = state::clear_state(&session_id);
+        }
+
+        if selected.len() > 1 {
+            println!(
+                "{}{:<17}{} {}",
+                Colors::light_gray(),
+                fixture.name,
+                Colors::reset(),
+                line
+            );
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    if common::is_degraded() {
+        return Err(error::StatuslineError::PartialRender);
+    }
+    Ok(())
+}
+
+/// List active cost alerts, or acknowledge them
+#[cfg(feature = "sqlite")]
+fn handle_alerts_command(action: Option<AlertsAction>) -> Result<()> {
+    let db = database::SqliteDatabase::new(&common::get_database_path())?;
+    match action {
+        Some(AlertsAction::Ack) => {
+            let acknowledged = db.acknowledge_alerts()?;
+            println!("Acknowledged {} alert(s)", acknowledged);
+        }
+        None => {
+            let alerts = db.get_active_alerts()?;
+            if alerts.is_empty() {
+                println!("No active alerts");
+            }
+            for alert in alerts {
+                println!(
+                    "{} {}: ${:.2} exceeds ${:.2} (fired {})",
+                    alert.kind, alert.scope, alert.value, alert.threshold, alert.fired_at
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Longest accepted session note, in characters
+#[cfg(feature = "sqlite")]
+const MAX_NOTE_CHARS: usize = 60;
+
+/// Set, show or clear a session's note
+#[cfg(feature = "sqlite")]
+fn handle_session_command(action: SessionAction) -> Result<()> {
+    match action {
+        SessionAction::Note {
+            text,
+            session_id,
+            clear,
+        } => {
+            // The session of the last render is the one being worked in
+            let session_id = match session_id {
+                Some(id) => id,
+                None => last::load()?
+                    .and_then(|render| render.session_id)
+                    .ok_or_else(|| {
+                        error::StatuslineError::input("No active session yet; pass --session-id")
+                    })?,
+            };
+            let db = database::SqliteDatabase::new(&common::get_database_path())?;
+
+            let note = match (text, clear) {
+                (_, true) => None,
+                (Some(text), false) => {
+                    let text = text.trim().to_string();
+                    if text.is_empty() || text.chars().count() > MAX_NOTE_CHARS {
+                        return Err(error::StatuslineError::input(format!(
+                            "Notes must be 1-{} characters",
+                            MAX_NOTE_CHARS
+                        )));
+                    }
+                    Some(text)
+                }
+                (None, false) => {
+                    match db.get_session_note(&session_id)? {
+                        Some(note) => println!("{}", note),
+                        None => println!("No note for session {}", session_id),
+                    }
+                    return Ok(());
+                }
+            };
+
+            if !db.set_session_note(&session_id, note.as_deref())? {
+                return Err(error::StatuslineError::input(format!(
+                    "Session {} has no stats yet",
+                    session_id
+                )));
+            }
+            match note {
+                Some(note) => println!("Noted for session {}: {}", session_id, note),
+                None => println!("Cleared note for session {}", session_id),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Create or restore a backup of the stats database and config
+#[cfg(feature = "sqlite")]
+fn handle_backup_command(action: BackupAction) -> Result<()> {
+    match action {
+        BackupAction::Create { to } => {
+            let dest = backup::backup_path(to.as_deref());
+            let info = backup::create(&dest)?;
+            println!(
+                "Backed up stats.db (schema v{}) to {}",
+                info.schema_version,
+                dest.display()
+            );
+            if info.config.is_none() {
+                println!("No config file found; only the database was saved");
+            }
+        }
+        BackupAction::Restore { file, skip_config } => {
+            let info = backup::restore(&file, skip_config)?;
+            println!(
+                "Restored backup from {} (statusline {}, schema v{})",
+                info.created_at, info.statusline_version, info.schema_version
+            );
+            let previous = format!("{}.pre-restore", common::get_database_path().display());
+            if std::path::Path::new(&previous).exists() {
+                println!("Previous database kept at {}", previous);
+            }
+            match (&info.config, skip_config) {
+                (Some(_), false) => println!("Config file restored"),
+                (Some(_), true) => println!("Config file left unchanged (--skip-config)"),
+                (None, _) => println!("Backup has no config file; config left unchanged"),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn handle_db_command(action: DbAction) -> Result<()> {
+    let db_path = common::get_database_path();
+    if !db_path.exists() {
+        return Err(error::StatuslineError::stats(format!(
+            "No database at {}",
+            db_path.display()
+        )));
+    }
+    // Inspection never writes
+    let read_only = || database::open_read_only(&db_path);
+
+    match action {
+        DbAction::Schema { json } => {
+            let report = database::read_schema(&read_only()?)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+            print_schema_report(&db_path, &report);
+        }
+        DbAction::Query { sql, json } => {
+            if !database::is_select_statement(&sql) {
+                return Err(error::StatuslineError::input(
+                    "Only single SELECT statements are allowed",
+                ));
+            }
+            let result = database::run_select(&read_only()?, &sql)?;
+            if json {
+                let rows: Vec<serde_json::Map<String, serde_json::Value>> = result
+                    .rows
+                    .into_iter()
+                    .map(|row| result.columns.iter().cloned().zip(row).collect())
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&rows)?);
+                return Ok(());
+            }
+            print_query_table(&result);
+        }
+        DbAction::Repair { dry_run } => return repair_aggregates(dry_run),
+    }
+    Ok(())
+}
+
+/// `rebuild --from-transcripts`: recompute known sessions from their transcripts
+#[cfg(feature = "sqlite")]
+fn handle_rebuild_command(dir: &std::path::Path, dry_run: bool) -> Result<()> {
+    if !dir.is_dir() {
+        return Err(error::StatuslineError::input(format!(
+            "Not a directory: {}",
+            dir.display()
+        )));
+    }
+    let db_path = common::get_database_path();
+    if !db_path.exists() {
+        return Err(error::StatuslineError::stats(format!(
+            "No database at {}",
+            db_path.display()
+        )));
+    }
+    let db = database::SqliteDatabase::new(&db_path)?;
+
+    let transcripts = transcripts::index(dir);
+    let mut session_ids: Vec<String> = db.get_all_sessions()?.into_keys().collect();
+    session_ids.sort();
+
+    let (mut matched, mut changed) = (0, 0);
+    for session_id in &session_ids {
+        let Some(path) = transcripts.get(session_id) else {
+            continue;
+        };
+        let Some(rebuilt) = utils::transcript_stats(&path.to_string_lossy()) else {
+            continue;
+        };
+        matched += 1;
+        if db.rebuild_session(session_id, &rebuilt, dry_run)? {
+            changed += 1;
+            println!("  {}", session_id);
+        }
+    }
+
+    println!(
+        "\n🔍 {} transcripts in {}, {} of {} known sessions matched",
+        transcripts.len(),
+        dir.display(),
+        matched,
+        session_ids.len()
+    );
+    match (changed, dry_run) {
+        (0, _) => println!("✅ All matched sessions are up to date"),
+        (n, true) => println!(
+            "⚠️  {} sessions would change. Run without --dry-run to rebuild them",
+            n
+        ),
+        (n, false) => println!("✅ Rebuilt {} sessions from their transcripts", n),
+    }
+    Ok(())
+}
+
+/// `db repair`: report aggregate anomalies and, unless `dry_run`, fix them
+#[cfg(feature = "sqlite")]
+fn repair_aggregates(dry_run: bool) -> Result<()> {
+    let report = database::repair_aggregates(dry_run)?;
+    println!(
+        "🔍 Checked daily and monthly aggregates against {} sessions",
+        report.sessions
+    );
+
+    let totals = |t: &database::AggregateTotals| {
+        format!(
+            "${:.2}, +{}/-{} lines, {} sessions",
+            t.cost, t.lines_added, t.lines_removed, t.sessions
+        )
+    };
+    for anomaly in &report.anomalies {
+        let user = if anomaly.user.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", anomaly.user)
+        };
+        let kind = match anomaly.kind {
+            database::AnomalyKind::DuplicateSessions => "sessions counted more than once",
+            database::AnomalyKind::TotalsMismatch => "totals differ from its sessions",
+        };
+        println!("\n  {} {}{}: {}", anomaly.table, anomaly.period, user, kind);
+        println!("    stored:   {}", totals(&anomaly.stored));
+        println!("    sessions: {}", totals(&anomaly.expected));
+    }
+    if report.partial_periods > 0 {
+        println!(
+            "\nℹ️  {} periods have sessions spanning their boundary; only their session counts were checked",
+            report.partial_periods
+        );
+    }
+
+    if report.anomalies.is_empty() {
+        println!("\n✅ No anomalies found");
+    } else if report.repaired {
+        println!(
+            "\n✅ Repaired {} aggregate rows from the sessions table",
+            report.anomalies.len()
+        );
+    } else {
+        println!(
+            "\n⚠️  {} anomalies found. Run 'statusline db repair' to fix them",
+            report.anomalies.len()
+        );
+    }
+    Ok(())
+}
+
+/// Print query results as an aligned text table
+#[cfg(feature = "sqlite")]
+fn print_query_table(result: &database::QueryResult) {
+    let cell = |value: &serde_json::Value| match value {
+        serde_json::Value::Null => "NULL".to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let cells: Vec<Vec<String>> = result
+        .rows
+        .iter()
+        .map(|row| row.iter().map(cell).collect())
+        .collect();
+    let widths: Vec<usize> = result
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(name.chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let line = |values: &[String]| {
+        values
+            .iter()
+            .zip(&widths)
+            .map(|(v, w)| format!("{:<w$}", v, w = *w))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", line(&result.columns));
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<_>>()
+            .join("  ")
+    );
+    for row in &cells {
+        println!("{}", line(row));
+    }
+    println!("({} rows)", cells.len());
+}
+
+/// Pretty-print a schema snapshot
+#[cfg(feature = "sqlite")]
+fn print_schema_report(db_path: &std::path::Path, report: &database::SchemaReport) {
+    println!("Database: {}", db_path.display());
+
+    for (kind, heading) in [("table", "Tables"), ("view", "Views"), ("index", "Indices")] {
+        let objects: Vec<_> = report.objects.iter().filter(|o| o.kind == kind).collect();
+        if objects.is_empty() {
+            continue;
+        }
+        println!();
+        println!("{}:", heading);
+        for object in objects {
+            let detail = match object.rows {
+                Some(rows) => format!("{} rows", rows),
+                None if kind == "index" => format!("on {}", object.table),
+                None => String::new(),
+            };
+            println!("  {:<32} {:<28} {}", object.name, detail, object.checksum);
+        }
+    }
+
+    println!();
+    println!("Applied migrations:");
+    if report.migrations.is_empty() {
+        println!("  (none)");
+    }
+    for migration in &report.migrations {
+        println!(
+            "  v{:<3} {:<27} {:<40} {}",
+            migration.version,
+            migration.applied_at,
+            migration.description.as_deref().unwrap_or("-"),
+            migration.checksum.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+fn handle_last_command(json: bool, ansi: bool) -> Result<()> {
+    let Some(render) = last::load()? else {
+        // Nothing rendered yet: print nothing so bars show an empty block
+        return Ok(());
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&render)?);
+    } else if ansi {
+        println!("{}", render.ansi);
+    } else {
+        println!("{}", render.text);
+    }
+    Ok(())
+}
+
+fn handle_input_command(action: InputAction) -> Result<()> {
+    match action {
+        InputAction::Inspect => {
+            let mut buffer = String::new();
+            io::stdin().read_to_string(&mut buffer)?;
+            let raw: serde_json::Value = serde_json::from_str(&buffer)?;
+            let input: StatuslineInput = serde_json::from_str(&buffer)?;
+            print_input_inspection(&input, &models::diagnose_input(&raw));
+        }
     }
     Ok(())
 }
 
+/// Pretty-print the fields understood from an input payload
+fn print_input_inspection(input: &StatuslineInput, diagnostics: &models::InputDiagnostics) {
+    fn show<T: std::fmt::Display>(value: Option<T>) -> String {
+        value.map_or_else(|| "-".to_string(), |v| v.to_string())
+    }
+
+    let cost = input.cost.as_ref();
+    let context_window = input.context_window.as_ref();
+    let reported = input.reported_context();
+    let fields = [
+        (
+            "workspace.current_dir",
+            show(
+                input
+                    .workspace
+                    .as_ref()
+                    .and_then(|w| w.current_dir.as_ref()),
+            ),
+        ),
+        (
+            "model.display_name",
+            show(input.model.as_ref().and_then(|m| m.display_name.as_ref())),
+        ),
+        ("session_id", show(input.session_id.as_ref())),
+        ("transcript_path", show(input.transcript.as_ref())),
+        (
+            "cost.total_cost_usd",
+            show(
+                cost.and_then(|c| c.total_cost_usd)
+                    .map(|v| format!("${:.2}", v)),
+            ),
+        ),
+        (
+            "cost.total_lines_added",
+            show(cost.and_then(|c| c.total_lines_added)),
+        ),
+        (
+            "cost.total_lines_removed",
+            show(cost.and_then(|c| c.total_lines_removed)),
+        ),
+        (
+            "cost.total_duration_ms",
+            show(cost.and_then(|c| c.total_duration_ms)),
+        ),
+        (
+            "cost.total_api_duration_ms",
+            show(cost.and_then(|c| c.total_api_duration_ms)),
+        ),
+        (
+            "context_window.context_window_size",
+            show(context_window.and_then(|cw| cw.context_window_size)),
+        ),
+        (
+            "context_window.current_usage",
+            show(reported.breakdown.as_ref().map(|b| {
+                format!(
+                    "{} tokens (input {}, output {}, cache read {}, cache write {})",
+                    b.total(),
+                    b.input_tokens,
+                    b.output_tokens,
+                    b.cache_read_tokens,
+                    b.cache_creation_tokens
+                )
+            })),
+        ),
+        ("exceeds_200k_tokens", show(input.exceeds_200k_tokens)),
+        ("profile", show(input.profile.as_ref())),
+    ];
+
+    println!("{}Understood fields{}", Colors::cyan(), Colors::reset());
+    println!("{}", "-".repeat(60));
+    for (name, value) in &fields {
+        println!("  {:<36} {}", name, value);
+    }
+    println!();
+
+    if diagnostics.missing.is_empty() {
+        println!(
+            "{}All expected fields present{}",
+            Colors::green(),
+            Colors::reset()
+        );
+    } else {
+        println!(
+            "{}Missing expected fields:{} {}",
+            Colors::yellow(),
+            Colors::reset(),
+            diagnostics.missing.join(", ")
+        );
+    }
+
+    if !diagnostics.unknown.is_empty() {
+        println!(
+            "{}Unrecognized fields (ignored):{}",
+            Colors::yellow(),
+            Colors::reset()
+        );
+        for path in &diagnostics.unknown {
+            match input.extra.get(path) {
+                Some(value) => println!("  {} = {}", path, value),
+                None => println!("  {}", path),
+            }
+        }
+    }
+}
+
 /// Read hook event JSON from stdin
 ///
 /// Claude Code sends hook data as JSON via stdin with fields:
@@ -1577,7 +4068,7 @@ fn read_hook_json_from_stdin() -> Result<(String, String)> {
     let session_id = json
         .get("session_id")
         .and_then(|v| v.as_str())
-        .ok_or_else(|| error::StatuslineError::other("Missing 'session_id' in hook JSON"))?
+        .ok_or_else(|| error::StatuslineError::input("Missing 'session_id' in hook JSON"))?
         .to_string();
 
     let trigger = json
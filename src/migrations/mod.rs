@@ -1,5 +1,4 @@
 use crate::stats::StatsData;
-use chrono::Local;
 use rusqlite::{params, Connection, Result, Transaction};
 use std::path::Path;
 
@@ -30,7 +29,7 @@ pub struct MigrationRunner {
 impl MigrationRunner {
     pub fn new(db_path: &Path) -> Result<Self> {
         // Open connection for migrations (don't call SqliteDatabase::new to avoid infinite recursion)
-        let conn = Connection::open(db_path)?;
+        let conn = crate::database::open_connection(db_path)?;
 
         // Enable WAL for concurrent access
         conn.pragma_update(None, "journal_mode", "WAL")?;
@@ -88,6 +87,21 @@ impl MigrationRunner {
             Box::new(AddMetaTable),
             Box::new(AddSyncMetadata),
             Box::new(AddAdaptiveLearning),
+            Box::new(AddContextSamples),
+            Box::new(AddUserColumns),
+            Box::new(AddReportingViews),
+            Box::new(AddLearningRetentionIndexes),
+            Box::new(AddActiveTime),
+            Box::new(AddCostAlerts),
+            Box::new(AddSessionNotes),
+            Box::new(AddSessionEnd),
+            Box::new(AddDailyTokens),
+            Box::new(AddAlertLog),
+            Box::new(AddSessionFiles),
+            Box::new(AddBranchStats),
+            Box::new(AddSessionUsage),
+            Box::new(AddLinesSamples),
+            Box::new(AddLastTokens),
         ]
     }
 
@@ -137,7 +151,7 @@ impl MigrationRunner {
                  VALUES (?1, ?2, ?3, ?4, ?5)",
                 params![
                     migration.version(),
-                    Local::now().to_rfc3339(),
+                    crate::environment::now_local().to_rfc3339(),
                     "", // Checksum placeholder
                     migration.description(),
                     start.elapsed().as_millis() as i64,
@@ -248,7 +262,7 @@ impl Migration for AddMetaTable {
         )?;
 
         // Add initial values
-        let now = Local::now().to_rfc3339();
+        let now = crate::environment::now_local().to_rfc3339();
         tx.execute(
             "INSERT OR IGNORE INTO meta (key, value) VALUES ('created_at', ?1)",
             params![now],
@@ -451,6 +465,536 @@ impl Migration for AddAdaptiveLearning {
     }
 }
 
+/// Migration 005: Add per-session context usage samples (for the sparkline)
+pub struct AddContextSamples;
+
+impl Migration for AddContextSamples {
+    fn version(&self) -> u32 {
+        5
+    }
+
+    fn description(&self) -> &str {
+        "Add context_samples table for recent context usage history"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS context_samples (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                recorded_at TEXT NOT NULL,
+                percentage REAL NOT NULL
+            )",
+            [],
+        )?;
+        tx.execute(
+            "CREATE INDEX IF NOT EXISTS idx_context_samples_session
+             ON context_samples(session_id, id DESC)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("DROP INDEX IF EXISTS idx_context_samples_session", [])?;
+        tx.execute("DROP TABLE IF EXISTS context_samples", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 006: Attribute sessions and aggregates to a user (shared team databases)
+pub struct AddUserColumns;
+
+impl Migration for AddUserColumns {
+    fn version(&self) -> u32 {
+        6
+    }
+
+    fn description(&self) -> &str {
+        "Add user column to sessions, daily_stats and monthly_stats"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        // Existing rows were all written by whoever owns this database
+        let user = crate::common::get_username();
+
+        tx.execute("ALTER TABLE sessions ADD COLUMN user TEXT", [])?;
+        tx.execute("UPDATE sessions SET user = ?1", params![&user])?;
+        tx.execute(
+            "CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user)",
+            [],
+        )?;
+
+        // Aggregates are keyed per user, so the tables must be rebuilt with a
+        // composite primary key (SQLite cannot alter a primary key in place)
+        tx.execute_batch(
+            "CREATE TABLE daily_stats_v6 (
+                date TEXT NOT NULL,
+                user TEXT NOT NULL DEFAULT '',
+                total_cost REAL DEFAULT 0.0,
+                total_lines_added INTEGER DEFAULT 0,
+                total_lines_removed INTEGER DEFAULT 0,
+                session_count INTEGER DEFAULT 0,
+                device_id TEXT,
+                PRIMARY KEY (date, user)
+            );
+            CREATE TABLE monthly_stats_v6 (
+                month TEXT NOT NULL,
+                user TEXT NOT NULL DEFAULT '',
+                total_cost REAL DEFAULT 0.0,
+                total_lines_added INTEGER DEFAULT 0,
+                total_lines_removed INTEGER DEFAULT 0,
+                session_count INTEGER DEFAULT 0,
+                device_id TEXT,
+                PRIMARY KEY (month, user)
+            );",
+        )?;
+        tx.execute(
+            "INSERT INTO daily_stats_v6
+                (date, user, total_cost, total_lines_added, total_lines_removed, session_count, device_id)
+             SELECT date, ?1, total_cost, total_lines_added, total_lines_removed, session_count, device_id
+             FROM daily_stats",
+            params![&user],
+        )?;
+        tx.execute(
+            "INSERT INTO monthly_stats_v6
+                (month, user, total_cost, total_lines_added, total_lines_removed, session_count, device_id)
+             SELECT month, ?1, total_cost, total_lines_added, total_lines_removed, session_count, device_id
+             FROM monthly_stats",
+            params![&user],
+        )?;
+        tx.execute_batch(
+            "DROP TABLE daily_stats;
+            DROP TABLE monthly_stats;
+            ALTER TABLE daily_stats_v6 RENAME TO daily_stats;
+            ALTER TABLE monthly_stats_v6 RENAME TO monthly_stats;
+            CREATE INDEX IF NOT EXISTS idx_daily_date_cost ON daily_stats(date DESC, total_cost DESC);
+            CREATE INDEX IF NOT EXISTS idx_daily_device ON daily_stats(device_id);
+            CREATE INDEX IF NOT EXISTS idx_monthly_device ON monthly_stats(device_id);",
+        )?;
+
+        Ok(())
+    }
+
+    fn down(&self, _tx: &Transaction) -> Result<()> {
+        // Collapsing per-user aggregates back into one row per period would lose
+        // attribution, so the user columns are left in place
+        Ok(())
+    }
+}
+
+/// Migration 007: Reporting views for BI tools pointed at stats.db
+pub struct AddReportingViews;
+
+impl Migration for AddReportingViews {
+    fn version(&self) -> u32 {
+        7
+    }
+
+    fn description(&self) -> &str {
+        "Add reporting views (v_daily_project_costs, v_model_usage, v_session_summary)"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute_batch(crate::database::REPORTING_VIEWS)
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute_batch(
+            "DROP VIEW IF EXISTS v_daily_project_costs;
+             DROP VIEW IF EXISTS v_model_usage;
+             DROP VIEW IF EXISTS v_session_summary;",
+        )
+    }
+}
+
+/// Migration 008: Indexes for pruning learning data by age
+pub struct AddLearningRetentionIndexes;
+
+impl Migration for AddLearningRetentionIndexes {
+    fn version(&self) -> u32 {
+        8
+    }
+
+    fn description(&self) -> &str {
+        "Add last_updated/recorded_at indexes for learning data retention"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "CREATE INDEX IF NOT EXISTS idx_learned_last_updated
+             ON learned_context_windows(last_updated)",
+            [],
+        )?;
+        tx.execute(
+            "CREATE INDEX IF NOT EXISTS idx_context_samples_recorded
+             ON context_samples(recorded_at)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("DROP INDEX IF EXISTS idx_context_samples_recorded", [])?;
+        tx.execute("DROP INDEX IF EXISTS idx_learned_last_updated", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 009: Active (non-idle) time per session
+pub struct AddActiveTime;
+
+impl Migration for AddActiveTime {
+    fn version(&self) -> u32 {
+        9
+    }
+
+    fn description(&self) -> &str {
+        "Add active_seconds column to sessions"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "ALTER TABLE sessions ADD COLUMN active_seconds INTEGER DEFAULT 0",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("ALTER TABLE sessions DROP COLUMN active_seconds", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 010: Cost alerts that persist until acknowledged
+pub struct AddCostAlerts;
+
+impl Migration for AddCostAlerts {
+    fn version(&self) -> u32 {
+        10
+    }
+
+    fn description(&self) -> &str {
+        "Add cost_alerts table for acknowledgeable cost alerts"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS cost_alerts (
+                kind TEXT NOT NULL,
+                scope TEXT NOT NULL,
+                threshold REAL NOT NULL,
+                value REAL NOT NULL,
+                fired_at TEXT NOT NULL,
+                acknowledged_at TEXT,
+                PRIMARY KEY (kind, scope)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("DROP TABLE IF EXISTS cost_alerts", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 011: Free-text note per session (`statusline session note`)
+pub struct AddSessionNotes;
+
+impl Migration for AddSessionNotes {
+    fn version(&self) -> u32 {
+        11
+    }
+
+    fn description(&self) -> &str {
+        "Add note column to sessions"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("ALTER TABLE sessions ADD COLUMN note TEXT", [])?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("ALTER TABLE sessions DROP COLUMN note", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 012: When a session ended, so idle sessions stop counting as open
+pub struct AddSessionEnd;
+
+impl Migration for AddSessionEnd {
+    fn version(&self) -> u32 {
+        12
+    }
+
+    fn description(&self) -> &str {
+        "Add ended_at column to sessions"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("ALTER TABLE sessions ADD COLUMN ended_at TEXT", [])?;
+        // v_session_summary measures durations up to ended_at
+        tx.execute("DROP VIEW IF EXISTS v_session_summary", [])?;
+        tx.execute_batch(crate::database::REPORTING_VIEWS)
+    }
+
+    fn down(&self, _tx: &Transaction) -> Result<()> {
+        // v_session_summary depends on the column, so it is left in place
+        Ok(())
+    }
+}
+
+/// Migration 013: Daily token sums alongside cost, for quota-based plans
+pub struct AddDailyTokens;
+
+/// Token columns added to `daily_stats`, named after their `sessions` counterparts
+const DAILY_TOKEN_COLUMNS: [&str; 4] = [
+    "total_input_tokens",
+    "total_output_tokens",
+    "total_cache_read_tokens",
+    "total_cache_creation_tokens",
+];
+
+impl Migration for AddDailyTokens {
+    fn version(&self) -> u32 {
+        13
+    }
+
+    fn description(&self) -> &str {
+        "Add token total columns to daily_stats"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        for column in DAILY_TOKEN_COLUMNS {
+            tx.execute(
+                &format!("ALTER TABLE daily_stats ADD COLUMN {column} INTEGER DEFAULT 0"),
+                [],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        for column in DAILY_TOKEN_COLUMNS {
+            tx.execute(&format!("ALTER TABLE daily_stats DROP COLUMN {column}"), [])?;
+        }
+        Ok(())
+    }
+}
+
+/// Migration 014: Last-fired time per alert, so notifications respect a cooldown
+pub struct AddAlertLog;
+
+impl Migration for AddAlertLog {
+    fn version(&self) -> u32 {
+        14
+    }
+
+    fn description(&self) -> &str {
+        "Add alert_log table for rate-limited alert delivery"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS alert_log (
+                alert_type TEXT NOT NULL,
+                alert_key TEXT NOT NULL,
+                last_fired_at INTEGER NOT NULL,
+                fire_count INTEGER NOT NULL DEFAULT 1,
+                PRIMARY KEY (alert_type, alert_key)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("DROP TABLE IF EXISTS alert_log", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 015: Hashed paths of the files edited in each session
+pub struct AddSessionFiles;
+
+impl Migration for AddSessionFiles {
+    fn version(&self) -> u32 {
+        15
+    }
+
+    fn description(&self) -> &str {
+        "Add session_files table for distinct edited file counts"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS session_files (
+                session_id TEXT NOT NULL,
+                file_hash TEXT NOT NULL,
+                first_seen TEXT NOT NULL,
+                PRIMARY KEY (session_id, file_hash)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("DROP TABLE IF EXISTS session_files", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 016: Git branch per session, and cost per branch and day
+pub struct AddBranchStats;
+
+impl Migration for AddBranchStats {
+    fn version(&self) -> u32 {
+        16
+    }
+
+    fn description(&self) -> &str {
+        "Add sessions.git_branch and branch_stats for per-branch costs"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("ALTER TABLE sessions ADD COLUMN git_branch TEXT", [])?;
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS branch_stats (
+                date TEXT NOT NULL,
+                user TEXT NOT NULL DEFAULT '',
+                workspace_dir TEXT NOT NULL DEFAULT '',
+                branch TEXT NOT NULL,
+                total_cost REAL DEFAULT 0.0,
+                total_lines_added INTEGER DEFAULT 0,
+                total_lines_removed INTEGER DEFAULT 0,
+                PRIMARY KEY (date, user, workspace_dir, branch)
+            )",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("DROP TABLE IF EXISTS branch_stats", [])?;
+        tx.execute("ALTER TABLE sessions DROP COLUMN git_branch", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 017: Token usage per API call, the source of the daily token totals
+pub struct AddSessionUsage;
+
+impl Migration for AddSessionUsage {
+    fn version(&self) -> u32 {
+        17
+    }
+
+    fn description(&self) -> &str {
+        "Add session_usage with the token usage of each API call"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS session_usage (
+                session_id TEXT NOT NULL,
+                message_id TEXT NOT NULL,
+                date TEXT NOT NULL,
+                user TEXT NOT NULL DEFAULT '',
+                input_tokens INTEGER DEFAULT 0,
+                output_tokens INTEGER DEFAULT 0,
+                cache_read_tokens INTEGER DEFAULT 0,
+                cache_creation_tokens INTEGER DEFAULT 0,
+                PRIMARY KEY (session_id, message_id)
+            )",
+            [],
+        )?;
+        tx.execute(
+            "CREATE INDEX IF NOT EXISTS idx_session_usage_date ON session_usage(date, user)",
+            [],
+        )?;
+        // Earlier sums were of per-session context snapshots, not token volume
+        for column in DAILY_TOKEN_COLUMNS {
+            tx.execute(&format!("UPDATE daily_stats SET {column} = 0"), [])?;
+        }
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("DROP TABLE IF EXISTS session_usage", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 018: Line count samples for the recent change velocity
+pub struct AddLinesSamples;
+
+impl Migration for AddLinesSamples {
+    fn version(&self) -> u32 {
+        18
+    }
+
+    fn description(&self) -> &str {
+        "Add lines_samples with each session's line counts as they change"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS lines_samples (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                sampled_at INTEGER NOT NULL,
+                lines_added INTEGER NOT NULL,
+                lines_removed INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        tx.execute(
+            "CREATE INDEX IF NOT EXISTS idx_lines_samples_session
+             ON lines_samples(session_id, id)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("DROP INDEX IF EXISTS idx_lines_samples_session", [])?;
+        tx.execute("DROP TABLE IF EXISTS lines_samples", [])?;
+        Ok(())
+    }
+}
+
+/// Migration 019: The context size a session was last seen at, besides its peak
+pub struct AddLastTokens;
+
+impl Migration for AddLastTokens {
+    fn version(&self) -> u32 {
+        19
+    }
+
+    fn description(&self) -> &str {
+        "Add sessions.last_tokens_observed for the last-known context size"
+    }
+
+    fn up(&self, tx: &Transaction) -> Result<()> {
+        tx.execute(
+            "ALTER TABLE sessions ADD COLUMN last_tokens_observed INTEGER DEFAULT 0",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn down(&self, tx: &Transaction) -> Result<()> {
+        tx.execute("ALTER TABLE sessions DROP COLUMN last_tokens_observed", [])?;
+        Ok(())
+    }
+}
+
 /// Run migrations on a specific database path
 /// Returns Err only on critical failures that prevent migrations from running
 pub fn run_migrations_on_db(db_path: &Path) -> Result<()> {
@@ -480,8 +1024,8 @@ mod tests {
         assert_eq!(runner.current_version().unwrap(), 0);
 
         runner.migrate().unwrap();
-        // We now have 4 migrations: InitialJsonToSqlite (v1), AddMetaTable (v2), AddSyncMetadata (v3), AddAdaptiveLearning (v4 - consolidated from old v4, v5, v6)
-        assert_eq!(runner.current_version().unwrap(), 4);
+        // We now have 19 migrations: InitialJsonToSqlite (v1), AddMetaTable (v2), AddSyncMetadata (v3), AddAdaptiveLearning (v4 - consolidated from old v4, v5, v6), AddContextSamples (v5), AddUserColumns (v6), AddReportingViews (v7), AddLearningRetentionIndexes (v8), AddActiveTime (v9), AddCostAlerts (v10), AddSessionNotes (v11), AddSessionEnd (v12), AddDailyTokens (v13), AddAlertLog (v14), AddSessionFiles (v15), AddBranchStats (v16), AddSessionUsage (v17), AddLinesSamples (v18), AddLastTokens (v19)
+        assert_eq!(runner.current_version().unwrap(), 19);
     }
 
     #[test]
@@ -575,4 +1119,111 @@ mod tests {
             "sessions table should have max_tokens_observed column"
         );
     }
+
+    #[test]
+    fn test_context_samples_migration() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test_samples.db");
+
+        let mut runner = MigrationRunner::new(&db_path).unwrap();
+        runner.migrate().unwrap();
+
+        let table_exists: bool = runner
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='context_samples'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .unwrap()
+            > 0;
+
+        assert!(table_exists, "context_samples table should exist");
+    }
+
+    #[test]
+    fn test_user_columns_migration() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test_user.db");
+
+        let mut runner = MigrationRunner::new(&db_path).unwrap();
+        runner.migrate().unwrap();
+
+        // Two users can now hold a row for the same day
+        runner
+            .conn
+            .execute_batch(
+                "INSERT INTO daily_stats (date, user, total_cost) VALUES ('2025-01-01', 'alice', 1.0);
+                 INSERT INTO daily_stats (date, user, total_cost) VALUES ('2025-01-01', 'bob', 2.0);",
+            )
+            .unwrap();
+        let total: f64 = runner
+            .conn
+            .query_row(
+                "SELECT SUM(total_cost) FROM daily_stats WHERE date = '2025-01-01'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(total, 3.0);
+
+        let sessions_columns: Vec<String> = runner
+            .conn
+            .prepare("PRAGMA table_info(sessions)")
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(1))
+            .unwrap()
+            .filter_map(|r| r.ok())
+            .collect();
+        assert!(sessions_columns.contains(&"user".to_string()));
+    }
+
+    #[test]
+    fn test_reporting_views_migration() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test_views.db");
+
+        let mut runner = MigrationRunner::new(&db_path).unwrap();
+        runner.migrate().unwrap();
+
+        runner
+            .conn
+            .execute_batch(
+                "INSERT INTO sessions (session_id, start_time, last_updated, cost, model_name, workspace_dir, total_input_tokens)
+                 VALUES ('views-a', '2025-01-01T10:00:00Z', '2025-01-01T10:30:00Z', 1.5, 'Views Test Model', '/views-test', 100);
+                 INSERT INTO sessions (session_id, start_time, last_updated, cost, model_name, workspace_dir, total_input_tokens)
+                 VALUES ('views-b', '2025-01-01T11:00:00Z', '2025-01-01T11:10:00Z', 0.5, 'Views Test Model', '/views-test', 50);",
+            )
+            .unwrap();
+
+        let (sessions, cost, tokens): (i64, f64, i64) = runner
+            .conn
+            .query_row(
+                "SELECT SUM(sessions), SUM(cost), SUM(tokens) FROM v_daily_project_costs WHERE project = '/views-test'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((sessions, cost, tokens), (2, 2.0, 150));
+
+        let model_sessions: i64 = runner
+            .conn
+            .query_row(
+                "SELECT sessions FROM v_model_usage WHERE model = 'Views Test Model'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(model_sessions, 2);
+
+        let duration: f64 = runner
+            .conn
+            .query_row(
+                "SELECT duration_minutes FROM v_session_summary WHERE session_id = 'views-a'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(duration, 30.0);
+    }
 }
@@ -4,7 +4,9 @@
 //! including the input format from Claude Code and various status representations.
 
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::sync::OnceLock;
 
 /// Main input structure from Claude Code.
@@ -24,15 +26,191 @@ pub struct StatuslineInput {
     pub transcript: Option<String>,
     /// Cost and metrics information
     pub cost: Option<Cost>,
+    /// Claude Code profile name (overrides detection from `CLAUDE_CONFIG_DIR`)
+    pub profile: Option<String>,
+    /// Context window usage reported by Claude Code
+    pub context_window: Option<ContextWindow>,
+    /// Whether the conversation exceeds 200K tokens (reported by Claude Code)
+    pub exceeds_200k_tokens: Option<bool>,
+    /// Top-level fields not understood by this version (kept for diagnostics)
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// Input fields understood by this version, as dotted paths.
+const KNOWN_INPUT_FIELDS: &[&str] = &[
+    "workspace",
+    "workspace.current_dir",
+    "workspace.project_dir",
+    "model",
+    "model.display_name",
+    "session_id",
+    "transcript",
+    "transcript_path",
+    "cost",
+    "cost.total_cost_usd",
+    "cost.total_lines_added",
+    "cost.total_lines_removed",
+    "cost.total_duration_ms",
+    "cost.total_api_duration_ms",
+    "profile",
+    "context_window",
+    "context_window.context_window_size",
+    "context_window.current_usage",
+    "context_window.current_usage.input_tokens",
+    "context_window.current_usage.output_tokens",
+    "context_window.current_usage.cache_creation_input_tokens",
+    "context_window.current_usage.cache_read_input_tokens",
+    "exceeds_200k_tokens",
+];
+
+/// Fields Claude Code normally sends; their absence is worth reporting.
+const EXPECTED_INPUT_FIELDS: &[&str] = &[
+    "workspace.current_dir",
+    "model.display_name",
+    "session_id",
+    "transcript_path",
+    "cost.total_cost_usd",
+];
+
+/// Schema differences between a raw input payload and what this version understands.
+#[derive(Debug, Default, PartialEq)]
+pub struct InputDiagnostics {
+    /// Expected fields that were absent or null
+    pub missing: Vec<&'static str>,
+    /// Fields that were ignored, as dotted paths
+    pub unknown: Vec<String>,
+}
+
+/// Compares a raw input payload against the fields this version understands.
+pub fn diagnose_input(raw: &Value) -> InputDiagnostics {
+    let lookup = |path: &str| {
+        path.split('.')
+            .try_fold(raw, |value, key| value.get(key))
+            .filter(|value| !value.is_null())
+    };
+    let missing = EXPECTED_INPUT_FIELDS
+        .iter()
+        .copied()
+        .filter(|&path| {
+            // `transcript` is accepted as an alias of `transcript_path`
+            lookup(path).is_none() && !(path == "transcript_path" && lookup("transcript").is_some())
+        })
+        .collect();
+
+    let mut unknown = Vec::new();
+    collect_unknown_fields(raw, "", &mut unknown);
+    InputDiagnostics { missing, unknown }
+}
+
+fn collect_unknown_fields(value: &Value, prefix: &str, unknown: &mut Vec<String>) {
+    let Some(object) = value.as_object() else {
+        return;
+    };
+    for (key, child) in object {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        if KNOWN_INPUT_FIELDS.contains(&path.as_str()) {
+            collect_unknown_fields(child, &path, unknown);
+        } else {
+            unknown.push(path);
+        }
+    }
+}
+
+impl StatuslineInput {
+    /// Token and context data reported directly in the payload.
+    ///
+    /// When present, this is used instead of parsing the transcript.
+    pub fn reported_context(&self) -> ReportedContext {
+        let context_window = self.context_window.as_ref();
+        ReportedContext {
+            breakdown: context_window
+                .and_then(|cw| cw.current_usage.as_ref())
+                .map(CurrentUsage::to_breakdown)
+                .filter(|b| b.total() > 0),
+            window_size: context_window
+                .and_then(|cw| cw.context_window_size)
+                .filter(|&size| size > 0)
+                .map(|size| size as usize),
+            exceeds_200k_tokens: self.exceeds_200k_tokens.unwrap_or(false),
+        }
+    }
+}
+
+/// Context window information from Claude Code.
+#[derive(Debug, Default, Deserialize)]
+pub struct ContextWindow {
+    /// Size of the model's context window in tokens
+    pub context_window_size: Option<u64>,
+    /// Token usage of the most recent API call (the current context)
+    pub current_usage: Option<CurrentUsage>,
+}
+
+/// Token usage of the most recent API call.
+#[derive(Debug, Default, Deserialize)]
+pub struct CurrentUsage {
+    pub input_tokens: Option<u32>,
+    pub output_tokens: Option<u32>,
+    pub cache_creation_input_tokens: Option<u32>,
+    pub cache_read_input_tokens: Option<u32>,
+}
+
+impl CurrentUsage {
+    fn to_breakdown(&self) -> TokenBreakdown {
+        TokenBreakdown {
+            input_tokens: self.input_tokens.unwrap_or(0),
+            output_tokens: self.output_tokens.unwrap_or(0),
+            cache_read_tokens: self.cache_read_input_tokens.unwrap_or(0),
+            cache_creation_tokens: self.cache_creation_input_tokens.unwrap_or(0),
+        }
+    }
+}
+
+/// Context data taken from the input payload rather than the transcript.
+#[derive(Debug, Clone, Default)]
+pub struct ReportedContext {
+    /// Token breakdown of the current context, if reported
+    pub breakdown: Option<TokenBreakdown>,
+    /// Context window size, if reported
+    pub window_size: Option<usize>,
+    /// The `exceeds_200k_tokens` flag
+    pub exceeds_200k_tokens: bool,
 }
 
 /// Workspace information from Claude Code.
 ///
-/// Contains the current working directory path.
+/// Contains the current working directory and the project root Claude Code
+/// was started in, which stays put when the session changes directory.
 #[derive(Debug, Deserialize)]
 pub struct Workspace {
     /// Current working directory path
     pub current_dir: Option<String>,
+    /// Project root directory
+    #[serde(default)]
+    pub project_dir: Option<String>,
+}
+
+impl Workspace {
+    /// Project root, falling back to the current directory for payloads
+    /// without `project_dir`. Per-project stats are keyed on this.
+    pub fn project_root(&self) -> Option<&str> {
+        self.project_dir
+            .as_deref()
+            .filter(|dir| !dir.is_empty())
+            .or(self.current_dir.as_deref())
+    }
+
+    /// Directory to show for `display.path_style` ("project" or "cwd")
+    pub fn display_dir(&self, path_style: &str) -> Option<&str> {
+        match path_style {
+            "project" => self.project_root(),
+            _ => self.current_dir.as_deref(),
+        }
+    }
 }
 
 /// Model information from Claude Code.
@@ -47,7 +225,7 @@ pub struct Model {
 /// Cost and metrics information.
 ///
 /// Tracks the total cost in USD and code change metrics for the current session.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Cost {
     /// Total cost in USD for the session
     pub total_cost_usd: Option<f64>,
@@ -55,12 +233,29 @@ pub struct Cost {
     pub total_lines_added: Option<u64>,
     /// Total lines of code removed
     pub total_lines_removed: Option<u64>,
+    /// Wall-clock session duration in milliseconds
+    pub total_duration_ms: Option<u64>,
+    /// Time spent waiting on API responses in milliseconds
+    pub total_api_duration_ms: Option<u64>,
+}
+
+impl Cost {
+    /// Session duration in seconds for burn-rate math, as reported by Claude Code.
+    ///
+    /// Prefers API time (which excludes idle periods) over wall-clock time.
+    /// Returns `None` when neither field is present.
+    pub fn duration_secs(&self) -> Option<u64> {
+        self.total_api_duration_ms
+            .filter(|&ms| ms > 0)
+            .or(self.total_duration_ms.filter(|&ms| ms > 0))
+            .map(|ms| ms / 1000)
+    }
 }
 
 /// Token usage breakdown from transcript.
 ///
 /// Contains detailed token counts for cost analysis and cache efficiency tracking.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct TokenBreakdown {
     /// Input tokens (excluding cache)
     pub input_tokens: u32,
@@ -79,6 +274,17 @@ impl TokenBreakdown {
     }
 }
 
+/// Transcript size, gathered while tail-reading the transcript.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TranscriptSize {
+    /// File size in bytes
+    pub bytes: u64,
+    /// Number of JSONL entries (messages)
+    pub messages: u64,
+    /// Whether `messages` was extrapolated from the tail of a large file
+    pub estimated: bool,
+}
+
 /// Claude model type enumeration
 #[derive(Debug, PartialEq)]
 pub enum ModelType {
@@ -167,6 +373,25 @@ impl ModelType {
         }
     }
 
+    /// Looks up the configured icon for this model: "Family version" first, then family.
+    ///
+    /// Keys are matched case-insensitively.
+    pub fn icon<'a>(
+        &self,
+        icons: &'a std::collections::HashMap<String, String>,
+    ) -> Option<&'a str> {
+        let ModelType::Model { family, .. } = self else {
+            return None;
+        };
+        let find = |key: &str| {
+            icons
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, icon)| icon.as_str())
+        };
+        find(&self.canonical_name()).or_else(|| find(family))
+    }
+
     /// Returns the canonical model name for database storage
     /// This normalizes different display name variations to a consistent format
     /// Examples:
@@ -185,6 +410,33 @@ impl ModelType {
             ModelType::Unknown => "Unknown".to_string(),
         }
     }
+
+    /// Returns the API model ID without a date suffix, the key other usage
+    /// tools (such as ccusage) group by
+    /// Examples:
+    /// - "Opus 4.1" → "claude-opus-4-1"
+    /// - "Claude 3.5 Sonnet" → "claude-3-5-sonnet"
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+    pub fn model_id(&self) -> Option<String> {
+        let ModelType::Model { family, version } = self else {
+            return None;
+        };
+        let family = family.to_lowercase();
+        let version = version.replace('.', "-");
+        let major: u32 = version
+            .split('-')
+            .next()
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(0);
+        Some(if version.is_empty() {
+            format!("claude-{}", family)
+        } else if major >= 4 {
+            // Claude 4 and later put the family first
+            format!("claude-{}-{}", family, version)
+        } else {
+            format!("claude-{}-{}", version, family)
+        })
+    }
 }
 
 /// Entry in the Claude transcript file (JSONL format)
@@ -199,11 +451,13 @@ pub struct TranscriptEntry {
 /// Message within a transcript entry
 #[derive(Debug, Deserialize)]
 pub struct TranscriptMessage {
+    /// API message ID, shared by the lines of one assistant message
+    #[serde(default)]
+    pub id: Option<String>,
     /// Role of the message sender (user, assistant, etc.)
     pub role: String,
     /// Message content (can be string or array)
     #[serde(default)]
-    #[allow(dead_code)]
     pub content: Option<serde_json::Value>,
     /// Token usage information
     #[serde(default)]
@@ -244,6 +498,10 @@ pub struct ContextUsage {
 
     /// Compaction state detection
     pub compaction_state: CompactionState,
+
+    /// Estimated from the session's last-known tokens in the database because
+    /// no transcript was provided (rendered as `~62%`)
+    pub approximate: bool,
 }
 
 /// Compaction state detection
@@ -297,6 +555,26 @@ mod tests {
         assert_eq!(input.cost.unwrap().total_cost_usd.unwrap(), 2.50);
     }
 
+    #[test]
+    fn test_workspace_project_dir() {
+        let workspace: Workspace = serde_json::from_str(
+            r#"{"current_dir": "/home/user/repo/src/api", "project_dir": "/home/user/repo"}"#,
+        )
+        .unwrap();
+        assert_eq!(workspace.project_root(), Some("/home/user/repo"));
+        assert_eq!(workspace.display_dir("project"), Some("/home/user/repo"));
+        assert_eq!(
+            workspace.display_dir("cwd"),
+            Some("/home/user/repo/src/api")
+        );
+
+        // Older payloads without project_dir fall back to the cwd
+        let workspace: Workspace =
+            serde_json::from_str(r#"{"current_dir": "/home/user/repo"}"#).unwrap();
+        assert_eq!(workspace.project_root(), Some("/home/user/repo"));
+        assert_eq!(workspace.display_dir("project"), Some("/home/user/repo"));
+    }
+
     #[test]
     fn test_model_type_detection() {
         // Test Opus detection
@@ -402,6 +680,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_model_id() {
+        assert_eq!(
+            ModelType::from_name("Opus 4.1").model_id().as_deref(),
+            Some("claude-opus-4-1")
+        );
+        assert_eq!(
+            ModelType::from_name("claude-sonnet-4-5-20250929")
+                .model_id()
+                .as_deref(),
+            Some("claude-sonnet-4-5")
+        );
+        assert_eq!(
+            ModelType::from_name("Claude 3.5 Sonnet")
+                .model_id()
+                .as_deref(),
+            Some("claude-3-5-sonnet")
+        );
+        assert_eq!(
+            ModelType::from_name("Sonnet 4").model_id().as_deref(),
+            Some("claude-sonnet-4")
+        );
+        assert_eq!(ModelType::from_name("Unknown Model").model_id(), None);
+    }
+
     #[test]
     fn test_model_type_display() {
         assert_eq!(ModelType::from_name("Claude Opus").abbreviation(), "Opus");
@@ -418,6 +721,31 @@ mod tests {
         assert_eq!(ModelType::Unknown.abbreviation(), "Claude");
     }
 
+    #[test]
+    fn test_model_icon_lookup() {
+        let icons: std::collections::HashMap<String, String> =
+            [("opus", "🏛"), ("Sonnet", "✨"), ("Sonnet 4.5", "⚡")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+
+        assert_eq!(
+            ModelType::from_name("Claude Opus 4.1").icon(&icons),
+            Some("🏛")
+        );
+        // Family and version beats family
+        assert_eq!(
+            ModelType::from_name("Claude Sonnet 4.5").icon(&icons),
+            Some("⚡")
+        );
+        assert_eq!(
+            ModelType::from_name("Claude 3.5 Sonnet").icon(&icons),
+            Some("✨")
+        );
+        assert_eq!(ModelType::from_name("Claude Haiku").icon(&icons), None);
+        assert_eq!(ModelType::Unknown.icon(&icons), None);
+    }
+
     #[test]
     fn test_version_extraction() {
         // Test various version number formats
@@ -607,4 +935,35 @@ mod tests {
         assert!(cost.total_lines_added.is_none());
         assert!(cost.total_lines_removed.is_none());
     }
+
+    #[test]
+    fn test_cost_duration_prefers_api_time() {
+        let json = r#"{"cost": {"total_cost_usd": 1.0, "total_duration_ms": 7200000, "total_api_duration_ms": 1800500}}"#;
+        let input: StatuslineInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.cost.unwrap().duration_secs(), Some(1800));
+
+        let wall_only = Cost {
+            total_duration_ms: Some(90_000),
+            ..Default::default()
+        };
+        assert_eq!(wall_only.duration_secs(), Some(90));
+        assert_eq!(Cost::default().duration_secs(), None);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_preserved_and_reported() {
+        let json = r#"{
+            "session_id": "abc",
+            "transcript": "/tmp/t.jsonl",
+            "model": {"display_name": "Opus", "id": "claude-opus-4-5"},
+            "output_style": {"name": "default"},
+            "cost": {"total_cost_usd": 1.0}
+        }"#;
+        let input: StatuslineInput = serde_json::from_str(json).unwrap();
+        assert!(input.extra.contains_key("output_style"));
+
+        let diagnostics = diagnose_input(&serde_json::from_str(json).unwrap());
+        assert_eq!(diagnostics.missing, vec!["workspace.current_dir"]);
+        assert_eq!(diagnostics.unknown, vec!["model.id", "output_style"]);
+    }
 }
@@ -0,0 +1,155 @@
+//! Anthropic API pricing table used for cost estimates.
+//!
+//! Rates are in USD per million tokens. Claude Code reports the actual session
+//! cost, so this table is only used for derived figures such as cache savings
+//! and the split of cost by token type.
+
+use crate::models::ModelType;
+
+/// Per-million-token rates for a model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+const OPUS_LEGACY: ModelPricing = ModelPricing {
+    input: 15.0,
+    output: 75.0,
+    cache_write: 18.75,
+    cache_read: 1.50,
+};
+
+const OPUS: ModelPricing = ModelPricing {
+    input: 5.0,
+    output: 25.0,
+    cache_write: 6.25,
+    cache_read: 0.50,
+};
+
+const SONNET: ModelPricing = ModelPricing {
+    input: 3.0,
+    output: 15.0,
+    cache_write: 3.75,
+    cache_read: 0.30,
+};
+
+const HAIKU_LEGACY: ModelPricing = ModelPricing {
+    input: 0.80,
+    output: 4.0,
+    cache_write: 1.0,
+    cache_read: 0.08,
+};
+
+const HAIKU: ModelPricing = ModelPricing {
+    input: 1.0,
+    output: 5.0,
+    cache_write: 1.25,
+    cache_read: 0.10,
+};
+
+/// Returns the pricing for a model name, falling back to Sonnet rates for
+/// unknown models.
+pub fn pricing_for(model_name: &str) -> ModelPricing {
+    match ModelType::from_name(model_name) {
+        ModelType::Model { family, version } => {
+            let major_minor = version.parse::<f64>().unwrap_or(0.0);
+            match family.as_str() {
+                "Opus" if major_minor >= 4.5 => OPUS,
+                "Opus" => OPUS_LEGACY,
+                "Haiku" if major_minor >= 4.5 => HAIKU,
+                "Haiku" => HAIKU_LEGACY,
+                _ => SONNET,
+            }
+        }
+        ModelType::Unknown => SONNET,
+    }
+}
+
+/// Estimated dollars saved by serving `cache_read_tokens` from the prompt cache
+/// instead of as regular input tokens.
+pub fn cache_savings(model_name: &str, cache_read_tokens: u64) -> f64 {
+    let pricing = pricing_for(model_name);
+    cache_read_tokens as f64 * (pricing.input - pricing.cache_read) / 1_000_000.0
+}
+
+/// Estimated dollars per token type.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CostSplit {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+impl CostSplit {
+    pub fn total(&self) -> f64 {
+        self.input + self.output + self.cache_write + self.cache_read
+    }
+
+    pub fn add(&mut self, other: &CostSplit) {
+        self.input += other.input;
+        self.output += other.output;
+        self.cache_write += other.cache_write;
+        self.cache_read += other.cache_read;
+    }
+}
+
+/// Estimated cost of each token type at the model's rates.
+pub fn cost_split(
+    model_name: &str,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_write_tokens: u64,
+    cache_read_tokens: u64,
+) -> CostSplit {
+    let pricing = pricing_for(model_name);
+    let cost = |tokens: u64, rate: f64| tokens as f64 * rate / 1_000_000.0;
+    CostSplit {
+        input: cost(input_tokens, pricing.input),
+        output: cost(output_tokens, pricing.output),
+        cache_write: cost(cache_write_tokens, pricing.cache_write),
+        cache_read: cost(cache_read_tokens, pricing.cache_read),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pricing_for_model_families() {
+        assert_eq!(pricing_for("Claude Sonnet 4.5"), SONNET);
+        assert_eq!(pricing_for("claude-opus-4-1-20250805"), OPUS_LEGACY);
+        assert_eq!(pricing_for("Opus 4.5"), OPUS);
+        assert_eq!(pricing_for("claude-3-5-haiku"), HAIKU_LEGACY);
+        assert_eq!(pricing_for("Haiku 4.5"), HAIKU);
+        assert_eq!(pricing_for("something-else"), SONNET);
+    }
+
+    #[test]
+    fn test_cache_savings() {
+        // 1M cache-read tokens on Sonnet: $3.00 input vs $0.30 cache read
+        let saved = cache_savings("Sonnet 4.5", 1_000_000);
+        assert!((saved - 2.70).abs() < 1e-9);
+        assert_eq!(cache_savings("Sonnet 4.5", 0), 0.0);
+    }
+
+    #[test]
+    fn test_cost_split() {
+        // 1M of each token type on Sonnet
+        let split = cost_split("Sonnet 4.5", 1_000_000, 1_000_000, 1_000_000, 1_000_000);
+        assert_eq!(
+            split,
+            CostSplit {
+                input: 3.0,
+                output: 15.0,
+                cache_write: 3.75,
+                cache_read: 0.30,
+            }
+        );
+        assert!((split.total() - 22.05).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,1174 @@
+//! Statistics reports for the `stats` command.
+//!
+//! The helpers here work on data that has already been aggregated by the
+//! database layer, so the rendering logic can be tested without a database.
+
+use crate::database::{
+    DailyModelUsage, GroupedUsage, PeriodTotals, SessionSummary, TimesheetEntry,
+};
+use crate::error::{Result, StatuslineError};
+use crate::models::ModelType;
+use crate::pricing::{cost_split, CostSplit};
+use crate::utils::{format_duration, format_token_count, shorten_path};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Partial block characters used for sub-cell precision in bar charts.
+const PARTIAL_BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+/// Builds a continuous daily series ending at `end` (inclusive), filling gaps with zero.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::NaiveDate;
+/// use statusline::reports::fill_daily_series;
+///
+/// let end = NaiveDate::from_ymd_opt(2025, 1, 3).unwrap();
+/// let series = fill_daily_series(&[("2025-01-02".to_string(), 1.5)], end, 3);
+/// assert_eq!(series.len(), 3);
+/// assert_eq!(series[1], ("2025-01-02".to_string(), 1.5));
+/// assert_eq!(series[2].1, 0.0);
+/// ```
+pub fn fill_daily_series(rows: &[(String, f64)], end: NaiveDate, days: u32) -> Vec<(String, f64)> {
+    let lookup: HashMap<&str, f64> = rows.iter().map(|(d, v)| (d.as_str(), *v)).collect();
+
+    (0..days as i64)
+        .rev()
+        .map(|offset| {
+            let date = (end - Duration::days(offset))
+                .format("%Y-%m-%d")
+                .to_string();
+            let value = lookup.get(date.as_str()).copied().unwrap_or(0.0);
+            (date, value)
+        })
+        .collect()
+}
+
+/// Builds a 24-entry hourly series (labels "00".."23"), filling gaps with zero.
+pub fn fill_hourly_series(rows: &[(u32, f64)]) -> Vec<(String, f64)> {
+    let mut hours = [0.0; 24];
+    for (hour, value) in rows {
+        if let Some(slot) = hours.get_mut(*hour as usize) {
+            *slot += value;
+        }
+    }
+    hours
+        .iter()
+        .enumerate()
+        .map(|(hour, value)| (format!("{:02}", hour), *value))
+        .collect()
+}
+
+/// Renders a horizontal bar of `value / max` scaled to `width` cells.
+fn render_bar(value: f64, max: f64, width: usize) -> String {
+    if max <= 0.0 || value <= 0.0 {
+        return String::new();
+    }
+
+    let eighths = ((value / max).min(1.0) * (width * 8) as f64).round() as usize;
+    let (full, partial) = (eighths / 8, eighths % 8);
+    let mut bar = "█".repeat(full);
+    if partial > 0 {
+        bar.push(PARTIAL_BLOCKS[partial - 1]);
+    }
+    if bar.is_empty() {
+        // Never hide a non-zero value entirely
+        bar.push(PARTIAL_BLOCKS[0]);
+    }
+    bar
+}
+
+/// Renders labelled rows as a horizontal bar chart, one line per row.
+///
+/// Each line has the form `label │████▌      $1.23`, with bars scaled to the
+/// largest value in the series.
+pub fn render_bar_chart(rows: &[(String, f64)], width: usize) -> Vec<String> {
+    let max = rows.iter().map(|(_, v)| *v).fold(0.0_f64, f64::max);
+    let label_width = rows
+        .iter()
+        .map(|(l, _)| l.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    rows.iter()
+        .map(|(label, value)| {
+            let bar = render_bar(*value, max, width);
+            let padding = width.saturating_sub(bar.chars().count());
+            format!(
+                "{:<label_width$} │{}{} ${:.2}",
+                label,
+                bar,
+                " ".repeat(padding),
+                value,
+                label_width = label_width
+            )
+        })
+        .collect()
+}
+
+/// Report periods accepted by `stats --period`.
+pub const PERIODS: [&str; 4] = ["today", "week", "month", "all"];
+
+/// Returns the first date included in a report `period`, or `None` for all time.
+///
+/// `week` covers the last 7 days including today; `month` is the calendar month to date.
+pub fn period_start(period: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match period {
+        "today" => Some(today),
+        "week" => Some(today - Duration::days(6)),
+        "month" => today.with_day(1),
+        _ => None,
+    }
+}
+
+/// Human-readable description of a report `period`.
+pub fn period_label(period: &str) -> &'static str {
+    match period {
+        "today" => "today",
+        "week" => "last 7 days",
+        "month" => "this month",
+        _ => "all time",
+    }
+}
+
+/// Shade characters for heatmap cells, from no activity to the maximum.
+const HEAT_SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// Weekday labels for heatmap rows, Monday first.
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Builds a Monday-first weekday × hour grid from `(weekday, hour, value)` rows,
+/// where `weekday` uses SQLite's `%w` numbering (0 = Sunday).
+pub fn build_heatmap_grid(rows: &[(u32, u32, f64)]) -> [[f64; 24]; 7] {
+    let mut grid = [[0.0; 24]; 7];
+    for (weekday, hour, value) in rows {
+        if *weekday < 7 && *hour < 24 {
+            grid[((*weekday + 6) % 7) as usize][*hour as usize] += value;
+        }
+    }
+    grid
+}
+
+/// Renders a weekday × hour grid as shaded cells (two characters per hour),
+/// with an hour header every three hours.
+pub fn render_heatmap(grid: &[[f64; 24]; 7]) -> Vec<String> {
+    let max = grid.iter().flatten().copied().fold(0.0_f64, f64::max);
+
+    let mut lines = vec![format!(
+        "    {}",
+        (0..24)
+            .step_by(3)
+            .map(|h| format!("{:<6}", format!("{:02}", h)))
+            .collect::<String>()
+            .trim_end()
+    )];
+    for (label, row) in WEEKDAYS.iter().zip(grid.iter()) {
+        let cells: String = row
+            .iter()
+            .map(|value| {
+                let shade = if max <= 0.0 || *value <= 0.0 {
+                    HEAT_SHADES[0]
+                } else {
+                    // Any activity gets at least the lightest shade
+                    let level = (value / max * (HEAT_SHADES.len() - 1) as f64).ceil() as usize;
+                    HEAT_SHADES[level.clamp(1, HEAT_SHADES.len() - 1)]
+                };
+                format!("{}{}", shade, shade)
+            })
+            .collect();
+        lines.push(format!("{} {}", label, cells));
+    }
+    lines
+}
+
+/// Merges per-model usage rows by canonical model name (e.g. `claude-sonnet-4-5` and
+/// `Sonnet 4.5` are the same model), sorted by cost descending.
+pub fn merge_model_usage(rows: &[GroupedUsage]) -> Vec<GroupedUsage> {
+    let mut merged: Vec<GroupedUsage> = Vec::new();
+    for row in rows {
+        let key = ModelType::from_name(&row.key).canonical_name();
+        match merged.iter_mut().find(|m| m.key == key) {
+            Some(existing) => {
+                existing.cost += row.cost;
+                existing.tokens += row.tokens;
+                existing.sessions += row.sessions;
+            }
+            None => merged.push(GroupedUsage { key, ..row.clone() }),
+        }
+    }
+    merged.sort_by(|a, b| b.cost.total_cmp(&a.cost).then(b.tokens.cmp(&a.tokens)));
+    merged
+}
+
+/// Share of `part` in `total` as a percentage (0 when `total` is zero).
+pub fn share(part: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        part / total * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// One day's recorded cost with its estimated split by token type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyCostSplit {
+    pub date: String,
+    /// Cost reported by Claude Code
+    pub cost: f64,
+    /// Estimated from the pricing table; use its shares, not its absolute values
+    pub split: CostSplit,
+}
+
+/// Combines per-(date, model) token totals into one cost split per day, pricing
+/// each model's tokens at its own rates. Rows must be ordered by date.
+pub fn daily_cost_split(rows: &[DailyModelUsage]) -> Vec<DailyCostSplit> {
+    rows.chunk_by(|a, b| a.date == b.date)
+        .map(|day_rows| {
+            let mut split = CostSplit::default();
+            for row in day_rows {
+                split.add(&cost_split(
+                    &row.model_name,
+                    row.input_tokens,
+                    row.output_tokens,
+                    row.cache_creation_tokens,
+                    row.cache_read_tokens,
+                ));
+            }
+            DailyCostSplit {
+                date: day_rows[0].date.clone(),
+                cost: day_rows.iter().map(|row| row.cost).sum(),
+                split,
+            }
+        })
+        .collect()
+}
+
+/// A current date range paired with the equivalent previous range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonPeriod {
+    pub label: &'static str,
+    pub current: (NaiveDate, NaiveDate),
+    pub previous: (NaiveDate, NaiveDate),
+}
+
+/// Returns the today/week/month comparison periods for `today`.
+///
+/// - Today vs yesterday
+/// - Last 7 days vs the 7 days before
+/// - Month to date vs the same span of the previous month (clamped to its length)
+pub fn comparison_periods(today: NaiveDate) -> Vec<ComparisonPeriod> {
+    let yesterday = today - Duration::days(1);
+    let month_start = today.with_day(1).unwrap_or(today);
+    let prev_month_end = month_start - Duration::days(1);
+    let prev_month_start = prev_month_end.with_day(1).unwrap_or(prev_month_end);
+    let prev_month_cutoff =
+        (prev_month_start + Duration::days(today.day0() as i64)).min(prev_month_end);
+
+    vec![
+        ComparisonPeriod {
+            label: "Today vs yesterday",
+            current: (today, today),
+            previous: (yesterday, yesterday),
+        },
+        ComparisonPeriod {
+            label: "Last 7 days vs previous 7",
+            current: (today - Duration::days(6), today),
+            previous: (today - Duration::days(13), today - Duration::days(7)),
+        },
+        ComparisonPeriod {
+            label: "Month to date vs last month",
+            current: (month_start, today),
+            previous: (prev_month_start, prev_month_cutoff),
+        },
+    ]
+}
+
+/// Weeks of history used for the weekday-adjusted forecast
+pub const FORECAST_HISTORY_WEEKS: i64 = 8;
+
+/// Days with spend needed in the history before a weekday-adjusted forecast is made
+const FORECAST_MIN_ACTIVE_DAYS: usize = 7;
+
+/// End-of-month spend projected from month-to-date daily totals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthForecast {
+    /// Spent from the 1st through today
+    pub spent: f64,
+    /// Days of the month elapsed, today included
+    pub days_elapsed: u32,
+    pub days_in_month: u32,
+    /// Month-to-date daily average carried over the whole month
+    pub linear: f64,
+    /// Spent so far plus each remaining day's weekday average over the last
+    /// `FORECAST_HISTORY_WEEKS` weeks (or since the first recorded day), so
+    /// quiet weekends aren't projected as busy weekdays (None without enough
+    /// history)
+    pub seasonal: Option<f64>,
+}
+
+/// Forecasts the month's total spend.
+///
+/// `daily` holds `(date, cost)` rows from the start of the history window
+/// (`FORECAST_HISTORY_WEEKS` weeks before today) through today; missing days
+/// after the first row count as zero spend.
+pub fn forecast_month(daily: &[(String, f64)], today: NaiveDate) -> MonthForecast {
+    let lookup: HashMap<&str, f64> = daily.iter().map(|(d, v)| (d.as_str(), *v)).collect();
+    let cost_on = |date: NaiveDate| {
+        lookup
+            .get(date.format("%Y-%m-%d").to_string().as_str())
+            .copied()
+            .unwrap_or(0.0)
+    };
+
+    let month_start = today.with_day(1).unwrap_or(today);
+    let next_month = if today.month() == 12 {
+        NaiveDate::from_ymd_opt(today.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(today.year(), today.month() + 1, 1)
+    }
+    .unwrap_or(today);
+    let days_in_month = (next_month - month_start).num_days() as u32;
+    let days_elapsed = today.day();
+
+    let spent: f64 = month_start
+        .iter_days()
+        .take_while(|d| *d <= today)
+        .map(cost_on)
+        .sum();
+    let linear = spent / days_elapsed as f64 * days_in_month as f64;
+
+    // Average spend per weekday before today, over the history window or since
+    // the first recorded day if that is later, so short histories aren't
+    // averaged over weeks without data
+    let first_recorded = daily
+        .iter()
+        .filter_map(|(d, _)| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .min();
+    let history_start =
+        (today - Duration::days(FORECAST_HISTORY_WEEKS * 7)).max(first_recorded.unwrap_or(today));
+    let mut weekday_totals = [0.0; 7];
+    let mut weekday_counts = [0u32; 7];
+    let mut active_days = 0;
+    for date in history_start.iter_days().take_while(|d| *d < today) {
+        let cost = cost_on(date);
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        weekday_totals[weekday] += cost;
+        weekday_counts[weekday] += 1;
+        if cost > 0.0 {
+            active_days += 1;
+        }
+    }
+    let seasonal = (active_days >= FORECAST_MIN_ACTIVE_DAYS).then(|| {
+        let remaining: f64 = (today + Duration::days(1))
+            .iter_days()
+            .take_while(|d| *d < next_month)
+            .map(|d| {
+                let weekday = d.weekday().num_days_from_monday() as usize;
+                weekday_totals[weekday] / weekday_counts[weekday].max(1) as f64
+            })
+            .sum();
+        spent + remaining
+    });
+
+    MonthForecast {
+        spent,
+        days_elapsed,
+        days_in_month,
+        linear,
+        seasonal,
+    }
+}
+
+/// Percentage change from `previous` to `current`, or `None` if there is no baseline.
+pub fn percent_change(current: f64, previous: f64) -> Option<f64> {
+    if previous <= 0.0 {
+        None
+    } else {
+        Some((current - previous) / previous * 100.0)
+    }
+}
+
+/// Formats the change from `previous` to `current` as `▲ +25.0%`, `▼ -10.0%`,
+/// `= 0.0%`, `new` (no baseline) or `-` (no data in either period).
+pub fn format_change(current: f64, previous: f64) -> String {
+    match percent_change(current, previous) {
+        Some(pct) if pct > 0.05 => format!("▲ +{:.1}%", pct),
+        Some(pct) if pct < -0.05 => format!("▼ {:.1}%", pct),
+        Some(_) => "= 0.0%".to_string(),
+        None if current > 0.0 => "new".to_string(),
+        None => "-".to_string(),
+    }
+}
+
+/// Data for a shareable period report (`statusline report`).
+#[derive(Debug, Clone)]
+pub struct PeriodReport {
+    pub title: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub current: PeriodTotals,
+    pub previous: PeriodTotals,
+    pub projects: Vec<GroupedUsage>,
+    pub models: Vec<GroupedUsage>,
+    pub sessions: Vec<SessionSummary>,
+    /// Style for session durations (`display.duration_format`)
+    pub duration_format: String,
+}
+
+/// Escapes a value for use inside a markdown table cell.
+fn md_cell(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+/// Display name for an optional workspace path.
+fn project_name(workspace: Option<&str>) -> String {
+    match workspace {
+        Some(dir) if !dir.is_empty() => shorten_path(dir),
+        _ => "(unknown)".to_string(),
+    }
+}
+
+impl PeriodReport {
+    /// Renders the report as GitHub-flavored markdown.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}", self.title);
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "_{} to {}_",
+            self.start.format("%Y-%m-%d"),
+            self.end.format("%Y-%m-%d")
+        );
+
+        let (cur, prev) = (&self.current, &self.previous);
+        let _ = writeln!(out, "\n## Totals\n");
+        let _ = writeln!(out, "| Metric | This period | Previous | Change |");
+        let _ = writeln!(out, "|---|---:|---:|---:|");
+        for (metric, now, before, now_value, before_value) in [
+            (
+                "Cost",
+                format!("${:.2}", cur.cost),
+                format!("${:.2}", prev.cost),
+                cur.cost,
+                prev.cost,
+            ),
+            (
+                "Sessions",
+                cur.sessions.to_string(),
+                prev.sessions.to_string(),
+                cur.sessions as f64,
+                prev.sessions as f64,
+            ),
+            (
+                "Lines changed",
+                cur.lines_changed.to_string(),
+                prev.lines_changed.to_string(),
+                cur.lines_changed as f64,
+                prev.lines_changed as f64,
+            ),
+            (
+                "Tokens",
+                format_token_count(cur.tokens as usize),
+                format_token_count(prev.tokens as usize),
+                cur.tokens as f64,
+                prev.tokens as f64,
+            ),
+        ] {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} |",
+                metric,
+                now,
+                before,
+                format_change(now_value, before_value)
+            );
+        }
+
+        if !self.projects.is_empty() {
+            let _ = writeln!(out, "\n## Top projects\n");
+            let _ = writeln!(out, "| # | Project | Cost | Tokens | Sessions |");
+            let _ = writeln!(out, "|---:|---|---:|---:|---:|");
+            for (rank, project) in self.projects.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | ${:.2} | {} | {} |",
+                    rank + 1,
+                    md_cell(&project_name(Some(&project.key))),
+                    project.cost,
+                    format_token_count(project.tokens as usize),
+                    project.sessions
+                );
+            }
+        }
+
+        if !self.models.is_empty() {
+            let total_sessions: u64 = self.models.iter().map(|m| m.sessions).sum();
+            let total_tokens: u64 = self.models.iter().map(|m| m.tokens).sum();
+            let total_cost: f64 = self.models.iter().map(|m| m.cost).sum();
+            let _ = writeln!(out, "\n## Model mix\n");
+            let _ = writeln!(out, "| Model | Sessions | Tokens | Cost |");
+            let _ = writeln!(out, "|---|---:|---:|---:|");
+            for model in &self.models {
+                let _ = writeln!(
+                    out,
+                    "| {} | {:.1}% | {:.1}% | {:.1}% (${:.2}) |",
+                    md_cell(&model.key),
+                    share(model.sessions as f64, total_sessions as f64),
+                    share(model.tokens as f64, total_tokens as f64),
+                    share(model.cost, total_cost),
+                    model.cost
+                );
+            }
+        }
+
+        if !self.sessions.is_empty() {
+            let _ = writeln!(out, "\n## Biggest sessions\n");
+            let _ = writeln!(
+                out,
+                "| Started | Project | Model | Duration | Cost | Tokens |"
+            );
+            let _ = writeln!(out, "|---|---|---|---:|---:|---:|");
+            for session in &self.sessions {
+                let started = DateTime::parse_from_rfc3339(&session.start_time)
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|_| session.start_time.clone());
+                let model = session
+                    .model_name
+                    .as_deref()
+                    .map(|m| ModelType::from_name(m).canonical_name())
+                    .unwrap_or_else(|| "Unknown".to_string());
+                let duration = session
+                    .duration_secs
+                    .map(|d| format_duration(d, &self.duration_format))
+                    .unwrap_or_else(|| "-".to_string());
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} | ${:.2} | {} |",
+                    started,
+                    md_cell(&project_name(session.workspace_dir.as_deref())),
+                    md_cell(&model),
+                    duration,
+                    session.cost,
+                    format_token_count(session.tokens as usize)
+                );
+            }
+        }
+
+        out
+    }
+}
+
+/// Model key for ccusage: the API model ID when the stored name is recognized
+fn ccusage_model_name(model_name: &str) -> String {
+    if model_name.is_empty() {
+        return "unknown".to_string();
+    }
+    if model_name.starts_with("claude-") {
+        return model_name.to_string();
+    }
+    ModelType::from_name(model_name)
+        .model_id()
+        .unwrap_or_else(|| model_name.to_string())
+}
+
+/// Builds the JSON structure produced by `ccusage daily --json`
+/// (`daily` entries with per-model breakdowns plus overall `totals`).
+/// Models are keyed by API model ID, as ccusage does.
+pub fn to_ccusage_json(rows: &[DailyModelUsage]) -> serde_json::Value {
+    use serde_json::json;
+
+    let mut daily: Vec<serde_json::Value> = Vec::new();
+    let mut totals = [0u64; 4];
+    let mut total_cost = 0.0;
+
+    for day_rows in rows.chunk_by(|a, b| a.date == b.date) {
+        let mut sums = [0u64; 4];
+        let mut cost = 0.0;
+        let mut breakdowns = Vec::new();
+        for row in day_rows {
+            let model = ccusage_model_name(&row.model_name);
+            let counts = [
+                row.input_tokens,
+                row.output_tokens,
+                row.cache_creation_tokens,
+                row.cache_read_tokens,
+            ];
+            for (sum, count) in sums.iter_mut().zip(counts) {
+                *sum += count;
+            }
+            cost += row.cost;
+            breakdowns.push(json!({
+                "modelName": model,
+                "inputTokens": row.input_tokens,
+                "outputTokens": row.output_tokens,
+                "cacheCreationTokens": row.cache_creation_tokens,
+                "cacheReadTokens": row.cache_read_tokens,
+                "cost": row.cost,
+            }));
+        }
+        for (total, sum) in totals.iter_mut().zip(sums) {
+            *total += sum;
+        }
+        total_cost += cost;
+
+        let models_used: Vec<&serde_json::Value> =
+            breakdowns.iter().map(|b| &b["modelName"]).collect();
+        daily.push(json!({
+            "date": day_rows[0].date,
+            "inputTokens": sums[0],
+            "outputTokens": sums[1],
+            "cacheCreationTokens": sums[2],
+            "cacheReadTokens": sums[3],
+            "totalTokens": sums.iter().sum::<u64>(),
+            "totalCost": cost,
+            "modelsUsed": models_used,
+            "modelBreakdowns": breakdowns,
+        }));
+    }
+
+    json!({
+        "daily": daily,
+        "totals": {
+            "inputTokens": totals[0],
+            "outputTokens": totals[1],
+            "cacheCreationTokens": totals[2],
+            "cacheReadTokens": totals[3],
+            "totalTokens": totals.iter().sum::<u64>(),
+            "totalCost": total_cost,
+        }
+    })
+}
+
+/// Reads the (date, model) rows back out of `ccusage daily --json` output.
+///
+/// Days without `modelBreakdowns` become a single row for their first
+/// `modelsUsed` entry. Model IDs are stored under their canonical name, the
+/// way the statusline records models.
+pub fn from_ccusage_json(json: &serde_json::Value) -> Result<Vec<DailyModelUsage>> {
+    let invalid = |what: &str| StatuslineError::InvalidInput(format!("ccusage JSON: {}", what));
+    let daily = json["daily"]
+        .as_array()
+        .ok_or_else(|| invalid("missing the `daily` array"))?;
+
+    let mut rows = Vec::new();
+    for day in daily {
+        let date = day["date"]
+            .as_str()
+            .filter(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+            .ok_or_else(|| invalid("day without a YYYY-MM-DD `date`"))?;
+        let count = |entry: &serde_json::Value, key: &str| entry[key].as_u64().unwrap_or(0);
+        let row = |entry: &serde_json::Value, model: &str, cost: f64| DailyModelUsage {
+            date: date.to_string(),
+            model_name: stored_model_name(model),
+            input_tokens: count(entry, "inputTokens"),
+            output_tokens: count(entry, "outputTokens"),
+            cache_creation_tokens: count(entry, "cacheCreationTokens"),
+            cache_read_tokens: count(entry, "cacheReadTokens"),
+            cost,
+        };
+        match day["modelBreakdowns"].as_array() {
+            Some(breakdowns) if !breakdowns.is_empty() => {
+                for entry in breakdowns {
+                    rows.push(row(
+                        entry,
+                        entry["modelName"].as_str().unwrap_or_default(),
+                        entry["cost"].as_f64().unwrap_or(0.0),
+                    ));
+                }
+            }
+            _ => rows.push(row(
+                day,
+                day["modelsUsed"][0].as_str().unwrap_or_default(),
+                day["totalCost"].as_f64().unwrap_or(0.0),
+            )),
+        }
+    }
+    Ok(rows)
+}
+
+/// Model name to store for an imported model ID ("claude-sonnet-4-20250514"
+/// → "Sonnet 4"); unrecognized names are kept as they are
+fn stored_model_name(model_id: &str) -> String {
+    // Drop the date suffix so it isn't mistaken for the version
+    let base = match model_id.rsplit_once('-') {
+        Some((base, date)) if date.len() == 8 && date.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => model_id,
+    };
+    match ModelType::from_name(base) {
+        ModelType::Unknown if model_id == "unknown" => String::new(),
+        ModelType::Unknown => model_id.to_string(),
+        model => model.canonical_name(),
+    }
+}
+
+/// A session's start and the time worked in it, for timesheet rows.
+///
+/// The worked time is the recorded active time, or the span from start to end
+/// when none was recorded. Sessions without a parseable start or without any
+/// time worked are skipped.
+fn time_block(
+    entry: &TimesheetEntry,
+) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>, u64)> {
+    let start = DateTime::parse_from_rfc3339(&entry.start_time).ok()?;
+    let end = DateTime::parse_from_rfc3339(&entry.end_time)
+        .ok()
+        .filter(|end| *end >= start)
+        .unwrap_or(start);
+    let worked = if entry.active_secs > 0 {
+        entry.active_secs
+    } else {
+        (end - start).num_seconds().max(0) as u64
+    };
+    (worked > 0).then_some((start, end, worked))
+}
+
+/// Project name for timesheets: the workspace's directory name
+fn timesheet_project(workspace: Option<&str>) -> String {
+    workspace
+        .map(|dir| dir.trim_end_matches(['/', '\\']))
+        .and_then(|dir| dir.rsplit(['/', '\\']).next())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Quotes a CSV field when it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Builds a timesheet CSV with one row per session: local date, project,
+/// start and end times, hours worked, cost, note and session ID.
+pub fn to_timesheet_csv(entries: &[TimesheetEntry]) -> String {
+    let mut out = String::from("date,project,start,end,hours,cost,description,session_id\n");
+    for entry in entries {
+        let Some((start, end, worked)) = time_block(entry) else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{:.2},{:.2},{},{}",
+            start.format("%Y-%m-%d"),
+            csv_field(&timesheet_project(entry.workspace_dir.as_deref())),
+            start.format("%H:%M"),
+            end.format("%H:%M"),
+            worked as f64 / 3600.0,
+            entry.cost,
+            csv_field(entry.note.as_deref().unwrap_or_default()),
+            csv_field(&entry.session_id)
+        );
+    }
+    out
+}
+
+/// Builds a CSV in the Toggl Track import format (`Email`, `Project`,
+/// `Description`, `Start date`, `Start time`, `Duration`). Durations are the
+/// time worked, starting at the session start.
+pub fn to_toggl_csv(entries: &[TimesheetEntry], email: &str) -> String {
+    let mut out = String::from("Email,Project,Description,Start date,Start time,Duration\n");
+    for entry in entries {
+        let Some((start, _, worked)) = time_block(entry) else {
+            continue;
+        };
+        let description = entry.note.as_deref().unwrap_or("Claude Code session");
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{:02}:{:02}:{:02}",
+            csv_field(email),
+            csv_field(&timesheet_project(entry.workspace_dir.as_deref())),
+            csv_field(description),
+            start.format("%Y-%m-%d"),
+            start.format("%H:%M:%S"),
+            worked / 3600,
+            worked % 3600 / 60,
+            worked % 60
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_daily_series_fills_gaps() {
+        let end = NaiveDate::from_ymd_opt(2025, 3, 2).unwrap();
+        let rows = vec![
+            ("2025-02-28".to_string(), 2.0),
+            ("2025-03-02".to_string(), 1.0),
+        ];
+        let series = fill_daily_series(&rows, end, 4);
+        let labels: Vec<&str> = series.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["2025-02-27", "2025-02-28", "2025-03-01", "2025-03-02"]
+        );
+        assert_eq!(series[1].1, 2.0);
+        assert_eq!(series[2].1, 0.0);
+        assert_eq!(series[3].1, 1.0);
+    }
+
+    #[test]
+    fn test_forecast_month_linear() {
+        // 10 days in, $5 a day, no earlier history
+        let today = NaiveDate::from_ymd_opt(2025, 4, 10).unwrap();
+        let rows: Vec<(String, f64)> = (1..=10)
+            .map(|day| (format!("2025-04-{:02}", day), 5.0))
+            .collect();
+        let forecast = forecast_month(&rows, today);
+        assert_eq!(forecast.spent, 50.0);
+        assert_eq!(forecast.days_elapsed, 10);
+        assert_eq!(forecast.days_in_month, 30);
+        assert!((forecast.linear - 150.0).abs() < 1e-9);
+        // Nine active days of history are enough for a weekday-adjusted forecast,
+        // averaged over those days rather than the whole history window
+        assert!((forecast.seasonal.unwrap() - 150.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_forecast_month_seasonal_skips_weekends() {
+        // Eight weeks of $10 weekdays and free weekends before Wed 2025-10-15
+        let today = NaiveDate::from_ymd_opt(2025, 10, 15).unwrap();
+        let start = today - Duration::days(FORECAST_HISTORY_WEEKS * 7);
+        let rows: Vec<(String, f64)> = start
+            .iter_days()
+            .take_while(|d| *d <= today)
+            .filter(|d| d.weekday().num_days_from_monday() < 5)
+            .map(|d| (d.format("%Y-%m-%d").to_string(), 10.0))
+            .collect();
+        let forecast = forecast_month(&rows, today);
+
+        // Oct 1-15 has 11 weekdays; Oct 16-31 has 12 more
+        assert_eq!(forecast.spent, 110.0);
+        assert!((forecast.seasonal.unwrap() - 230.0).abs() < 1e-9);
+        // The linear projection spreads weekday spend over weekends too
+        assert!(forecast.linear > 220.0);
+
+        // Without history there is no weekday-adjusted forecast
+        let forecast = forecast_month(&[("2025-10-15".to_string(), 3.0)], today);
+        assert_eq!(forecast.seasonal, None);
+    }
+
+    #[test]
+    fn test_fill_hourly_series() {
+        let series = fill_hourly_series(&[(9, 1.5), (14, 3.0), (30, 9.0)]);
+        assert_eq!(series.len(), 24);
+        assert_eq!(series[9], ("09".to_string(), 1.5));
+        assert_eq!(series[14].1, 3.0);
+        assert_eq!(series.iter().map(|(_, v)| v).sum::<f64>(), 4.5);
+    }
+
+    #[test]
+    fn test_render_bar_chart_scaling() {
+        let rows = vec![
+            ("a".to_string(), 4.0),
+            ("bb".to_string(), 2.0),
+            ("c".to_string(), 0.0),
+        ];
+        let lines = render_bar_chart(&rows, 4);
+        assert_eq!(lines[0], "a  │████ $4.00");
+        assert_eq!(lines[1], "bb │██   $2.00");
+        assert_eq!(lines[2], "c  │     $0.00");
+    }
+
+    #[test]
+    fn test_render_bar_small_values_visible() {
+        assert_eq!(render_bar(0.001, 100.0, 10), "▏");
+        assert_eq!(render_bar(0.0, 100.0, 10), "");
+        assert_eq!(render_bar(5.0, 10.0, 2), "█");
+        assert_eq!(render_bar(1.0, 16.0, 2), "▏");
+    }
+
+    #[test]
+    fn test_comparison_periods() {
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+        let periods = comparison_periods(d(2025, 3, 31));
+
+        assert_eq!(periods[0].current, (d(2025, 3, 31), d(2025, 3, 31)));
+        assert_eq!(periods[0].previous, (d(2025, 3, 30), d(2025, 3, 30)));
+        assert_eq!(periods[1].current, (d(2025, 3, 25), d(2025, 3, 31)));
+        assert_eq!(periods[1].previous, (d(2025, 3, 18), d(2025, 3, 24)));
+        assert_eq!(periods[2].current, (d(2025, 3, 1), d(2025, 3, 31)));
+        // February is shorter, so the previous span is clamped to its last day
+        assert_eq!(periods[2].previous, (d(2025, 2, 1), d(2025, 2, 28)));
+
+        let periods = comparison_periods(d(2025, 1, 10));
+        assert_eq!(periods[2].previous, (d(2024, 12, 1), d(2024, 12, 10)));
+    }
+
+    #[test]
+    fn test_format_change() {
+        assert_eq!(format_change(1.25, 1.0), "▲ +25.0%");
+        assert_eq!(format_change(0.9, 1.0), "▼ -10.0%");
+        assert_eq!(format_change(1.0, 1.0), "= 0.0%");
+        assert_eq!(format_change(3.0, 0.0), "new");
+        assert_eq!(format_change(0.0, 0.0), "-");
+        assert_eq!(percent_change(0.0, 2.0), Some(-100.0));
+    }
+
+    #[test]
+    fn test_period_start() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 12).unwrap();
+        assert_eq!(period_start("today", today), Some(today));
+        assert_eq!(
+            period_start("week", today),
+            NaiveDate::from_ymd_opt(2025, 3, 6)
+        );
+        assert_eq!(
+            period_start("month", today),
+            NaiveDate::from_ymd_opt(2025, 3, 1)
+        );
+        assert_eq!(period_start("all", today), None);
+    }
+
+    #[test]
+    fn test_merge_model_usage() {
+        let row = |key: &str, cost, tokens, sessions| GroupedUsage {
+            key: key.to_string(),
+            cost,
+            tokens,
+            sessions,
+        };
+        let merged = merge_model_usage(&[
+            row("claude-sonnet-4-5-20250929", 1.0, 100, 1),
+            row("Opus", 5.0, 50, 1),
+            row("Sonnet 4.5", 2.0, 200, 2),
+            row("", 0.5, 0, 3),
+        ]);
+
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0].key, "Opus");
+        assert_eq!(merged[1], row("Sonnet 4.5", 3.0, 300, 3));
+        assert_eq!(merged[2].key, "Unknown");
+    }
+
+    #[test]
+    fn test_share() {
+        assert_eq!(share(1.0, 4.0), 25.0);
+        assert_eq!(share(1.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_heatmap_grid_and_render() {
+        // Sunday 09:00 and Monday 23:00
+        let grid = build_heatmap_grid(&[(0, 9, 4.0), (1, 23, 1.0), (9, 1, 5.0)]);
+        assert_eq!(grid[6][9], 4.0);
+        assert_eq!(grid[0][23], 1.0);
+
+        let lines = render_heatmap(&grid);
+        assert_eq!(lines.len(), 8);
+        assert!(lines[0].starts_with("    00    03"));
+        assert!(lines[1].starts_with("Mon "));
+        assert!(lines[1].ends_with("░░"));
+        assert_eq!(lines[7].chars().nth(4 + 18), Some('█'));
+    }
+
+    #[test]
+    fn test_period_report_markdown() {
+        let d = |day| NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+        let report = PeriodReport {
+            title: "Weekly usage report".to_string(),
+            start: d(6),
+            end: d(12),
+            current: PeriodTotals {
+                cost: 12.5,
+                sessions: 4,
+                lines_changed: 300,
+                tokens: 42_000,
+            },
+            previous: PeriodTotals {
+                cost: 10.0,
+                ..PeriodTotals::default()
+            },
+            projects: vec![GroupedUsage {
+                key: "/work/a|b".to_string(),
+                cost: 12.5,
+                tokens: 42_000,
+                sessions: 4,
+            }],
+            models: vec![],
+            sessions: vec![SessionSummary {
+                session_id: "s1".to_string(),
+                start_time: "2025-03-07T09:15:00+00:00".to_string(),
+                cost: 8.0,
+                tokens: 30_000,
+                model_name: Some("claude-opus-4-1".to_string()),
+                workspace_dir: None,
+                duration_secs: Some(3900),
+            }],
+            duration_format: "clock".to_string(),
+        };
+
+        let md = report.to_markdown();
+        assert!(md.starts_with("# Weekly usage report\n\n_2025-03-06 to 2025-03-12_\n"));
+        assert!(md.contains("| Cost | $12.50 | $10.00 | ▲ +25.0% |"));
+        assert!(md.contains("| Sessions | 4 | 0 | new |"));
+        assert!(md.contains("| 1 | /work/a\\|b | $12.50 | 42k | 4 |"));
+        assert!(!md.contains("## Model mix"));
+        assert!(md.contains("| 2025-03-07 09:15 | (unknown) | Opus 4.1 | 01:05 | $8.00 | 30k |"));
+    }
+
+    #[test]
+    fn test_daily_cost_split() {
+        let row = |date: &str, model: &str, output, cache_read, cost| DailyModelUsage {
+            date: date.to_string(),
+            model_name: model.to_string(),
+            input_tokens: 0,
+            output_tokens: output,
+            cache_creation_tokens: 0,
+            cache_read_tokens: cache_read,
+            cost,
+        };
+        let days = daily_cost_split(&[
+            row("2025-03-01", "Opus 4.5", 1_000_000, 0, 20.0),
+            row("2025-03-01", "Sonnet 4.5", 0, 1_000_000, 1.0),
+            row("2025-03-02", "Sonnet 4.5", 0, 0, 0.5),
+        ]);
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date, "2025-03-01");
+        assert_eq!(days[0].cost, 21.0);
+        // Each model is priced at its own rates
+        assert_eq!(days[0].split.output, 25.0);
+        assert!((days[0].split.cache_read - 0.30).abs() < 1e-9);
+        // No tokens recorded: nothing to split
+        assert_eq!(days[1].split.total(), 0.0);
+    }
+
+    #[test]
+    fn test_ccusage_json() {
+        let row = |date: &str, model: &str, input, cost| DailyModelUsage {
+            date: date.to_string(),
+            model_name: model.to_string(),
+            input_tokens: input,
+            output_tokens: 10,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 100,
+            cost,
+        };
+        let json = to_ccusage_json(&[
+            row("2025-03-01", "Opus 4.1", 50, 2.0),
+            row("2025-03-01", "Sonnet 4.5", 20, 0.5),
+            row("2025-03-02", "", 5, 0.25),
+        ]);
+
+        let daily = json["daily"].as_array().unwrap();
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily[0]["date"], "2025-03-01");
+        assert_eq!(daily[0]["inputTokens"], 70);
+        assert_eq!(daily[0]["totalTokens"], 290);
+        assert_eq!(daily[0]["totalCost"], 2.5);
+        assert_eq!(
+            daily[0]["modelsUsed"],
+            serde_json::json!(["claude-opus-4-1", "claude-sonnet-4-5"])
+        );
+        assert_eq!(daily[1]["modelBreakdowns"][0]["modelName"], "unknown");
+        assert_eq!(json["totals"]["inputTokens"], 75);
+        assert_eq!(json["totals"]["totalCost"], 2.75);
+
+        // Reading the export back gives the same rows
+        let rows = from_ccusage_json(&json).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].model_name, "Opus 4.1");
+        assert_eq!(rows[1].input_tokens, 20);
+        assert_eq!(rows[2].model_name, "");
+        assert_eq!(rows[2].cost, 0.25);
+    }
+
+    #[test]
+    fn test_from_ccusage_json() {
+        let json = serde_json::json!({
+            "daily": [
+                {
+                    "date": "2025-06-01",
+                    "inputTokens": 10,
+                    "totalCost": 1.5,
+                    "modelsUsed": ["claude-sonnet-4-20250514"],
+                    "modelBreakdowns": []
+                },
+                {
+                    "date": "2025-06-02",
+                    "modelBreakdowns": [{
+                        "modelName": "<synthetic>",
+                        "outputTokens": 7,
+                        "cost": 0.0
+                    }]
+                }
+            ]
+        });
+        let rows = from_ccusage_json(&json).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].model_name, "Sonnet 4");
+        assert_eq!(rows[0].input_tokens, 10);
+        assert_eq!(rows[0].cost, 1.5);
+        assert_eq!(rows[1].model_name, "<synthetic>");
+        assert_eq!(rows[1].output_tokens, 7);
+
+        assert!(from_ccusage_json(&serde_json::json!({"totals": {}})).is_err());
+        assert!(from_ccusage_json(&serde_json::json!({"daily": [{"date": "June"}]})).is_err());
+    }
+
+    #[test]
+    fn test_timesheet_csv() {
+        let entry = |id: &str, start: &str, end: &str, active, note: Option<&str>| TimesheetEntry {
+            session_id: id.to_string(),
+            start_time: start.to_string(),
+            end_time: end.to_string(),
+            active_secs: active,
+            workspace_dir: Some("/home/me/code/app".to_string()),
+            cost: 1.5,
+            note: note.map(str::to_string),
+        };
+        let entries = [
+            entry(
+                "s1",
+                "2025-03-07T09:00:00+01:00",
+                "2025-03-07T10:30:00+01:00",
+                0,
+                Some("fix login, \"again\""),
+            ),
+            entry(
+                "s2",
+                "2025-03-07T14:00:00+01:00",
+                "2025-03-07T16:00:00+01:00",
+                2700,
+                None,
+            ),
+            // Single update, no active time: nothing worked
+            entry(
+                "s3",
+                "2025-03-07T17:00:00+01:00",
+                "2025-03-07T17:00:00+01:00",
+                0,
+                None,
+            ),
+        ];
+
+        let csv = to_timesheet_csv(&entries);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "2025-03-07,app,09:00,10:30,1.50,1.50,\"fix login, \"\"again\"\"\",s1"
+        );
+        assert_eq!(lines[2], "2025-03-07,app,14:00,16:00,0.75,1.50,,s2");
+
+        let toggl = to_toggl_csv(&entries, "me@example.com");
+        let lines: Vec<&str> = toggl.lines().collect();
+        assert_eq!(
+            lines[0],
+            "Email,Project,Description,Start date,Start time,Duration"
+        );
+        assert_eq!(
+            lines[2],
+            "me@example.com,app,Claude Code session,2025-03-07,14:00:00,00:45:00"
+        );
+    }
+}
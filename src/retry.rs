@@ -63,6 +63,7 @@ impl RetryConfig {
     }
 
     /// Quick configuration for database operations (from config)
+    #[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
     pub fn for_db_ops() -> Self {
         let app_config = config::get_config();
         Self::from(&app_config.retry.db_ops)
@@ -84,6 +85,7 @@ impl RetryConfig {
 }
 
 /// Retry a fallible operation with exponential backoff
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
 pub fn retry_with_backoff<F, T>(config: &RetryConfig, mut operation: F) -> Result<T>
 where
     F: FnMut() -> Result<T>,
@@ -121,6 +123,7 @@ where
 }
 
 /// Retry a fallible operation with simple fixed delay
+#[cfg_attr(not(feature = "git"), allow(dead_code))]
 pub fn retry_simple<F, T>(max_attempts: u32, delay_ms: u64, operation: F) -> Result<T>
 where
     F: FnMut() -> Result<T>,
@@ -141,6 +144,7 @@ pub fn is_retryable_error(error: &StatuslineError) -> bool {
         // I/O errors are often transient
         StatuslineError::Io(_) => true,
         // Database busy errors are retryable
+        #[cfg(feature = "sqlite")]
         StatuslineError::Database(e) => {
             let error_string = e.to_string().to_lowercase();
             error_string.contains("busy")
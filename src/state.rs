@@ -2,11 +2,12 @@
 //
 // This module provides file-based state persistence for Claude Code hooks.
 // State files are session-scoped, ephemeral, and automatically cleaned up.
+// They live in the XDG state directory, separate from caches and durable data.
 
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::error::Result;
 
@@ -34,8 +35,9 @@ pub struct HookState {
 const STALE_TIMEOUT_SECONDS: i64 = 120; // 2 minutes
 
 /// Get the cache directory for state files
-fn get_cache_dir() -> Result<PathBuf> {
-    let cache_dir = dirs::cache_dir()
+pub(crate) fn get_cache_dir() -> Result<PathBuf> {
+    let cache_dir = crate::environment::current()
+        .cache_dir()
         .ok_or_else(|| {
             crate::error::StatuslineError::Config("Cannot determine cache directory".to_string())
         })?
@@ -47,10 +49,79 @@ fn get_cache_dir() -> Result<PathBuf> {
     Ok(cache_dir)
 }
 
+/// Runtime state files that older versions kept in the cache directory
+fn is_legacy_state_file(name: &str) -> bool {
+    (name.starts_with("state-") || name.starts_with("lines-")) && name.ends_with(".json")
+        || name == "sync-state.json"
+        || name == "update-check.stamp"
+}
+
+/// Get the directory for runtime state (hook state, sync backoff, update-check
+/// stamp): `$XDG_STATE_HOME/claudia-statusline`, or `~/.local/state/...`.
+///
+/// Unlike caches, these files can't be recomputed; unlike the data directory,
+/// they're only relevant while sessions run, so they stay out of backups.
+/// Platforms without a state directory (macOS, Windows) use the cache
+/// directory. State left in the cache directory by older versions is moved
+/// over when the state directory is first created.
+pub(crate) fn get_state_dir() -> Result<PathBuf> {
+    let env = crate::environment::current();
+    let base = env
+        .var("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env.state_dir());
+    let Some(base) = base else {
+        return get_cache_dir();
+    };
+    let state_dir = base.join("claudia-statusline");
+
+    if !state_dir.exists() {
+        fs::create_dir_all(&state_dir)?;
+        if let Ok(cache_dir) = get_cache_dir() {
+            if cache_dir != state_dir {
+                migrate_legacy_state(&cache_dir, &state_dir);
+            }
+        }
+    }
+
+    Ok(state_dir)
+}
+
+/// Moves state files from `from` to `to` (best effort), returning how many moved.
+fn migrate_legacy_state(from: &Path, to: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(from) else {
+        return 0;
+    };
+    let mut moved = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if !is_legacy_state_file(&name.to_string_lossy()) {
+            continue;
+        }
+        let target = to.join(&name);
+        // Renames fail across filesystems; fall back to copying
+        let ok = fs::rename(entry.path(), &target).is_ok()
+            || (fs::copy(entry.path(), &target).is_ok() && fs::remove_file(entry.path()).is_ok());
+        if ok {
+            moved += 1;
+        }
+    }
+    if moved > 0 {
+        log::info!(
+            "Moved {} state files from {} to {}",
+            moved,
+            from.display(),
+            to.display()
+        );
+    }
+    moved
+}
+
 /// Get the state file path for a session
 fn get_state_file_path(session_id: &str) -> Result<PathBuf> {
-    let cache_dir = get_cache_dir()?;
-    Ok(cache_dir.join(format!("state-{}.json", session_id)))
+    let state_dir = get_state_dir()?;
+    Ok(state_dir.join(format!("state-{}.json", session_id)))
 }
 
 /// Write state to file atomically
@@ -119,7 +190,9 @@ pub fn read_state(session_id: &str) -> Option<HookState> {
     }
 
     // Check staleness
-    let age = Utc::now().signed_duration_since(state.started_at);
+    let age = crate::environment::current()
+        .now()
+        .signed_duration_since(state.started_at);
     if age > Duration::seconds(STALE_TIMEOUT_SECONDS) {
         log::info!(
             "Stale state detected (age: {}s), deleting",
@@ -151,21 +224,25 @@ pub fn clear_state(session_id: &str) -> Result<()> {
     Ok(())
 }
 
-/// Clean up all stale state files in the cache directory
-#[allow(dead_code)]
+/// Clean up all stale state files in the state directory, along with the
+/// `lines-<session>.json` files older versions kept (now in the stats database)
 pub fn cleanup_stale_states() -> Result<usize> {
-    let cache_dir = get_cache_dir()?;
+    let state_dir = get_state_dir()?;
     let mut cleaned = 0;
 
     // Iterate over state files
-    for entry in fs::read_dir(&cache_dir)? {
+    for entry in fs::read_dir(&state_dir)? {
         let entry = entry?;
         let path = entry.path();
 
         // Only process state-*.json files
         if let Some(filename) = path.file_name() {
             let filename = filename.to_string_lossy();
-            if filename.starts_with("state-") && filename.ends_with(".json") {
+            if filename.starts_with("lines-") && filename.ends_with(".json") {
+                if fs::remove_file(&path).is_ok() {
+                    cleaned += 1;
+                }
+            } else if filename.starts_with("state-") && filename.ends_with(".json") {
                 // Extract session ID
                 if let Some(session_id) = filename
                     .strip_prefix("state-")
@@ -297,9 +374,42 @@ mod tests {
             write_state(&state).unwrap();
         }
 
+        let lines_file = get_state_dir()
+            .unwrap()
+            .join(format!("lines-{}.json", test_session_id()));
+        fs::write(&lines_file, "[]").unwrap();
+
         // Run cleanup
         let cleaned = cleanup_stale_states().unwrap();
-        assert!(cleaned >= 3, "Should clean at least 3 stale states");
+        assert!(
+            cleaned >= 4,
+            "Should clean at least 3 stale states and the lines file"
+        );
+        assert!(!lines_file.exists());
+    }
+
+    #[test]
+    fn test_migrate_legacy_state() {
+        let cache = tempfile::tempdir().unwrap();
+        let state = tempfile::tempdir().unwrap();
+        for name in [
+            "state-abc.json",
+            "lines-abc.json",
+            "sync-state.json",
+            "update-check.stamp",
+            "git-tag.json",
+            "token-cache.json",
+        ] {
+            fs::write(cache.path().join(name), "{}").unwrap();
+        }
+
+        assert_eq!(migrate_legacy_state(cache.path(), state.path()), 4);
+        assert!(state.path().join("state-abc.json").exists());
+        assert!(state.path().join("update-check.stamp").exists());
+        assert!(!cache.path().join("lines-abc.json").exists());
+        // Caches stay where they are
+        assert!(cache.path().join("git-tag.json").exists());
+        assert!(!state.path().join("token-cache.json").exists());
     }
 
     #[test]
@@ -3,23 +3,54 @@
 //! This module provides persistent statistics tracking for Claude Code sessions,
 //! including costs, line changes, and usage metrics. Statistics are stored in
 //! both JSON and SQLite formats for reliability and concurrent access.
+//!
+//! Without the `sqlite` cargo feature `stats.json` is the only store and the
+//! database-backed extras (context history, active time, cost alerts) are no-ops.
 
-use crate::common::{current_date, current_month, current_timestamp, get_data_dir};
+use crate::common::{current_date, current_month, current_timestamp, get_data_dir, mark_degraded};
 use crate::config::get_config;
+#[cfg(feature = "sqlite")]
 use crate::database::SqliteDatabase;
 use crate::error::{Result, StatuslineError};
 use crate::retry::{retry_if_retryable, RetryConfig};
 use fs2::FileExt;
-use log::{debug, error, warn};
+#[cfg(feature = "sqlite")]
+use log::{debug, info};
+use log::{error, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::{self, File, OpenOptions};
-use std::io::{Read, Seek, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+/// Parameters for updating a session in the database
+#[derive(Clone)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub struct SessionUpdate {
+    pub cost: f64,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+    pub model_name: Option<String>,
+    pub workspace_dir: Option<String>,
+    pub device_id: Option<String>,
+    pub token_breakdown: Option<crate::models::TokenBreakdown>,
+    pub max_tokens_observed: Option<u32>,
+    /// Git branch checked out in the working directory, for per-branch costs
+    pub git_branch: Option<String>,
+}
+
+/// Structure version of `stats.json`. Bump it and add an upgrade step to
+/// [`StatsData::from_json`] when fields are added, moved or change meaning.
+///
+/// - 1: original layout (also assumed for files without a `version`)
+/// - 2: missing fields default instead of failing the whole file, and unknown
+///   top-level fields are kept on rewrite
+pub const STATS_JSON_VERSION: u32 = 2;
+
 /// Persistent stats tracking structures
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StatsData {
     pub version: String,
     pub created: String,
@@ -28,9 +59,13 @@ pub struct StatsData {
     pub daily: HashMap<String, DailyStats>,
     pub monthly: HashMap<String, MonthlyStats>,
     pub all_time: AllTimeStats,
+    /// Top-level fields written by a newer version, preserved as-is
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SessionStats {
     pub last_updated: String,
     pub cost: f64,
@@ -42,7 +77,8 @@ pub struct SessionStats {
     pub max_tokens_observed: Option<u32>, // For adaptive context learning
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DailyStats {
     pub total_cost: f64,
     pub sessions: Vec<String>,
@@ -50,7 +86,8 @@ pub struct DailyStats {
     pub lines_removed: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MonthlyStats {
     pub total_cost: f64,
     pub sessions: usize,
@@ -59,6 +96,7 @@ pub struct MonthlyStats {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct AllTimeStats {
     pub total_cost: f64,
     pub sessions: usize,
@@ -69,7 +107,7 @@ impl Default for StatsData {
     fn default() -> Self {
         let now = current_timestamp();
         StatsData {
-            version: "1.0".to_string(),
+            version: format!("{}.0", STATS_JSON_VERSION),
             created: now.clone(),
             last_updated: now.clone(),
             sessions: HashMap::new(),
@@ -80,13 +118,36 @@ impl Default for StatsData {
                 sessions: 0,
                 since: now,
             },
+            extra: serde_json::Map::new(),
         }
     }
 }
 
 impl StatsData {
+    /// Parses a `stats.json` document, upgrading backups written with an older
+    /// [`STATS_JSON_VERSION`] so restoring them doesn't drop data.
+    pub fn from_json(contents: &str) -> Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(contents)?;
+        let data = value
+            .as_object_mut()
+            .ok_or_else(|| StatuslineError::other("stats file is not a JSON object"))?;
+
+        let version = json_version(data);
+        if version < 2 {
+            upgrade_json_v1(data);
+        } else if version > STATS_JSON_VERSION {
+            warn!(
+                "stats.json is version {}, newer than this binary supports ({}); unknown fields are kept as-is",
+                version, STATS_JSON_VERSION
+            );
+        }
+
+        Ok(serde_json::from_value(value)?)
+    }
+
     pub fn load() -> Self {
         // Phase 2: Try SQLite first, then fall back to JSON
+        #[cfg(feature = "sqlite")]
         if let Ok(data) = Self::load_from_sqlite() {
             return data;
         }
@@ -96,9 +157,10 @@ impl StatsData {
 
         if path.exists() {
             if let Ok(contents) = fs::read_to_string(&path) {
-                match serde_json::from_str(&contents) {
+                match Self::from_json(&contents) {
                     Ok(data) => {
                         // Migrate JSON data to SQLite if needed
+                        #[cfg(feature = "sqlite")]
                         if let Err(e) = Self::migrate_to_sqlite(&data) {
                             log::warn!("Failed to migrate JSON to SQLite: {}", e);
                         }
@@ -115,14 +177,31 @@ impl StatsData {
             }
         }
 
-        // Only create default if file doesn't exist (not if corrupted)
+        // Only create default if file doesn't exist (not if corrupted), and
+        // not while the disk guard is holding back stats writes
         let default_data = Self::default();
-        // Try to save the default, but don't fail if we can't
-        let _ = default_data.save();
+        if check_disk_space().is_ok() {
+            // Try to save the default, but don't fail if we can't
+            let _ = default_data.save();
+        }
         default_data
     }
 
+    /// Loads stats without creating, migrating or backing up any file.
+    fn load_read_only() -> Self {
+        #[cfg(feature = "sqlite")]
+        if let Ok(data) = Self::load_from_sqlite() {
+            return data;
+        }
+
+        fs::read_to_string(Self::get_stats_file_path())
+            .ok()
+            .and_then(|contents| Self::from_json(&contents).ok())
+            .unwrap_or_default()
+    }
+
     /// Load stats data from SQLite database (Phase 2)
+    #[cfg(feature = "sqlite")]
     pub fn load_from_sqlite() -> Result<Self> {
         let db_path = Self::get_sqlite_path()?;
 
@@ -163,6 +242,7 @@ impl StatsData {
     }
 
     /// Migrate JSON data to SQLite if not already done
+    #[cfg(feature = "sqlite")]
     fn migrate_to_sqlite(data: &Self) -> Result<()> {
         let db_path = Self::get_sqlite_path()?;
         let db = SqliteDatabase::new(&db_path)?;
@@ -198,38 +278,39 @@ impl StatsData {
     pub fn save(&self) -> Result<()> {
         let config = get_config();
 
-        // Save to JSON if backup is enabled
-        if config.database.json_backup {
+        // Save to JSON if backup is enabled (always without the SQLite backend)
+        if config.database.json_backup || !cfg!(feature = "sqlite") {
             let path = Self::get_stats_file_path();
 
-            // Acquire and lock the file with retry
-            let mut file = acquire_stats_file(&path)?;
+            // Hold the lock while replacing the file
+            let _lock = acquire_stats_lock(&path)?;
 
             // Save the data using our helper
-            save_stats_data(&mut file, self);
+            save_stats_data(&path, self);
         } else {
             log::info!("Skipping JSON backup (json_backup=false, SQLite-only mode)");
         }
 
         // Always save to SQLite (it's now the primary storage)
+        #[cfg(feature = "sqlite")]
         perform_sqlite_dual_write(self);
 
         Ok(())
     }
 
     pub fn get_stats_file_path() -> PathBuf {
-        get_data_dir().join("stats.json")
+        let path = get_data_dir().join("stats.json");
+        match crate::common::get_profile() {
+            Some(profile) => crate::common::namespaced_path(&path, &profile),
+            None => path,
+        }
     }
 
     pub fn get_sqlite_path() -> Result<PathBuf> {
-        Ok(get_data_dir().join("stats.db"))
+        Ok(crate::common::get_database_path())
     }
 
-    pub fn update_session(
-        &mut self,
-        session_id: &str,
-        update: crate::database::SessionUpdate,
-    ) -> (f64, f64) {
+    pub fn update_session(&mut self, session_id: &str, update: SessionUpdate) -> (f64, f64) {
         let today = current_date();
         let month = current_month();
         let now = current_timestamp();
@@ -238,19 +319,26 @@ impl StatsData {
         // This ensures model_name, workspace_dir, device_id, and token breakdown are persisted immediately
         // SqliteDatabase::new() will create the database if it doesn't exist
         // Note: max_tokens_observed will be updated separately from main.rs/lib.rs
-        if let Ok(db_path) = Self::get_sqlite_path() {
-            if let Ok(db) = SqliteDatabase::new(&db_path) {
-                if let Err(e) = db.update_session(session_id, update.clone()) {
-                    log::warn!("Failed to persist session {} to SQLite: {}", session_id, e);
+        // Skipped while disk space is low (see check_disk_space)
+        #[cfg(feature = "sqlite")]
+        if check_disk_space().is_ok() {
+            if let Ok(db_path) = Self::get_sqlite_path() {
+                if let Ok(db) = SqliteDatabase::new(&db_path) {
+                    if let Err(e) = db.update_session(session_id, update.clone()) {
+                        let e = StatuslineError::from(e).coded();
+                        log::warn!("Failed to persist session {} to SQLite: {}", session_id, e);
+                        mark_degraded("stats database", &e);
+                    }
+                } else {
+                    log::warn!(
+                        "Failed to open SQLite database at {:?} for session update",
+                        db_path
+                    );
+                    mark_degraded("stats database", "failed to open database");
                 }
             } else {
-                log::warn!(
-                    "Failed to open SQLite database at {:?} for session update",
-                    db_path
-                );
+                log::warn!("Failed to get SQLite path for session update");
             }
-        } else {
-            log::warn!("Failed to get SQLite path for session update");
         }
 
         // Calculate delta from last known session cost
@@ -300,6 +388,7 @@ impl StatsData {
         let mut session_seen_this_month = false;
 
         // Try to check SQLite first (authoritative source)
+        #[cfg(feature = "sqlite")]
         if let Ok(db_path) = Self::get_sqlite_path() {
             if db_path.exists() {
                 if let Ok(db) = SqliteDatabase::new(&db_path) {
@@ -396,7 +485,8 @@ impl StatsData {
         }
 
         // Persist to SQLite database using dedicated method
-        if let Ok(db_path) = Self::get_sqlite_path() {
+        #[cfg(feature = "sqlite")]
+        if let (Ok(()), Ok(db_path)) = (check_disk_space(), Self::get_sqlite_path()) {
             if let Ok(db) = SqliteDatabase::new(&db_path) {
                 if let Err(e) = db.update_max_tokens_observed(session_id, current_tokens) {
                     log::warn!(
@@ -428,13 +518,87 @@ pub fn get_or_load_stats_data() -> StatsData {
     StatsData::load()
 }
 
+/// Major structure version of a parsed `stats.json` ("1.0" -> 1); files
+/// written before the field was checked count as version 1
+fn json_version(data: &serde_json::Map<String, serde_json::Value>) -> u32 {
+    match data.get("version") {
+        Some(serde_json::Value::String(v)) => v
+            .split('.')
+            .next()
+            .and_then(|major| major.parse().ok())
+            .unwrap_or(1),
+        Some(serde_json::Value::Number(n)) => n.as_u64().map_or(1, |v| v as u32),
+        _ => 1,
+    }
+}
+
+/// Upgrades a version 1 `stats.json` in place. Early version 1 writers did
+/// not keep `all_time`, `monthly` or `created`; rebuild them from the sessions
+/// and daily totals instead of letting them default to zero.
+fn upgrade_json_v1(data: &mut serde_json::Map<String, serde_json::Value>) {
+    use serde_json::{json, Value};
+
+    let parse = |key: &str| data.get(key).cloned().unwrap_or(Value::Null);
+    let sessions: HashMap<String, SessionStats> =
+        serde_json::from_value(parse("sessions")).unwrap_or_default();
+    let daily: HashMap<String, DailyStats> =
+        serde_json::from_value(parse("daily")).unwrap_or_default();
+
+    let since = sessions
+        .values()
+        .map(|s| s.start_time.as_deref().unwrap_or(&s.last_updated))
+        .filter(|started| !started.is_empty())
+        .min()
+        .map(str::to_string)
+        .unwrap_or_else(current_timestamp);
+
+    if !data.contains_key("all_time") {
+        let all_time = AllTimeStats {
+            total_cost: sessions.values().map(|s| s.cost).sum(),
+            sessions: sessions.len(),
+            since: since.clone(),
+        };
+        data.insert("all_time".to_string(), json!(all_time));
+    }
+
+    if !data.contains_key("monthly") {
+        let mut monthly: HashMap<String, MonthlyStats> = HashMap::new();
+        for (date, day) in &daily {
+            let month = monthly
+                .entry(date.get(..7).unwrap_or(date).to_string())
+                .or_default();
+            month.total_cost += day.total_cost;
+            month.sessions += day.sessions.len();
+            month.lines_added += day.lines_added;
+            month.lines_removed += day.lines_removed;
+        }
+        data.insert("monthly".to_string(), json!(monthly));
+    }
+
+    if !data.contains_key("created") {
+        data.insert("created".to_string(), json!(since));
+    }
+    data.insert(
+        "version".to_string(),
+        json!(format!("{}.0", STATS_JSON_VERSION)),
+    );
+}
+
 fn get_stats_backup_path() -> Result<PathBuf> {
-    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let timestamp = crate::environment::now_local().format("%Y%m%d_%H%M%S");
     Ok(get_data_dir().join(format!("stats_backup_{}.json", timestamp)))
 }
 
-// Helper function to acquire and lock the stats file with retry
-fn acquire_stats_file(path: &Path) -> Result<File> {
+/// Lock file guarding `stats.json`. The JSON file itself is replaced by rename
+/// on every save, so a lock held on it would not outlive the write.
+fn stats_lock_path(path: &Path) -> PathBuf {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+// Helper function to acquire the stats lock file with retry
+fn acquire_stats_lock(path: &Path) -> Result<File> {
     // Ensure directory exists
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
@@ -442,15 +606,15 @@ fn acquire_stats_file(path: &Path) -> Result<File> {
 
     // Use retry configuration for file operations
     let retry_config = RetryConfig::for_file_ops();
+    let lock_path = stats_lock_path(path);
 
-    // Try to open the file with retry
+    // Try to open the lock file with retry
     let file = retry_if_retryable(&retry_config, || {
         OpenOptions::new()
-            .read(true)
             .write(true)
             .create(true)
             .truncate(false)
-            .open(path)
+            .open(&lock_path)
             .map_err(StatuslineError::from)
     })?;
 
@@ -465,55 +629,96 @@ fn acquire_stats_file(path: &Path) -> Result<File> {
 }
 
 // Helper function to load stats data from file
-fn load_stats_data(file: &mut File, path: &Path) -> StatsData {
-    let mut contents = String::new();
-    if file.read_to_string(&mut contents).is_ok() && !contents.is_empty() {
-        match serde_json::from_str(&contents) {
-            Ok(data) => {
-                // Migrate JSON data to SQLite if needed
-                if let Err(e) = StatsData::migrate_to_sqlite(&data) {
-                    log::warn!("Failed to migrate JSON to SQLite: {}", e);
-                }
-                data
+fn load_stats_data(path: &Path) -> StatsData {
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    if contents.is_empty() {
+        return StatsData::default();
+    }
+    match StatsData::from_json(&contents) {
+        Ok(data) => {
+            // Migrate JSON data to SQLite if needed
+            #[cfg(feature = "sqlite")]
+            if let Err(e) = StatsData::migrate_to_sqlite(&data) {
+                log::warn!("Failed to migrate JSON to SQLite: {}", e);
             }
-            Err(e) => {
-                warn!(
-                    "Stats file corrupted: {}. Creating backup and starting fresh.",
-                    e
-                );
-                // Try to create a backup of the corrupted file
-                if let Ok(backup_path) = get_stats_backup_path() {
-                    if let Err(e) = std::fs::copy(path, &backup_path) {
-                        error!("Failed to backup corrupted stats file: {}", e);
-                    } else {
-                        warn!("Corrupted stats backed up to: {:?}", backup_path);
-                    }
+            data
+        }
+        Err(e) => {
+            warn!(
+                "Stats file corrupted: {}. Creating backup and starting fresh.",
+                e
+            );
+            // Try to create a backup of the corrupted file
+            if let Ok(backup_path) = get_stats_backup_path() {
+                if let Err(e) = std::fs::copy(path, &backup_path) {
+                    error!("Failed to backup corrupted stats file: {}", e);
+                } else {
+                    warn!("Corrupted stats backed up to: {:?}", backup_path);
                 }
-                StatsData::default()
             }
+            StatsData::default()
         }
-    } else {
-        StatsData::default()
     }
 }
 
 // Helper function to save stats data to file
-fn save_stats_data(file: &mut File, stats_data: &StatsData) {
-    // Write back to file (truncate and write)
-    if let Err(e) = file.set_len(0) {
-        error!("Failed to truncate stats file: {}", e);
+fn save_stats_data(path: &Path, stats_data: &StatsData) {
+    if let Err(e) = write_stats_file(path, stats_data) {
+        error!("Failed to write stats file: {}", e);
     }
-    if let Err(e) = file.seek(std::io::SeekFrom::Start(0)) {
-        error!("Failed to seek stats file: {}", e);
+}
+
+/// Replace `path` with the serialized stats: the JSON is written to a temp
+/// file, fsynced, read back and checked before being renamed over the target,
+/// so an interrupted or short write leaves the previous backup intact.
+fn write_stats_file(path: &Path, stats_data: &StatsData) -> Result<()> {
+    let json = serde_json::to_string_pretty(stats_data)?;
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".tmp.{}", std::process::id()));
+    let temp = PathBuf::from(temp);
+
+    let result = (|| -> Result<()> {
+        let mut file = File::create(&temp)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        verify_stats_file(&temp, &json)?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+        return result;
     }
 
-    let json = serde_json::to_string_pretty(stats_data).unwrap_or_else(|_| "{}".to_string());
-    if let Err(e) = file.write_all(json.as_bytes()) {
-        error!("Failed to write stats file: {}", e);
+    // Persist the rename itself (directories can't be opened for sync on Windows)
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
     }
+    Ok(())
+}
+
+/// Check that a written stats file holds exactly `expected` and parses back.
+fn verify_stats_file(path: &Path, expected: &str) -> Result<()> {
+    let written = fs::read_to_string(path)?;
+    if written != expected {
+        return Err(StatuslineError::other(format!(
+            "stats file {} is incomplete ({} of {} bytes)",
+            path.display(),
+            written.len(),
+            expected.len()
+        )));
+    }
+    StatsData::from_json(&written)?;
+    Ok(())
 }
 
 // Write the current session to SQLite
+#[cfg(feature = "sqlite")]
 #[allow(dead_code)]
 fn write_current_session_to_sqlite(db: &SqliteDatabase, stats_data: &StatsData) {
     if let Some((session_id, session)) = stats_data
@@ -521,7 +726,6 @@ fn write_current_session_to_sqlite(db: &SqliteDatabase, stats_data: &StatsData)
         .iter()
         .max_by_key(|(_, s)| &s.last_updated)
     {
-        use crate::database::SessionUpdate;
         match db.update_session(
             session_id,
             SessionUpdate {
@@ -533,6 +737,7 @@ fn write_current_session_to_sqlite(db: &SqliteDatabase, stats_data: &StatsData)
                 device_id: None,       // not available in dual-write
                 token_breakdown: None, // not available in dual-write
                 max_tokens_observed: session.max_tokens_observed, // from in-memory stats
+                git_branch: None,      // not available in dual-write
             },
         ) {
             Ok((day_total, session_total)) => {
@@ -549,6 +754,7 @@ fn write_current_session_to_sqlite(db: &SqliteDatabase, stats_data: &StatsData)
 }
 
 // Helper function to write to SQLite (primary storage)
+#[cfg(feature = "sqlite")]
 fn perform_sqlite_dual_write(_stats_data: &StatsData) {
     // Write to SQLite (primary storage as of Phase 2)
     let db_path = match StatsData::get_sqlite_path() {
@@ -562,10 +768,12 @@ fn perform_sqlite_dual_write(_stats_data: &StatsData) {
     let _db = match SqliteDatabase::new(&db_path) {
         Ok(d) => d,
         Err(e) => {
+            let e = StatuslineError::from(e).coded();
             error!(
                 "Failed to initialize SQLite database at {:?}: {}",
                 db_path, e
             );
+            mark_degraded("stats database", &e);
             return;
         }
     };
@@ -573,6 +781,13 @@ fn perform_sqlite_dual_write(_stats_data: &StatsData) {
     // NOTE: Migration is now handled in load_stats_data() when JSON is loaded
     // Current session is written directly in update_session() with all migration v5 fields
     // No need to call write_current_session_to_sqlite() as that would overwrite model_name/workspace_dir/tokens with NULL
+
+    if let Err(e) = crate::database::enforce_size_limit(&db_path) {
+        warn!(
+            "Failed to enforce database size limit: {}",
+            StatuslineError::from(e).coded()
+        );
+    }
 }
 
 /// Updates the statistics data with process-safe file locking.
@@ -594,7 +809,7 @@ fn perform_sqlite_dual_write(_stats_data: &StatsData) {
 ///
 /// ```rust,no_run
 /// use statusline::stats::update_stats_data;
-/// use statusline::database::SessionUpdate;
+/// use statusline::stats::SessionUpdate;
 ///
 /// let (daily, monthly) = update_stats_data(|stats| {
 ///     stats.update_session(
@@ -608,6 +823,7 @@ fn perform_sqlite_dual_write(_stats_data: &StatsData) {
 ///             device_id: None,
 ///             token_breakdown: None,
 ///             max_tokens_observed: None,
+///             git_branch: None,
 ///         },
 ///     )
 /// });
@@ -616,58 +832,459 @@ pub fn update_stats_data<F>(updater: F) -> (f64, f64)
 where
     F: FnOnce(&mut StatsData) -> (f64, f64),
 {
-    let config = get_config();
-    let path = StatsData::get_stats_file_path();
-
-    // Load existing stats data
-    let mut stats_data = if config.database.json_backup {
-        // Acquire and lock the file with retry
-        let mut file = match acquire_stats_file(&path) {
-            Ok(f) => f,
-            Err(e) => {
-                error!("Failed to acquire stats file after retries: {}", e);
-                return (0.0, 0.0);
-            }
-        };
+    // Never add to a nearly full disk: apply the update in memory only
+    if let Err(e) = check_disk_space() {
+        warn!("Not saving stats: {}", e.coded());
+        return updater(&mut StatsData::load_read_only());
+    }
 
-        let mut data = load_stats_data(&mut file, &path);
+    #[cfg(feature = "sqlite")]
+    if !get_config().database.json_backup {
+        // SQLite-only mode: load from SQLite
+        debug!("Operating in SQLite-only mode (json_backup=false)");
+        let mut stats_data = StatsData::load_from_sqlite().unwrap_or_else(|e| {
+            warn!("Failed to load from SQLite: {}", e.coded());
+            mark_degraded("stats database", &e.coded());
+            StatsData::default()
+        });
 
         // Apply the update
-        let result = updater(&mut data);
+        let result = updater(&mut stats_data);
 
-        // Save updated stats data to JSON
-        save_stats_data(&mut file, &data);
+        // Save to SQLite (primary storage)
+        perform_sqlite_dual_write(&stats_data);
 
-        // Perform SQLite write
-        perform_sqlite_dual_write(&data);
-
-        // File lock is automatically released when file is dropped
         return result;
-    } else {
-        // SQLite-only mode: load from SQLite
-        debug!("Operating in SQLite-only mode (json_backup=false)");
-        StatsData::load_from_sqlite().unwrap_or_else(|e| {
-            warn!("Failed to load from SQLite: {}", e);
-            StatsData::default()
-        })
+    }
+
+    // Acquire the stats lock with retry
+    let path = StatsData::get_stats_file_path();
+    let _lock = match acquire_stats_lock(&path) {
+        Ok(f) => f,
+        Err(e) => {
+            error!("Failed to acquire stats file after retries: {}", e.coded());
+            mark_degraded("stats file", &e.coded());
+            return (0.0, 0.0);
+        }
     };
 
+    let mut data = load_stats_data(&path);
+
     // Apply the update
-    let result = updater(&mut stats_data);
+    let result = updater(&mut data);
+
+    // Save updated stats data to JSON
+    save_stats_data(&path, &data);
 
-    // Save to SQLite (primary storage)
-    perform_sqlite_dual_write(&stats_data);
+    // Perform SQLite write
+    #[cfg(feature = "sqlite")]
+    perform_sqlite_dual_write(&data);
 
+    // Lock is automatically released when the lock file is dropped
     result
 }
 
 /// Get the daily total from stats data
 #[allow(dead_code)]
 pub fn get_daily_total(data: &StatsData) -> f64 {
-    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let today = crate::environment::now_local()
+        .format("%Y-%m-%d")
+        .to_string();
     data.daily.get(&today).map(|d| d.total_cost).unwrap_or(0.0)
 }
 
+/// Fails with `LowDiskSpace` while the filesystem holding the stats database
+/// (`database.path`) has less free space than `database.min_free_space_mb`;
+/// stats writes are skipped then.
+pub fn check_disk_space() -> Result<()> {
+    let min_mb = get_config().database.min_free_space_mb;
+    if min_mb == 0 {
+        return Ok(());
+    }
+    match crate::common::free_space_mb(&crate::common::get_database_path()) {
+        Some(free_mb) if free_mb < min_mb => Err(StatuslineError::LowDiskSpace { free_mb, min_mb }),
+        _ => Ok(()),
+    }
+}
+
+/// Records a context usage sample for the sparkline history (best effort).
+#[cfg(feature = "sqlite")]
+pub fn record_context_sample(session_id: &str, percentage: f64) {
+    if check_disk_space().is_err() {
+        return;
+    }
+    let keep = get_config().display.sparkline_samples.max(1);
+    if let Ok(db_path) = StatsData::get_sqlite_path() {
+        if let Ok(db) = SqliteDatabase::new(&db_path) {
+            if let Err(e) = db.record_context_sample(session_id, percentage, keep) {
+                warn!(
+                    "Failed to record context sample for session {}: {}",
+                    session_id, e
+                );
+            }
+        }
+    }
+}
+
+/// Records what a render reads from the session's transcript (best effort):
+/// its active time, the usage of API calls since the last render and, with
+/// `display.show_files_edited`, the files edited. The transcript is scanned
+/// once and everything is written in one transaction. Shared by the binary's
+/// and the library's render paths.
+#[cfg(feature = "sqlite")]
+pub fn record_transcript_activity(session_id: &str, transcript_path: &str) {
+    let Some(activity) = crate::utils::transcript_activity(transcript_path) else {
+        return;
+    };
+    if check_disk_space().is_err() {
+        return;
+    }
+    if let Ok(db_path) = StatsData::get_sqlite_path() {
+        if let Ok(db) = SqliteDatabase::new(&db_path) {
+            let with_files = get_config().display.show_files_edited;
+            if let Err(e) = db.record_transcript_activity(session_id, &activity, with_files) {
+                warn!(
+                    "Failed to record transcript activity for session {}: {}",
+                    session_id, e
+                );
+            }
+        }
+    }
+}
+
+/// Whether `cost.alert_daily` or `cost.alert_session` is set.
+///
+/// Alerts are stored in the database, so they stay off without the `sqlite` feature.
+pub fn alerts_enabled() -> bool {
+    let cost = &get_config().cost;
+    cfg!(feature = "sqlite") && (cost.alert_daily > 0.0 || cost.alert_session > 0.0)
+}
+
+/// Fires the configured cost alerts whose thresholds are exceeded (best effort).
+///
+/// Fired alerts are stored in the database and shown until acknowledged. With
+/// `cost.alert_command` set, an alert that stays exceeded is delivered through
+/// it at most once per `cost.alert_cooldown_minutes`, tracked in the
+/// `alert_log` table.
+#[cfg(feature = "sqlite")]
+pub fn check_cost_alerts(session_id: Option<&str>, session_cost: Option<f64>, daily_total: f64) {
+    let cost = &get_config().cost;
+    let mut exceeded = Vec::new();
+    if cost.alert_daily > 0.0 && daily_total > cost.alert_daily {
+        exceeded.push(("daily", current_date(), cost.alert_daily, daily_total));
+    }
+    if let (Some(sid), Some(session_cost)) = (session_id, session_cost) {
+        if cost.alert_session > 0.0 && session_cost > cost.alert_session {
+            exceeded.push(("session", sid.to_string(), cost.alert_session, session_cost));
+        }
+    }
+    if exceeded.is_empty() || check_disk_space().is_err() {
+        return;
+    }
+
+    if let Ok(db_path) = StatsData::get_sqlite_path() {
+        if let Ok(db) = SqliteDatabase::new(&db_path) {
+            let cooldown_secs = cost.alert_cooldown_minutes * 60;
+            for (kind, scope, threshold, value) in exceeded {
+                if let Err(e) = db.record_cost_alert(kind, &scope, threshold, value) {
+                    warn!("Failed to record {} cost alert: {}", kind, e);
+                    continue;
+                }
+                // Without a delivery command the statusline segment is the only output
+                if cost.alert_command.is_empty() {
+                    continue;
+                }
+                // Acknowledged alerts are not delivered again
+                let active = db.get_active_alerts().unwrap_or_default();
+                if !active.iter().any(|a| a.kind == kind && a.scope == scope) {
+                    continue;
+                }
+                match db.try_fire_alert(&format!("cost_{}", kind), &scope, cooldown_secs) {
+                    Ok(true) => deliver_alert(&cost.alert_command, kind, &scope, threshold, value),
+                    Ok(false) => debug!("{} cost alert for {} is cooling down", kind, scope),
+                    Err(e) => warn!("Failed to check {} cost alert cooldown: {}", kind, e),
+                }
+            }
+        }
+    }
+}
+
+/// Runs `cost.alert_command` for an alert without waiting for it to finish.
+#[cfg(feature = "sqlite")]
+fn deliver_alert(command: &str, kind: &str, scope: &str, threshold: f64, value: f64) {
+    use std::process::{Command, Stdio};
+
+    let message = format!(
+        "{} cost alert for {}: ${:.2} exceeds ${:.2}",
+        kind, scope, value, threshold
+    );
+    info!("{}", message);
+
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    let spawned = cmd
+        .arg(command)
+        .env("STATUSLINE_ALERT_KIND", kind)
+        .env("STATUSLINE_ALERT_SCOPE", scope)
+        .env("STATUSLINE_ALERT_THRESHOLD", format!("{:.2}", threshold))
+        .env("STATUSLINE_ALERT_VALUE", format!("{:.2}", value))
+        .env("STATUSLINE_ALERT_MESSAGE", &message)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(e) = spawned {
+        warn!("Failed to run alert_command for {} cost alert: {}", kind, e);
+    }
+}
+
+/// Gets the cost alerts that have not been acknowledged yet.
+#[cfg(feature = "sqlite")]
+pub fn get_active_alerts() -> Vec<crate::database::CostAlert> {
+    StatsData::get_sqlite_path()
+        .ok()
+        .filter(|p| p.exists())
+        .and_then(|p| SqliteDatabase::new(&p).ok())
+        .and_then(|db| db.get_active_alerts().ok())
+        .unwrap_or_default()
+}
+
+/// Gets the recent context usage samples for a session, oldest first.
+#[cfg(feature = "sqlite")]
+pub fn get_context_samples(session_id: &str) -> Vec<f64> {
+    let limit = get_config().display.sparkline_samples.max(1);
+    StatsData::get_sqlite_path()
+        .ok()
+        .filter(|p| p.exists())
+        .and_then(|p| SqliteDatabase::new(&p).ok())
+        .and_then(|db| db.get_context_samples(session_id, limit).ok())
+        .unwrap_or_default()
+}
+
+/// Gets the number of distinct files edited in a session, if any were recorded.
+#[cfg(feature = "sqlite")]
+pub fn get_edited_file_count(session_id: &str) -> Option<u64> {
+    StatsData::get_sqlite_path()
+        .ok()
+        .filter(|p| p.exists())
+        .and_then(|p| SqliteDatabase::new(&p).ok())
+        .and_then(|db| db.count_session_files(session_id).ok())
+        .filter(|count| *count > 0)
+}
+
+/// Gets the other sessions active within `display.active_session_minutes` and
+/// the combined burn rate of all active ones, including `current_session`.
+#[cfg(feature = "sqlite")]
+pub fn get_active_sessions(
+    current_session: Option<&str>,
+) -> Option<crate::database::ActiveSessions> {
+    let window = get_config().display.active_session_minutes.max(1);
+    StatsData::get_sqlite_path()
+        .ok()
+        .filter(|p| p.exists())
+        .and_then(|p| SqliteDatabase::new(&p).ok())
+        .and_then(|db| db.get_active_sessions(window, current_session).ok())
+}
+
+/// Marks a session as ended (SessionEnd hook, best effort).
+#[cfg(feature = "sqlite")]
+pub fn record_session_end(session_id: &str) {
+    if check_disk_space().is_err() {
+        return;
+    }
+    if let Ok(db_path) = StatsData::get_sqlite_path() {
+        if !db_path.exists() {
+            return;
+        }
+        if let Ok(db) = SqliteDatabase::new(&db_path) {
+            if let Err(e) = db.end_session(session_id) {
+                warn!("Failed to record end of session {}: {}", session_id, e);
+            }
+        }
+    }
+}
+
+/// Gets the note set with `statusline session note`, if any.
+#[cfg(feature = "sqlite")]
+pub fn get_session_note(session_id: &str) -> Option<String> {
+    StatsData::get_sqlite_path()
+        .ok()
+        .filter(|p| p.exists())
+        .and_then(|p| SqliteDatabase::new(&p).ok())
+        .and_then(|db| db.get_session_note(session_id).ok())
+        .flatten()
+}
+
+/// Days of history compared against for the daily-cost anomaly marker
+const ANOMALY_WINDOW_DAYS: i64 = 7;
+
+/// Active days needed in the window before the anomaly marker can show
+const ANOMALY_MIN_DAYS: usize = 3;
+
+/// Smallest share of a day the baseline is prorated to, so the first minutes
+/// after midnight don't compare against a near-zero expectation
+const ANOMALY_MIN_DAY_FRACTION: f64 = 1.0 / 24.0;
+
+/// Whether today's total has reached `cost.anomaly_factor` times the average
+/// active day of the previous week, prorated to the time of day (e.g. a
+/// runaway agent loop). Off unless `cost.anomaly_factor` is set.
+pub fn daily_cost_anomaly(daily_total: f64) -> bool {
+    let factor = get_config().cost.anomaly_factor;
+    if factor <= 0.0 || daily_total <= 0.0 {
+        return false;
+    }
+    let now = crate::environment::now_local();
+    let today = now.date_naive();
+    let start = (today - chrono::Duration::days(ANOMALY_WINDOW_DAYS))
+        .format("%Y-%m-%d")
+        .to_string();
+    let day_fraction =
+        f64::from(chrono::Timelike::num_seconds_from_midnight(&now.time())) / 86_400.0;
+    let (average, days) = trailing_daily_average(&start, &current_date());
+    is_cost_anomaly(daily_total, average, days, factor, day_fraction)
+}
+
+fn is_cost_anomaly(
+    daily_total: f64,
+    average: f64,
+    days: usize,
+    factor: f64,
+    day_fraction: f64,
+) -> bool {
+    let expected = average * day_fraction.clamp(ANOMALY_MIN_DAY_FRACTION, 1.0);
+    days >= ANOMALY_MIN_DAYS && expected > 0.0 && daily_total >= expected * factor
+}
+
+#[cfg(feature = "sqlite")]
+fn trailing_daily_average(start: &str, end: &str) -> (f64, usize) {
+    StatsData::get_sqlite_path()
+        .ok()
+        .filter(|p| p.exists())
+        .and_then(|p| SqliteDatabase::new(&p).ok())
+        .and_then(|db| db.get_average_daily_cost(start, end).ok())
+        .unwrap_or((0.0, 0))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn trailing_daily_average(start: &str, end: &str) -> (f64, usize) {
+    let data = get_or_load_stats_data();
+    let costs: Vec<f64> = data
+        .daily
+        .iter()
+        .filter(|(date, day)| date.as_str() >= start && date.as_str() < end && day.total_cost > 0.0)
+        .map(|(_, day)| day.total_cost)
+        .collect();
+    if costs.is_empty() {
+        return (0.0, 0);
+    }
+    (costs.iter().sum::<f64>() / costs.len() as f64, costs.len())
+}
+
+/// Spend so far in the current `cost.budget_period` ("month" from
+/// `monthly_stats`, "week" from Monday's `daily_stats` onwards).
+#[cfg(feature = "sqlite")]
+pub fn budget_period_spend() -> f64 {
+    let db = match StatsData::get_sqlite_path()
+        .ok()
+        .filter(|p| p.exists())
+        .and_then(|p| SqliteDatabase::new(&p).ok())
+    {
+        Some(db) => db,
+        None => return 0.0,
+    };
+    let result = if get_config().cost.budget_period == "week" {
+        db.get_cost_between(&week_start(), &current_date())
+    } else {
+        db.get_month_total()
+    };
+    result.unwrap_or(0.0)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn budget_period_spend() -> f64 {
+    let data = get_or_load_stats_data();
+    if get_config().cost.budget_period == "week" {
+        let (start, end) = (week_start(), current_date());
+        data.daily
+            .iter()
+            .filter(|(date, _)| date.as_str() >= start.as_str() && date.as_str() <= end.as_str())
+            .map(|(_, day)| day.total_cost)
+            .sum()
+    } else {
+        data.monthly
+            .get(&crate::common::current_month())
+            .map(|m| m.total_cost)
+            .unwrap_or(0.0)
+    }
+}
+
+/// Monday of the current week (YYYY-MM-DD)
+fn week_start() -> String {
+    use chrono::Datelike;
+    let today = crate::environment::now_local().date_naive();
+    (today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// Past sessions needed before a cost percentile is shown
+#[cfg(feature = "sqlite")]
+const PERCENTILE_MIN_SESSIONS: usize = 10;
+
+/// Percentile of a session's cost among the user's past sessions (e.g. 92 when
+/// 92% of them were cheaper), once there are enough sessions to compare against.
+#[cfg(feature = "sqlite")]
+pub fn session_cost_percentile(session_id: &str, cost: f64) -> Option<u32> {
+    StatsData::get_sqlite_path()
+        .ok()
+        .filter(|p| p.exists())
+        .and_then(|p| SqliteDatabase::new(&p).ok())
+        .and_then(|db| db.get_cost_percentile(session_id, cost).ok())
+        .filter(|(_, sessions)| *sessions >= PERCENTILE_MIN_SESSIONS)
+        .map(|(percentile, _)| percentile)
+}
+
+// Without the SQLite backend there is nowhere to keep these histories.
+#[cfg(not(feature = "sqlite"))]
+pub fn session_cost_percentile(_session_id: &str, _cost: f64) -> Option<u32> {
+    None
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn record_context_sample(_session_id: &str, _percentage: f64) {}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn record_transcript_activity(_session_id: &str, _transcript_path: &str) {}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn get_edited_file_count(_session_id: &str) -> Option<u64> {
+    None
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn check_cost_alerts(_session_id: Option<&str>, _session_cost: Option<f64>, _daily_total: f64) {
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn get_context_samples(_session_id: &str) -> Vec<f64> {
+    Vec::new()
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn get_session_note(_session_id: &str) -> Option<String> {
+    None
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn record_session_end(_session_id: &str) {}
+
 pub fn get_session_duration(session_id: &str) -> Option<u64> {
     let data = get_or_load_stats_data();
 
@@ -696,10 +1313,26 @@ mod tests {
     use std::path::Path;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_is_cost_anomaly() {
+        // 2× a $10 average at the end of the day
+        assert!(is_cost_anomaly(20.0, 10.0, 7, 2.0, 1.0));
+        assert!(!is_cost_anomaly(19.0, 10.0, 7, 2.0, 1.0));
+        // At noon the baseline is half a day's average
+        assert!(is_cost_anomaly(10.0, 10.0, 7, 2.0, 0.5));
+        assert!(!is_cost_anomaly(9.0, 10.0, 7, 2.0, 0.5));
+        // Just after midnight the baseline is floored at an hour's share
+        assert!(!is_cost_anomaly(0.5, 10.0, 7, 2.0, 0.0));
+        assert!(is_cost_anomaly(1.0, 10.0, 7, 2.0, 0.0));
+        // Too little history
+        assert!(!is_cost_anomaly(50.0, 10.0, 2, 2.0, 1.0));
+        assert!(!is_cost_anomaly(50.0, 0.0, 0, 2.0, 1.0));
+    }
+
     #[test]
     fn test_stats_data_default() {
         let stats = StatsData::default();
-        assert_eq!(stats.version, "1.0");
+        assert_eq!(stats.version, format!("{}.0", STATS_JSON_VERSION));
         assert!(stats.sessions.is_empty());
         assert!(stats.daily.is_empty());
         assert!(stats.monthly.is_empty());
@@ -709,7 +1342,7 @@ mod tests {
 
     #[test]
     fn test_stats_data_update_session() {
-        use crate::database::SessionUpdate;
+        use super::SessionUpdate;
         let mut stats = StatsData::default();
         let (daily, monthly) = stats.update_session(
             "test-session",
@@ -722,6 +1355,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         );
 
@@ -748,7 +1382,7 @@ mod tests {
     #[test]
     #[serial]
     fn test_stats_save_and_load() {
-        use crate::database::SessionUpdate;
+        use super::SessionUpdate;
         let temp_dir = TempDir::new().unwrap();
         env::set_var("XDG_DATA_HOME", temp_dir.path().to_str().unwrap());
         env::set_var("XDG_CONFIG_HOME", temp_dir.path().to_str().unwrap());
@@ -766,6 +1400,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         );
 
@@ -775,10 +1410,15 @@ mod tests {
         // Make sure data was persisted (either JSON or SQLite)
         // Note: In SQLite-only mode, stats.json may not exist
         let data_dir = env::var("XDG_DATA_HOME").unwrap();
-        let db_path = PathBuf::from(&data_dir)
+        let store = if cfg!(feature = "sqlite") {
+            "stats.db"
+        } else {
+            "stats.json"
+        };
+        let store_path = PathBuf::from(&data_dir)
             .join("claudia-statusline")
-            .join("stats.db");
-        assert!(db_path.exists(), "Database should be created");
+            .join(store);
+        assert!(store_path.exists(), "{} should be created", store);
 
         let loaded_stats = StatsData::load();
         // Check that the session was saved and loaded correctly
@@ -791,7 +1431,7 @@ mod tests {
     #[test]
     #[serial]
     fn test_session_start_time_tracking() {
-        use crate::database::SessionUpdate;
+        use super::SessionUpdate;
         let mut stats = StatsData::default();
 
         // First update creates session with start_time
@@ -806,6 +1446,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         );
 
@@ -826,6 +1467,7 @@ mod tests {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         );
 
@@ -868,7 +1510,7 @@ mod tests {
             let temp_path_clone = temp_path.clone();
             let handle = thread::spawn(move || {
                 // Ensure the thread uses the temp directory
-                use crate::database::SessionUpdate;
+                use super::SessionUpdate;
                 env::set_var("XDG_DATA_HOME", &temp_path_clone);
                 env::set_var("XDG_CONFIG_HOME", &temp_path_clone);
                 let (daily, _) = update_stats_data(|stats| {
@@ -883,6 +1525,7 @@ mod tests {
                             device_id: None,
                             token_breakdown: None,
                             max_tokens_observed: None,
+                            git_branch: None,
                         },
                     )
                 });
@@ -951,7 +1594,7 @@ mod tests {
         initial_stats.save().unwrap();
 
         // Create a session with a specific start time
-        use crate::database::SessionUpdate;
+        use super::SessionUpdate;
         update_stats_data(|stats| {
             stats.update_session(
                 "duration-test-session",
@@ -964,6 +1607,7 @@ mod tests {
                     device_id: None,
                     token_breakdown: None,
                     max_tokens_observed: None,
+                    git_branch: None,
                 },
             )
         });
@@ -991,6 +1635,79 @@ mod tests {
         env::remove_var("XDG_DATA_HOME");
     }
 
+    #[test]
+    fn test_from_json_upgrades_v1_backup() {
+        // Early layout: no version, all_time, monthly or created
+        let data = StatsData::from_json(
+            r#"{
+                "last_updated": "2024-03-02T10:00:00Z",
+                "sessions": {
+                    "a": {"last_updated": "2024-03-01T10:00:00Z", "cost": 1.5, "lines_added": 10, "lines_removed": 2},
+                    "b": {"last_updated": "2024-03-02T10:00:00Z", "cost": 2.5, "lines_added": 5, "lines_removed": 0,
+                          "start_time": "2024-02-28T09:00:00Z"}
+                },
+                "daily": {
+                    "2024-03-01": {"total_cost": 1.5, "sessions": ["a"], "lines_added": 10, "lines_removed": 2},
+                    "2024-03-02": {"total_cost": 2.5, "sessions": ["b"], "lines_added": 5, "lines_removed": 0}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(data.version, format!("{}.0", STATS_JSON_VERSION));
+        assert_eq!(data.all_time.total_cost, 4.0);
+        assert_eq!(data.all_time.sessions, 2);
+        assert_eq!(data.all_time.since, "2024-02-28T09:00:00Z");
+        assert_eq!(data.created, "2024-02-28T09:00:00Z");
+        let march = &data.monthly["2024-03"];
+        assert_eq!(
+            (march.total_cost, march.sessions, march.lines_added),
+            (4.0, 2, 15)
+        );
+    }
+
+    #[test]
+    fn test_from_json_keeps_newer_fields() {
+        let data = StatsData::from_json(
+            r#"{"version": "3.0", "sessions": {}, "daily": {}, "monthly": {}, "budgets": {"daily": 5}}"#,
+        )
+        .unwrap();
+        assert_eq!(data.version, "3.0");
+
+        let written: serde_json::Value = serde_json::to_value(&data).unwrap();
+        assert_eq!(written["budgets"]["daily"], 5);
+        assert_eq!(written["version"], "3.0");
+    }
+
+    #[test]
+    fn test_write_stats_file_replaces_atomically() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("stats.json");
+        fs::write(&path, "{ truncated").unwrap();
+
+        let mut data = StatsData::default();
+        data.all_time.total_cost = 4.2;
+        write_stats_file(&path, &data).unwrap();
+
+        let loaded: StatsData = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(loaded.all_time.total_cost, 4.2);
+        // Only the target is left behind, no temp file
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_verify_stats_file_rejects_short_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("stats.json.tmp");
+        let json = serde_json::to_string_pretty(&StatsData::default()).unwrap();
+
+        fs::write(&path, &json).unwrap();
+        assert!(verify_stats_file(&path, &json).is_ok());
+
+        fs::write(&path, &json[..json.len() / 2]).unwrap();
+        assert!(verify_stats_file(&path, &json).is_err());
+    }
+
     #[test]
     #[serial]
     fn test_file_corruption_recovery() {
@@ -1011,7 +1728,7 @@ mod tests {
 
         // Load should handle corruption gracefully
         let stats = StatsData::load();
-        assert_eq!(stats.version, "1.0");
+        assert_eq!(stats.version, format!("{}.0", STATS_JSON_VERSION));
 
         // Check that backup was created
         let backup_path = stats_path.with_extension("backup");
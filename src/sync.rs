@@ -1,14 +1,26 @@
 // Sync module for cloud synchronization
 // Only compiled when turso-sync feature is enabled
+//
+// Background pushes: every render marks local changes as pending and, once
+// the next attempt is due, spawns a detached `statusline sync flush`. A failed
+// push leaves the change pending and delays the next attempt with exponential
+// backoff plus jitter (`retry.sync_ops`), so an offline machine doesn't retry
+// every render and pending changes go out once the network is back. Pushes
+// send the full local state, so a pending flag is all the queue needs.
 
 use crate::common::get_device_id;
-use crate::config::SyncConfig;
+use crate::config::{self, RetrySettings, SyncConfig};
 use crate::database::SqliteDatabase;
 use crate::error::{Result, StatuslineError};
 use crate::stats::StatsData;
-use chrono::Local;
 use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
 use std::env;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// How long a spawned flush may run before another render may start one
+const FLUSH_IN_FLIGHT_SECONDS: i64 = 120;
 
 /// Sync status information
 #[derive(Debug, Clone)]
@@ -428,13 +440,76 @@ impl SyncManager {
         Ok((sessions, daily_stats, monthly_stats))
     }
 
-    /// Resolve auth token, handling environment variable references
-    /// Supports both ${VAR} and $VAR syntax
+    /// Async helper to measure remote usage
+    /// Returns (rows, storage_bytes); storage is None if the server hides page counts
+    async fn quota_from_turso_async(
+        &self,
+        database_url: &str,
+        auth_token: &str,
+    ) -> Result<(u64, Option<u64>)> {
+        use libsql::Builder;
+
+        let db = Builder::new_remote(database_url.to_string(), auth_token.to_string())
+            .build()
+            .await
+            .map_err(|e| StatuslineError::Sync(format!("Failed to build database: {}", e)))?;
+
+        let conn = db
+            .connect()
+            .map_err(|e| StatuslineError::Sync(format!("Failed to connect: {}", e)))?;
+
+        async fn single_i64(conn: &libsql::Connection, query: &str) -> Result<i64> {
+            let mut rows = conn
+                .query(query, ())
+                .await
+                .map_err(|e| StatuslineError::Sync(format!("Failed to run {}: {}", query, e)))?;
+            let row = rows
+                .next()
+                .await
+                .map_err(|e| StatuslineError::Sync(format!("Failed to read {}: {}", query, e)))?
+                .ok_or_else(|| StatuslineError::Sync(format!("No result for {}", query)))?;
+            row.get(0)
+                .map_err(|e| StatuslineError::Sync(format!("Failed to read {}: {}", query, e)))
+        }
+
+        let mut rows = 0u64;
+        for table in ["sessions", "daily_stats", "monthly_stats"] {
+            rows += single_i64(&conn, &format!("SELECT COUNT(*) FROM {}", table)).await? as u64;
+        }
+
+        let storage = match (
+            single_i64(&conn, "PRAGMA page_count").await,
+            single_i64(&conn, "PRAGMA page_size").await,
+        ) {
+            (Ok(pages), Ok(size)) => Some((pages * size) as u64),
+            _ => None,
+        };
+
+        debug!("Turso usage: {} rows, {:?} bytes", rows, storage);
+        Ok((rows, storage))
+    }
+
+    /// Keyring account holding the auth token for this provider
+    pub fn keyring_account(&self) -> String {
+        format!("sync-{}", self.config.provider)
+    }
+
+    /// Resolve auth token, handling environment variable and keyring references
+    /// Supports ${VAR}, $VAR and "keyring" (stored via `statusline sync login`)
     fn resolve_auth_token(&self, token_config: &str) -> Result<String> {
         if token_config.is_empty() {
             return Ok(String::new());
         }
 
+        if token_config == "keyring" {
+            return crate::keyring::get_secret(&self.keyring_account()).ok_or_else(|| {
+                StatuslineError::Sync(format!(
+                    "No {} token in the OS keyring (run `statusline sync login`)",
+                    self.config.provider
+                ))
+            });
+        }
+
         // Check for environment variable reference
         if token_config.starts_with("${") && token_config.ends_with('}') {
             // Extract variable name: ${VAR_NAME} -> VAR_NAME
@@ -453,6 +528,39 @@ impl SyncManager {
         }
     }
 
+    /// Measure remote usage against the configured plan limits
+    pub fn quota(&mut self) -> Result<QuotaUsage> {
+        if !self.is_enabled() {
+            return Err(StatuslineError::Sync(
+                "Sync is not enabled or not configured".to_string(),
+            ));
+        }
+
+        let auth_token = self.resolve_auth_token(&self.config.turso.auth_token)?;
+
+        let runtime = tokio::runtime::Runtime::new()
+            .map_err(|e| StatuslineError::Sync(format!("Failed to create async runtime: {}", e)))?;
+
+        let result = runtime.block_on(async {
+            self.quota_from_turso_async(&self.config.turso.database_url, &auth_token)
+                .await
+        });
+
+        match result {
+            Ok((rows, storage_bytes)) => Ok(QuotaUsage {
+                rows,
+                storage_bytes,
+                row_limit: self.config.quota_rows,
+                storage_limit_bytes: self.config.quota_storage_mb * 1024 * 1024,
+            }),
+            Err(e) => {
+                self.status.error_message = Some(e.to_string());
+                warn!("Failed to read Turso usage: {}", e);
+                Err(e)
+            }
+        }
+    }
+
     /// Push local stats to remote (Turso)
     pub fn push(&mut self, dry_run: bool) -> Result<PushResult> {
         if !self.is_enabled() {
@@ -472,7 +580,7 @@ impl SyncManager {
         let db = SqliteDatabase::new(&db_path)?;
 
         // Get current timestamp for sync tracking
-        let _sync_timestamp = Local::now().timestamp();
+        let _sync_timestamp = crate::environment::current().now().timestamp();
 
         // Count records to sync
         let sessions_count = db.count_sessions()?;
@@ -528,7 +636,7 @@ impl SyncManager {
 
         match result {
             Ok(counts) => {
-                self.status.last_sync = Some(Local::now().timestamp());
+                self.status.last_sync = Some(crate::environment::current().now().timestamp());
                 info!(
                     "Successfully pushed {} sessions, {} daily, {} monthly stats",
                     counts.0, counts.1, counts.2
@@ -659,7 +767,7 @@ impl SyncManager {
                     monthly_pulled += 1;
                 }
 
-                self.status.last_sync = Some(Local::now().timestamp());
+                self.status.last_sync = Some(crate::environment::current().now().timestamp());
                 info!(
                     "Successfully merged {} sessions ({} conflicts), {} daily, {} monthly stats",
                     sessions_pulled, conflicts_resolved, daily_pulled, monthly_pulled
@@ -682,6 +790,173 @@ impl SyncManager {
     }
 }
 
+/// Remote usage measured against plan limits
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaUsage {
+    /// Rows stored across the synced tables
+    pub rows: u64,
+    /// Database size, when the server reports page counts
+    pub storage_bytes: Option<u64>,
+    /// Row limit (0 = none)
+    pub row_limit: u64,
+    /// Storage limit (0 = none)
+    pub storage_limit_bytes: u64,
+}
+
+impl QuotaUsage {
+    /// Fraction of the row limit used, if one is set
+    pub fn row_fraction(&self) -> Option<f64> {
+        (self.row_limit > 0).then(|| self.rows as f64 / self.row_limit as f64)
+    }
+
+    /// Fraction of the storage limit used, if known and a limit is set
+    pub fn storage_fraction(&self) -> Option<f64> {
+        let used = self.storage_bytes?;
+        (self.storage_limit_bytes > 0).then(|| used as f64 / self.storage_limit_bytes as f64)
+    }
+
+    /// Whether any measured limit is above the soft quota fraction
+    pub fn exceeds(&self, soft_quota_fraction: f64) -> bool {
+        [self.row_fraction(), self.storage_fraction()]
+            .into_iter()
+            .flatten()
+            .any(|f| f >= soft_quota_fraction)
+    }
+}
+
+/// Background push scheduling state, persisted between renders
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncState {
+    /// Local changes not yet pushed
+    pub pending: bool,
+    /// Consecutive failed pushes
+    pub failures: u32,
+    /// Unix time before which no background push starts
+    pub next_attempt: i64,
+    /// Unix time of the last successful push
+    pub last_success: Option<i64>,
+    /// Error from the last failed push
+    pub last_error: Option<String>,
+}
+
+impl SyncState {
+    fn path() -> Option<PathBuf> {
+        crate::state::get_state_dir()
+            .ok()
+            .map(|dir| dir.join("sync-state.json"))
+    }
+
+    /// Loads the saved state (default when missing or unreadable)
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let Some(path) = Self::path() else { return };
+        if let Ok(json) = serde_json::to_string(self) {
+            if let Err(e) = std::fs::write(&path, json) {
+                debug!("Failed to save sync state to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Clears the queue; the next background push waits a full sync interval
+    pub fn record_success(&mut self, now: i64, interval_seconds: u64) {
+        self.pending = false;
+        self.failures = 0;
+        self.next_attempt = now + interval_seconds as i64;
+        self.last_success = Some(now);
+        self.last_error = None;
+    }
+
+    /// Keeps the queue and backs off; `jitter` in 0.0..1.0 spreads retries
+    pub fn record_failure(&mut self, now: i64, error: &str, settings: &RetrySettings, jitter: f64) {
+        self.failures += 1;
+        let delay_ms = backoff_delay_ms(self.failures, settings) as f64;
+        // Equal jitter: half the delay is fixed, half is random
+        let delay_ms = delay_ms / 2.0 + delay_ms / 2.0 * jitter.clamp(0.0, 1.0);
+        self.next_attempt = now + (delay_ms / 1000.0).ceil() as i64;
+        self.last_error = Some(error.to_string());
+    }
+}
+
+/// Exponential backoff for the n-th consecutive failure, capped at `max_delay_ms`
+fn backoff_delay_ms(failures: u32, settings: &RetrySettings) -> u64 {
+    let exponent = failures.saturating_sub(1).min(32) as i32;
+    let delay = settings.initial_delay_ms as f64 * (settings.backoff_factor as f64).powi(exponent);
+    (delay as u64).min(settings.max_delay_ms)
+}
+
+/// Pseudo-random fraction in 0.0..1.0 (no RNG dependency needed)
+fn jitter() -> f64 {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    (hasher.finish() % 10_000) as f64 / 10_000.0
+}
+
+/// Marks local changes as pending and starts a detached push when one is due
+///
+/// Called on every render, so it never touches the network itself.
+pub fn schedule_push() {
+    let config = config::get_config();
+    if !SyncManager::new(config.sync.clone()).is_enabled() {
+        return;
+    }
+
+    let mut state = SyncState::load();
+    let now = crate::environment::current().now().timestamp();
+    let due = now >= state.next_attempt;
+    if state.pending && !due {
+        return;
+    }
+    state.pending = true;
+    if due {
+        // Claimed before spawning so concurrent renders don't start a second push
+        state.next_attempt = now + FLUSH_IN_FLIGHT_SECONDS;
+    }
+    state.save();
+    if !due {
+        return;
+    }
+
+    let spawned = env::current_exe().and_then(|exe| {
+        Command::new(exe)
+            .args(["sync", "flush"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    });
+    if let Err(e) = spawned {
+        debug!("Background sync push not started: {}", e);
+    }
+}
+
+/// Pushes now and records the outcome for the background scheduler
+pub fn flush(manager: &mut SyncManager) -> Result<PushResult> {
+    let result = manager.push(false);
+    let mut state = SyncState::load();
+    let now = crate::environment::current().now().timestamp();
+    match &result {
+        Ok(_) => state.record_success(now, manager.config.sync_interval_seconds),
+        Err(e) => state.record_failure(
+            now,
+            &e.to_string(),
+            &config::get_config().retry.sync_ops,
+            jitter(),
+        ),
+    }
+    state.save();
+    result
+}
+
 /// Result of a push operation
 #[derive(Debug, Clone)]
 pub struct PushResult {
@@ -731,6 +1006,60 @@ mod tests {
         assert!(!manager.is_enabled()); // Not enabled because URL is empty
     }
 
+    #[test]
+    fn test_quota_usage_fractions() {
+        let usage = QuotaUsage {
+            rows: 800,
+            storage_bytes: Some(10 * 1024 * 1024),
+            row_limit: 1000,
+            storage_limit_bytes: 100 * 1024 * 1024,
+        };
+        assert_eq!(usage.row_fraction(), Some(0.8));
+        assert_eq!(usage.storage_fraction(), Some(0.1));
+        assert!(usage.exceeds(0.75));
+        assert!(!usage.exceeds(0.9));
+
+        // Unset limits and unknown storage never trigger the warning
+        let unlimited = QuotaUsage {
+            row_limit: 0,
+            storage_bytes: None,
+            ..usage
+        };
+        assert_eq!(unlimited.row_fraction(), None);
+        assert_eq!(unlimited.storage_fraction(), None);
+        assert!(!unlimited.exceeds(0.0));
+    }
+
+    #[test]
+    fn test_sync_backoff_with_jitter() {
+        let settings = RetrySettings {
+            max_attempts: 0,
+            initial_delay_ms: 30_000,
+            max_delay_ms: 600_000,
+            backoff_factor: 2.0,
+        };
+        assert_eq!(backoff_delay_ms(1, &settings), 30_000);
+        assert_eq!(backoff_delay_ms(3, &settings), 120_000);
+        assert_eq!(backoff_delay_ms(10, &settings), 600_000);
+
+        let mut state = SyncState {
+            pending: true,
+            ..Default::default()
+        };
+        state.record_failure(1_000, "offline", &settings, 0.0);
+        assert_eq!(state.next_attempt, 1_015); // half of 30s without jitter
+        state.record_failure(1_000, "offline", &settings, 1.0);
+        assert_eq!(state.next_attempt, 1_060); // full 60s with maximal jitter
+        assert!(state.pending);
+        assert_eq!(state.failures, 2);
+
+        state.record_success(2_000, 60);
+        assert!(!state.pending);
+        assert_eq!(state.failures, 0);
+        assert_eq!(state.next_attempt, 2_060);
+        assert_eq!(state.last_error, None);
+    }
+
     #[test]
     fn test_resolve_auth_token_direct() {
         let config = SyncConfig::default();
@@ -756,6 +1085,22 @@ mod tests {
         env::remove_var("TEST_TURSO_TOKEN");
     }
 
+    #[test]
+    fn test_resolve_auth_token_keyring() {
+        let config = SyncConfig {
+            provider: "test-provider-without-entry".to_string(),
+            ..Default::default()
+        };
+        let manager = SyncManager::new(config);
+        assert_eq!(
+            manager.keyring_account(),
+            "sync-test-provider-without-entry"
+        );
+
+        let err = manager.resolve_auth_token("keyring").unwrap_err();
+        assert!(err.to_string().contains("sync login"));
+    }
+
     #[test]
     fn test_resolve_auth_token_missing_env() {
         let config = SyncConfig::default();
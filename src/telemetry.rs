@@ -0,0 +1,476 @@
+//! OpenTelemetry span export for statusline renders.
+//!
+//! A render opens with [`start_render`], its stages are timed with [`timed`] and
+//! annotated with [`set_attribute`] from anywhere in the render path. With the
+//! `otel` feature enabled and `telemetry.enabled = true`, [`export_render`] sends
+//! one trace per render (a root span plus one child span per stage) to an
+//! OTLP/HTTP collector using the JSON encoding. Without the feature these
+//! helpers are plain function calls.
+
+/// Span attribute value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    String(String),
+    Double(f64),
+    Int(i64),
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        AttributeValue::String(value.to_string())
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        AttributeValue::Double(value)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(value: i64) -> Self {
+        AttributeValue::Int(value)
+    }
+}
+
+/// Starts the root span of a render (no-op unless enabled).
+///
+/// The first call also resolves the collector address on a background thread,
+/// so the lookup overlaps the render instead of delaying it.
+pub fn start_render() {
+    #[cfg(feature = "otel")]
+    if otel::enabled() {
+        otel::start();
+    }
+}
+
+/// Runs `f` as a named render stage, recording its timing when telemetry is enabled.
+pub fn timed<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "otel")]
+    {
+        if !otel::enabled() {
+            return f();
+        }
+        let start = std::time::SystemTime::now();
+        let result = f();
+        otel::record_stage(name, start, std::time::SystemTime::now());
+        result
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        let _ = name;
+        f()
+    }
+}
+
+/// Sets an attribute on the render's root span.
+pub fn set_attribute(key: &'static str, value: impl Into<AttributeValue>) {
+    #[cfg(feature = "otel")]
+    if otel::enabled() {
+        otel::record_attribute(key, value.into());
+    }
+
+    #[cfg(not(feature = "otel"))]
+    let _ = (key, value.into());
+}
+
+/// Exports the spans recorded for this render (no-op unless enabled).
+pub fn export_render() {
+    #[cfg(feature = "otel")]
+    if otel::enabled() {
+        otel::export();
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use super::AttributeValue;
+    use crate::config::{self, TelemetryConfig};
+    use serde_json::{json, Value};
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+    use std::sync::{Arc, Condvar, Mutex, OnceLock};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    /// A timed render stage.
+    #[derive(Debug, Clone)]
+    pub(super) struct Stage {
+        pub name: &'static str,
+        pub start: SystemTime,
+        pub end: SystemTime,
+    }
+
+    /// Spans and attributes collected during one render.
+    #[derive(Debug)]
+    pub(super) struct Recorder {
+        pub start: SystemTime,
+        pub stages: Vec<Stage>,
+        pub attributes: Vec<(&'static str, AttributeValue)>,
+    }
+
+    fn recorder() -> &'static Mutex<Recorder> {
+        static RECORDER: OnceLock<Mutex<Recorder>> = OnceLock::new();
+        RECORDER.get_or_init(|| {
+            Mutex::new(Recorder {
+                start: SystemTime::now(),
+                stages: Vec::new(),
+                attributes: Vec::new(),
+            })
+        })
+    }
+
+    pub(super) fn enabled() -> bool {
+        config::get_config().telemetry.enabled
+    }
+
+    /// Where spans are posted, once the endpoint has been parsed and resolved.
+    #[derive(Debug, Clone)]
+    struct Collector {
+        host: String,
+        port: u16,
+        path: String,
+        addr: SocketAddr,
+    }
+
+    /// Outcome of the background endpoint lookup; None while it is still running.
+    type Lookup = Arc<(Mutex<Option<Result<Collector, String>>>, Condvar)>;
+
+    /// Starts resolving the collector address on first use.
+    fn collector_lookup() -> &'static Lookup {
+        static LOOKUP: OnceLock<Lookup> = OnceLock::new();
+        LOOKUP.get_or_init(|| {
+            let lookup: Lookup = Arc::new((Mutex::new(None), Condvar::new()));
+            let endpoint = config::get_config().telemetry.endpoint.clone();
+            let shared = Arc::clone(&lookup);
+            let spawned = std::thread::Builder::new()
+                .name("otel-resolve".to_string())
+                .spawn(move || {
+                    let result = resolve_collector(&endpoint);
+                    let (slot, ready) = &*shared;
+                    if let Ok(mut slot) = slot.lock() {
+                        *slot = Some(result);
+                        ready.notify_all();
+                    }
+                });
+            if let Err(e) = spawned {
+                if let Ok(mut slot) = lookup.0.lock() {
+                    *slot = Some(Err(e.to_string()));
+                }
+            }
+            lookup
+        })
+    }
+
+    fn resolve_collector(endpoint: &str) -> Result<Collector, String> {
+        let (host, port, path) = parse_endpoint(endpoint)
+            .ok_or_else(|| "only http://host[:port]/path endpoints are supported".to_string())?;
+        let addr = (host.as_str(), port)
+            .to_socket_addrs()
+            .map_err(|e| e.to_string())?
+            .next()
+            .ok_or_else(|| format!("could not resolve {}", host))?;
+        Ok(Collector {
+            host,
+            port,
+            path,
+            addr,
+        })
+    }
+
+    /// Waits up to `timeout` for the background lookup to finish.
+    fn collector(timeout: Duration) -> Result<Collector, String> {
+        let (slot, ready) = &**collector_lookup();
+        let slot = slot.lock().map_err(|e| e.to_string())?;
+        let (slot, _) = ready
+            .wait_timeout_while(slot, timeout, |slot| slot.is_none())
+            .map_err(|e| e.to_string())?;
+        slot.clone()
+            .unwrap_or_else(|| Err("collector address not resolved in time".to_string()))
+    }
+
+    pub(super) fn start() {
+        collector_lookup();
+        if let Ok(mut rec) = recorder().lock() {
+            rec.start = SystemTime::now();
+            rec.stages.clear();
+            rec.attributes.clear();
+        }
+    }
+
+    pub(super) fn record_stage(name: &'static str, start: SystemTime, end: SystemTime) {
+        if let Ok(mut rec) = recorder().lock() {
+            rec.stages.push(Stage { name, start, end });
+        }
+    }
+
+    pub(super) fn record_attribute(key: &'static str, value: AttributeValue) {
+        if let Ok(mut rec) = recorder().lock() {
+            rec.attributes.retain(|(k, _)| *k != key);
+            rec.attributes.push((key, value));
+        }
+    }
+
+    pub(super) fn export() {
+        let config = &config::get_config().telemetry;
+        let body = match recorder().lock() {
+            Ok(mut rec) => {
+                let body = to_otlp_json(&rec, SystemTime::now(), &config.service_name);
+                // Start a fresh trace for the next render (`--stream` renders repeatedly)
+                rec.start = SystemTime::now();
+                rec.stages.clear();
+                rec.attributes.clear();
+                body
+            }
+            Err(_) => return,
+        };
+        if let Err(e) = post_json(config, &body.to_string()) {
+            log::debug!("OTLP export to {} failed: {}", config.endpoint, e);
+        }
+    }
+
+    /// Random hex identifier of `bytes` length (no RNG dependency needed).
+    fn random_id(bytes: usize) -> String {
+        let mut out = String::with_capacity(bytes * 2);
+        while out.len() < bytes * 2 {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or(0),
+            );
+            hasher.write_u32(std::process::id());
+            out.push_str(&format!("{:016x}", hasher.finish()));
+        }
+        out.truncate(bytes * 2);
+        out
+    }
+
+    fn unix_nanos(time: SystemTime) -> String {
+        time.duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)
+            .to_string()
+    }
+
+    fn attribute_json(key: &str, value: &AttributeValue) -> Value {
+        let value = match value {
+            AttributeValue::String(s) => json!({ "stringValue": s }),
+            AttributeValue::Double(d) => json!({ "doubleValue": d }),
+            // OTLP JSON encodes 64-bit integers as strings
+            AttributeValue::Int(i) => json!({ "intValue": i.to_string() }),
+        };
+        json!({ "key": key, "value": value })
+    }
+
+    /// Builds an OTLP `ExportTraceServiceRequest` in JSON encoding.
+    pub(super) fn to_otlp_json(rec: &Recorder, end: SystemTime, service_name: &str) -> Value {
+        let trace_id = random_id(16);
+        let root_id = random_id(8);
+
+        let mut spans = vec![json!({
+            "traceId": trace_id,
+            "spanId": root_id,
+            "name": "statusline.render",
+            "kind": 1,
+            "startTimeUnixNano": unix_nanos(rec.start),
+            "endTimeUnixNano": unix_nanos(end),
+            "attributes": rec
+                .attributes
+                .iter()
+                .map(|(k, v)| attribute_json(k, v))
+                .collect::<Vec<_>>(),
+        })];
+        for stage in &rec.stages {
+            spans.push(json!({
+                "traceId": trace_id,
+                "spanId": random_id(8),
+                "parentSpanId": root_id,
+                "name": format!("statusline.{}", stage.name),
+                "kind": 1,
+                "startTimeUnixNano": unix_nanos(stage.start),
+                "endTimeUnixNano": unix_nanos(stage.end),
+            }));
+        }
+
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        attribute_json("service.name", &AttributeValue::from(service_name)),
+                        attribute_json(
+                            "service.version",
+                            &AttributeValue::from(env!("CARGO_PKG_VERSION")),
+                        ),
+                    ]
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "statusline", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": spans,
+                }]
+            }]
+        })
+    }
+
+    /// Splits `http://host[:port]/path` into (host, port, path).
+    pub(super) fn parse_endpoint(endpoint: &str) -> Option<(String, u16, String)> {
+        let rest = endpoint.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/v1/traces"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().ok()?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some((host.to_string(), port, path.to_string()))
+    }
+
+    /// Time left until `deadline`, or an error once it has passed.
+    fn remaining(deadline: Instant) -> Result<Duration, String> {
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or_else(|| "export timed out".to_string())
+    }
+
+    /// POSTs a JSON body to the configured endpoint, returning the HTTP status.
+    ///
+    /// `timeout_ms` bounds the whole export: the address lookup, connect, write
+    /// and read each get only what is left of it, so a slow collector delays
+    /// exit by at most one timeout. Gives up if the background lookup of the
+    /// address hasn't finished in time, rather than resolving it here.
+    fn post_json(config: &TelemetryConfig, body: &str) -> Result<u16, String> {
+        let deadline = Instant::now() + Duration::from_millis(config.timeout_ms.max(1));
+        let Collector {
+            host,
+            port,
+            path,
+            addr,
+        } = collector(remaining(deadline)?)?;
+
+        let mut stream =
+            TcpStream::connect_timeout(&addr, remaining(deadline)?).map_err(|e| e.to_string())?;
+        stream
+            .set_write_timeout(Some(remaining(deadline)?))
+            .map_err(|e| e.to_string())?;
+
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            host,
+            port,
+            body.len(),
+            body
+        );
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        // Read the status line piecewise so each read only waits for the time left
+        let mut status_line = [0u8; 12];
+        let mut filled = 0;
+        while filled < status_line.len() {
+            stream
+                .set_read_timeout(Some(remaining(deadline)?))
+                .map_err(|e| e.to_string())?;
+            match stream.read(&mut status_line[filled..]) {
+                Ok(0) => return Err("invalid HTTP response".to_string()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        String::from_utf8_lossy(&status_line[9..12])
+            .parse()
+            .map_err(|_| "invalid HTTP response".to_string())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_post_json_shares_one_deadline() {
+            // A collector that accepts connections but never answers, whose
+            // address lookup takes most of the timeout
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+            let lookup = collector_lookup();
+            let _ = collector(Duration::from_secs(5));
+            *lookup.0.lock().unwrap() = None;
+            let resolved = resolve_collector(&endpoint);
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(300));
+                *lookup.0.lock().unwrap() = Some(resolved);
+                lookup.1.notify_all();
+            });
+            let config = TelemetryConfig {
+                endpoint,
+                timeout_ms: 400,
+                ..Default::default()
+            };
+
+            // Separate timeouts per step would wait ~300ms + 400ms for the reply
+            let started = Instant::now();
+            assert!(post_json(&config, "{}").is_err());
+            assert!(started.elapsed() < Duration::from_millis(600));
+        }
+
+        #[test]
+        fn test_parse_endpoint() {
+            assert_eq!(
+                parse_endpoint("http://localhost:4318/v1/traces"),
+                Some(("localhost".to_string(), 4318, "/v1/traces".to_string()))
+            );
+            assert_eq!(
+                parse_endpoint("http://collector"),
+                Some(("collector".to_string(), 80, "/v1/traces".to_string()))
+            );
+            assert_eq!(parse_endpoint("https://collector:4318/v1/traces"), None);
+        }
+
+        #[test]
+        fn test_resolve_collector() {
+            let collector = resolve_collector("http://127.0.0.1:4318/v1/traces").unwrap();
+            assert_eq!(collector.addr, "127.0.0.1:4318".parse().unwrap());
+            assert_eq!(collector.path, "/v1/traces");
+            assert!(resolve_collector("https://127.0.0.1:4318").is_err());
+        }
+
+        #[test]
+        fn test_otlp_json_structure() {
+            let start = UNIX_EPOCH + Duration::from_millis(1_000);
+            let rec = Recorder {
+                start,
+                stages: vec![Stage {
+                    name: "git",
+                    start,
+                    end: start + Duration::from_millis(5),
+                }],
+                attributes: vec![("statusline.context.percentage", 42.5.into())],
+            };
+            let json = to_otlp_json(&rec, start + Duration::from_millis(10), "svc");
+
+            let spans = &json["resourceSpans"][0]["scopeSpans"][0]["spans"];
+            assert_eq!(spans.as_array().unwrap().len(), 2);
+            assert_eq!(spans[0]["name"], "statusline.render");
+            assert_eq!(spans[0]["traceId"].as_str().unwrap().len(), 32);
+            assert_eq!(spans[0]["startTimeUnixNano"], "1000000000");
+            assert_eq!(spans[0]["attributes"][0]["value"]["doubleValue"], 42.5);
+            assert_eq!(spans[1]["name"], "statusline.git");
+            assert_eq!(spans[1]["parentSpanId"], spans[0]["spanId"]);
+            assert_eq!(spans[1]["endTimeUnixNano"], "1005000000");
+            assert_eq!(
+                json["resourceSpans"][0]["resource"]["attributes"][0]["value"]["stringValue"],
+                "svc"
+            );
+        }
+    }
+}
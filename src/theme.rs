@@ -18,26 +18,41 @@
 //!   5. Embedded themes: dark.toml / light.toml
 //!   6. Fallback: Theme::default()
 //! ```
+//!
+//! Discovery and the embedded themes need the `themes` cargo feature. Without
+//! it only [`Theme`] itself is compiled and every render uses `Theme::default()`.
 
-use serde::{de::Error as _, Deserialize, Serialize};
+#[cfg(feature = "themes")]
+use serde::de::Error as _;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+#[cfg(feature = "themes")]
 use std::fs;
+#[cfg(feature = "themes")]
 use std::path::PathBuf;
+#[cfg(feature = "themes")]
 use std::sync::{Arc, Mutex, OnceLock};
 
 // Embedded theme files compiled into binary
-const EMBEDDED_DARK_THEME: &str = include_str!("../themes/dark.toml");
-const EMBEDDED_LIGHT_THEME: &str = include_str!("../themes/light.toml");
-const EMBEDDED_MONOKAI_THEME: &str = include_str!("../themes/monokai.toml");
-const EMBEDDED_SOLARIZED_THEME: &str = include_str!("../themes/solarized.toml");
-const EMBEDDED_HIGH_CONTRAST_THEME: &str = include_str!("../themes/high-contrast.toml");
-const EMBEDDED_GRUVBOX_THEME: &str = include_str!("../themes/gruvbox.toml");
-const EMBEDDED_NORD_THEME: &str = include_str!("../themes/nord.toml");
-const EMBEDDED_DRACULA_THEME: &str = include_str!("../themes/dracula.toml");
-const EMBEDDED_ONE_DARK_THEME: &str = include_str!("../themes/one-dark.toml");
-const EMBEDDED_TOKYO_NIGHT_THEME: &str = include_str!("../themes/tokyo-night.toml");
-const EMBEDDED_CATPPUCCIN_THEME: &str = include_str!("../themes/catppuccin.toml");
+#[cfg(feature = "themes")]
+mod embedded {
+    pub const EMBEDDED_DARK_THEME: &str = include_str!("../themes/dark.toml");
+    pub const EMBEDDED_LIGHT_THEME: &str = include_str!("../themes/light.toml");
+    pub const EMBEDDED_MONOKAI_THEME: &str = include_str!("../themes/monokai.toml");
+    pub const EMBEDDED_SOLARIZED_THEME: &str = include_str!("../themes/solarized.toml");
+    pub const EMBEDDED_HIGH_CONTRAST_THEME: &str = include_str!("../themes/high-contrast.toml");
+    pub const EMBEDDED_GRUVBOX_THEME: &str = include_str!("../themes/gruvbox.toml");
+    pub const EMBEDDED_NORD_THEME: &str = include_str!("../themes/nord.toml");
+    pub const EMBEDDED_DRACULA_THEME: &str = include_str!("../themes/dracula.toml");
+    pub const EMBEDDED_ONE_DARK_THEME: &str = include_str!("../themes/one-dark.toml");
+    pub const EMBEDDED_TOKYO_NIGHT_THEME: &str = include_str!("../themes/tokyo-night.toml");
+    pub const EMBEDDED_CATPPUCCIN_THEME: &str = include_str!("../themes/catppuccin.toml");
+    pub const EMBEDDED_DEUTERANOPIA_THEME: &str = include_str!("../themes/deuteranopia.toml");
+    pub const EMBEDDED_PROTANOPIA_THEME: &str = include_str!("../themes/protanopia.toml");
+}
+#[cfg(feature = "themes")]
+use embedded::*;
 
 /// Main theme structure containing all color definitions.
 ///
@@ -58,6 +73,10 @@ pub struct Theme {
     /// Optional custom color palette
     #[serde(default)]
     pub palette: Option<Palette>,
+
+    /// Optional severity-dependent glyphs
+    #[serde(default)]
+    pub shapes: ThemeShapes,
 }
 
 /// Complete set of theme colors for all statusline components.
@@ -137,6 +156,27 @@ pub struct Palette {
     pub custom: HashMap<String, String>,
 }
 
+/// Glyphs that change with severity, so states stay distinguishable without
+/// relying on hue alone.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThemeShapes {
+    /// Context bar fill per level: normal, caution, warning, critical.
+    /// Missing entries fall back to `=`.
+    #[serde(default)]
+    pub context_fill: Vec<String>,
+}
+
+impl ThemeShapes {
+    /// Fill glyph for a context level (0 = normal .. 3 = critical).
+    pub fn context_fill(&self, level: usize) -> &str {
+        self.context_fill
+            .get(level)
+            .map(String::as_str)
+            .filter(|glyph| !glyph.is_empty())
+            .unwrap_or("=")
+    }
+}
+
 // ===== Default Color Values =====
 
 fn default_cyan() -> String {
@@ -175,6 +215,7 @@ fn default_light_gray() -> String {
 /// Searches for themes in this order:
 /// 1. User themes: ~/.config/claudia-statusline/themes/
 /// 2. Embedded themes: dark.toml, light.toml
+#[cfg(feature = "themes")]
 pub struct ThemeManager {
     /// Path to user themes directory
     themes_dir: PathBuf,
@@ -182,6 +223,7 @@ pub struct ThemeManager {
     cache: Arc<Mutex<Option<Theme>>>,
 }
 
+#[cfg(feature = "themes")]
 impl ThemeManager {
     /// Creates a new ThemeManager.
     ///
@@ -312,6 +354,7 @@ impl ThemeManager {
     }
 }
 
+#[cfg(feature = "themes")]
 impl Default for ThemeManager {
     fn default() -> Self {
         Self::new()
@@ -319,9 +362,11 @@ impl Default for ThemeManager {
 }
 
 /// Global theme manager instance.
+#[cfg(feature = "themes")]
 static THEME_MANAGER: OnceLock<ThemeManager> = OnceLock::new();
 
 /// Gets the global theme manager instance.
+#[cfg(feature = "themes")]
 pub fn get_theme_manager() -> &'static ThemeManager {
     THEME_MANAGER.get_or_init(ThemeManager::new)
 }
@@ -345,6 +390,7 @@ impl Theme {
     /// let theme = Theme::from_toml(toml).unwrap();
     /// assert_eq!(theme.name, "custom");
     /// ```
+    #[cfg_attr(not(feature = "themes"), allow(dead_code))]
     pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
         toml::from_str(content)
     }
@@ -369,6 +415,7 @@ impl Theme {
     /// let theme = Theme::load_embedded("light").unwrap();
     /// assert_eq!(theme.name, "light");
     /// ```
+    #[cfg(feature = "themes")]
     pub fn load_embedded(name: &str) -> Result<Self, toml::de::Error> {
         let content = match name.to_lowercase().as_str() {
             "dark" => EMBEDDED_DARK_THEME,
@@ -382,6 +429,8 @@ impl Theme {
             "one-dark" => EMBEDDED_ONE_DARK_THEME,
             "tokyo-night" => EMBEDDED_TOKYO_NIGHT_THEME,
             "catppuccin" => EMBEDDED_CATPPUCCIN_THEME,
+            "deuteranopia" => EMBEDDED_DEUTERANOPIA_THEME,
+            "protanopia" => EMBEDDED_PROTANOPIA_THEME,
             _ => {
                 return Err(toml::de::Error::custom(format!(
                     "Unknown embedded theme '{}'. Available: {}",
@@ -405,6 +454,7 @@ impl Theme {
     /// assert!(themes.contains(&"dark"));
     /// assert!(themes.contains(&"light"));
     /// ```
+    #[cfg(feature = "themes")]
     pub fn embedded_themes() -> Vec<&'static str> {
         vec![
             "dark",
@@ -418,6 +468,8 @@ impl Theme {
             "one-dark",
             "tokyo-night",
             "catppuccin",
+            "deuteranopia",
+            "protanopia",
         ]
     }
 
@@ -559,6 +611,7 @@ impl Default for Theme {
                 context_critical: "red".to_string(),
             },
             palette: None,
+            shapes: ThemeShapes::default(),
         }
     }
 }
@@ -594,7 +647,7 @@ impl Default for ThemeColors {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "themes"))]
 mod tests {
     use super::*;
 
@@ -652,7 +705,10 @@ mod tests {
         assert!(themes.contains(&"one-dark".to_string()));
         assert!(themes.contains(&"tokyo-night".to_string()));
         assert!(themes.contains(&"catppuccin".to_string()));
-        assert_eq!(themes.len(), 11); // All embedded themes in test env
+        // Color-blind friendly themes
+        assert!(themes.contains(&"deuteranopia".to_string()));
+        assert!(themes.contains(&"protanopia".to_string()));
+        assert_eq!(themes.len(), 13); // All embedded themes in test env
     }
 
     #[test]
@@ -726,7 +782,7 @@ mod tests {
     #[test]
     fn test_embedded_themes_list() {
         let themes = Theme::embedded_themes();
-        assert_eq!(themes.len(), 11);
+        assert_eq!(themes.len(), 13);
         assert!(themes.contains(&"dark"));
         assert!(themes.contains(&"light"));
         assert!(themes.contains(&"monokai"));
@@ -738,6 +794,33 @@ mod tests {
         assert!(themes.contains(&"one-dark"));
         assert!(themes.contains(&"tokyo-night"));
         assert!(themes.contains(&"catppuccin"));
+        assert!(themes.contains(&"deuteranopia"));
+        assert!(themes.contains(&"protanopia"));
+    }
+
+    #[test]
+    fn test_color_blind_themes_vary_shape() {
+        for name in ["deuteranopia", "protanopia"] {
+            let theme = Theme::load_embedded(name).unwrap();
+            assert_eq!(theme.name, name);
+            // Every context level has its own fill glyph
+            let fills: Vec<&str> = (0..4).map(|l| theme.shapes.context_fill(l)).collect();
+            let mut unique = fills.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), 4, "{}: {:?}", name, fills);
+            // No red/green pair for cost or line deltas
+            assert_ne!(theme.colors.cost_high, "red");
+            assert_ne!(theme.colors.lines_added, "green");
+        }
+    }
+
+    #[test]
+    fn test_shapes_default_fill() {
+        let theme = Theme::default();
+        for level in 0..4 {
+            assert_eq!(theme.shapes.context_fill(level), "=");
+        }
     }
 
     #[test]
@@ -859,6 +942,7 @@ mod tests {
             description: None,
             colors: ThemeColors::default(),
             palette: None,
+            shapes: ThemeShapes::default(),
         };
         assert_eq!(format!("{}", minimal), "test");
     }
@@ -0,0 +1,175 @@
+//! Transcript discovery for payloads without `transcript_path`.
+//!
+//! Claude Code stores transcripts as `<config dir>/projects/<project>/<session-id>.jsonl`.
+//! When a payload carries a `session_id` but no transcript path, the projects
+//! tree is searched for the matching file so context and duration still work.
+//! Hits are cached in `transcripts.json` in the cache directory, making
+//! repeated renders of the same session a single `stat`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the cache file in the cache directory
+const CACHE_FILE: &str = "transcripts.json";
+
+/// Number of session → transcript mappings kept in the cache
+const CACHE_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedTranscript {
+    session_id: String,
+    path: PathBuf,
+}
+
+/// Finds the transcript for a session in the Claude Code projects directory.
+pub fn discover(session_id: &str) -> Option<PathBuf> {
+    let projects = crate::common::claude_config_dir()?.join("projects");
+    let cache = crate::state::get_cache_dir().ok()?.join(CACHE_FILE);
+    discover_in(&projects, &cache, session_id)
+}
+
+/// Session IDs become file names, so only plain identifiers are searched for.
+fn is_valid_session_id(session_id: &str) -> bool {
+    !session_id.is_empty()
+        && session_id.len() <= 128
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn discover_in(projects: &Path, cache: &Path, session_id: &str) -> Option<PathBuf> {
+    if !is_valid_session_id(session_id) {
+        return None;
+    }
+
+    let mut entries: Vec<CachedTranscript> = fs::read_to_string(cache)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    if let Some(hit) = entries
+        .iter()
+        .find(|e| e.session_id == session_id && e.path.is_file())
+    {
+        return Some(hit.path.clone());
+    }
+
+    let file_name = format!("{}.jsonl", session_id);
+    let found = search(projects, &file_name, 2)?;
+    log::debug!(
+        "Discovered transcript for {}: {}",
+        session_id,
+        found.display()
+    );
+
+    entries.retain(|e| e.session_id != session_id);
+    entries.push(CachedTranscript {
+        session_id: session_id.to_string(),
+        path: found.clone(),
+    });
+    let excess = entries.len().saturating_sub(CACHE_ENTRIES);
+    entries.drain(..excess);
+    if let Ok(json) = serde_json::to_string(&entries) {
+        // Best effort: a failed cache write only costs a rescan next time
+        let _ = fs::write(cache, json);
+    }
+    Some(found)
+}
+
+/// Looks for `file_name` in `dir` and its subdirectories, up to `depth` levels down.
+fn search(dir: &Path, file_name: &str, depth: usize) -> Option<PathBuf> {
+    let candidate = dir.join(file_name);
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+    if depth == 0 {
+        return None;
+    }
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .find_map(|entry| search(&entry.path(), file_name, depth - 1))
+}
+
+/// Maps session IDs to the transcripts in `dir`, its subdirectories and theirs
+/// (so a projects directory, or the Claude config directory above it, works).
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub fn index(dir: &Path) -> HashMap<String, PathBuf> {
+    let mut found = HashMap::new();
+    collect(dir, 2, &mut found);
+    found
+}
+
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+fn collect(dir: &Path, depth: usize, found: &mut HashMap<String, PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            if depth > 0 {
+                collect(&path, depth - 1, found);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "jsonl") {
+            if let Some(session_id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                if is_valid_session_id(session_id) {
+                    found.insert(session_id.to_string(), path);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_discover_and_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let projects = temp_dir.path().join("projects");
+        let cache = temp_dir.path().join(CACHE_FILE);
+        let project = projects.join("-home-user-repo");
+        fs::create_dir_all(&project).unwrap();
+        let transcript = project.join("abc-123.jsonl");
+        fs::write(&transcript, "{}\n").unwrap();
+
+        assert_eq!(
+            discover_in(&projects, &cache, "abc-123"),
+            Some(transcript.clone())
+        );
+        assert!(fs::read_to_string(&cache).unwrap().contains("abc-123"));
+
+        // Served from the cache even if the projects tree is gone
+        assert_eq!(
+            discover_in(&temp_dir.path().join("missing"), &cache, "abc-123"),
+            Some(transcript.clone())
+        );
+
+        // A cached path that no longer exists is not returned
+        fs::remove_file(&transcript).unwrap();
+        assert_eq!(discover_in(&projects, &cache, "abc-123"), None);
+
+        assert_eq!(discover_in(&projects, &cache, "other"), None);
+        assert_eq!(discover_in(&projects, &cache, "../etc/passwd"), None);
+    }
+
+    #[test]
+    fn test_index() {
+        let temp_dir = TempDir::new().unwrap();
+        let project = temp_dir.path().join("projects/-home-user-repo");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("abc-123.jsonl"), "{}\n").unwrap();
+        fs::write(project.join("notes.txt"), "").unwrap();
+        fs::write(project.join("bad name.jsonl"), "").unwrap();
+
+        let found = index(temp_dir.path());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found["abc-123"], project.join("abc-123.jsonl"));
+        assert_eq!(index(&project).len(), 1);
+    }
+}
@@ -0,0 +1,124 @@
+//! Update availability check (`update.check`).
+//!
+//! Disabled by default. When enabled, the latest GitHub release is fetched at
+//! most once per `update.interval_hours` by a detached `curl` process, so a
+//! render never waits on the network. The response is cached on disk and only
+//! read back on later renders; being offline or lacking `curl` simply leaves the
+//! cache empty.
+
+use crate::config;
+use crate::version::VersionInfo;
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+/// GitHub API endpoint for the latest release
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/marvin-bitterlich/claudia-statusline/releases/latest";
+
+/// Cached GitHub API response
+fn release_cache_path() -> Option<PathBuf> {
+    crate::state::get_cache_dir()
+        .ok()
+        .map(|dir| dir.join("latest-release.json"))
+}
+
+/// Touched whenever a check starts, so failed checks aren't retried every render
+fn check_stamp_path() -> Option<PathBuf> {
+    crate::state::get_state_dir()
+        .ok()
+        .map(|dir| dir.join("update-check.stamp"))
+}
+
+/// When the last check was started, if any.
+pub fn last_checked() -> Option<DateTime<Utc>> {
+    let modified = fs::metadata(check_stamp_path()?).ok()?.modified().ok()?;
+    Some(DateTime::<Utc>::from(modified))
+}
+
+/// Starts a background check if enabled and the last one is older than the interval.
+pub fn refresh_if_stale() {
+    let config = config::get_config();
+    if !config.update.check {
+        return;
+    }
+    let (Some(stamp), Some(cache)) = (check_stamp_path(), release_cache_path()) else {
+        return;
+    };
+
+    let interval = Duration::from_secs(config.update.interval_hours.max(1) * 3600);
+    let fresh = fs::metadata(&stamp)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < interval);
+    if fresh || fs::write(&stamp, crate::environment::current().now().to_rfc3339()).is_err() {
+        return;
+    }
+
+    // Detached: the statusline exits without waiting, curl finishes on its own
+    let spawned = Command::new("curl")
+        .args([
+            "-fsSL",
+            "--max-time",
+            "10",
+            "-H",
+            "Accept: application/vnd.github+json",
+        ])
+        .arg("-o")
+        .arg(&cache)
+        .arg(LATEST_RELEASE_URL)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(e) = spawned {
+        log::debug!("Update check not started: {}", e);
+    }
+}
+
+/// Latest released version from the cache (without a leading "v").
+pub fn latest_version() -> Option<String> {
+    let json = fs::read_to_string(release_cache_path()?).ok()?;
+    let release: serde_json::Value = serde_json::from_str(&json).ok()?;
+    let tag = release.get("tag_name")?.as_str()?;
+    Some(tag.trim_start_matches('v').to_string())
+}
+
+/// Latest version if it is newer than the running one.
+pub fn newer_version() -> Option<String> {
+    let latest = latest_version()?;
+    is_newer(&latest, VersionInfo::current().version).then_some(latest)
+}
+
+/// Compares dotted numeric versions, ignoring pre-release/build suffixes.
+fn is_newer(candidate: &str, current: &str) -> bool {
+    fn parts(version: &str) -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or("")
+            .split('.')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect()
+    }
+    parts(candidate) > parts(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("2.20.0", "2.19.0"));
+        assert!(is_newer("2.19.1", "2.19.0"));
+        assert!(is_newer("3.0.0", "2.19.9"));
+        assert!(!is_newer("2.19.0", "2.19.0"));
+        assert!(!is_newer("2.18.5", "2.19.0"));
+        assert!(!is_newer("2.19.0-rc1", "2.19.0"));
+        assert!(is_newer("2.10.0", "2.9.0")); // Numeric, not lexical
+    }
+}
@@ -6,12 +6,14 @@
 use crate::common::validate_path_security;
 use crate::config;
 use crate::error::{Result, StatuslineError};
-use crate::models::{ContextUsage, TranscriptEntry};
-use chrono::DateTime;
-use std::env;
+use crate::models::{
+    ContextUsage, ReportedContext, TokenBreakdown, TranscriptEntry, TranscriptSize,
+};
+use chrono::{DateTime, Local};
+use std::cell::RefCell;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Sanitizes a string for safe terminal output by removing control characters
 /// and ANSI escape sequences. This prevents malicious strings from manipulating
@@ -81,17 +83,120 @@ pub fn shorten_path(path: &str) -> String {
         return String::new();
     }
 
-    if let Ok(home) = env::var("HOME") {
+    if let Some(home) = crate::environment::current().home_dir() {
+        let home = home.to_string_lossy();
         if path == home {
             return "~".to_string();
         }
-        if path.starts_with(&home) {
-            return path.replacen(&home, "~", 1);
+        if path.starts_with(home.as_ref()) {
+            return path.replacen(home.as_ref(), "~", 1);
         }
     }
     path.to_string()
 }
 
+/// Marker file that turns a directory tree into an ignored directory
+pub const IGNORE_MARKER: &str = ".statuslineignore";
+
+/// Whether only the path should be rendered for `dir`: it lies inside one of
+/// `ignore_dirs` (`display.ignore_dirs`), or it or a parent contains a
+/// `.statuslineignore` file.
+///
+/// The configured list is checked first, so listed directories cost no file
+/// system access at all.
+pub fn is_ignored_dir(dir: &str, ignore_dirs: &[String]) -> bool {
+    let path = Path::new(dir);
+    let listed = ignore_dirs.iter().any(|entry| {
+        // `${VAR}` references were already expanded when the config was loaded
+        let entry = entry.trim();
+        let home = crate::environment::current().home_dir();
+        let expanded = match (entry.strip_prefix('~'), home) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+                format!("{}{}", home.display(), rest)
+            }
+            _ => entry.to_string(),
+        };
+        !expanded.is_empty() && path.starts_with(&expanded)
+    });
+
+    listed
+        || path
+            .ancestors()
+            .any(|ancestor| ancestor.join(IGNORE_MARKER).is_file())
+}
+
+/// Whether the current user can create files in `dir`.
+///
+/// Covers permissions, ACLs and read-only mounts on Unix. Missing directories
+/// count as writable, so the read-only marker only shows when it is certain.
+pub fn is_dir_writable(dir: &Path) -> bool {
+    if !dir.is_dir() {
+        return true;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+            return true;
+        };
+        // SAFETY: `path` is a valid NUL-terminated string that outlives the call
+        unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+    }
+
+    #[cfg(not(unix))]
+    {
+        std::fs::metadata(dir)
+            .map(|metadata| !metadata.permissions().readonly())
+            .unwrap_or(true)
+    }
+}
+
+/// Shortens a path and abbreviates every component but the last to its first
+/// character, fish-style (e.g. "~/projects/app" → "~/p/app").
+pub fn abbreviate_path(path: &str) -> String {
+    let short = shorten_path(path);
+    let components: Vec<&str> = short.split('/').collect();
+    let last = components.len().saturating_sub(1);
+
+    components
+        .iter()
+        .enumerate()
+        .map(|(i, component)| {
+            if i == last || component.is_empty() || *component == "~" {
+                component.to_string()
+            } else {
+                // Keep the leading dot of hidden directories (".config" → ".c")
+                let skip = if component.starts_with('.') { 2 } else { 1 };
+                component.chars().take(skip).collect()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Formats a dollar amount using the configured precision and rounding
+///
+/// Examples (decimals = 2):
+/// - 1.234 → "$1.23" ("nearest"), "$1.24" ("up")
+/// - 0.004 → "$0.00", or "<$0.01" with `show_less_than`
+pub fn format_cost(amount: f64, config: &crate::config::CostConfig) -> String {
+    let decimals = config.decimals.min(4);
+    let factor = 10f64.powi(decimals as i32);
+    // Scale first, rounding away float noise so 1.10 * 100 doesn't ceil to 111
+    let scaled = (amount * factor * 1e6).round() / 1e6;
+    let rounded = match config.rounding.as_str() {
+        "up" => scaled.ceil(),
+        "down" => scaled.floor(),
+        _ => scaled.round(),
+    } / factor;
+
+    if config.show_less_than && amount > 0.0 && rounded == 0.0 {
+        return format!("<${:.*}", decimals, 1.0 / factor);
+    }
+    format!("${:.*}", decimals, rounded)
+}
+
 /// Formats a token count with "k" suffix for thousands
 ///
 /// Examples:
@@ -117,6 +222,40 @@ pub fn format_token_count(tokens: usize) -> String {
     }
 }
 
+/// Formats a duration in seconds in one of the `display.duration_format` styles
+///
+/// - "compact": "45s", "5m", "1h5m"
+/// - "clock": hours and minutes, "00:05", "01:05"
+/// - "verbose": "45 sec", "5 min", "1 hr 5 min"
+///
+/// Unknown styles fall back to "compact".
+pub fn format_duration(seconds: u64, style: &str) -> String {
+    let (hours, minutes) = (seconds / 3600, (seconds % 3600) / 60);
+    match style {
+        "clock" => format!("{:02}:{:02}", hours, minutes),
+        "verbose" => {
+            if seconds < 60 {
+                format!("{} sec", seconds)
+            } else if hours == 0 {
+                format!("{} min", minutes)
+            } else if minutes == 0 {
+                format!("{} hr", hours)
+            } else {
+                format!("{} hr {} min", hours, minutes)
+            }
+        }
+        _ => {
+            if seconds < 60 {
+                format!("{}s", seconds)
+            } else if hours == 0 {
+                format!("{}m", minutes)
+            } else {
+                format!("{}h{}m", hours, minutes)
+            }
+        }
+    }
+}
+
 /// Determines the context window size for a given model
 ///
 /// Uses intelligent defaults based on model family and version:
@@ -135,15 +274,16 @@ pub fn format_token_count(tokens: usize) -> String {
 /// hardcoded defaults and manual config updates.
 ///
 /// Get learned context window from database (if available and confident)
+#[cfg(feature = "adaptive-learning")]
 fn get_learned_context_window(
     model_name: &str,
     config: &config::Config,
 ) -> crate::error::Result<Option<usize>> {
-    use crate::common::get_data_dir;
+    use crate::common::get_database_path;
     use crate::context_learning::ContextLearner;
     use crate::database::SqliteDatabase;
 
-    let db_path = get_data_dir().join("stats.db");
+    let db_path = get_database_path();
     let db = SqliteDatabase::new(&db_path)?;
     let learner = ContextLearner::new(db);
 
@@ -170,23 +310,63 @@ fn get_learned_context_window(
 ///
 /// Context window size in tokens
 pub fn get_context_window_for_model(model_name: Option<&str>, config: &config::Config) -> usize {
+    context_window_with_source(model_name, config).0
+}
+
+/// Where a context window size came from, for `--explain`
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowSource {
+    /// `context_window.context_window_size` in the input payload
+    Reported,
+    /// `[context.model_windows]` entry for the model
+    ConfigModelWindow,
+    /// Compaction point learned by adaptive learning
+    Learned,
+    /// "(1M context)" marker in the model's display name
+    DisplayName,
+    /// Built-in default for the model family and version
+    ModelDefault(String),
+    /// `context.window_size` (unknown model, or no model name)
+    ConfigDefault,
+}
+
+impl std::fmt::Display for WindowSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowSource::Reported => write!(f, "reported by Claude Code (context_window_size)"),
+            WindowSource::ConfigModelWindow => write!(f, "context.model_windows override"),
+            WindowSource::Learned => write!(f, "learned compaction point (adaptive learning)"),
+            WindowSource::DisplayName => write!(f, "\"1M context\" in the model name"),
+            WindowSource::ModelDefault(family) => write!(f, "built-in default for {}", family),
+            WindowSource::ConfigDefault => write!(f, "context.window_size"),
+        }
+    }
+}
+
+/// Determines the context window size for a model along with the rule that
+/// produced it (see `get_context_window_for_model` for the priority order).
+pub fn context_window_with_source(
+    model_name: Option<&str>,
+    config: &config::Config,
+) -> (usize, WindowSource) {
     if let Some(model) = model_name {
         // Priority 1: User config overrides (highest priority)
         if let Some(&custom_size) = config.context.model_windows.get(model) {
-            return custom_size;
+            return (custom_size, WindowSource::ConfigModelWindow);
         }
 
         // Priority 2: Learned values (if adaptive learning enabled and confident)
+        #[cfg(feature = "adaptive-learning")]
         if config.context.adaptive_learning {
             if let Ok(Some(window)) = get_learned_context_window(model, config) {
-                return window;
+                return (window, WindowSource::Learned);
             }
         }
 
         // Priority 3: Check for explicit context window markers in display name
         // E.g., "Sonnet 4.5 (1M context)" → 1M tokens
         if model.contains("(1M context)") || model.contains("(1M)") {
-            return 1_000_000;
+            return (1_000_000, WindowSource::DisplayName);
         }
 
         // Priority 4: Smart defaults based on model family and version
@@ -208,36 +388,37 @@ pub fn get_context_window_for_model(model_name: Option<&str>, config: &config::C
                     .and_then(|s| s.parse::<u32>().ok())
                     .unwrap_or(0);
 
+                let default_source = WindowSource::ModelDefault(format!("{} {}", family, version));
                 match family.as_str() {
                     "Sonnet" => {
                         // Sonnet 3.5+, 4.x+: 200k tokens
                         if version_number >= 4 || (version_number == 3 && minor_version >= 5) {
-                            200_000
+                            (200_000, default_source)
                         } else {
-                            160_000
+                            (160_000, default_source)
                         }
                     }
                     "Opus" => {
                         // Opus 3.5+: 200k tokens
                         if version_number >= 4 || (version_number == 3 && minor_version >= 5) {
-                            200_000
+                            (200_000, default_source)
                         } else {
-                            160_000
+                            (160_000, default_source)
                         }
                     }
                     "Haiku" => {
                         // Haiku models typically have smaller windows
                         // Future versions might increase, but default to config
-                        config.context.window_size
+                        (config.context.window_size, WindowSource::ConfigDefault)
                     }
-                    _ => config.context.window_size,
+                    _ => (config.context.window_size, WindowSource::ConfigDefault),
                 }
             }
-            ModelType::Unknown => config.context.window_size,
+            ModelType::Unknown => (config.context.window_size, WindowSource::ConfigDefault),
         }
     } else {
         // No model name provided, use config default
-        config.context.window_size
+        (config.context.window_size, WindowSource::ConfigDefault)
     }
 }
 
@@ -291,36 +472,199 @@ pub fn get_token_count_from_transcript(transcript_path: &str) -> Option<u32> {
 ///
 /// Implementation: Reads from the end of the file for efficiency with large transcripts.
 /// Only processes the last N lines (configured via transcript.buffer_lines).
+/// Results are cached by file identity (path, size, mtime), in memory for the
+/// rest of the render and on disk for rapid back-to-back invocations.
 pub fn get_token_breakdown_from_transcript(
     transcript_path: &str,
 ) -> Option<crate::models::TokenBreakdown> {
-    use crate::models::TokenBreakdown;
-    use std::io::{Seek, SeekFrom};
-
     // Validate and canonicalize the file path
     let safe_path = validate_transcript_file(transcript_path).ok()?;
 
-    // Open file and get size
-    let mut file = File::open(&safe_path).ok()?;
-    let file_size = file.metadata().ok()?.len();
-
     // Load config once to avoid repeated TOML parsing
     let config = config::get_config();
-    let buffer_size = config.transcript.buffer_lines;
+    let key = TranscriptKey::of(&safe_path, config.transcript.buffer_lines)?;
+
+    if let Some(cached) = TOKEN_BREAKDOWN.with(|cache| {
+        cache
+            .borrow()
+            .as_ref()
+            .filter(|(k, _)| *k == key)
+            .map(|(_, breakdown)| breakdown.clone())
+    }) {
+        return cached;
+    }
+
+    let breakdown =
+        token_cache::load(&key).or_else(|| parse_token_breakdown(&safe_path, key.buffer_lines));
+    if let Some(ref found) = breakdown {
+        token_cache::store(&key, found);
+    }
+    TOKEN_BREAKDOWN.with(|cache| *cache.borrow_mut() = Some((key, breakdown.clone())));
+    breakdown
+}
+
+/// Identity of a transcript's contents: a changed size or mtime invalidates cached results.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct TranscriptKey {
+    path: PathBuf,
+    size: u64,
+    mtime_nanos: u128,
+    buffer_lines: usize,
+}
+
+impl TranscriptKey {
+    fn of(path: &Path, buffer_lines: usize) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let mtime_nanos = metadata
+            .modified()
+            .ok()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .ok()?
+            .as_nanos();
+        Some(Self {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            mtime_nanos,
+            buffer_lines,
+        })
+    }
+}
+
+/// On-disk token breakdown cache shared by consecutive invocations.
+mod token_cache {
+    use super::TranscriptKey;
+    use crate::models::TokenBreakdown;
+
+    /// Transcripts remembered at once (one per concurrently active session)
+    const ENTRIES: usize = 16;
+
+    type Entries = Vec<(TranscriptKey, TokenBreakdown)>;
+
+    fn path() -> Option<std::path::PathBuf> {
+        crate::state::get_cache_dir()
+            .ok()
+            .map(|dir| dir.join("token-cache.json"))
+    }
+
+    fn read() -> Entries {
+        path()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub(super) fn load(key: &TranscriptKey) -> Option<TokenBreakdown> {
+        read()
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, breakdown)| breakdown)
+    }
+
+    /// Best effort: a failed write only costs a re-parse next time.
+    pub(super) fn store(key: &TranscriptKey, breakdown: &TokenBreakdown) {
+        let Some(path) = path() else { return };
+        let mut entries = read();
+        if entries.iter().any(|(k, _)| k == key) {
+            return;
+        }
+        entries.retain(|(k, _)| k.path != key.path);
+        entries.push((key.clone(), breakdown.clone()));
+        let excess = entries.len().saturating_sub(ENTRIES);
+        entries.drain(..excess);
+        if let Ok(json) = serde_json::to_string(&entries) {
+            // Rename into place so concurrent readers never see a partial file
+            let temp = path.with_extension(format!("json.{}", std::process::id()));
+            if std::fs::write(&temp, json).is_ok() && std::fs::rename(&temp, &path).is_err() {
+                let _ = std::fs::remove_file(&temp);
+            }
+        }
+    }
+}
+
+/// Parses the breakdown with the highest total from the transcript tail.
+fn parse_token_breakdown(safe_path: &Path, buffer_lines: usize) -> Option<TokenBreakdown> {
+    let (lines, _) = read_transcript_tail(safe_path, buffer_lines)?;
+
+    // Find the most recent assistant message with usage data
+    let mut best_breakdown = TokenBreakdown::default();
+    let mut max_total = 0u32;
+
+    for line in lines {
+        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(&line) {
+            if entry.message.role == "assistant" {
+                if let Some(usage) = entry.message.usage {
+                    // Extract individual token counts
+                    let input = usage.input_tokens.unwrap_or(0);
+                    let cache_read = usage.cache_read_input_tokens.unwrap_or(0);
+                    let cache_creation = usage.cache_creation_input_tokens.unwrap_or(0);
+                    let output = usage.output_tokens.unwrap_or(0);
+                    let current_total = input + cache_read + cache_creation + output;
+
+                    // Keep the breakdown with the highest total token count
+                    if current_total > max_total {
+                        max_total = current_total;
+                        best_breakdown = TokenBreakdown {
+                            input_tokens: input,
+                            output_tokens: output,
+                            cache_read_tokens: cache_read,
+                            cache_creation_tokens: cache_creation,
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    if max_total > 0 {
+        Some(best_breakdown)
+    } else {
+        None
+    }
+}
+
+/// Reads the last `buffer_size` lines of a transcript, measuring its size on the way.
+///
+/// Small files are read from the start, so the message count is exact. Files over
+/// 1MB are read from the end and the count is extrapolated from the average line
+/// length of the tail. The size is cached for `transcript_size`.
+fn read_transcript_tail(
+    safe_path: &Path,
+    buffer_size: usize,
+) -> Option<(Vec<String>, TranscriptSize)> {
+    use std::io::{Seek, SeekFrom};
+
+    // An unreadable transcript drops the context segment, so flag it; one that
+    // doesn't exist yet is normal at the start of a session
+    let unreadable = |e: std::io::Error| {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            crate::common::mark_degraded("transcript", &e.to_string());
+        }
+    };
+
+    // Open file and get size
+    let mut file = File::open(safe_path).map_err(unreadable).ok()?;
+    let file_size = file.metadata().map_err(unreadable).ok()?.len();
 
     // For small files, read normally from start
     // For large files (>1MB), read from end to avoid processing entire file
-    let lines: Vec<String> = if file_size < 1024 * 1024 {
+    let (lines, size): (Vec<String>, TranscriptSize) = if file_size < 1024 * 1024 {
         // Small file: read normally
         let reader = BufReader::new(file);
         let mut circular_buffer = std::collections::VecDeque::with_capacity(buffer_size);
+        let mut messages = 0u64;
         for line in reader.lines().map_while(|l| l.ok()) {
+            messages += 1;
             if circular_buffer.len() == buffer_size {
                 circular_buffer.pop_front();
             }
             circular_buffer.push_back(line);
         }
-        circular_buffer.into_iter().collect()
+        let size = TranscriptSize {
+            bytes: file_size,
+            messages,
+            estimated: false,
+        };
+        (circular_buffer.into_iter().collect(), size)
     } else {
         // Large file: read from end
         // Estimate: average line ~2KB, read last 200KB to get ~100 lines (buffer for safety)
@@ -328,70 +672,297 @@ pub fn get_token_breakdown_from_transcript(
         let start_pos = file_size.saturating_sub(read_size);
 
         // Seek to position
-        file.seek(SeekFrom::Start(start_pos)).ok()?;
+        file.seek(SeekFrom::Start(start_pos))
+            .map_err(unreadable)
+            .ok()?;
 
         // Read from that position
         let reader = BufReader::new(file);
         let all_lines: Vec<String> = reader.lines().map_while(|l| l.ok()).collect();
 
+        // Extrapolate the message count from the average line length of the tail
+        let tail_bytes = file_size - start_pos;
+        let messages = if all_lines.is_empty() {
+            0
+        } else {
+            let avg_line = (tail_bytes / all_lines.len() as u64).max(1);
+            file_size / avg_line
+        };
+        let size = TranscriptSize {
+            bytes: file_size,
+            messages,
+            estimated: true,
+        };
+
         // Skip first line if we started mid-line (partial line)
         let skip_first = if start_pos > 0 { 1 } else { 0 };
 
         // Take last N lines
-        all_lines
+        let lines = all_lines
             .into_iter()
             .skip(skip_first)
             .rev()
             .take(buffer_size)
             .rev()
-            .collect()
+            .collect();
+        (lines, size)
     };
 
-    // Find the most recent assistant message with usage data
-    let mut best_breakdown = TokenBreakdown::default();
-    let mut max_total = 0u32;
+    // Lines are parsed one by one and partial ones skipped, but a tail without
+    // a single JSON line is not a transcript
+    if !lines.is_empty()
+        && !lines
+            .iter()
+            .any(|line| serde_json::from_str::<serde_json::Value>(line).is_ok())
+    {
+        crate::common::mark_degraded("transcript", "no JSON lines in the transcript tail");
+    }
 
-    for line in lines {
-        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(&line) {
-            if entry.message.role == "assistant" {
-                if let Some(usage) = entry.message.usage {
-                    // Extract individual token counts
-                    let input = usage.input_tokens.unwrap_or(0);
-                    let cache_read = usage.cache_read_input_tokens.unwrap_or(0);
-                    let cache_creation = usage.cache_creation_input_tokens.unwrap_or(0);
-                    let output = usage.output_tokens.unwrap_or(0);
-                    let current_total = input + cache_read + cache_creation + output;
+    TRANSCRIPT_SIZE.with(|cache| {
+        *cache.borrow_mut() = Some((safe_path.to_path_buf(), size));
+    });
+    Some((lines, size))
+}
 
-                    // Keep the breakdown with the highest total token count
-                    if current_total > max_total {
-                        max_total = current_total;
-                        best_breakdown = TokenBreakdown {
-                            input_tokens: input,
-                            output_tokens: output,
-                            cache_read_tokens: cache_read,
-                            cache_creation_tokens: cache_creation,
-                        };
-                    }
+/// Returns the transcript's size and message count.
+///
+/// Reuses the measurement from this render's tail read when the file hasn't
+/// grown since; otherwise performs the tail read itself.
+pub fn transcript_size(transcript_path: &str) -> Option<TranscriptSize> {
+    let safe_path = validate_transcript_file(transcript_path).ok()?;
+    let file_size = std::fs::metadata(&safe_path).ok()?.len();
+
+    let cached = TRANSCRIPT_SIZE.with(|cache| {
+        cache
+            .borrow()
+            .as_ref()
+            .filter(|(path, size)| *path == safe_path && size.bytes == file_size)
+            .map(|(_, size)| *size)
+    });
+    if cached.is_some() {
+        return cached;
+    }
+
+    let config = config::get_config();
+    read_transcript_tail(&safe_path, config.transcript.buffer_lines).map(|(_, size)| size)
+}
+
+/// Token usage of one API call (one assistant message) in a transcript
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub struct ApiCallUsage {
+    /// API message ID
+    pub message_id: String,
+    /// Local date (YYYY-MM-DD) of the call
+    pub date: String,
+    pub tokens: TokenBreakdown,
+}
+
+/// What a render records from a session's transcript
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub struct TranscriptActivity {
+    /// Wall-clock and active time, from every entry
+    pub times: Option<SessionTimes>,
+    /// Usage of the API calls in the last `transcript.buffer_lines` lines
+    pub api_calls: Vec<ApiCallUsage>,
+    /// Files edited or written by tool calls in those lines
+    pub edited_files: Vec<String>,
+}
+
+/// Reads a session's times, recent API calls and recently edited files in a
+/// single pass over the transcript, parsing each line once.
+///
+/// Only the tail is kept for calls and files, so callers record them per
+/// session and count each once (see `stats::record_transcript_activity`).
+/// The times are cached for `parse_session_times` later in the render.
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub fn transcript_activity(transcript_path: &str) -> Option<TranscriptActivity> {
+    let safe_path = validate_transcript_file(transcript_path).ok()?;
+    let config = config::get_config();
+    let buffer_lines = config.transcript.buffer_lines;
+    let key = TranscriptKey::of(&safe_path, buffer_lines)?;
+
+    let file = File::open(&safe_path).ok()?;
+    let mut times = SessionTimesBuilder::new(config.transcript.idle_threshold_secs);
+    let mut tail: std::collections::VecDeque<Option<TranscriptEntry>> =
+        std::collections::VecDeque::with_capacity(buffer_lines);
+    for line in BufReader::new(file).lines().map_while(|l| l.ok()) {
+        let entry = serde_json::from_str::<TranscriptEntry>(&line).ok();
+        times.push(
+            entry
+                .as_ref()
+                .and_then(|entry| parse_iso8601_to_unix(&entry.timestamp)),
+        );
+        if tail.len() == buffer_lines {
+            tail.pop_front();
+        }
+        if buffer_lines > 0 {
+            tail.push_back(entry);
+        }
+    }
+
+    let times = times.finish();
+    SESSION_TIMES.with(|cache| *cache.borrow_mut() = Some((key, times)));
+    let entries: Vec<TranscriptEntry> = tail.into_iter().flatten().collect();
+    Some(TranscriptActivity {
+        times,
+        api_calls: api_calls_in(&entries),
+        edited_files: edited_files_in(&entries),
+    })
+}
+
+/// Usage per assistant message ID, in order of first appearance.
+///
+/// Claude Code writes one line per content block of a message, each repeating
+/// the message's usage; the line with the most output tokens is the final one.
+fn api_calls_in(entries: &[TranscriptEntry]) -> Vec<ApiCallUsage> {
+    let mut calls: Vec<ApiCallUsage> = Vec::new();
+    for entry in entries {
+        if entry.message.role != "assistant" {
+            continue;
+        }
+        let (Some(message_id), Some(usage)) = (&entry.message.id, &entry.message.usage) else {
+            continue;
+        };
+        let tokens = TokenBreakdown {
+            input_tokens: usage.input_tokens.unwrap_or(0),
+            output_tokens: usage.output_tokens.unwrap_or(0),
+            cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
+            cache_creation_tokens: usage.cache_creation_input_tokens.unwrap_or(0),
+        };
+        if tokens.total() == 0 {
+            continue;
+        }
+        match calls.iter_mut().find(|c| c.message_id == *message_id) {
+            Some(call) if tokens.output_tokens > call.tokens.output_tokens => call.tokens = tokens,
+            Some(_) => {}
+            None => {
+                let date = DateTime::parse_from_rfc3339(&entry.timestamp)
+                    .map(|t| t.with_timezone(&Local).format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|_| crate::common::current_date());
+                calls.push(ApiCallUsage {
+                    message_id: message_id.clone(),
+                    date,
+                    tokens,
+                });
+            }
+        }
+    }
+    calls
+}
+
+/// Tools whose input names a file Claude changed
+const EDIT_TOOLS: [&str; 4] = ["Edit", "MultiEdit", "Write", "NotebookEdit"];
+
+/// Distinct `file_path`/`notebook_path` inputs of assistant edit tool calls.
+fn edited_files_in(entries: &[TranscriptEntry]) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for entry in entries {
+        if entry.message.role != "assistant" {
+            continue;
+        }
+        let Some(serde_json::Value::Array(blocks)) = &entry.message.content else {
+            continue;
+        };
+        for block in blocks {
+            let is_edit = block.get("type").and_then(|t| t.as_str()) == Some("tool_use")
+                && block
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .is_some_and(|name| EDIT_TOOLS.contains(&name));
+            let path = block
+                .get("input")
+                .and_then(|input| {
+                    input
+                        .get("file_path")
+                        .or_else(|| input.get("notebook_path"))
+                })
+                .and_then(|p| p.as_str());
+            if let (true, Some(path)) = (is_edit, path) {
+                if !paths.iter().any(|p| p == path) {
+                    paths.push(path.to_string());
                 }
             }
         }
     }
+    paths
+}
 
-    if max_total > 0 {
-        Some(best_breakdown)
+thread_local! {
+    // Token/context data from the current input payload (see `set_reported_context`)
+    static REPORTED_CONTEXT: RefCell<ReportedContext> = RefCell::new(ReportedContext::default());
+    // Size of the last tail-read transcript (see `read_transcript_tail`)
+    static TRANSCRIPT_SIZE: RefCell<Option<(PathBuf, TranscriptSize)>> = const { RefCell::new(None) };
+    // Session times of the last scanned transcript (see `transcript_activity`)
+    static SESSION_TIMES: RefCell<Option<(TranscriptKey, Option<SessionTimes>)>> = const { RefCell::new(None) };
+    // Token breakdown of the last parsed transcript (see `get_token_breakdown_from_transcript`)
+    static TOKEN_BREAKDOWN: RefCell<Option<(TranscriptKey, Option<TokenBreakdown>)>> = const { RefCell::new(None) };
+}
+
+/// Records the token and context data reported in the current input payload.
+///
+/// Reported values take precedence over transcript parsing for the rest of the render.
+pub fn set_reported_context(context: ReportedContext) {
+    REPORTED_CONTEXT.with(|reported| *reported.borrow_mut() = context);
+}
+
+pub(crate) fn reported_context() -> ReportedContext {
+    REPORTED_CONTEXT.with(|reported| reported.borrow().clone())
+}
+
+/// Gets the current token breakdown, preferring values reported in the input
+/// over parsing the transcript.
+pub fn current_token_breakdown(transcript_path: Option<&str>) -> Option<TokenBreakdown> {
+    reported_context()
+        .breakdown
+        .or_else(|| transcript_path.and_then(get_token_breakdown_from_transcript))
+}
+
+/// Gets the current context size in tokens, preferring values reported in the
+/// input. The transcript is only parsed when no token counts were reported; an
+/// `exceeds_200k_tokens` flag raises the result to at least 200K.
+pub fn current_context_tokens(transcript_path: Option<&str>) -> Option<u32> {
+    let reported = reported_context();
+    if let Some(breakdown) = reported.breakdown {
+        return Some(breakdown.total());
+    }
+    let tokens = transcript_path.and_then(get_token_count_from_transcript);
+    if reported.exceeds_200k_tokens {
+        Some(tokens.unwrap_or(0).max(200_000))
     } else {
-        None
+        tokens
+    }
+}
+
+/// Gets the context window size for display and percentage calculations.
+///
+/// A window size reported in the input wins over model detection, except with
+/// adaptive learning enabled (learned windows are compaction points, not sizes).
+pub fn context_window_size(model_name: Option<&str>, config: &config::Config) -> usize {
+    match reported_context().window_size {
+        Some(size) if !config.context.adaptive_learning => size,
+        _ => get_context_window_for_model(model_name, config),
+    }
+}
+
+/// `context_window_size` along with where the size came from.
+pub fn context_window_size_with_source(
+    model_name: Option<&str>,
+    config: &config::Config,
+) -> (usize, WindowSource) {
+    match reported_context().window_size {
+        Some(size) if !config.context.adaptive_learning => (size, WindowSource::Reported),
+        _ => context_window_with_source(model_name, config),
     }
 }
 
 /// Detect compaction state based on token count changes and file modification time
 fn detect_compaction_state(
-    transcript_path: &str,
+    transcript_path: Option<&str>,
     current_tokens: usize,
     session_id: Option<&str>,
 ) -> crate::models::CompactionState {
-    use crate::common::get_data_dir;
-    use crate::database::SqliteDatabase;
     use crate::models::CompactionState;
     use std::fs;
     use std::time::SystemTime;
@@ -411,23 +982,18 @@ fn detect_compaction_state(
     }
 
     // Get last known token count from database
-    let last_known_tokens = if let Some(sid) = session_id {
-        let db_path = get_data_dir().join("stats.db");
-        if let Ok(db) = SqliteDatabase::new(&db_path) {
-            db.get_session_max_tokens(sid)
-        } else {
-            None
-        }
-    } else {
-        None
-    };
+    let last_known_tokens = session_id.and_then(session_max_tokens);
 
     // Check file modification time
-    let recently_modified = if let Ok(safe_path) = validate_transcript_file(transcript_path) {
-        if let Ok(metadata) = fs::metadata(&safe_path) {
-            if let Ok(modified) = metadata.modified() {
-                if let Ok(elapsed) = SystemTime::now().duration_since(modified) {
-                    elapsed.as_secs() < 10 // Modified in last 10 seconds
+    let recently_modified =
+        if let Some(Ok(safe_path)) = transcript_path.map(validate_transcript_file) {
+            if let Ok(metadata) = fs::metadata(&safe_path) {
+                if let Ok(modified) = metadata.modified() {
+                    if let Ok(elapsed) = SystemTime::now().duration_since(modified) {
+                        elapsed.as_secs() < 10 // Modified in last 10 seconds
+                    } else {
+                        false
+                    }
                 } else {
                     false
                 }
@@ -436,10 +1002,7 @@ fn detect_compaction_state(
             }
         } else {
             false
-        }
-    } else {
-        false
-    };
+        };
 
     // Detect compaction state
     if let Some(last_tokens) = last_known_tokens {
@@ -488,6 +1051,8 @@ fn detect_compaction_state(
     }
 }
 
+/// Calculates context usage from the transcript alone.
+#[allow(dead_code)]
 pub fn calculate_context_usage(
     transcript_path: &str,
     model_name: Option<&str>,
@@ -495,16 +1060,101 @@ pub fn calculate_context_usage(
     config_override: Option<&crate::config::Config>,
 ) -> Option<ContextUsage> {
     let total_tokens = get_token_count_from_transcript(transcript_path)?;
+    context_usage_for_tokens(
+        total_tokens,
+        Some(transcript_path),
+        model_name,
+        session_id,
+        config_override,
+    )
+}
+
+/// Calculates context usage from the tokens reported in the input payload,
+/// falling back to the transcript only when none were reported.
+pub fn calculate_current_context_usage(
+    transcript_path: Option<&str>,
+    model_name: Option<&str>,
+    session_id: Option<&str>,
+    config_override: Option<&crate::config::Config>,
+) -> Option<ContextUsage> {
+    if let Some(total_tokens) = current_context_tokens(transcript_path) {
+        return context_usage_for_tokens(
+            total_tokens,
+            transcript_path,
+            model_name,
+            session_id,
+            config_override,
+        );
+    }
+
+    // No transcript (older Claude Code or exotic setups): estimate from the
+    // session's last-known token count rather than dropping the segment
+    if transcript_path.is_some() {
+        return None;
+    }
+    let total_tokens = last_known_session_tokens(session_id?)?;
+    let mut usage =
+        context_usage_for_tokens(total_tokens, None, model_name, session_id, config_override)?;
+    usage.approximate = true;
+    Some(usage)
+}
+
+/// Last-known context size for a session from the database, if any was recorded.
+fn last_known_session_tokens(session_id: &str) -> Option<u32> {
+    session_last_tokens(session_id)
+        .filter(|&tokens| tokens > 0)
+        .map(|tokens| tokens.min(u32::MAX as usize) as u32)
+}
+
+/// Context size a session was last seen at in the stats database (not its
+/// peak, which compaction leaves far above the current size).
+#[cfg(feature = "sqlite")]
+fn session_last_tokens(session_id: &str) -> Option<usize> {
+    let db = crate::database::SqliteDatabase::new(&crate::common::get_database_path()).ok()?;
+    db.get_session_last_tokens(session_id)
+}
+
+/// Without the SQLite backend no token history is kept.
+#[cfg(not(feature = "sqlite"))]
+fn session_last_tokens(_session_id: &str) -> Option<usize> {
+    None
+}
+
+/// Highest token count recorded for a session in the stats database.
+#[cfg(feature = "sqlite")]
+fn session_max_tokens(session_id: &str) -> Option<usize> {
+    let db = crate::database::SqliteDatabase::new(&crate::common::get_database_path()).ok()?;
+    db.get_session_max_tokens(session_id)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn session_max_tokens(_session_id: &str) -> Option<usize> {
+    None
+}
 
-    let config = config_override.unwrap_or_else(|| config::get_config());
-    let buffer_size = config.context.buffer_size;
+fn context_usage_for_tokens(
+    total_tokens: u32,
+    transcript_path: Option<&str>,
+    model_name: Option<&str>,
+    session_id: Option<&str>,
+    config_override: Option<&crate::config::Config>,
+) -> Option<ContextUsage> {
+    let global;
+    let config = match config_override {
+        Some(config) => config,
+        None => {
+            global = config::get_config();
+            &global
+        }
+    };
+    let buffer_size = config.context.buffer_size_for(model_name);
 
     // Detect compaction state
     let compaction_state =
         detect_compaction_state(transcript_path, total_tokens as usize, session_id);
 
-    // Get base context window from model detection (may be learned or advertised)
-    let base_window = get_context_window_for_model(model_name, config);
+    // Get base context window (reported, learned or advertised)
+    let base_window = context_window_size(model_name, config);
 
     // Interpretation of base_window depends on whether adaptive learning is enabled:
     // - If adaptive learning ENABLED: base_window is the learned compaction point (e.g., 156K)
@@ -569,7 +1219,7 @@ pub fn calculate_context_usage(
     let tokens_remaining = working_window.saturating_sub(total_tokens as usize);
 
     // Check if approaching auto-compact threshold (mode-aware: 75% for "full", 94% for "working")
-    let effective_threshold = config.context.get_effective_threshold();
+    let effective_threshold = config.context.effective_threshold_for(model_name);
     let approaching_limit = percentage >= effective_threshold;
 
     Some(ContextUsage {
@@ -577,46 +1227,190 @@ pub fn calculate_context_usage(
         approaching_limit,
         tokens_remaining,
         compaction_state,
+        approximate: false,
     })
 }
 
 pub fn parse_duration(transcript_path: &str) -> Option<u64> {
+    parse_session_times(transcript_path).map(|times| times.wall_secs)
+}
+
+/// Wall-clock and active time of a session, in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionTimes {
+    /// From the first to the last transcript entry
+    pub wall_secs: u64,
+    /// Sum of the gaps between consecutive entries that are at most
+    /// `transcript.idle_threshold_secs` long
+    pub active_secs: u64,
+    /// Active time since the last idle gap
+    pub streak_secs: u64,
+    /// Unix time of the last transcript entry
+    pub last_activity: u64,
+}
+
+impl SessionTimes {
+    /// Continuous active time at `now`, or 0 if the session has gone idle since.
+    pub fn continuous_secs(&self, now: u64, idle_threshold: u64) -> u64 {
+        if now.saturating_sub(self.last_activity) > idle_threshold {
+            0
+        } else {
+            self.streak_secs
+        }
+    }
+}
+
+/// Accumulates `SessionTimes` from transcript timestamps, in file order
+struct SessionTimesBuilder {
+    idle_threshold: u64,
+    first_line: bool,
+    first_timestamp: Option<u64>,
+    last_timestamp: Option<u64>,
+    active_secs: u64,
+    streak_secs: u64,
+}
+
+impl SessionTimesBuilder {
+    fn new(idle_threshold: u64) -> Self {
+        Self {
+            idle_threshold,
+            first_line: true,
+            first_timestamp: None,
+            last_timestamp: None,
+            active_secs: 0,
+            streak_secs: 0,
+        }
+    }
+
+    /// Adds the timestamp of the next line (None if it has none)
+    fn push(&mut self, timestamp: Option<u64>) {
+        if self.first_line {
+            self.first_line = false;
+            self.first_timestamp = timestamp;
+        }
+
+        if let Some(current) = timestamp {
+            // Gaps longer than the idle threshold are time the session sat open unused
+            if let Some(gap) = self
+                .last_timestamp
+                .and_then(|last| current.checked_sub(last))
+            {
+                if gap <= self.idle_threshold {
+                    self.active_secs += gap;
+                    self.streak_secs += gap;
+                } else {
+                    self.streak_secs = 0;
+                }
+            }
+            self.last_timestamp = Some(current);
+        }
+    }
+
+    fn finish(self) -> Option<SessionTimes> {
+        match (self.first_timestamp, self.last_timestamp) {
+            (Some(first), Some(last)) if last > first => Some(SessionTimes {
+                wall_secs: last - first,
+                active_secs: self.active_secs.min(last - first),
+                streak_secs: self.streak_secs.min(last - first),
+                last_activity: last,
+            }),
+            _ => None, // Can't calculate duration without valid timestamps
+        }
+    }
+}
+
+/// Reads a session's wall-clock and active time from its transcript timestamps.
+///
+/// Reuses the result of this render's `transcript_activity` scan when the
+/// file hasn't changed since.
+pub fn parse_session_times(transcript_path: &str) -> Option<SessionTimes> {
     // Validate and canonicalize the file path
     let safe_path = validate_transcript_file(transcript_path).ok()?;
+    let config = config::get_config();
 
-    // Read first and last timestamps from transcript efficiently
+    if let Some(key) = TranscriptKey::of(&safe_path, config.transcript.buffer_lines) {
+        if let Some(cached) = SESSION_TIMES.with(|cache| {
+            cache
+                .borrow()
+                .as_ref()
+                .filter(|(k, _)| *k == key)
+                .map(|(_, times)| *times)
+        }) {
+            return cached;
+        }
+    }
+
+    // Read timestamps from transcript efficiently, one line at a time
     let file = File::open(&safe_path).ok()?;
     let reader = BufReader::new(file);
 
-    let mut first_timestamp = None;
-    let mut last_timestamp = None;
-    let mut first_line = None;
-
-    // Read lines one at a time, keeping track of first and updating last
+    let mut times = SessionTimesBuilder::new(config.transcript.idle_threshold_secs);
     for line in reader.lines().map_while(|l| l.ok()) {
-        if first_line.is_none() {
-            first_line = Some(line.clone());
-            // Parse first line
-            if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(&line) {
-                first_timestamp = parse_iso8601_to_unix(&entry.timestamp);
-            }
-        }
+        times.push(
+            serde_json::from_str::<TranscriptEntry>(&line)
+                .ok()
+                .and_then(|entry| parse_iso8601_to_unix(&entry.timestamp)),
+        );
+    }
+    times.finish()
+}
 
-        // Always try to parse the current line as the last one
-        if let Ok(entry) = serde_json::from_str::<TranscriptEntry>(&line) {
-            last_timestamp = parse_iso8601_to_unix(&entry.timestamp);
+/// Session statistics re-derived from a whole transcript
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub struct TranscriptStats {
+    /// Unix time of the first transcript entry
+    pub started: Option<u64>,
+    /// Active time, as recorded on each render
+    pub active_secs: Option<u64>,
+    /// Usage of the last assistant message, i.e. the final context breakdown
+    pub tokens: Option<TokenBreakdown>,
+    /// Highest context size of any assistant message
+    pub max_tokens: Option<u32>,
+}
+
+/// Scans a whole transcript for the statistics stored per session, for
+/// rebuilding sessions whose rows were lost or predate newer columns.
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub fn transcript_stats(transcript_path: &str) -> Option<TranscriptStats> {
+    let safe_path = validate_transcript_file(transcript_path).ok()?;
+    let times = parse_session_times(transcript_path);
+
+    let file = File::open(&safe_path).ok()?;
+    let mut tokens = None;
+    let mut max_tokens: Option<u32> = None;
+    for line in BufReader::new(file).lines().map_while(|l| l.ok()) {
+        let Ok(entry) = serde_json::from_str::<TranscriptEntry>(&line) else {
+            continue;
+        };
+        let Some(usage) = entry
+            .message
+            .usage
+            .filter(|_| entry.message.role == "assistant")
+        else {
+            continue;
+        };
+        let breakdown = TokenBreakdown {
+            input_tokens: usage.input_tokens.unwrap_or(0),
+            output_tokens: usage.output_tokens.unwrap_or(0),
+            cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
+            cache_creation_tokens: usage.cache_creation_input_tokens.unwrap_or(0),
+        };
+        if breakdown.total() > 0 {
+            max_tokens = Some(max_tokens.unwrap_or(0).max(breakdown.total()));
+            tokens = Some(breakdown);
         }
     }
 
-    if first_timestamp.is_none() || last_timestamp.is_none() {
+    if times.is_none() && tokens.is_none() {
         return None;
     }
-
-    // Calculate duration in seconds
-    match (first_timestamp, last_timestamp) {
-        (Some(first), Some(last)) if last > first => Some(last - first),
-        _ => None, // Can't calculate duration without valid timestamps
-    }
+    Some(TranscriptStats {
+        started: times.map(|t| t.last_activity - t.wall_secs),
+        active_secs: times.map(|t| t.active_secs),
+        tokens,
+        max_tokens,
+    })
 }
 
 #[cfg(test)]
@@ -634,6 +1428,77 @@ mod tests {
     }
     use std::fs;
 
+    #[test]
+    fn test_transcript_stats() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("session.jsonl");
+        fs::write(
+            &path,
+            concat!(
+                r#"{"message":{"role":"user","content":"hi"},"timestamp":"2025-03-07T10:00:00Z"}"#,
+                "\n",
+                r#"{"message":{"role":"assistant","usage":{"input_tokens":10,"cache_read_input_tokens":90000,"output_tokens":500}},"timestamp":"2025-03-07T10:01:00Z"}"#,
+                "\n",
+                r#"{"message":{"role":"assistant","usage":{"input_tokens":5,"cache_read_input_tokens":20000,"output_tokens":100}},"timestamp":"2025-03-07T10:02:00Z"}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let stats = transcript_stats(path.to_str().unwrap()).unwrap();
+        assert_eq!(stats.started, Some(1741341600));
+        assert_eq!(stats.active_secs, Some(120));
+        // Final breakdown, but the peak context size
+        assert_eq!(stats.tokens.unwrap().cache_read_tokens, 20_000);
+        assert_eq!(stats.max_tokens, Some(90_510));
+    }
+
+    #[test]
+    fn test_is_ignored_dir() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let repo = temp_dir.path().join("repo");
+        let nested = repo.join("src/deep");
+        fs::create_dir_all(&nested).unwrap();
+        let dir = |p: &Path| p.to_str().unwrap().to_string();
+
+        assert!(!is_ignored_dir(&dir(&nested), &[]));
+
+        // Listed directories match themselves and everything below, not siblings
+        let listed = vec![dir(&repo)];
+        assert!(is_ignored_dir(&dir(&repo), &listed));
+        assert!(is_ignored_dir(&dir(&nested), &listed));
+        assert!(!is_ignored_dir(&format!("{}-other", dir(&repo)), &listed));
+
+        // Entries arrive already expanded, so a literal `${` is not expanded again
+        let literal = temp_dir.path().join("${HOME}");
+        let listed = vec![dir(&literal)];
+        assert!(is_ignored_dir(&dir(&literal.join("x")), &listed));
+
+        // Marker file in a parent
+        fs::write(repo.join(IGNORE_MARKER), "").unwrap();
+        assert!(is_ignored_dir(&dir(&nested), &[]));
+        assert!(!is_ignored_dir(&dir(temp_dir.path()), &[]));
+    }
+
+    #[test]
+    fn test_is_dir_writable() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        assert!(is_dir_writable(temp_dir.path()));
+        assert!(is_dir_writable(&temp_dir.path().join("missing")));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let locked = temp_dir.path().join("locked");
+            std::fs::create_dir(&locked).unwrap();
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o555)).unwrap();
+            // Privileged users (root in containers) can write anyway
+            let privileged = std::fs::write(locked.join("probe"), "").is_ok();
+            assert_eq!(is_dir_writable(&locked), privileged);
+            std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
     #[test]
     fn test_validate_transcript_file_security() {
         // Test null byte injection
@@ -750,20 +1615,64 @@ mod tests {
 
     #[test]
     fn test_shorten_path() {
-        let home = env::var("HOME").unwrap_or_else(|_| "/home/user".to_string());
+        use crate::environment::{scoped, FixedEnvironment};
+
+        scoped(FixedEnvironment::new().with_home("/home/user"), || {
+            // Test home directory substitution
+            assert_eq!(shorten_path("/home/user/projects/test"), "~/projects/test");
+
+            // Test path that doesn't start with home
+            assert_eq!(shorten_path("/usr/local/bin"), "/usr/local/bin");
+
+            // Test exact home directory
+            assert_eq!(shorten_path("/home/user"), "~");
+
+            // Test empty path
+            assert_eq!(shorten_path(""), "");
+        });
+    }
 
-        // Test home directory substitution
-        let path = format!("{}/projects/test", home);
-        assert_eq!(shorten_path(&path), "~/projects/test");
+    #[test]
+    fn test_format_cost() {
+        let mut config = crate::config::CostConfig::default();
+        assert_eq!(format_cost(1.234, &config), "$1.23");
+        assert_eq!(format_cost(0.004, &config), "$0.00");
+
+        config.rounding = "up".to_string();
+        assert_eq!(format_cost(1.234, &config), "$1.24");
+        assert_eq!(format_cost(1.10, &config), "$1.10");
 
-        // Test path that doesn't start with home
-        assert_eq!(shorten_path("/usr/local/bin"), "/usr/local/bin");
+        config.rounding = "down".to_string();
+        assert_eq!(format_cost(1.239, &config), "$1.23");
 
-        // Test exact home directory
-        assert_eq!(shorten_path(&home), "~");
+        config.rounding = "nearest".to_string();
+        config.decimals = 0;
+        assert_eq!(format_cost(12.6, &config), "$13");
 
-        // Test empty path
-        assert_eq!(shorten_path(""), "");
+        config.decimals = 9; // Clamped to 4
+        assert_eq!(format_cost(0.123456, &config), "$0.1235");
+
+        config.decimals = 2;
+        config.show_less_than = true;
+        assert_eq!(format_cost(0.004, &config), "<$0.01");
+        assert_eq!(format_cost(0.0, &config), "$0.00");
+    }
+
+    #[test]
+    fn test_abbreviate_path() {
+        use crate::environment::{scoped, FixedEnvironment};
+
+        scoped(FixedEnvironment::new().with_home("/home/user"), || {
+            assert_eq!(
+                abbreviate_path("/home/user/projects/demo-app"),
+                "~/p/demo-app"
+            );
+            assert_eq!(abbreviate_path("/usr/local/bin"), "/u/l/bin");
+            assert_eq!(abbreviate_path("/srv/.config/app"), "/s/.c/app");
+            assert_eq!(abbreviate_path("/home/user"), "~");
+            assert_eq!(abbreviate_path("/"), "/");
+            assert_eq!(abbreviate_path(""), "");
+        });
     }
 
     #[test]
@@ -775,24 +1684,28 @@ mod tests {
             approaching_limit: false,
             tokens_remaining: 180_000,
             compaction_state: CompactionState::Normal,
+            approximate: false,
         };
         let medium = ContextUsage {
             percentage: 55.0,
             approaching_limit: false,
             tokens_remaining: 90_000,
             compaction_state: CompactionState::Normal,
+            approximate: false,
         };
         let high = ContextUsage {
             percentage: 75.0,
             approaching_limit: false,
             tokens_remaining: 50_000,
             compaction_state: CompactionState::Normal,
+            approximate: false,
         };
         let critical = ContextUsage {
             percentage: 95.0,
             approaching_limit: true, // Above 80% threshold
             tokens_remaining: 10_000,
             compaction_state: CompactionState::Normal,
+            approximate: false,
         };
 
         assert_eq!(low.percentage, 10.0);
@@ -853,6 +1766,139 @@ mod tests {
         assert_eq!(usage.percentage, 15.4);
     }
 
+    #[test]
+    fn test_transcript_size() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let mut file = NamedTempFile::with_suffix(".jsonl").unwrap();
+        for _ in 0..3 {
+            writeln!(file, r#"{{"message":{{"role":"user","content":"hi"}},"timestamp":"2025-08-22T18:32:37.789Z"}}"#).unwrap();
+        }
+        let path = file.path().to_str().unwrap().to_string();
+        let bytes = std::fs::metadata(&path).unwrap().len();
+
+        let size = transcript_size(&path).unwrap();
+        assert_eq!(size.bytes, bytes);
+        assert_eq!(size.messages, 3);
+        assert!(!size.estimated);
+
+        // A grown file is measured again instead of served from the cache
+        writeln!(file, r#"{{"message":{{"role":"user","content":"hi"}},"timestamp":"2025-08-22T18:32:38.789Z"}}"#).unwrap();
+        assert_eq!(transcript_size(&path).unwrap().messages, 4);
+    }
+
+    #[test]
+    fn test_edited_files_in() {
+        let lines: Vec<TranscriptEntry> = [
+            r#"{"message":{"role":"assistant","content":[{"type":"text","text":"ok"},{"type":"tool_use","name":"Edit","input":{"file_path":"/p/src/a.rs"}}]},"timestamp":"2025-08-22T18:32:37.789Z"}"#,
+            r#"{"message":{"role":"assistant","content":[{"type":"tool_use","name":"Write","input":{"file_path":"/p/src/b.rs"}},{"type":"tool_use","name":"Read","input":{"file_path":"/p/src/c.rs"}}]},"timestamp":"2025-08-22T18:32:38.789Z"}"#,
+            r#"{"message":{"role":"assistant","content":[{"type":"tool_use","name":"MultiEdit","input":{"file_path":"/p/src/a.rs"}},{"type":"tool_use","name":"NotebookEdit","input":{"notebook_path":"/p/n.ipynb"}}]},"timestamp":"2025-08-22T18:32:39.789Z"}"#,
+            r#"{"message":{"role":"user","content":[{"type":"tool_result","content":"Edit /p/src/d.rs"}]},"timestamp":"2025-08-22T18:32:40.789Z"}"#,
+        ]
+        .iter()
+        .map(|s| serde_json::from_str(s).unwrap())
+        .collect();
+
+        assert_eq!(
+            edited_files_in(&lines),
+            vec!["/p/src/a.rs", "/p/src/b.rs", "/p/n.ipynb"]
+        );
+    }
+
+    #[test]
+    fn test_api_calls_in() {
+        let lines: Vec<TranscriptEntry> = [
+            r#"{"message":{"id":"msg_1","role":"assistant","content":[{"type":"text","text":"a"}],"usage":{"input_tokens":10,"cache_read_input_tokens":9000,"output_tokens":1}},"timestamp":"2025-08-22T12:00:00Z"}"#,
+            r#"{"message":{"id":"msg_1","role":"assistant","content":[{"type":"tool_use","name":"Read","input":{}}],"usage":{"input_tokens":10,"cache_read_input_tokens":9000,"output_tokens":250}},"timestamp":"2025-08-22T12:00:01Z"}"#,
+            r#"{"message":{"role":"user","content":"ok"},"timestamp":"2025-08-22T12:00:02Z"}"#,
+            r#"{"message":{"id":"msg_2","role":"assistant","content":"b","usage":{"input_tokens":5,"cache_creation_input_tokens":400,"output_tokens":80}},"timestamp":"2025-08-22T12:00:03Z"}"#,
+            // No message ID: can't be counted once, so it isn't counted
+            r#"{"message":{"role":"assistant","content":"c","usage":{"input_tokens":7,"output_tokens":7}},"timestamp":"2025-08-22T12:00:04Z"}"#,
+        ]
+        .iter()
+        .map(|s| serde_json::from_str(s).unwrap())
+        .collect();
+
+        let calls = api_calls_in(&lines);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].message_id, "msg_1");
+        assert_eq!(calls[0].tokens.output_tokens, 250);
+        assert_eq!(calls[0].tokens.cache_read_tokens, 9000);
+        assert_eq!(calls[1].tokens.cache_creation_tokens, 400);
+        assert_eq!(calls[1].date.len(), 10);
+    }
+
+    #[test]
+    fn test_transcript_activity() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let mut file = NamedTempFile::with_suffix(".jsonl").unwrap();
+        writeln!(
+            file,
+            r#"{{"message":{{"role":"user","content":"go"}},"timestamp":"2025-08-22T12:00:00Z"}}"#
+        )
+        .unwrap();
+        writeln!(file, r#"{{"message":{{"id":"msg_1","role":"assistant","content":[{{"type":"tool_use","name":"Edit","input":{{"file_path":"/p/a.rs"}}}}],"usage":{{"input_tokens":10,"output_tokens":5}}}},"timestamp":"2025-08-22T12:01:00Z"}}"#).unwrap();
+        writeln!(file, "not json").unwrap();
+        writeln!(file, r#"{{"message":{{"id":"msg_2","role":"assistant","content":[{{"type":"tool_use","name":"Write","input":{{"file_path":"/p/b.rs"}}}}],"usage":{{"input_tokens":20,"output_tokens":8}}}},"timestamp":"2025-08-22T12:03:00Z"}}"#).unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let activity = transcript_activity(path).unwrap();
+        let times = activity.times.unwrap();
+        assert_eq!(times.wall_secs, 180);
+        assert_eq!(times.active_secs, 180);
+        let ids: Vec<&str> = activity
+            .api_calls
+            .iter()
+            .map(|c| c.message_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["msg_1", "msg_2"]);
+        assert_eq!(activity.edited_files, vec!["/p/a.rs", "/p/b.rs"]);
+
+        // The same scan answers the duration segment
+        assert_eq!(parse_session_times(path), Some(times));
+    }
+
+    #[test]
+    fn test_token_breakdown_cache_follows_file_changes() {
+        use crate::environment::{self, FixedEnvironment};
+        use std::io::Write;
+        use tempfile::{NamedTempFile, TempDir};
+
+        // Keep the on-disk token cache out of the user's real cache directory
+        let cache_home = TempDir::new().unwrap();
+        let env = FixedEnvironment::new()
+            .with_home(cache_home.path())
+            .with_var("XDG_CACHE_HOME", cache_home.path().to_str().unwrap());
+
+        environment::scoped(env, || {
+            let mut file = NamedTempFile::with_suffix(".jsonl").unwrap();
+            writeln!(file, r#"{{"message":{{"role":"assistant","content":"a","usage":{{"input_tokens":1000,"output_tokens":10}}}},"timestamp":"2025-08-22T18:32:37.789Z"}}"#).unwrap();
+            let path = file.path().to_str().unwrap().to_string();
+
+            let first = get_token_breakdown_from_transcript(&path).unwrap();
+            assert_eq!(first.input_tokens, 1000);
+            assert_eq!(get_token_breakdown_from_transcript(&path), Some(first));
+            assert!(cache_home
+                .path()
+                .join("claudia-statusline/token-cache.json")
+                .exists());
+
+            // Appending changes the size, so the transcript is parsed again
+            writeln!(file, r#"{{"message":{{"role":"assistant","content":"b","usage":{{"input_tokens":5000,"output_tokens":10}}}},"timestamp":"2025-08-22T18:32:38.789Z"}}"#).unwrap();
+            let key = TranscriptKey::of(file.path(), 50).unwrap();
+            assert_ne!(key, TranscriptKey::of(file.path(), 100).unwrap());
+            assert_eq!(
+                get_token_breakdown_from_transcript(&path)
+                    .unwrap()
+                    .input_tokens,
+                5000
+            );
+        });
+    }
+
     #[test]
     fn test_calculate_context_usage_with_array_content() {
         use std::io::Write;
@@ -948,6 +1994,37 @@ mod tests {
         assert_eq!(result2.unwrap(), 600); // 10 minutes = 600 seconds
     }
 
+    #[test]
+    fn test_parse_session_times_skips_idle_gaps() {
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        // Two minutes of work, a two-hour break, then one more minute
+        let mut file = NamedTempFile::with_suffix(".jsonl").unwrap();
+        for timestamp in [
+            "2025-08-25T10:00:00.000Z",
+            "2025-08-25T10:02:00.000Z",
+            "2025-08-25T12:02:00.000Z",
+            "2025-08-25T12:03:00.000Z",
+        ] {
+            writeln!(
+                file,
+                r#"{{"message":{{"role":"user","content":"x"}},"timestamp":"{}"}}"#,
+                timestamp
+            )
+            .unwrap();
+        }
+
+        let times = parse_session_times(file.path().to_str().unwrap()).unwrap();
+        assert_eq!(times.wall_secs, 7380);
+        assert_eq!(times.active_secs, 180);
+
+        // Only the minute after the break counts as continuous, and only while not idle
+        assert_eq!(times.streak_secs, 60);
+        assert_eq!(times.continuous_secs(times.last_activity + 30, 300), 60);
+        assert_eq!(times.continuous_secs(times.last_activity + 600, 300), 0);
+    }
+
     #[test]
     fn test_model_based_context_window() {
         use std::io::Write;
@@ -1015,6 +2092,40 @@ mod tests {
         assert_eq!(usage.percentage, 50.0);
     }
 
+    #[test]
+    fn test_model_override_buffer_and_threshold() {
+        use crate::config::ModelContextOverride;
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let mut file = NamedTempFile::with_suffix(".jsonl").unwrap();
+        writeln!(file, r#"{{"message":{{"role":"assistant","content":"test","usage":{{"input_tokens":150000,"output_tokens":0}}}},"timestamp":"2025-08-22T18:32:37.789Z"}}"#).unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let mut cfg = test_config();
+        cfg.context.percentage_mode = "working".to_string();
+        cfg.context.model_overrides.insert(
+            "Claude Opus 4.5".to_string(),
+            ModelContextOverride {
+                buffer_size: Some(50_000),
+                auto_compact_threshold: Some(99.0),
+            },
+        );
+
+        // Default buffer: 150K / (200K - 40K) = 93.75%, below the 94% warning
+        let usage =
+            calculate_context_usage(path, Some("Claude Sonnet 4.5"), None, Some(&cfg)).unwrap();
+        assert!((usage.percentage - 93.75).abs() < 0.01);
+        assert!(!usage.approaching_limit);
+
+        // Override: working window is 150K, so 100%, and the warning starts at 99%
+        let usage =
+            calculate_context_usage(path, Some("Claude Opus 4.5"), None, Some(&cfg)).unwrap();
+        assert_eq!(usage.percentage, 100.0);
+        assert!(usage.approaching_limit);
+        assert_eq!(usage.tokens_remaining, 0);
+    }
+
     #[test]
     fn test_format_token_count() {
         // Test zero
@@ -1035,4 +2146,54 @@ mod tests {
         assert_eq!(format_token_count(1), "1k");
         assert_eq!(format_token_count(100), "1k");
     }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(45, "compact"), "45s");
+        assert_eq!(format_duration(90, "compact"), "1m");
+        assert_eq!(format_duration(3665, "compact"), "1h1m");
+        assert_eq!(format_duration(3900, "clock"), "01:05");
+        assert_eq!(format_duration(300, "clock"), "00:05");
+        assert_eq!(format_duration(3900, "verbose"), "1 hr 5 min");
+        assert_eq!(format_duration(7200, "verbose"), "2 hr");
+        assert_eq!(format_duration(45, "verbose"), "45 sec");
+        assert_eq!(format_duration(90, "unknown"), "1m");
+    }
+
+    #[test]
+    fn test_reported_context_skips_transcript() {
+        use crate::models::StatuslineInput;
+
+        let mut cfg = crate::config::Config::default();
+        cfg.context.adaptive_learning = false;
+        cfg.context.percentage_mode = "full".to_string();
+
+        let input: StatuslineInput = serde_json::from_str(
+            r#"{"context_window": {"context_window_size": 200000,
+                "current_usage": {"input_tokens": 1000, "output_tokens": 500,
+                                  "cache_creation_input_tokens": 8500, "cache_read_input_tokens": 40000}}}"#,
+        )
+        .unwrap();
+        set_reported_context(input.reported_context());
+
+        // The transcript path does not exist; reported tokens are used instead
+        assert_eq!(
+            current_context_tokens(Some("/nonexistent.jsonl")),
+            Some(50_000)
+        );
+        let usage =
+            calculate_current_context_usage(Some("/nonexistent.jsonl"), None, None, Some(&cfg))
+                .unwrap();
+        assert!((usage.percentage - 25.0).abs() < 0.01);
+
+        // Only the 200K flag: at least 200K tokens
+        set_reported_context(ReportedContext {
+            exceeds_200k_tokens: true,
+            ..Default::default()
+        });
+        assert_eq!(current_context_tokens(None), Some(200_000));
+
+        set_reported_context(ReportedContext::default());
+        assert_eq!(current_context_tokens(None), None);
+    }
 }
@@ -0,0 +1,143 @@
+//! Recent lines-changed velocity (e.g., "+37 in 5m").
+//!
+//! Claude Code only reports cumulative line counts per session. Each render
+//! compares the incoming counts with the ones seen `display.lines_delta_window_minutes`
+//! ago, so bursts of generated code stand out. The counts are kept per session
+//! in the stats database (`lines_samples`), one sample per change, so they are
+//! pruned along with the session. Builds without SQLite show no delta.
+
+/// Cumulative line counts first seen at `at` (unix seconds)
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinesSample {
+    pub at: i64,
+    pub added: u64,
+    pub removed: u64,
+}
+
+/// Lines changed within the window, and how long the window actually spans
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinesDelta {
+    pub added: u64,
+    pub removed: u64,
+    pub span_secs: u64,
+}
+
+/// Records the incoming counts and returns the change over the last `window_secs`.
+///
+/// Returns None when nothing changed within the window, or when the samples
+/// can't be read or written.
+#[cfg(feature = "sqlite")]
+pub fn lines_delta(
+    session_id: &str,
+    added: u64,
+    removed: u64,
+    window_secs: u64,
+) -> Option<LinesDelta> {
+    if crate::stats::check_disk_space().is_err() {
+        return None;
+    }
+    let db_path = crate::stats::StatsData::get_sqlite_path().ok()?;
+    let db = crate::database::SqliteDatabase::new(&db_path).ok()?;
+    let now = crate::environment::current().now().timestamp();
+    db.update_lines_samples(session_id, |samples| {
+        apply_sample(samples, now, added, removed, window_secs)
+    })
+    .map_err(|e| log::warn!("Failed to update line samples for {}: {}", session_id, e))
+    .ok()
+    .flatten()
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn lines_delta(
+    _session_id: &str,
+    _added: u64,
+    _removed: u64,
+    _window_secs: u64,
+) -> Option<LinesDelta> {
+    None
+}
+
+/// Adds the counts seen at `now` and diffs them against the counts at the
+/// start of the window (or the oldest sample, for histories shorter than it).
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+fn apply_sample(
+    samples: &mut Vec<LinesSample>,
+    now: i64,
+    added: u64,
+    removed: u64,
+    window_secs: u64,
+) -> Option<LinesDelta> {
+    // Lower counts mean the session was reset; start over
+    if samples
+        .last()
+        .is_some_and(|last| added < last.added || removed < last.removed)
+    {
+        samples.clear();
+    }
+    if samples
+        .last()
+        .is_none_or(|last| last.added != added || last.removed != removed)
+    {
+        samples.push(LinesSample {
+            at: now,
+            added,
+            removed,
+        });
+    }
+
+    // Counts at the window start: the last sample first seen before it
+    let window_start = now - window_secs as i64;
+    let reference = samples
+        .iter()
+        .rposition(|s| s.at <= window_start)
+        .unwrap_or(0);
+    samples.drain(..reference);
+
+    let base = samples[0];
+    let delta = LinesDelta {
+        added: added - base.added,
+        removed: removed - base.removed,
+        span_secs: (now - base.at.max(window_start)).max(0) as u64,
+    };
+    (delta.added > 0 || delta.removed > 0).then_some(delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_sample_window() {
+        let mut samples = Vec::new();
+        // First sight of a session: nothing to compare against yet
+        assert_eq!(apply_sample(&mut samples, 1000, 100, 10, 300), None);
+        // Unchanged counts don't add samples
+        assert_eq!(apply_sample(&mut samples, 1100, 100, 10, 300), None);
+        assert_eq!(samples.len(), 1);
+
+        let delta = apply_sample(&mut samples, 1200, 137, 12, 300).unwrap();
+        assert_eq!((delta.added, delta.removed, delta.span_secs), (37, 2, 200));
+
+        // Window start falls after the first sample: span is the whole window
+        let delta = apply_sample(&mut samples, 1400, 150, 12, 300).unwrap();
+        assert_eq!((delta.added, delta.removed, delta.span_secs), (50, 2, 300));
+
+        // Only the change since the window start counts
+        let delta = apply_sample(&mut samples, 1600, 150, 12, 300).unwrap();
+        assert_eq!((delta.added, delta.removed), (13, 0));
+        assert_eq!(samples.len(), 2);
+
+        // Quiet for a whole window: hidden again
+        assert_eq!(apply_sample(&mut samples, 1800, 150, 12, 300), None);
+        assert_eq!(samples.len(), 1);
+    }
+
+    #[test]
+    fn test_apply_sample_reset() {
+        let mut samples = Vec::new();
+        apply_sample(&mut samples, 1000, 500, 50, 300);
+        assert_eq!(apply_sample(&mut samples, 1010, 3, 0, 300), None);
+        assert_eq!(samples.len(), 1);
+    }
+}
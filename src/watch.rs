@@ -0,0 +1,187 @@
+//! Live re-rendering of the statusline (`statusline watch`).
+//!
+//! Subscribes to filesystem change notifications (inotify on Linux, FSEvents on
+//! macOS) for the directories holding the transcript and the stats database,
+//! and re-renders the line in place when one of the watched files changes.
+//! The directories are watched rather than the files so the database's WAL
+//! file is picked up when it is created. Falls back to polling modification
+//! times if no watcher can be set up (e.g. the inotify watch limit is reached).
+
+use crate::common::get_database_path;
+use crate::database::{SessionSummary, SqliteDatabase};
+use crate::display::format_output_to_string;
+use crate::error::Result;
+use crate::models::Cost;
+use crate::stats;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Derives the session id from a transcript path (`<session-id>.jsonl`).
+pub fn session_id_from_transcript(transcript: &Path) -> Option<String> {
+    transcript
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty())
+        .map(|stem| stem.to_string())
+}
+
+/// Files whose changes trigger a re-render.
+fn watched_paths(transcript: &Path) -> Vec<PathBuf> {
+    let db_path = get_database_path();
+    let wal_path = PathBuf::from(format!("{}-wal", db_path.display()));
+    vec![transcript.to_path_buf(), db_path, wal_path]
+}
+
+/// Watches the directories of `paths`, signalling on `Receiver` whenever an
+/// event touches one of the watched file names.
+fn start_watcher(paths: &[PathBuf]) -> notify::Result<(RecommendedWatcher, Receiver<()>)> {
+    let names: HashSet<OsString> = paths
+        .iter()
+        .filter_map(|path| path.file_name().map(|name| name.to_os_string()))
+        .collect();
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let relevant = event.is_ok_and(|event| {
+            event
+                .paths
+                .iter()
+                .any(|path| path.file_name().is_some_and(|name| names.contains(name)))
+        });
+        if relevant {
+            let _ = tx.send(());
+        }
+    })?;
+
+    let dirs: HashSet<PathBuf> = paths
+        .iter()
+        .map(|path| match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        })
+        .collect();
+    for dir in &dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    Ok((watcher, rx))
+}
+
+fn modification_times(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|path| path.metadata().and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Renders the statusline for a session from its transcript and stored stats.
+fn render(transcript: &Path, session_id: Option<&str>) -> String {
+    let data = stats::get_or_load_stats_data();
+    let daily_total = stats::get_daily_total(&data);
+    let session = session_id.and_then(|sid| data.sessions.get(sid));
+    let summary: Option<SessionSummary> = session_id.and_then(|sid| {
+        SqliteDatabase::new(&get_database_path())
+            .ok()
+            .and_then(|db| db.get_session_summary(sid).ok().flatten())
+    });
+
+    let current_dir = summary
+        .as_ref()
+        .and_then(|s| s.workspace_dir.clone())
+        .or_else(|| {
+            std::env::current_dir()
+                .ok()
+                .map(|p| p.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "~".to_string());
+    let model_name = summary.as_ref().and_then(|s| s.model_name.clone());
+    let cost = session.map(|s| Cost {
+        total_cost_usd: Some(s.cost),
+        total_lines_added: Some(s.lines_added),
+        total_lines_removed: Some(s.lines_removed),
+        ..Default::default()
+    });
+
+    format_output_to_string(
+        &current_dir,
+        model_name.as_deref(),
+        transcript.to_str(),
+        cost.as_ref(),
+        daily_total,
+        session_id,
+    )
+}
+
+/// Re-renders the statusline in place until interrupted.
+///
+/// `interval` is how long a burst of change notifications is coalesced before
+/// redrawing, or the polling period when no watcher is available.
+pub fn run(transcript: &Path, session_id: Option<&str>, interval: Duration) -> Result<()> {
+    let session_id = session_id
+        .map(|s| s.to_string())
+        .or_else(|| session_id_from_transcript(transcript));
+    let paths = watched_paths(transcript);
+    // Keep the watcher alive for the whole loop; dropping it stops the events
+    let watcher = match start_watcher(&paths) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            log::warn!("File watching unavailable ({}), polling instead", e);
+            None
+        }
+    };
+    let mut last_seen = None;
+    let mut stdout = io::stdout();
+
+    loop {
+        // Events for other files in the same directories don't change these
+        let current = modification_times(&paths);
+        if last_seen.as_ref() != Some(&current) {
+            let line = render(transcript, session_id.as_deref());
+            // Return to column 0 and clear the line before redrawing
+            write!(stdout, "\r\x1b[2K{}", line)?;
+            stdout.flush()?;
+            last_seen = Some(current);
+        }
+
+        match &watcher {
+            Some((_, events)) => {
+                if events.recv().is_err() {
+                    return Ok(());
+                }
+                thread::sleep(interval);
+                while events.try_recv().is_ok() {}
+            }
+            None => thread::sleep(interval),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_id_from_transcript() {
+        assert_eq!(
+            session_id_from_transcript(Path::new("/home/u/.claude/projects/x/abc-123.jsonl")),
+            Some("abc-123".to_string())
+        );
+        assert_eq!(session_id_from_transcript(Path::new("/")), None);
+    }
+
+    #[test]
+    fn test_watcher_signals_transcript_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let transcript = temp_dir.path().join("session.jsonl");
+        std::fs::write(&transcript, "").unwrap();
+
+        let (_watcher, events) = start_watcher(std::slice::from_ref(&transcript)).unwrap();
+        std::fs::write(temp_dir.path().join("other.jsonl"), "{}\n").unwrap();
+        std::fs::write(&transcript, "{}\n").unwrap();
+        assert!(events.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+}
@@ -1,5 +1,7 @@
 //! Integration tests for database maintenance functionality
 
+#![cfg(feature = "sqlite")]
+
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
@@ -332,3 +334,93 @@ fn test_db_maintain_pruning() {
     // Check that pruning section exists in output
     assert!(stdout.contains("Pruning"), "Output should mention pruning");
 }
+
+// Exceeding database.max_size_mb prunes old rows and vacuums on the next render
+#[test]
+fn test_max_size_limit_prunes_and_vacuums() {
+    use chrono::{Duration, Utc};
+    use rusqlite::Connection;
+    use std::io::Write;
+
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("claudia-statusline");
+    let db_path = data_dir.join("stats.db");
+    fs::create_dir_all(&data_dir).unwrap();
+    fs::write(
+        data_dir.join("config.toml"),
+        "[database]\nmax_size_mb = 1\n",
+    )
+    .unwrap();
+
+    let render = |session_id: &str| {
+        let mut child = Command::new(get_binary_path())
+            .env("XDG_DATA_HOME", temp_dir.path())
+            .env("XDG_CONFIG_HOME", temp_dir.path())
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        write!(
+            child.stdin.as_mut().unwrap(),
+            r#"{{"workspace":{{"current_dir":"/tmp"}},"session_id":"{}","cost":{{"total_cost_usd":0.5}}}}"#,
+            session_id
+        )
+        .unwrap();
+        assert!(child.wait_with_output().unwrap().status.success());
+    };
+    render("size-limit-setup");
+    assert!(db_path.exists());
+
+    // ~3 MB of sessions past the 90-day retention period
+    {
+        let conn = Connection::open(&db_path).unwrap();
+        let old = (Utc::now() - Duration::days(100))
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string();
+        let padding = "x".repeat(3000);
+        for i in 0..1000 {
+            conn.execute(
+                "INSERT INTO sessions (session_id, start_time, last_updated, workspace_dir)
+                 VALUES (?1, ?2, ?2, ?3)",
+                rusqlite::params![format!("old-{}", i), old, padding],
+            )
+            .unwrap();
+        }
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .unwrap();
+    }
+    let size_before = fs::metadata(&db_path).unwrap().len();
+    assert!(size_before > 1024 * 1024);
+
+    render("size-limit");
+
+    let conn = Connection::open(&db_path).unwrap();
+    let old_sessions: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sessions WHERE session_id LIKE 'old-%'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(old_sessions, 0, "Old sessions should be pruned");
+    let auto_vacuum: i64 = conn
+        .query_row("PRAGMA auto_vacuum", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(
+        auto_vacuum, 2,
+        "Database should use incremental auto-vacuum"
+    );
+    assert!(fs::metadata(&db_path).unwrap().len() < size_before);
+
+    // health reports the sizes and the limit
+    let output = Command::new(get_binary_path())
+        .env("XDG_DATA_HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path())
+        .args(["health", "--json"])
+        .output()
+        .unwrap();
+    let health: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(health["database_size_bytes"].as_u64().unwrap() > 0);
+    assert!(health["wal_size_bytes"].is_u64());
+    assert_eq!(health["max_size_mb"], 1);
+}
@@ -27,6 +27,14 @@ fn get_test_binary() -> PathBuf {
     panic!("Could not find statusline binary");
 }
 
+/// Directory holding hook state files (the cache directory where there is no state directory)
+fn state_dir() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap()
+        .join("claudia-statusline")
+}
+
 /// Create a test transcript with token counts
 fn create_test_transcript(dir: &TempDir) -> PathBuf {
     let transcript_path = dir.path().join("test-transcript.jsonl");
@@ -65,8 +73,8 @@ fn test_hook_precompact_creates_state_file() {
     assert!(output.status.success(), "Hook command failed");
 
     // Verify state file was created
-    let cache_dir = dirs::cache_dir().unwrap().join("claudia-statusline");
-    let state_file = cache_dir.join(format!("state-{}.json", session_id));
+    let state_dir = state_dir();
+    let state_file = state_dir.join(format!("state-{}.json", session_id));
 
     assert!(state_file.exists(), "State file should exist");
 
@@ -96,8 +104,8 @@ fn test_hook_stop_clears_state_file() {
         .output()
         .expect("Failed to execute precompact");
 
-    let cache_dir = dirs::cache_dir().unwrap().join("claudia-statusline");
-    let state_file = cache_dir.join(format!("state-{}.json", session_id));
+    let state_dir = state_dir();
+    let state_file = state_dir.join(format!("state-{}.json", session_id));
     assert!(
         state_file.exists(),
         "State file should exist after precompact"
@@ -164,8 +172,8 @@ fn test_statusline_detects_hook_compaction() {
     );
 
     // Cleanup
-    let cache_dir = dirs::cache_dir().unwrap().join("claudia-statusline");
-    let _ = fs::remove_file(cache_dir.join(format!("state-{}.json", session_id)));
+    let state_dir = state_dir();
+    let _ = fs::remove_file(state_dir.join(format!("state-{}.json", session_id)));
 }
 
 #[test]
@@ -177,8 +185,8 @@ fn test_statusline_without_hook_shows_percentage() {
     let input = create_test_input(&session_id, &transcript);
 
     // Ensure no hook state exists
-    let cache_dir = dirs::cache_dir().unwrap().join("claudia-statusline");
-    let state_file = cache_dir.join(format!("state-{}.json", session_id));
+    let state_dir = state_dir();
+    let state_file = state_dir.join(format!("state-{}.json", session_id));
     let _ = fs::remove_file(&state_file);
 
     // Run statusline
@@ -273,8 +281,8 @@ fn test_hook_state_transition() {
     );
 
     // Cleanup
-    let cache_dir = dirs::cache_dir().unwrap().join("claudia-statusline");
-    let _ = fs::remove_file(cache_dir.join(format!("state-{}.json", session_id)));
+    let state_dir = state_dir();
+    let _ = fs::remove_file(state_dir.join(format!("state-{}.json", session_id)));
 }
 
 #[test]
@@ -295,9 +303,9 @@ fn test_multiple_sessions_isolated() {
         .expect("Failed to set hook for session A");
 
     // Verify session A has state
-    let cache_dir = dirs::cache_dir().unwrap().join("claudia-statusline");
-    let state_a = cache_dir.join(format!("state-{}.json", session_a));
-    let state_b = cache_dir.join(format!("state-{}.json", session_b));
+    let state_dir = state_dir();
+    let state_a = state_dir.join(format!("state-{}.json", session_a));
+    let state_b = state_dir.join(format!("state-{}.json", session_b));
 
     assert!(state_a.exists(), "Session A should have state");
     assert!(!state_b.exists(), "Session B should not have state");
@@ -311,8 +319,8 @@ fn test_multiple_sessions_isolated() {
 fn test_hook_trigger_types() {
     let session_id = format!("test-triggers-{}", std::process::id());
     let binary = get_test_binary();
-    let cache_dir = dirs::cache_dir().unwrap().join("claudia-statusline");
-    let state_file = cache_dir.join(format!("state-{}.json", session_id));
+    let state_dir = state_dir();
+    let state_file = state_dir.join(format!("state-{}.json", session_id));
 
     // Test auto trigger
     Command::new(&binary)
@@ -1,5 +1,9 @@
 use std::io::Write;
-use std::process::{Command, Stdio};
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+/// Environment for plain-text output in `run_sandboxed`
+const PLAIN: &[(&str, &str)] = &[("NO_COLOR", "1")];
 
 /// Get the path to the test-built binary
 fn get_test_binary() -> String {
@@ -20,6 +24,35 @@ fn get_test_binary() -> String {
         .unwrap()
 }
 
+/// Runs the binary with every XDG directory pointed at `dir`, so it never
+/// touches the real user's config, stats or state, and feeds it `stdin`.
+///
+/// Inherited color, theme and log settings are cleared; pass them in `envs`.
+fn run_sandboxed(dir: &Path, args: &[&str], stdin: &str, envs: &[(&str, &str)]) -> Output {
+    let mut child = Command::new(get_test_binary())
+        .args(args)
+        .env("XDG_DATA_HOME", dir)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("XDG_CACHE_HOME", dir)
+        .env("XDG_STATE_HOME", dir)
+        .env("XDG_RUNTIME_DIR", dir)
+        .env_remove("NO_COLOR")
+        .env_remove("CLICOLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .env_remove("CLAUDE_THEME")
+        .env_remove("STATUSLINE_THEME")
+        .env_remove("RUST_LOG")
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute binary");
+    // Commands that don't read stdin may exit before it is written
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
 #[test]
 fn test_binary_with_empty_input() {
     let output = Command::new(get_test_binary())
@@ -38,6 +71,32 @@ fn test_binary_with_empty_input() {
     assert!(stdout.contains("~")); // Should show home directory
 }
 
+#[test]
+fn test_binary_soft_fails_without_payload() {
+    for stdin in ["", "   \n", r#"{"workspace":{"current_di"#, "not json"] {
+        let output = Command::new(get_test_binary())
+            .env("PWD", "/tmp/startup-race")
+            .env("NO_COLOR", "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                child.stdin.as_mut().unwrap().write_all(stdin.as_bytes())?;
+                child.wait_with_output()
+            })
+            .expect("Failed to execute binary");
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "/tmp/startup-race no data",
+            "stdin: {:?}",
+            stdin
+        );
+    }
+}
+
 #[test]
 fn test_binary_with_workspace() {
     let json = r#"{"workspace":{"current_dir":"/tmp"}}"#;
@@ -537,6 +596,226 @@ fn test_health_command_json() {
     assert!(json.get("session_count").is_some());
 }
 
+#[test]
+fn test_last_prints_previous_render() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let run = |args: &[&str], stdin: &str| {
+        let output = run_sandboxed(temp_dir.path(), args, stdin, PLAIN);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    // Nothing rendered yet
+    assert_eq!(run(&["last"], ""), "");
+
+    let line = run(
+        &[],
+        r#"{"session_id":"last-test","workspace":{"current_dir":"/tmp/last-test"},"model":{"display_name":"Opus"}}"#,
+    );
+    assert_eq!(run(&["last"], ""), format!("{}\n", line));
+
+    let json: serde_json::Value = serde_json::from_str(&run(&["last", "--json"], "")).unwrap();
+    assert_eq!(json["text"], line.as_str());
+    assert_eq!(json["session_id"], "last-test");
+    assert_eq!(json["model"], "Opus");
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_session_note() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let app_dir = temp_dir.path().join("claudia-statusline");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    std::fs::write(
+        app_dir.join("config.toml"),
+        "[display]\nshow_session_note = true\n",
+    )
+    .unwrap();
+    let run = |args: &[&str], stdin: &str| run_sandboxed(temp_dir.path(), args, stdin, PLAIN);
+    let payload = r#"{"session_id":"note-test","workspace":{"current_dir":"/tmp"},"cost":{"total_cost_usd":1.5}}"#;
+
+    // No render yet, so there is no active session
+    assert_eq!(
+        run(&["session", "note", "migrating auth"], "")
+            .status
+            .code(),
+        Some(5)
+    );
+
+    run(&[], payload);
+    assert!(run(&["session", "note", "migrating auth"], "")
+        .status
+        .success());
+    let line = run(&[], payload);
+    assert!(String::from_utf8_lossy(&line.stdout).ends_with(" • migrating auth"));
+
+    let shown = run(&["session", "note"], "");
+    assert_eq!(String::from_utf8_lossy(&shown.stdout), "migrating auth\n");
+
+    assert!(run(&["session", "note", "--clear"], "").status.success());
+    let line = run(&[], payload);
+    assert!(!String::from_utf8_lossy(&line.stdout).contains("migrating auth"));
+}
+
+#[test]
+fn test_hide_below_keeps_day_total() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let app_dir = temp_dir.path().join("claudia-statusline");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    std::fs::write(app_dir.join("config.toml"), "[cost]\nhide_below = 0.05\n").unwrap();
+    let run = |stdin: &str| {
+        let output = run_sandboxed(temp_dir.path(), &[], stdin, PLAIN);
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    run(
+        r#"{"session_id":"hide-a","workspace":{"current_dir":"/tmp"},"cost":{"total_cost_usd":1.5}}"#,
+    );
+    let line = run(
+        r#"{"session_id":"hide-b","workspace":{"current_dir":"/tmp"},"cost":{"total_cost_usd":0.01,"total_duration_ms":120000}}"#,
+    );
+    assert!(!line.contains("$0.01"), "session cost shown: {}", line);
+    assert!(line.contains("($0.30/hr)"), "burn rate missing: {}", line);
+    assert!(line.contains("day: $1.51"), "day total missing: {}", line);
+}
+
+#[test]
+fn test_zsh_rprompt_output() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let output = run_sandboxed(
+        temp_dir.path(),
+        &["--output", "zsh-rprompt"],
+        r#"{"session_id":"rprompt-test","workspace":{"current_dir":"/tmp/rprompt-test"},"model":{"display_name":"Opus"},"cost":{"total_cost_usd":1.5},"context_window":{"context_window_size":200000,"current_usage":{"input_tokens":90000}}}"#,
+        PLAIN,
+    );
+    assert!(output.status.success());
+
+    // Left prompt: the full line, with % escaped for zsh
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("rprompt-test"));
+    assert!(stdout.contains("45%%"));
+
+    // Right prompt: only context and cost
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "45%% $1.50");
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_color_decision() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let run = |args: &[&str], envs: &[(&str, &str)]| {
+        let args: Vec<&str> = args.iter().copied().chain(["stats"]).collect();
+        let output = run_sandboxed(temp_dir.path(), &args, "", envs);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).contains('\x1b')
+    };
+
+    // Piped command output is plain unless forced
+    assert!(!run(&[], &[]));
+    assert!(run(&[], &[("CLICOLOR_FORCE", "1")]));
+    assert!(run(&["--color", "always"], &[]));
+    assert!(!run(&[], &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]));
+    assert!(!run(&["--color", "never"], &[("CLICOLOR_FORCE", "1")]));
+    assert!(!run(&["--no-color", "--color", "always"], &[]));
+}
+
+#[test]
+#[cfg(feature = "themes")]
+fn test_render_theme_override() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let render = |args: &[&str]| {
+        let args: Vec<&str> = ["render", "--fixture", "low-context"]
+            .into_iter()
+            .chain(args.iter().copied())
+            .collect();
+        run_sandboxed(temp_dir.path(), &args, "", &[])
+    };
+
+    let dark = render(&[]);
+    let light = render(&["--theme", "light"]);
+    assert!(dark.status.success());
+    assert!(light.status.success());
+    assert!(String::from_utf8_lossy(&light.stdout).contains("demo-app"));
+    assert_ne!(dark.stdout, light.stdout);
+
+    // Unknown themes are rejected instead of silently falling back
+    let unknown = render(&["--theme", "no-such-theme"]);
+    assert_eq!(unknown.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("no-such-theme"));
+}
+
+#[test]
+fn test_exit_codes_by_failure_type() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let run = |args: &[&str], stdin: &str| run_sandboxed(temp_dir.path(), args, stdin, PLAIN);
+
+    // Input parse errors exit with 5
+    let inspect = run(&["input", "inspect"], "{not json");
+    assert_eq!(inspect.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&inspect.stderr).contains("E002_JSON_PARSE"));
+    assert_eq!(run(&["hook", "stop"], "{}").status.code(), Some(5));
+
+    // The plain render always succeeds; --strict-exit reports the bad payload
+    // but still prints a line
+    assert_eq!(run(&[], "{not json").status.code(), Some(0));
+    let strict = run(&["--strict-exit"], "{not json");
+    assert_eq!(strict.status.code(), Some(5));
+    assert!(!strict.stdout.is_empty());
+
+    // Invalid arguments are reported by clap with 2
+    assert_eq!(run(&["--no-such-flag"], "").status.code(), Some(2));
+}
+
+#[test]
+fn test_health_reports_error_codes() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let app_dir = temp_dir.path().join("claudia-statusline");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    std::fs::write(app_dir.join("config.toml"), "display = 3\n").unwrap();
+
+    let output = run_sandboxed(temp_dir.path(), &["health", "--json"], "", &[]);
+
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let problems = json["problems"].as_array().unwrap();
+    assert_eq!(problems.len(), 1);
+    assert_eq!(problems[0]["component"], "config");
+    assert_eq!(problems[0]["code"], "E050_CONFIG");
+    assert!(!problems[0]["hint"].as_str().unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_low_disk_space_skips_stats_writes() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let app_dir = temp_dir.path().join("claudia-statusline");
+    std::fs::create_dir_all(&app_dir).unwrap();
+    // No disk is this large, so the guard always trips
+    std::fs::write(
+        app_dir.join("config.toml"),
+        "[database]\nmin_free_space_mb = 999999999999\n",
+    )
+    .unwrap();
+    let run = |args: &[&str], stdin: &str| run_sandboxed(temp_dir.path(), args, stdin, PLAIN);
+
+    let output = run(
+        &[],
+        r#"{"session_id":"disk-test","workspace":{"current_dir":"/tmp"},"cost":{"total_cost_usd":2.0}}"#,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("$2.00"), "totals still shown: {}", stdout);
+    assert!(stdout.contains("⚠ disk"), "warning shown: {}", stdout);
+    assert!(!app_dir.join("stats.json").exists());
+
+    let output = run(&["health", "--json"], "");
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["session_count"], 0);
+    let problems = json["problems"].as_array().unwrap();
+    assert!(problems
+        .iter()
+        .any(|p| p["component"] == "disk" && p["code"] == "E042_DISK_SPACE"));
+}
+
 #[test]
 fn test_no_color_flag() {
     let input = r#"{"workspace":{"current_dir":"/test"}}"#;
@@ -730,3 +1009,188 @@ fn test_multiple_cli_flags_precedence() {
         stdout
     );
 }
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_context_estimated_without_transcript() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let transcript = temp_dir.path().join("approx-session.jsonl");
+    std::fs::write(
+        &transcript,
+        r#"{"type":"assistant","timestamp":"2025-01-01T10:00:00Z","message":{"role":"assistant","content":"x","usage":{"input_tokens":100000,"output_tokens":10,"cache_read_input_tokens":0,"cache_creation_input_tokens":0}}}"#,
+    )
+    .unwrap();
+
+    let render = |payload: String| {
+        let output = run_sandboxed(temp_dir.path(), &[], &payload, PLAIN);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let with_transcript = render(format!(
+        r#"{{"session_id":"approx-session","transcript_path":"{}","workspace":{{"current_dir":"/tmp"}},"model":{{"display_name":"Claude Sonnet 4.5"}},"cost":{{"total_cost_usd":0.5}}}}"#,
+        transcript.display()
+    ));
+    assert!(with_transcript.contains("50%"));
+    assert!(!with_transcript.contains("~50%"));
+
+    // Same session without a transcript: estimated from the stored token count
+    let estimated = render(
+        r#"{"session_id":"approx-session","workspace":{"current_dir":"/tmp"},"model":{"display_name":"Claude Sonnet 4.5"},"cost":{"total_cost_usd":0.6}}"#
+            .to_string(),
+    );
+    assert!(estimated.contains("~50%"), "{}", estimated);
+}
+
+#[test]
+fn test_stream_mode_replies_per_payload() {
+    use std::io::{BufRead, BufReader};
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let mut child = Command::new(get_test_binary())
+        .arg("--stream")
+        .env("XDG_DATA_HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path())
+        .env("XDG_CACHE_HOME", temp_dir.path())
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to execute binary");
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+
+    // Each payload gets its reply before the next one is written
+    for (payload, expected) in [
+        (r#"{"workspace":{"current_dir":"/tmp"}}"#, "/tmp"),
+        (
+            r#"{"workspace":{"current_dir":"/usr"},"model":{"display_name":"Opus"}}"#,
+            "Opus",
+        ),
+    ] {
+        writeln!(stdin, "{}", payload).unwrap();
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        assert!(line.ends_with('\n'));
+        assert!(line.contains(expected), "unexpected reply: {}", line);
+    }
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_data_dir_flag_overrides_xdg() {
+    let xdg = tempfile::TempDir::new().unwrap();
+    let data_dir = tempfile::TempDir::new().unwrap();
+    let config_dir = tempfile::TempDir::new().unwrap();
+
+    let json = r#"{"workspace":{"current_dir":"/tmp"},"session_id":"data-dir-flag","cost":{"total_cost_usd":1.0}}"#;
+    let output = Command::new(get_test_binary())
+        .arg("--data-dir")
+        .arg(data_dir.path())
+        .arg("--config")
+        .arg(config_dir.path())
+        .env("XDG_DATA_HOME", xdg.path())
+        .env("XDG_CONFIG_HOME", xdg.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.as_mut().unwrap().write_all(json.as_bytes())?;
+            child.wait_with_output()
+        })
+        .expect("Failed to execute binary");
+    assert!(output.status.success());
+
+    // Stats land in the given directory as-is, nothing under XDG_DATA_HOME
+    assert!(data_dir.path().join("stats.json").exists());
+    assert!(!xdg.path().join("claudia-statusline").exists());
+}
+
+#[test]
+fn test_statuslineignore_renders_path_only() {
+    let data_dir = tempfile::TempDir::new().unwrap();
+    let config_dir = tempfile::TempDir::new().unwrap();
+    let workspace = tempfile::TempDir::new().unwrap();
+    std::fs::write(workspace.path().join(".statuslineignore"), "").unwrap();
+
+    let json = format!(
+        r#"{{"workspace":{{"current_dir":"{}"}},"session_id":"ignored-dir","model":{{"display_name":"Claude Sonnet"}},"cost":{{"total_cost_usd":1.0}}}}"#,
+        workspace.path().display()
+    );
+    let output = Command::new(get_test_binary())
+        .arg("--data-dir")
+        .arg(data_dir.path())
+        .arg("--config")
+        .arg(config_dir.path())
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.as_mut().unwrap().write_all(json.as_bytes())?;
+            child.wait_with_output()
+        })
+        .expect("Failed to execute binary");
+    assert!(output.status.success());
+
+    // Only the path, no model or cost segments
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim(), workspace.path().display().to_string());
+    // No stats work at all
+    assert!(!data_dir.path().join("stats.json").exists());
+    assert!(!data_dir.path().join("stats.db").exists());
+}
+
+#[test]
+#[cfg(feature = "git")]
+fn test_degraded_marker_when_git_unavailable() {
+    let sandbox = tempfile::TempDir::new().unwrap();
+    let repo = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir(repo.path().join(".git")).unwrap();
+    let transcript = repo.path().join("transcript.jsonl");
+    std::fs::write(&transcript, "not a transcript\nstill not one\n").unwrap();
+
+    let render = |payload: String, path: &str| {
+        let output = run_sandboxed(
+            sandbox.path(),
+            &[],
+            &payload,
+            &[("PATH", path), ("NO_COLOR", "1")],
+        );
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let workspace = format!(
+        r#""workspace":{{"current_dir":"{}"}},"model":{{"display_name":"Opus"}}"#,
+        repo.path().display()
+    );
+
+    // A repository whose status can't be read: git is not on PATH
+    let empty_path = sandbox.path().join("bin");
+    std::fs::create_dir(&empty_path).unwrap();
+    let line = render(format!("{{{}}}", workspace), empty_path.to_str().unwrap());
+    assert!(line.trim_end().ends_with('!'), "{}", line);
+
+    // Outside a repository: no marker without a failure, one for a transcript
+    // without a single JSON line
+    let plain = format!(
+        r#""workspace":{{"current_dir":"{}"}},"model":{{"display_name":"Opus"}}"#,
+        sandbox.path().display()
+    );
+    let line = render(format!("{{{}}}", plain), empty_path.to_str().unwrap());
+    assert!(!line.contains('!'), "{}", line);
+    let line = render(
+        format!(
+            r#"{{{},"transcript_path":"{}"}}"#,
+            plain,
+            transcript.display()
+        ),
+        empty_path.to_str().unwrap(),
+    );
+    assert!(line.trim_end().ends_with('!'), "{}", line);
+}
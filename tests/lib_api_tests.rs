@@ -17,6 +17,7 @@ fn test_render_statusline_basic() {
     let input = StatuslineInput {
         workspace: Some(Workspace {
             current_dir: Some(test_dir),
+            project_dir: None,
         }),
         model: Some(Model {
             display_name: Some("Claude 3.5 Sonnet".to_string()),
@@ -121,6 +122,7 @@ fn test_render_without_stats_update() {
 }
 
 #[test]
+#[cfg(feature = "git")]
 fn test_render_with_git_repo() {
     let _lock = ENV_MUTEX.lock().unwrap();
 
@@ -138,6 +140,7 @@ fn test_render_with_git_repo() {
     let input = StatuslineInput {
         workspace: Some(Workspace {
             current_dir: Some(repo_path.to_string()),
+            project_dir: None,
         }),
         model: Some(Model {
             display_name: Some("Claude 3.5 Sonnet".to_string()),
@@ -243,3 +246,79 @@ fn test_render_with_context_usage() {
 
     std::env::remove_var("NO_COLOR");
 }
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_render_records_edited_files() {
+    use statusline::environment::{scoped, FixedEnvironment};
+
+    let _lock = ENV_MUTEX.lock().unwrap();
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.toml");
+    std::fs::write(&config_path, "[display]\nshow_files_edited = true\n").unwrap();
+    let transcript_path = temp_dir.path().join("transcript.jsonl");
+    std::fs::write(&transcript_path, r#"{"message":{"role":"assistant","content":[{"type":"tool_use","name":"Edit","input":{"file_path":"/p/src/a.rs"}},{"type":"tool_use","name":"Write","input":{"file_path":"/p/src/b.rs"}}]},"timestamp":"2025-08-31T10:00:00.000Z"}"#).unwrap();
+
+    let json = format!(
+        r#"{{
+        "workspace": {{"current_dir": "/home/user/project"}},
+        "model": {{"display_name": "Opus"}},
+        "session_id": "lib-files-edited",
+        "transcript": "{}",
+        "cost": {{"total_cost_usd": 1.0}}
+    }}"#,
+        transcript_path.to_str().unwrap()
+    );
+
+    let data_dir = temp_dir.path().to_str().unwrap();
+    let env = FixedEnvironment::new()
+        .with_home(temp_dir.path())
+        .with_var("STATUSLINE_CONFIG_PATH", config_path.to_str().unwrap())
+        .with_var("XDG_DATA_HOME", data_dir)
+        .with_var("XDG_CONFIG_HOME", data_dir)
+        .with_var("XDG_CACHE_HOME", data_dir)
+        .with_var("XDG_STATE_HOME", data_dir)
+        .with_var("NO_COLOR", "1");
+    let output = scoped(env, || render_from_json(&json, true)).unwrap();
+
+    // The library render path records the edited files like the binary does
+    assert!(output.contains("2 files"), "{}", output);
+}
+
+#[test]
+#[cfg(feature = "sqlite")]
+fn test_render_in_ignored_dir() {
+    use statusline::environment::{scoped, FixedEnvironment};
+
+    let _lock = ENV_MUTEX.lock().unwrap();
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let project = temp_dir.path().join("mount/project");
+    std::fs::create_dir_all(&project).unwrap();
+    std::fs::write(temp_dir.path().join("mount/.statuslineignore"), "").unwrap();
+
+    let json = format!(
+        r#"{{
+        "workspace": {{"current_dir": "{}"}},
+        "model": {{"display_name": "Opus"}},
+        "session_id": "lib-ignored-dir",
+        "cost": {{"total_cost_usd": 1.0}}
+    }}"#,
+        project.to_str().unwrap()
+    );
+
+    let data_dir = temp_dir.path().to_str().unwrap();
+    let env = FixedEnvironment::new()
+        .with_home(temp_dir.path())
+        .with_var("XDG_DATA_HOME", data_dir)
+        .with_var("XDG_CONFIG_HOME", data_dir)
+        .with_var("XDG_CACHE_HOME", data_dir)
+        .with_var("XDG_STATE_HOME", data_dir)
+        .with_var("NO_COLOR", "1");
+    let output = scoped(env, || render_from_json(&json, true)).unwrap();
+
+    // Only the path, and nothing recorded for the session
+    assert_eq!(output, "~/mount/project");
+    assert!(!temp_dir.path().join("claudia-statusline/stats.db").exists());
+}
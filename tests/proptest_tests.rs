@@ -6,7 +6,6 @@
 use proptest::prelude::*;
 use serde_json::json;
 use statusline::{
-    git::get_git_status,
     models::{ContextUsage, Cost, ModelType, StatuslineInput},
     stats::StatsData,
     utils::{parse_iso8601_to_unix, shorten_path},
@@ -121,13 +120,14 @@ proptest! {
 }
 
 // Test git status parsing with arbitrary directory paths
+#[cfg(feature = "git")]
 proptest! {
     #[test]
     fn test_git_status_doesnt_panic(
         dir in prop::string::string_regex("[a-zA-Z0-9/._-]{0,200}").unwrap()
     ) {
         // Should not panic, just return None for invalid paths
-        let _status = get_git_status(&dir);
+        let _status = statusline::git::get_git_status(&dir);
     }
 }
 
@@ -163,6 +163,7 @@ proptest! {
             total_cost_usd: Some(cost),
             total_lines_added: Some(lines_added),
             total_lines_removed: Some(lines_removed),
+            ..Default::default()
         };
 
         // Properties:
@@ -222,6 +223,7 @@ proptest! {
                 device_id: None,
                 token_breakdown: None,
                 max_tokens_observed: None,
+                git_branch: None,
             },
         );
 
@@ -265,6 +267,7 @@ proptest! {
             approaching_limit: percentage > 80.0,
             tokens_remaining: 160000_usize.saturating_sub(total as usize),
             compaction_state: statusline::models::CompactionState::Normal,
+            approximate: false,
         };
 
         // Verify percentage is non-negative
@@ -1,3 +1,5 @@
+#![cfg(feature = "sqlite")]
+
 use std::fs;
 use std::io::Write;
 use std::sync::Arc;
@@ -859,3 +861,92 @@ fn test_monthly_sessions_no_overcount() {
     // Should show 1 unique session, not 3
     assert_eq!(health_json["session_count"].as_u64().unwrap(), 1);
 }
+
+#[test]
+fn test_backup_create_and_restore() {
+    let temp_dir = TempDir::new().unwrap();
+    let statusline = |args: &[&str], stdin: Option<&str>| {
+        let mut child = std::process::Command::new(get_test_binary())
+            .args(args)
+            .env("NO_COLOR", "1")
+            .env("XDG_DATA_HOME", temp_dir.path())
+            .env("XDG_CONFIG_HOME", temp_dir.path())
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        if let Some(input) = stdin {
+            child
+                .stdin
+                .as_mut()
+                .unwrap()
+                .write_all(input.as_bytes())
+                .unwrap();
+        }
+        child.wait_with_output().unwrap()
+    };
+    let session_count = || {
+        let output = statusline(&["health", "--json"], None);
+        let health: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        health["session_count"].as_u64().unwrap()
+    };
+    let render = |session: &str| {
+        let input = format!(
+            r#"{{"workspace":{{"current_dir":"/test"}},"session_id":"{}","cost":{{"total_cost_usd":1.0}}}}"#,
+            session
+        );
+        assert!(statusline(&[], Some(&input)).status.success());
+    };
+
+    let config_path = temp_dir
+        .path()
+        .join("claudia-statusline")
+        .join("config.toml");
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, "[display]\nshow_cost = true\n").unwrap();
+    render("backed-up");
+
+    let backup_path = temp_dir.path().join("snapshot.db");
+    let created = statusline(
+        &["backup", "create", "--to", backup_path.to_str().unwrap()],
+        None,
+    );
+    assert!(created.status.success(), "{:?}", created);
+    assert!(backup_path.exists());
+    // Refuses to overwrite an existing backup
+    assert!(!statusline(
+        &["backup", "create", "--to", backup_path.to_str().unwrap()],
+        None
+    )
+    .status
+    .success());
+
+    render("after-backup");
+    fs::write(&config_path, "# changed\n").unwrap();
+    assert_eq!(session_count(), 2);
+
+    let restored = statusline(&["backup", "restore", backup_path.to_str().unwrap()], None);
+    assert!(restored.status.success(), "{:?}", restored);
+    assert_eq!(session_count(), 1);
+    assert_eq!(
+        fs::read_to_string(&config_path).unwrap(),
+        "[display]\nshow_cost = true\n"
+    );
+    assert!(temp_dir
+        .path()
+        .join("claudia-statusline")
+        .join("stats.db.pre-restore")
+        .exists());
+
+    // A plain database is not accepted as a backup
+    let not_backup = temp_dir
+        .path()
+        .join("claudia-statusline")
+        .join("stats.db.pre-restore");
+    assert!(
+        !statusline(&["backup", "restore", not_backup.to_str().unwrap()], None)
+            .status
+            .success()
+    );
+}
@@ -3,6 +3,8 @@
 //! Tests theme loading, color resolution, user themes,
 //! and ANSI escape sequence handling.
 
+#![cfg(feature = "themes")]
+
 use statusline::theme::{get_theme_manager, Theme};
 
 // ============================================================================